@@ -0,0 +1,63 @@
+use crate::schema::{self, Severity};
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: rgc check [paths...]
+
+Reports every syntax and validation error in the given files (directories are searched for .rgc
+files), without stopping at the first one.
+";
+
+pub fn run(args: &[String]) -> ExitCode {
+    let mut paths = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                print!("{USAGE}");
+                return ExitCode::SUCCESS;
+            }
+            flag if flag.starts_with("--") => {
+                eprintln!("rgc check: unknown option `{flag}`\n\n{USAGE}");
+                return ExitCode::FAILURE;
+            }
+            path => paths.push(PathBuf::from(path)),
+        }
+    }
+    if paths.is_empty() {
+        paths.push(PathBuf::from("."));
+    }
+
+    let files = match super::collect_schema_files(&paths) {
+        Ok(files) => files,
+        Err(err) => {
+            eprintln!("rgc check: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut errors = 0;
+    for file in files {
+        let source = match std::fs::read_to_string(&file) {
+            Ok(source) => source,
+            Err(err) => {
+                eprintln!("rgc check: {}: {err}", file.display());
+                return ExitCode::FAILURE;
+            }
+        };
+        for diagnostic in schema::check(&source) {
+            if diagnostic.severity == Severity::Error {
+                errors += 1;
+            }
+            println!(
+                "{}",
+                diagnostic.render(&file.display().to_string(), &source)
+            );
+        }
+    }
+    if errors > 0 {
+        eprintln!("rgc check: {errors} error(s)");
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
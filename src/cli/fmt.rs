@@ -0,0 +1,124 @@
+use crate::schema::{self, FormatOptions};
+
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: rgc fmt [options] [paths...]
+
+Reformats the given files (directories are searched for .rgc files).
+Without paths, reads a schema from stdin and writes the result to stdout.
+
+Options:
+    --check             Do not write anything, exit with 1 if a file is not formatted
+    --trailing-comma    Put a comma after the last field of every type
+    --indent <n>        Number of spaces per indentation level (default: 4)
+";
+
+pub fn run(args: &[String]) -> ExitCode {
+    let mut check = false;
+    let mut options = FormatOptions::default();
+    let mut paths = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--check" => check = true,
+            "--trailing-comma" => options.trailing_comma = true,
+            "--indent" => match args.next().map(|n| n.parse()) {
+                Some(Ok(n)) => options.indent_width = n,
+                _ => {
+                    eprintln!("rgc fmt: --indent expects a number");
+                    return ExitCode::FAILURE;
+                }
+            },
+            "-h" | "--help" => {
+                print!("{USAGE}");
+                return ExitCode::SUCCESS;
+            }
+            flag if flag.starts_with("--") => {
+                eprintln!("rgc fmt: unknown option `{flag}`\n\n{USAGE}");
+                return ExitCode::FAILURE;
+            }
+            path => paths.push(PathBuf::from(path)),
+        }
+    }
+
+    if paths.is_empty() {
+        return format_stdin(check, &options);
+    }
+
+    let files = match super::collect_schema_files(&paths) {
+        Ok(files) => files,
+        Err(err) => {
+            eprintln!("rgc fmt: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut unformatted = 0;
+    for file in files {
+        let source = match std::fs::read_to_string(&file) {
+            Ok(source) => source,
+            Err(err) => {
+                eprintln!("rgc fmt: {}: {err}", file.display());
+                return ExitCode::FAILURE;
+            }
+        };
+        let formatted = match schema::format(&source, &options) {
+            Ok(formatted) => formatted,
+            Err(diagnostics) => {
+                for diagnostic in diagnostics {
+                    eprintln!(
+                        "{}",
+                        diagnostic.render(&file.display().to_string(), &source)
+                    );
+                }
+                return ExitCode::FAILURE;
+            }
+        };
+        if formatted == source {
+            continue;
+        }
+        if check {
+            println!("{}", file.display());
+            unformatted += 1;
+        } else if let Err(err) = std::fs::write(&file, formatted) {
+            eprintln!("rgc fmt: {}: {err}", file.display());
+            return ExitCode::FAILURE;
+        }
+    }
+    if unformatted > 0 {
+        eprintln!("rgc fmt: {unformatted} file(s) need formatting");
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+fn format_stdin(check: bool, options: &FormatOptions) -> ExitCode {
+    let mut source = String::new();
+    if let Err(err) = std::io::stdin().read_to_string(&mut source) {
+        eprintln!("rgc fmt: {err}");
+        return ExitCode::FAILURE;
+    }
+    let formatted = match schema::format(&source, options) {
+        Ok(formatted) => formatted,
+        Err(diagnostics) => {
+            for diagnostic in diagnostics {
+                eprintln!("{}", diagnostic.render("<stdin>", &source));
+            }
+            return ExitCode::FAILURE;
+        }
+    };
+    if check {
+        return if formatted == source {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::FAILURE
+        };
+    }
+    if let Err(err) = std::io::stdout().write_all(formatted.as_bytes()) {
+        eprintln!("rgc fmt: {err}");
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
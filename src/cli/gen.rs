@@ -0,0 +1,190 @@
+use crate::codegen::{csharp, go, protobuf, python, Options, Target};
+use crate::schema;
+
+use std::io::Read;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: rgc gen --target <target> [options] [schema]
+
+Generates code for the types of a schema file. Without a schema, reads it from stdin.
+
+Options:
+    --target <target>   Output language: rust, typescript, python, go, kotlin,
+                        swift, csharp, jsonschema, openapi, protobuf,
+                        graphql, avro
+    --pydantic          Python: generate pydantic models instead of dataclasses
+    --package <name>    Go: package of the generated code, named after the schema file by default
+                        Protobuf: package of the definitions, mapped from the schema path by
+                        default: billing/invoices.rgc -> billing.invoices
+    --namespace <name>  C#: namespace of the generated code, mapped from the schema path by
+                        default: billing/invoices.rgc -> Billing.Invoices
+    --title <title>     OpenAPI: title of the API, the name of the schema file by default
+    -o, --out <file>    Write the generated code to a file instead of stdout. For Go, a directory
+                        gets the package as a subdirectory: <dir>/<package>/<package>.go
+";
+
+pub fn run(args: &[String]) -> ExitCode {
+    let mut target = None;
+    let mut options = Options::default();
+    let mut package = None;
+    let mut out = None;
+    let mut path = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--target" => match args.next().map(|name| Target::from_name(name)) {
+                Some(Some(name)) => target = Some(name),
+                _ => {
+                    let names = Target::ALL.iter().map(|target| target.name());
+                    eprintln!(
+                        "rgc gen: --target expects one of: {}",
+                        names.collect::<Vec<_>>().join(", ")
+                    );
+                    return ExitCode::FAILURE;
+                }
+            },
+            "--pydantic" => options.python = python::Flavor::Pydantic,
+            "--package" => match args.next() {
+                Some(name) => package = Some(name.clone()),
+                None => {
+                    eprintln!("rgc gen: --package expects a package name");
+                    return ExitCode::FAILURE;
+                }
+            },
+            "--namespace" => match args.next() {
+                Some(name) if csharp::is_namespace(name) => {
+                    options.csharp_namespace = Some(name.clone());
+                }
+                _ => {
+                    eprintln!("rgc gen: --namespace expects a C# namespace");
+                    return ExitCode::FAILURE;
+                }
+            },
+            "--title" => match args.next() {
+                Some(title) => options.openapi_title = Some(title.clone()),
+                None => {
+                    eprintln!("rgc gen: --title expects a title");
+                    return ExitCode::FAILURE;
+                }
+            },
+            "-o" | "--out" => match args.next() {
+                Some(file) => out = Some(PathBuf::from(file)),
+                None => {
+                    eprintln!("rgc gen: {arg} expects a file");
+                    return ExitCode::FAILURE;
+                }
+            },
+            "-h" | "--help" => {
+                print!("{USAGE}");
+                return ExitCode::SUCCESS;
+            }
+            flag if flag.starts_with('-') => {
+                eprintln!("rgc gen: unknown option `{flag}`\n\n{USAGE}");
+                return ExitCode::FAILURE;
+            }
+            file if path.is_none() => path = Some(PathBuf::from(file)),
+            _ => {
+                eprintln!("rgc gen: expected a single schema file\n\n{USAGE}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    let Some(target) = target else {
+        eprintln!("rgc gen: missing --target\n\n{USAGE}");
+        return ExitCode::FAILURE;
+    };
+    match package {
+        Some(name) if target == Target::Go => {
+            if go::package_name(&name) != name {
+                eprintln!("rgc gen: --package expects a lowercase Go package name");
+                return ExitCode::FAILURE;
+            }
+            options.go_package = Some(name);
+        }
+        Some(name) if target == Target::Protobuf => {
+            if !protobuf::is_package(&name) {
+                eprintln!("rgc gen: --package expects a protobuf package, such as billing.v1");
+                return ExitCode::FAILURE;
+            }
+            options.proto_package = Some(name);
+        }
+        Some(_) => {
+            eprintln!("rgc gen: --package only applies to go and protobuf");
+            return ExitCode::FAILURE;
+        }
+        None => {}
+    }
+
+    let (name, source) = match &path {
+        Some(path) => match std::fs::read_to_string(path) {
+            Ok(source) => (path.display().to_string(), source),
+            Err(err) => {
+                eprintln!("rgc gen: {}: {err}", path.display());
+                return ExitCode::FAILURE;
+            }
+        },
+        None => {
+            let mut source = String::new();
+            if let Err(err) = std::io::stdin().read_to_string(&mut source) {
+                eprintln!("rgc gen: {err}");
+                return ExitCode::FAILURE;
+            }
+            ("<stdin>".to_string(), source)
+        }
+    };
+    let assembly = match schema::parse(&source) {
+        Ok(assembly) => assembly,
+        Err(diagnostics) => {
+            for diagnostic in diagnostics {
+                eprintln!("{}", diagnostic.render(&name, &source));
+            }
+            return ExitCode::FAILURE;
+        }
+    };
+    // Each schema is a namespace of its own, generated as a Go package.
+    if options.go_package.is_none() {
+        let stem = path.as_ref().and_then(|path| path.file_stem());
+        options.go_package = stem.map(|stem| go::package_name(&stem.to_string_lossy()));
+    }
+    if options.openapi_title.is_none() {
+        let stem = path.as_ref().and_then(|path| path.file_stem());
+        options.openapi_title = stem.map(|stem| stem.to_string_lossy().into_owned());
+    }
+    if options.csharp_namespace.is_none() {
+        options.csharp_namespace = path.as_deref().map(csharp::namespace);
+    }
+    if options.proto_package.is_none() {
+        options.proto_package = path.as_deref().map(protobuf::package);
+    }
+    let code = match target.generate(&assembly, &options) {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("rgc gen: {name}: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let out = match out {
+        Some(dir) if target == Target::Go && dir.is_dir() => {
+            let package = options.go_package.as_deref().unwrap_or(go::DEFAULT_PACKAGE);
+            let dir = dir.join(package);
+            if let Err(err) = std::fs::create_dir_all(&dir) {
+                eprintln!("rgc gen: {}: {err}", dir.display());
+                return ExitCode::FAILURE;
+            }
+            Some(dir.join(format!("{package}.go")))
+        }
+        out => out,
+    };
+    match out {
+        Some(out) => {
+            if let Err(err) = std::fs::write(&out, code) {
+                eprintln!("rgc gen: {}: {err}", out.display());
+                return ExitCode::FAILURE;
+            }
+        }
+        None => print!("{code}"),
+    }
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,98 @@
+use crate::import::{self, Imported};
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: rgc import <format> [options] <file>
+
+Converts a schema written in another language to rgc. Constructs that rgc cannot express are
+approximated and reported as warnings.
+
+Formats:
+    graphql     GraphQL schemas, root type fields becoming service functions
+    jsonschema  JSON Schema documents, types being read from $defs
+    proto       Protobuf definitions, field numbers being kept as @tag
+
+Options:
+    -o, --out <file>    Write the schema to a file instead of stdout
+";
+
+pub fn run(args: &[String]) -> ExitCode {
+    let mut format = None;
+    let mut out = None;
+    let mut path = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--out" => match args.next() {
+                Some(file) => out = Some(PathBuf::from(file)),
+                None => {
+                    eprintln!("rgc import: {arg} expects a file");
+                    return ExitCode::FAILURE;
+                }
+            },
+            "-h" | "--help" => {
+                print!("{USAGE}");
+                return ExitCode::SUCCESS;
+            }
+            flag if flag.starts_with('-') => {
+                eprintln!("rgc import: unknown option `{flag}`\n\n{USAGE}");
+                return ExitCode::FAILURE;
+            }
+            name if format.is_none() => format = Some(name.to_string()),
+            file if path.is_none() => path = Some(PathBuf::from(file)),
+            _ => {
+                eprintln!("rgc import: expected a single file\n\n{USAGE}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    let (Some(format), Some(path)) = (format, path) else {
+        eprintln!("rgc import: missing format or file\n\n{USAGE}");
+        return ExitCode::FAILURE;
+    };
+
+    let source = match std::fs::read_to_string(&path) {
+        Ok(source) => source,
+        Err(err) => {
+            eprintln!("rgc import: {}: {err}", path.display());
+            return ExitCode::FAILURE;
+        }
+    };
+    let imported = match format.as_str() {
+        "jsonschema" => import::jsonschema::import(&source, &stem(&path)),
+        "graphql" => import::graphql::import(&source),
+        "proto" => import::protobuf::import(&source),
+        format => {
+            eprintln!("rgc import: unknown format `{format}`\n\n{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let Imported { source, warnings } = match imported {
+        Ok(imported) => imported,
+        Err(err) => {
+            eprintln!("rgc import: {}: {err}", path.display());
+            return ExitCode::FAILURE;
+        }
+    };
+    for warning in warnings {
+        eprintln!("{}:{warning}", path.display());
+    }
+    match out {
+        Some(out) => {
+            if let Err(err) = std::fs::write(&out, source) {
+                eprintln!("rgc import: {}: {err}", out.display());
+                return ExitCode::FAILURE;
+            }
+        }
+        None => print!("{source}"),
+    }
+    ExitCode::SUCCESS
+}
+
+/// Name of the file without its extensions, e.g. `user` for `user.schema.json`.
+fn stem(path: &Path) -> String {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    name.split('.').next().unwrap_or_default().to_string()
+}
@@ -0,0 +1,16 @@
+use std::process::ExitCode;
+
+pub fn run(args: &[String]) -> ExitCode {
+    // Editors commonly pass `--stdio`; stdio is the only supported transport anyway.
+    if let Some(arg) = args.iter().find(|arg| *arg != "--stdio") {
+        eprintln!("rgc lsp: unknown option `{arg}`");
+        return ExitCode::FAILURE;
+    }
+    match crate::lsp::run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("rgc lsp: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
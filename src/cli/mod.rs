@@ -0,0 +1,69 @@
+pub mod check;
+pub mod fmt;
+pub mod gen;
+pub mod import;
+pub mod lsp;
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: rgc <command> [options]
+
+Commands:
+    check   Report all errors in .rgc files
+    fmt     Reformat .rgc files in the canonical layout
+    gen     Generate code from a schema
+    import  Convert a schema written in another language to rgc
+    lsp     Start the language server on stdin/stdout
+";
+
+pub fn run(args: &[String]) -> ExitCode {
+    match args.first().map(String::as_str) {
+        Some("check") => check::run(&args[1..]),
+        Some("fmt") => fmt::run(&args[1..]),
+        Some("gen") => gen::run(&args[1..]),
+        Some("import") => import::run(&args[1..]),
+        Some("lsp") => lsp::run(&args[1..]),
+        Some("-h" | "--help") => {
+            print!("{USAGE}");
+            ExitCode::SUCCESS
+        }
+        Some(command) => {
+            eprintln!("rgc: unknown command `{command}`\n\n{USAGE}");
+            ExitCode::FAILURE
+        }
+        None => {
+            eprint!("{USAGE}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Expands the given paths into a sorted list of `.rgc` files, descending into directories.
+pub fn collect_schema_files(paths: &[PathBuf]) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            collect_dir(path, &mut files)?;
+        } else {
+            files.push(path.clone());
+        }
+    }
+    Ok(files)
+}
+
+fn collect_dir(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    let mut entries = std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            collect_dir(&path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "rgc") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,320 @@
+//! Avro backend: an `.avsc` schema with a record per type, listed in a top-level array. A record
+//! is defined where it is first used and referenced by name afterwards, which also covers
+//! recursive types: a record referencing itself, or a record it contains, is defined inline at
+//! that point. Avro has no generics, so each instance of a generic type gets a record of its
+//! own, e.g. `PageUser` for `Page<User>`. Optional values are unions with `null`, and maps whose
+//! keys are not strings are arrays of `key`/`value` records. Avro unions cannot be nested, so
+//! `Optional<Optional<T>>` holds a record wrapping the inner value. rgc has no date, decimal or
+//! uuid builtins, so no logical types are used: such values are written as the `string` they are
+//! declared as. `stream` and `sync` fields have no static value, see [`generate`].
+
+use super::{
+    deprecation, flat_name, live_types, renamed, substitute, value_type, written, written_instance,
+    Names, TypeArgs,
+};
+use crate::json::Json;
+use crate::schema::assembly::*;
+use crate::schema::module::{BuiltinType, SchemeValue};
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::rc::Rc;
+
+/// Fails if the assembly has `stream` or `sync` fields, or if generated record names clash.
+pub fn generate(assembly: &Assembly) -> Result<String, String> {
+    // Records hold values at rest, which `stream` and `sync` fields do not have.
+    let live = live_fields(assembly);
+    if !live.is_empty() {
+        return Err(format!(
+            "`stream` and `sync` fields cannot be represented in Avro records: {}",
+            live.join(", ")
+        ));
+    }
+    let mut generator = Generator {
+        defined: HashSet::new(),
+        names: Names::default(),
+    };
+    let mut order = Vec::new();
+    let mut visited = HashSet::new();
+    let live = live_types(assembly);
+    for ty in &assembly.types {
+        if ty.borrow().params.is_empty() && !live.contains(&ty.borrow().name) {
+            let node = (record_name(&ty.borrow()), ty.clone(), TypeArgs::new());
+            dependencies_first(node, &mut visited, &mut order);
+        }
+    }
+    // Records that others use come first, so that most of them are referenced by name.
+    let mut records = Vec::new();
+    for (name, ty, args) in order {
+        if !generator.defined.contains(&name) {
+            records.push(generator.record(&ty.borrow(), &name, &args));
+        }
+    }
+    generator.names.check()?;
+    Ok(Json::Array(records).pretty())
+}
+
+/// The `stream` and `sync` fields of the assembly, as `Type.field`. Their values only exist at
+/// runtime, so a schema with such fields has no Avro equivalent.
+fn live_fields(assembly: &Assembly) -> Vec<String> {
+    let mut fields = Vec::new();
+    for ty in &assembly.types {
+        let ty = ty.borrow();
+        for field in &ty.fields {
+            if field.ty.kind != AssemblyFieldTypeKind::Normal {
+                fields.push(format!("{}.{}", ty.name, field.name));
+            }
+        }
+    }
+    fields
+}
+
+/// A record to generate: name, type, and arguments of its parameters.
+type Node = (String, Rc<RefCell<AssemblyType>>, TypeArgs);
+
+/// Adds `node` to `order` after the records its fields use, unless it is already there.
+fn dependencies_first(node: Node, visited: &mut HashSet<String>, order: &mut Vec<Node>) {
+    if !visited.insert(node.0.clone()) {
+        return;
+    }
+    for field in &node.1.borrow().fields {
+        let mut used = Vec::new();
+        records_used(&substitute(&field.ty.ty_ref, &node.2), &mut used);
+        for used in used {
+            dependencies_first(used, visited, order);
+        }
+    }
+    order.push(node);
+}
+
+/// The records of the custom types mentioned by `ty_ref`.
+fn records_used(ty_ref: &AssemblyTypeRef, used: &mut Vec<Node>) {
+    match ty_ref {
+        AssemblyTypeRef::Builtin(_) | AssemblyTypeRef::Param(_) => {}
+        AssemblyTypeRef::Custom(ty) => {
+            used.push((record_name(&ty.borrow()), ty.clone(), TypeArgs::new()))
+        }
+        AssemblyTypeRef::Instance(ty, args) => {
+            let params = ty.borrow().params.clone();
+            let type_args = params.into_iter().zip(args.iter().cloned()).collect();
+            used.push((flat_name(ty_ref, "avro"), ty.clone(), type_args));
+        }
+        AssemblyTypeRef::Array(item) | AssemblyTypeRef::Optional(item) => records_used(item, used),
+        AssemblyTypeRef::Map(key, value) => {
+            records_used(key, used);
+            records_used(value, used);
+        }
+    }
+}
+
+struct Generator {
+    /// Names of the records and wrappers already defined, which are referenced by name.
+    defined: HashSet<String>,
+    names: Names,
+}
+
+impl Generator {
+    fn record(&mut self, ty: &AssemblyType, name: &str, args: &TypeArgs) -> Json {
+        // Defined before its fields, which may refer back to it.
+        self.defined.insert(name.to_string());
+        self.names
+            .claim(name, format!("`{}`", written_instance(ty, args)));
+        let mut record = Json::object([("type", Json::from("record")), ("name", Json::from(name))]);
+        if let Some(reason) = deprecation(&ty.annotations) {
+            record.insert("doc", Json::from(deprecated(reason)));
+        }
+        let fields = ty
+            .fields
+            .iter()
+            .map(|field| self.field(field, args))
+            .collect::<Vec<_>>();
+        record.insert("fields", Json::Array(fields));
+        record
+    }
+
+    fn field(&mut self, field: &AssemblyField, args: &TypeArgs) -> Json {
+        let ty_ref = substitute(&value_type(field), args);
+        let name = renamed(&field.annotations, "avro").unwrap_or(&field.name);
+        let default = match (&field.default, field.optional) {
+            (None, true) => Some(&SchemeValue::Null),
+            (default, _) => default.as_ref(),
+        };
+        let schema = match &ty_ref {
+            // The default of a union is a value of its first branch.
+            AssemblyTypeRef::Optional(item) => self.optional(
+                item,
+                default.is_some_and(|value| *value != SchemeValue::Null),
+            ),
+            ty_ref => self.schema(ty_ref),
+        };
+        let mut json = Json::object([("name", Json::from(name)), ("type", schema)]);
+        if let Some(default) = default {
+            json.insert("default", literal(default, &ty_ref));
+        }
+        if let Some(reason) = deprecation(&field.annotations) {
+            json.insert("doc", Json::from(deprecated(reason)));
+        }
+        json
+    }
+
+    /// Avro schema of the values of `ty_ref`.
+    fn schema(&mut self, ty_ref: &AssemblyTypeRef) -> Json {
+        match ty_ref {
+            AssemblyTypeRef::Builtin(builtin) => Json::from(builtin_type(*builtin)),
+            AssemblyTypeRef::Custom(ty) => {
+                let name = record_name(&ty.borrow());
+                self.named(&name, ty, &TypeArgs::new())
+            }
+            AssemblyTypeRef::Instance(ty, args) => {
+                let params = ty.borrow().params.clone();
+                let type_args = params.into_iter().zip(args.iter().cloned()).collect();
+                self.named(&flat_name(ty_ref, "avro"), ty, &type_args)
+            }
+            AssemblyTypeRef::Optional(item) => self.optional(item, false),
+            AssemblyTypeRef::Array(item) => {
+                Json::object([("type", Json::from("array")), ("items", self.schema(item))])
+            }
+            // Keys of Avro maps are strings.
+            AssemblyTypeRef::Map(key, value) => match &**key {
+                AssemblyTypeRef::Builtin(BuiltinType::String) => {
+                    Json::object([("type", Json::from("map")), ("values", self.schema(value))])
+                }
+                _ => Json::object([
+                    ("type", Json::from("array")),
+                    ("items", self.entry(key, value)),
+                ]),
+            },
+            AssemblyTypeRef::Param(_) => unreachable!("type parameters are substituted"),
+        }
+    }
+
+    /// The record `name`, defined here on its first use.
+    fn named(&mut self, name: &str, ty: &Rc<RefCell<AssemblyType>>, args: &TypeArgs) -> Json {
+        match self.defined.contains(name) {
+            true => {
+                let owner = format!("`{}`", written_instance(&ty.borrow(), args));
+                self.names.claim(name, owner);
+                Json::from(name)
+            }
+            false => self.record(&ty.borrow(), name, args),
+        }
+    }
+
+    /// A union of `null` and the values of `item`, `null` being last if `value_first`, for
+    /// defaults other than `null`.
+    fn optional(&mut self, item: &AssemblyTypeRef, value_first: bool) -> Json {
+        let value = match item {
+            // Unions cannot hold unions: the inner value is wrapped in a record.
+            AssemblyTypeRef::Optional(inner) => {
+                let name = flat_name(item, "avro");
+                self.names.claim(&name, format!("`{}`", written(item)));
+                match self.defined.insert(name.clone()) {
+                    false => Json::from(name),
+                    true => Json::object([
+                        ("type", Json::from("record")),
+                        ("name", Json::from(name)),
+                        (
+                            "fields",
+                            Json::Array(vec![Json::object([
+                                ("name", Json::from("value")),
+                                ("type", self.optional(inner, false)),
+                            ])]),
+                        ),
+                    ]),
+                }
+            }
+            item => self.schema(item),
+        };
+        match value_first {
+            true => Json::Array(vec![value, Json::from("null")]),
+            false => Json::Array(vec![Json::from("null"), value]),
+        }
+    }
+
+    /// Record of the entries of maps from `key` to `value`.
+    fn entry(&mut self, key: &AssemblyTypeRef, value: &AssemblyTypeRef) -> Json {
+        let name = format!(
+            "{}{}Entry",
+            flat_name(key, "avro"),
+            flat_name(value, "avro")
+        );
+        let owner = format!("the entries of `Map<{}, {}>`", written(key), written(value));
+        self.names.claim(&name, owner);
+        if !self.defined.insert(name.clone()) {
+            return Json::from(name);
+        }
+        let fields = vec![
+            Json::object([("name", Json::from("key")), ("type", self.schema(key))]),
+            Json::object([("name", Json::from("value")), ("type", self.schema(value))]),
+        ];
+        Json::object([
+            ("type", Json::from("record")),
+            ("name", Json::from(name)),
+            ("fields", Json::Array(fields)),
+        ])
+    }
+}
+
+fn record_name(ty: &AssemblyType) -> String {
+    renamed(&ty.annotations, "avro")
+        .unwrap_or(&ty.name)
+        .to_string()
+}
+
+fn builtin_type(builtin: BuiltinType) -> &'static str {
+    match builtin {
+        BuiltinType::Integer => "long",
+        BuiltinType::Float => "double",
+        BuiltinType::String => "string",
+        BuiltinType::Boolean => "boolean",
+    }
+}
+
+/// Avro has no deprecation, which is documented instead.
+fn deprecated(reason: &str) -> String {
+    match reason {
+        "" => "Deprecated.".to_string(),
+        reason => format!("Deprecated: {reason}"),
+    }
+}
+
+/// Default value of type `ty_ref`, as written in Avro schemas. Defaults of unions are values of
+/// their first branch, and wrapped optional values are records.
+fn literal(value: &SchemeValue, ty_ref: &AssemblyTypeRef) -> Json {
+    match (value, ty_ref) {
+        (SchemeValue::Null, _) => Json::Null,
+        (value, AssemblyTypeRef::Optional(item)) => match &**item {
+            AssemblyTypeRef::Optional(_) => Json::object([("value", literal(value, item))]),
+            item => literal(value, item),
+        },
+        (SchemeValue::Integer(n), _) => Json::from(*n),
+        (SchemeValue::Float(x), _) => Json::from(*x),
+        (SchemeValue::String(text), _) => Json::from(text.as_str()),
+        (SchemeValue::Boolean(b), _) => Json::from(*b),
+        (SchemeValue::Array(items), AssemblyTypeRef::Array(item)) => {
+            Json::Array(items.iter().map(|value| literal(value, item)).collect())
+        }
+        (SchemeValue::Map(entries), AssemblyTypeRef::Map(key, value)) => match &**key {
+            AssemblyTypeRef::Builtin(BuiltinType::String) => Json::Object(
+                entries
+                    .iter()
+                    .map(|(k, v)| {
+                        (
+                            literal(k, key).as_str().unwrap().to_string(),
+                            literal(v, value),
+                        )
+                    })
+                    .collect(),
+            ),
+            _ => Json::Array(
+                entries
+                    .iter()
+                    .map(|(k, v)| {
+                        Json::object([("key", literal(k, key)), ("value", literal(v, value))])
+                    })
+                    .collect(),
+            ),
+        },
+        _ => unreachable!("defaults have the type of their field"),
+    }
+}
@@ -0,0 +1,559 @@
+//! C# backend: a record per type, serialized with System.Text.Json. A schema is a module, whose
+//! path gives the namespace of the generated code. Services get an interface, implemented by
+//! servers or by client stubs.
+
+use super::{camel_case, deprecation, is_map_key, live_types, renamed};
+use crate::schema::assembly::*;
+use crate::schema::module::{BuiltinType, SchemeAnnotation, SchemeConst, SchemeValue};
+
+use std::collections::{BTreeSet, HashSet};
+use std::fmt::Write;
+use std::path::{Component, Path};
+
+const KEYWORDS: &[&str] = &[
+    "abstract",
+    "as",
+    "base",
+    "bool",
+    "break",
+    "byte",
+    "case",
+    "catch",
+    "char",
+    "checked",
+    "class",
+    "const",
+    "continue",
+    "decimal",
+    "default",
+    "delegate",
+    "do",
+    "double",
+    "else",
+    "enum",
+    "event",
+    "explicit",
+    "extern",
+    "false",
+    "finally",
+    "fixed",
+    "float",
+    "for",
+    "foreach",
+    "goto",
+    "if",
+    "implicit",
+    "in",
+    "int",
+    "interface",
+    "internal",
+    "is",
+    "lock",
+    "long",
+    "namespace",
+    "new",
+    "null",
+    "object",
+    "operator",
+    "out",
+    "override",
+    "params",
+    "private",
+    "protected",
+    "public",
+    "readonly",
+    "ref",
+    "return",
+    "sbyte",
+    "sealed",
+    "short",
+    "sizeof",
+    "stackalloc",
+    "static",
+    "string",
+    "struct",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "uint",
+    "ulong",
+    "unchecked",
+    "unsafe",
+    "ushort",
+    "using",
+    "virtual",
+    "void",
+    "volatile",
+    "while",
+];
+
+/// Namespace used for schemas read from stdin.
+pub const DEFAULT_NAMESPACE: &str = "Schema";
+
+const PAIRS_PRELUDE: &str = r#"
+/// <summary>
+/// A dictionary sent as an array of <c>[key, value]</c> pairs, its keys not being strings or
+/// numbers.
+/// </summary>
+[JsonConverter(typeof(PairsConverter))]
+public class Pairs<TKey, TValue> : Dictionary<TKey, TValue> where TKey : notnull
+{
+}
+
+public sealed class PairsConverter : JsonConverterFactory
+{
+    public override bool CanConvert(Type type) =>
+        type.IsGenericType && type.GetGenericTypeDefinition() == typeof(Pairs<,>);
+
+    public override JsonConverter CreateConverter(Type type, JsonSerializerOptions options) =>
+        (JsonConverter)Activator.CreateInstance(
+            typeof(Converter<,>).MakeGenericType(type.GetGenericArguments()))!;
+
+    private sealed class Converter<TKey, TValue> : JsonConverter<Pairs<TKey, TValue>>
+        where TKey : notnull
+    {
+        public override Pairs<TKey, TValue> Read(
+            ref Utf8JsonReader reader, Type type, JsonSerializerOptions options)
+        {
+            var pairs = new Pairs<TKey, TValue>();
+            foreach (var pair in JsonSerializer.Deserialize<JsonElement[][]>(ref reader, options)!)
+            {
+                pairs[pair[0].Deserialize<TKey>(options)!] = pair[1].Deserialize<TValue>(options)!;
+            }
+            return pairs;
+        }
+
+        public override void Write(
+            Utf8JsonWriter writer, Pairs<TKey, TValue> value, JsonSerializerOptions options)
+        {
+            writer.WriteStartArray();
+            foreach (var (key, item) in value)
+            {
+                writer.WriteStartArray();
+                JsonSerializer.Serialize(writer, key, options);
+                JsonSerializer.Serialize(writer, item, options);
+                writer.WriteEndArray();
+            }
+            writer.WriteEndArray();
+        }
+    }
+}
+"#;
+
+/// Namespaces imported by the generated code, collected while generating it.
+#[derive(Default)]
+struct Uses {
+    usings: BTreeSet<&'static str>,
+    pairs: bool,
+}
+
+pub fn generate(assembly: &Assembly, namespace: &str) -> String {
+    let live = live_types(assembly);
+    let mut uses = Uses::default();
+    let mut body = String::new();
+    if !assembly.consts.is_empty() {
+        body.push_str("\npublic static class Constants\n{\n");
+        for (i, constant) in assembly.consts.iter().enumerate() {
+            if i > 0 {
+                body.push('\n');
+            }
+            generate_const(&mut body, constant, &mut uses);
+        }
+        body.push_str("}\n");
+    }
+    for ty in &assembly.types {
+        body.push('\n');
+        generate_record(&mut body, &ty.borrow(), &live, &mut uses);
+    }
+    for service in &assembly.services {
+        body.push('\n');
+        generate_service(&mut body, service, &mut uses);
+    }
+
+    if uses.pairs {
+        uses.usings.extend([
+            "System",
+            "System.Collections.Generic",
+            "System.Text.Json",
+            "System.Text.Json.Serialization",
+        ]);
+    }
+    let mut out = String::from("// Generated by rgc. Do not edit.\n\n#nullable enable\n");
+    if !uses.usings.is_empty() {
+        out.push('\n');
+        for using in &uses.usings {
+            writeln!(out, "using {using};").unwrap();
+        }
+    }
+    writeln!(out, "\nnamespace {namespace};").unwrap();
+    if uses.pairs {
+        out.push_str(PAIRS_PRELUDE);
+    }
+    out.push_str(&body);
+    out
+}
+
+/// Namespace of the schema file at `path`: its directories and name, without the extension, in
+/// PascalCase (`billing/invoice-items.rgc` -> `Billing.InvoiceItems`). Only the file name is used
+/// for paths that are not relative to the working directory.
+pub fn namespace(path: &Path) -> String {
+    let path = path.with_extension("");
+    let relative = path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+    let segments = if relative {
+        path.components()
+            .filter_map(|component| match component {
+                Component::Normal(segment) => Some(segment.to_string_lossy().into_owned()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    } else {
+        path.file_name()
+            .map(|name| vec![name.to_string_lossy().into_owned()])
+            .unwrap_or_default()
+    };
+    let segments = segments
+        .iter()
+        .map(|segment| identifier(segment))
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>();
+    if segments.is_empty() {
+        DEFAULT_NAMESPACE.to_string()
+    } else {
+        segments.join(".")
+    }
+}
+
+/// Whether `name` is a valid namespace, dotted C# identifiers.
+pub fn is_namespace(name: &str) -> bool {
+    name.split('.').all(|segment| {
+        let mut chars = segment.chars();
+        chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+            && !KEYWORDS.contains(&segment)
+    })
+}
+
+/// `invoice-items` -> `InvoiceItems`, with a leading `_` when it would start with a digit.
+fn identifier(segment: &str) -> String {
+    let name = segment
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .map(pascal_case)
+        .collect::<String>();
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{name}")
+    } else {
+        name
+    }
+}
+
+fn generate_const(out: &mut String, constant: &SchemeConst, uses: &mut Uses) {
+    obsolete_attribute(out, "    ", &constant.annotations, uses);
+    let ty = AssemblyTypeRef::Builtin(constant.ty);
+    writeln!(
+        out,
+        "    public const {} {} = {};",
+        builtin_type(constant.ty),
+        renamed(&constant.annotations, "csharp")
+            .map_or_else(|| pascal_case(&constant.name), str::to_string),
+        value(&constant.value, &ty, uses)
+    )
+    .unwrap();
+}
+
+fn generate_record(out: &mut String, ty: &AssemblyType, live: &HashSet<String>, uses: &mut Uses) {
+    // Live types only exist at runtime, they are not serialized.
+    let serializable = !live.contains(&ty.name);
+    obsolete_attribute(out, "", &ty.annotations, uses);
+    let generics = if ty.params.is_empty() {
+        String::new()
+    } else {
+        format!("<{}>", ty.params.join(", "))
+    };
+    write!(out, "public record {}{generics}", type_name(ty)).unwrap();
+    let inherited = match &ty.base {
+        Some(base) => {
+            let base = base.borrow();
+            write!(out, " : {}", type_name(&base)).unwrap();
+            base.fields.len()
+        }
+        None => 0,
+    };
+    out.push('\n');
+    // Parameters used as dictionary keys cannot be null.
+    for param in &ty.params {
+        if ty
+            .fields
+            .iter()
+            .any(|field| is_map_key(&field.ty.ty_ref, param))
+        {
+            writeln!(out, "    where {param} : notnull").unwrap();
+        }
+    }
+    out.push_str("{\n");
+    for (i, field) in ty.fields[inherited..].iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        obsolete_attribute(out, "    ", &field.annotations, uses);
+        if serializable {
+            uses.usings.insert("System.Text.Json.Serialization");
+            writeln!(out, "    [JsonPropertyName({})]", string(&field.name)).unwrap();
+            if field.optional {
+                out.push_str("    [JsonIgnore(Condition = JsonIgnoreCondition.WhenWritingNull)]\n");
+            }
+        }
+        let mut field_type = field_type(&field.ty, uses);
+        if field.optional && !field_type.ends_with('?') {
+            field_type.push('?');
+        }
+        // Properties without a default value must be given, even the nullable ones. `sync`
+        // values are given by the transport.
+        let required = !field.optional
+            && (field.default.is_none() || field.ty.kind == AssemblyFieldTypeKind::Sync);
+        let default = match &field.default {
+            Some(default) if !required => {
+                format!(" = {};", value(default, &field.ty.ty_ref, uses))
+            }
+            _ => String::new(),
+        };
+        writeln!(
+            out,
+            "    public {}{field_type} {} {{ get; init; }}{default}",
+            if required { "required " } else { "" },
+            field_name(field, type_name(ty))
+        )
+        .unwrap();
+    }
+    out.push_str("}\n");
+}
+
+/// Servers implement the interface of each service, one method per remote function.
+fn generate_service(out: &mut String, service: &AssemblyService, uses: &mut Uses) {
+    obsolete_attribute(out, "", &service.annotations, uses);
+    writeln!(
+        out,
+        "public interface {}\n{{",
+        renamed(&service.annotations, "csharp")
+            .map_or_else(|| format!("I{}", service.name), str::to_string)
+    )
+    .unwrap();
+    for function in &service.functions {
+        obsolete_attribute(out, "    ", &function.annotations, uses);
+        let mut params = function
+            .params
+            .iter()
+            .map(|param| {
+                format!(
+                    "{} {}",
+                    type_ref(&param.ty, uses),
+                    escape(camel_case(&param.name))
+                )
+            })
+            .collect::<Vec<_>>();
+        let name = renamed(&function.annotations, "csharp")
+            .map_or_else(|| pascal_case(&function.name), str::to_string);
+        // `sync` values are handed out at once and then updated by the transport.
+        let (returns, name) = match &function.returns {
+            Some(ty) if ty.kind != AssemblyFieldTypeKind::Normal => (field_type(ty, uses), name),
+            Some(ty) => {
+                uses.usings.insert("System.Threading.Tasks");
+                (
+                    format!("Task<{}>", field_type(ty, uses)),
+                    format!("{name}Async"),
+                )
+            }
+            None => {
+                uses.usings.insert("System.Threading.Tasks");
+                ("Task".to_string(), format!("{name}Async"))
+            }
+        };
+        if !matches!(&function.returns, Some(ty) if ty.kind == AssemblyFieldTypeKind::Sync) {
+            uses.usings.insert("System.Threading");
+            params.push("CancellationToken cancellationToken = default".to_string());
+        }
+        writeln!(out, "    {returns} {name}({});", params.join(", ")).unwrap();
+    }
+    out.push_str("}\n");
+}
+
+fn obsolete_attribute(
+    out: &mut String,
+    indent: &str,
+    annotations: &[SchemeAnnotation],
+    uses: &mut Uses,
+) {
+    match deprecation(annotations) {
+        Some("") => writeln!(out, "{indent}[Obsolete]"),
+        Some(reason) => writeln!(out, "{indent}[Obsolete({})]", string(reason)),
+        None => return,
+    }
+    .unwrap();
+    uses.usings.insert("System");
+}
+
+/// `createdAt` -> `CreatedAt`.
+fn pascal_case(name: &str) -> String {
+    let name = camel_case(name);
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn type_name(ty: &AssemblyType) -> &str {
+    renamed(&ty.annotations, "csharp").unwrap_or(&ty.name)
+}
+
+/// Name of the property of `field` in the record `owner`. Members cannot have the name of their
+/// type, so such a property gets a `Value` suffix, its name in JSON being unchanged.
+fn field_name(field: &AssemblyField, owner: &str) -> String {
+    let name = renamed(&field.annotations, "csharp")
+        .map_or_else(|| pascal_case(&field.name), str::to_string);
+    match name == owner {
+        true => format!("{name}Value"),
+        false => name,
+    }
+}
+
+/// Names that are C# keywords are prefixed with `@`.
+fn escape(name: String) -> String {
+    if KEYWORDS.contains(&name.as_str()) {
+        format!("@{name}")
+    } else {
+        name
+    }
+}
+
+fn field_type(ty: &AssemblyFieldType, uses: &mut Uses) -> String {
+    let inner = type_ref(&ty.ty_ref, uses);
+    match ty.kind {
+        AssemblyFieldTypeKind::Normal => inner,
+        AssemblyFieldTypeKind::Stream => {
+            uses.usings.insert("System.Collections.Generic");
+            format!("IAsyncEnumerable<{inner}>")
+        }
+        AssemblyFieldTypeKind::Sync => {
+            uses.usings.insert("System");
+            format!("IObservable<{inner}>")
+        }
+    }
+}
+
+fn type_ref(ty_ref: &AssemblyTypeRef, uses: &mut Uses) -> String {
+    match ty_ref {
+        AssemblyTypeRef::Builtin(builtin) => builtin_type(*builtin).to_string(),
+        AssemblyTypeRef::Custom(ty) => type_name(&ty.borrow()).to_string(),
+        AssemblyTypeRef::Instance(ty, args) => {
+            let args = args
+                .iter()
+                .map(|arg| type_ref(arg, uses))
+                .collect::<Vec<_>>();
+            format!("{}<{}>", type_name(&ty.borrow()), args.join(", "))
+        }
+        AssemblyTypeRef::Param(name) => name.clone(),
+        AssemblyTypeRef::Array(item) => {
+            uses.usings.insert("System.Collections.Generic");
+            format!("List<{}>", type_ref(item, uses))
+        }
+        AssemblyTypeRef::Optional(item) => {
+            let item = type_ref(item, uses);
+            if item.ends_with('?') {
+                item
+            } else {
+                format!("{item}?")
+            }
+        }
+        AssemblyTypeRef::Map(key, value) => {
+            uses.usings.insert("System.Collections.Generic");
+            let key_type = type_ref(key, uses);
+            let value = type_ref(value, uses);
+            match **key {
+                // Written as JSON object keys by System.Text.Json.
+                AssemblyTypeRef::Builtin(
+                    BuiltinType::String | BuiltinType::Integer | BuiltinType::Float,
+                ) => format!("Dictionary<{key_type}, {value}>"),
+                _ => {
+                    uses.pairs = true;
+                    format!("Pairs<{key_type}, {value}>")
+                }
+            }
+        }
+    }
+}
+
+fn builtin_type(builtin: BuiltinType) -> &'static str {
+    match builtin {
+        BuiltinType::Integer => "long",
+        BuiltinType::Float => "double",
+        BuiltinType::String => "string",
+        BuiltinType::Boolean => "bool",
+    }
+}
+
+fn string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn value(value: &SchemeValue, ty_ref: &AssemblyTypeRef, uses: &mut Uses) -> String {
+    match (value, ty_ref) {
+        (SchemeValue::Null, _) => "null".to_string(),
+        (value, AssemblyTypeRef::Optional(item)) => self::value(value, item, uses),
+        (SchemeValue::Integer(n), _) => n.to_string(),
+        (SchemeValue::Float(x), _) => format!("{x:?}"),
+        (SchemeValue::String(s), _) => string(s),
+        (SchemeValue::Boolean(b), _) => b.to_string(),
+        (SchemeValue::Array(items), AssemblyTypeRef::Array(item)) => {
+            let items = items
+                .iter()
+                .map(|v| self::value(v, item, uses))
+                .collect::<Vec<_>>();
+            let ty = type_ref(ty_ref, uses);
+            if items.is_empty() {
+                format!("new {ty}()")
+            } else {
+                format!("new {ty} {{ {} }}", items.join(", "))
+            }
+        }
+        (SchemeValue::Map(entries), AssemblyTypeRef::Map(key, item)) => {
+            let entries = entries
+                .iter()
+                .map(|(k, v)| {
+                    format!(
+                        "[{}] = {}",
+                        self::value(k, key, uses),
+                        self::value(v, item, uses)
+                    )
+                })
+                .collect::<Vec<_>>();
+            let ty = type_ref(ty_ref, uses);
+            if entries.is_empty() {
+                format!("new {ty}()")
+            } else {
+                format!("new {ty} {{ {} }}", entries.join(", "))
+            }
+        }
+        _ => unreachable!("default values are checked against the field type"),
+    }
+}
@@ -0,0 +1,664 @@
+//! Go backend: a struct per type with `encoding/json` tags, and a `NewX` constructor setting the
+//! default values of types that have some. A schema is a namespace, generated as its own package.
+//! Constraints are not checked.
+
+use super::{contains_inline, deprecation, is_map_key, renamed, snake_case, uses_kind};
+use crate::schema::assembly::*;
+use crate::schema::module::{BuiltinType, SchemeAnnotation, SchemeConst, SchemeValue};
+
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+const KEYWORDS: &[&str] = &[
+    "break",
+    "case",
+    "chan",
+    "const",
+    "continue",
+    "default",
+    "defer",
+    "else",
+    "fallthrough",
+    "for",
+    "func",
+    "go",
+    "goto",
+    "if",
+    "import",
+    "interface",
+    "map",
+    "package",
+    "range",
+    "return",
+    "select",
+    "struct",
+    "switch",
+    "type",
+    "var",
+];
+
+/// Words written in capitals in Go names, e.g. `userId` -> `UserID`.
+const INITIALISMS: &[&str] = &[
+    "API", "HTML", "HTTP", "HTTPS", "ID", "IP", "JSON", "SQL", "UI", "URI", "URL", "UUID", "XML",
+];
+
+/// Package used for schemas read from stdin, or whose file name has no letters.
+pub const DEFAULT_PACKAGE: &str = "schema";
+
+const SYNC_PRELUDE: &str = r#"
+// SyncValue holds the current value of a `sync` field, kept up to date by the transport.
+type SyncValue[T any] struct {
+    mu        sync.Mutex
+    value     T
+    listeners []chan T
+}
+
+// NewSyncValue returns a SyncValue holding value.
+func NewSyncValue[T any](value T) *SyncValue[T] {
+    return &SyncValue[T]{value: value}
+}
+
+// Get returns the current value.
+func (s *SyncValue[T]) Get() T {
+    s.mu.Lock()
+    defer s.mu.Unlock()
+    return s.value
+}
+
+// Set replaces the value and notifies the subscribers, called by the transport.
+func (s *SyncValue[T]) Set(value T) {
+    s.mu.Lock()
+    defer s.mu.Unlock()
+    s.value = value
+    for _, listener := range s.listeners {
+        // Subscribers that fall behind only get the latest value.
+        select {
+        case <-listener:
+        default:
+        }
+        listener <- value
+    }
+}
+
+// Subscribe returns a channel receiving every new value.
+func (s *SyncValue[T]) Subscribe() <-chan T {
+    s.mu.Lock()
+    defer s.mu.Unlock()
+    listener := make(chan T, 1)
+    s.listeners = append(s.listeners, listener)
+    return listener
+}
+"#;
+
+const PAIRS_PRELUDE: &str = r#"
+// Pairs is a map sent as an array of [key, value] pairs, its keys not being strings or numbers.
+type Pairs[K comparable, V any] map[K]V
+
+func (m Pairs[K, V]) MarshalJSON() ([]byte, error) {
+    pairs := make([][2]any, 0, len(m))
+    for key, value := range m {
+        pairs = append(pairs, [2]any{key, value})
+    }
+    return json.Marshal(pairs)
+}
+
+func (m *Pairs[K, V]) UnmarshalJSON(data []byte) error {
+    var pairs [][2]json.RawMessage
+    if err := json.Unmarshal(data, &pairs); err != nil {
+        return err
+    }
+    *m = make(Pairs[K, V], len(pairs))
+    for _, pair := range pairs {
+        var key K
+        var value V
+        if err := json.Unmarshal(pair[0], &key); err != nil {
+            return err
+        }
+        if err := json.Unmarshal(pair[1], &value); err != nil {
+            return err
+        }
+        (*m)[key] = value
+    }
+    return nil
+}
+"#;
+
+const FLOAT_MAP_PRELUDE: &str = r#"
+// FloatMap is a map with float keys, sent as an object. encoding/json only supports string and
+// integer keys.
+type FloatMap[V any] map[float64]V
+
+func (m FloatMap[V]) MarshalJSON() ([]byte, error) {
+    object := make(map[string]V, len(m))
+    for key, value := range m {
+        object[strconv.FormatFloat(key, 'g', -1, 64)] = value
+    }
+    return json.Marshal(object)
+}
+
+func (m *FloatMap[V]) UnmarshalJSON(data []byte) error {
+    var object map[string]V
+    if err := json.Unmarshal(data, &object); err != nil {
+        return err
+    }
+    *m = make(FloatMap[V], len(object))
+    for key, value := range object {
+        number, err := strconv.ParseFloat(key, 64)
+        if err != nil {
+            return err
+        }
+        (*m)[number] = value
+    }
+    return nil
+}
+"#;
+
+const PTR_PRELUDE: &str = r#"
+func ptr[T any](value T) *T {
+    return &value
+}
+"#;
+
+/// Packages and helpers used by the generated code, collected while generating it.
+#[derive(Default)]
+struct Uses {
+    imports: BTreeSet<&'static str>,
+    pairs: bool,
+    float_map: bool,
+    ptr: bool,
+}
+
+pub fn generate(assembly: &Assembly, package: &str) -> String {
+    let mut uses = Uses::default();
+    let mut body = String::new();
+    if !assembly.consts.is_empty() {
+        body.push('\n');
+        for constant in &assembly.consts {
+            generate_const(&mut body, constant);
+        }
+    }
+    for ty in &assembly.types {
+        let ty = ty.borrow();
+        body.push('\n');
+        generate_struct(&mut body, &ty, &mut uses);
+        generate_constructor(&mut body, &ty, &mut uses);
+    }
+    for service in &assembly.services {
+        body.push('\n');
+        generate_service(&mut body, service, &mut uses);
+    }
+
+    let mut preludes = String::new();
+    if uses_kind(assembly, AssemblyFieldTypeKind::Sync) {
+        uses.imports.insert("sync");
+        preludes.push_str(SYNC_PRELUDE);
+    }
+    if uses.pairs {
+        uses.imports.insert("encoding/json");
+        preludes.push_str(PAIRS_PRELUDE);
+    }
+    if uses.float_map {
+        uses.imports.extend(["encoding/json", "strconv"]);
+        preludes.push_str(FLOAT_MAP_PRELUDE);
+    }
+    if uses.ptr {
+        preludes.push_str(PTR_PRELUDE);
+    }
+    let mut out = format!("// Code generated by rgc. DO NOT EDIT.\n\npackage {package}\n");
+    match uses.imports.len() {
+        0 => {}
+        1 => writeln!(out, "\nimport {:?}", uses.imports.first().unwrap()).unwrap(),
+        _ => {
+            out.push_str("\nimport (\n");
+            for import in &uses.imports {
+                writeln!(out, "\t{import:?}").unwrap();
+            }
+            out.push_str(")\n");
+        }
+    }
+    out.push_str(&tabs(&preludes));
+    out.push_str(&body);
+    out
+}
+
+/// Package name for a schema file named `file_stem`: Go package names are lowercase letters and
+/// digits, starting with a letter.
+pub fn package_name(file_stem: &str) -> String {
+    let name = file_stem
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .collect::<String>()
+        .to_ascii_lowercase();
+    match name.chars().next() {
+        Some(c) if c.is_ascii_alphabetic() => name,
+        _ => DEFAULT_PACKAGE.to_string(),
+    }
+}
+
+/// Indents the preludes, written with four spaces, with tabs like gofmt does.
+fn tabs(code: &str) -> String {
+    let mut out = String::with_capacity(code.len());
+    for line in code.lines() {
+        let trimmed = line.trim_start_matches(' ');
+        let depth = (line.len() - trimmed.len()) / 4;
+        out.push_str(&"\t".repeat(depth));
+        out.push_str(trimmed);
+        out.push('\n');
+    }
+    out
+}
+
+/// A line of a struct, whose cells gofmt aligns in columns.
+enum Line {
+    Blank,
+    Comment(String),
+    Cells(Vec<String>),
+}
+
+/// Writes `lines` aligned like gofmt does: every cell but the last one of a line is padded to the
+/// width of the widest cell of its column, among the adjacent lines that have one.
+fn write_aligned(out: &mut String, indent: &str, lines: &[Line]) {
+    let cells = |line: &Line| match line {
+        Line::Cells(cells) => cells.len(),
+        _ => 0,
+    };
+    let mut widths = lines
+        .iter()
+        .map(|line| vec![0; cells(line).saturating_sub(1)])
+        .collect::<Vec<_>>();
+    let columns = lines.iter().map(cells).max().unwrap_or(0);
+    for column in 0..columns {
+        let mut start = 0;
+        while start < lines.len() {
+            let end = (start..lines.len())
+                .find(|&i| cells(&lines[i]) <= column + 1)
+                .unwrap_or(lines.len());
+            if end == start {
+                start += 1;
+                continue;
+            }
+            let width = lines[start..end]
+                .iter()
+                .map(|line| match line {
+                    Line::Cells(cells) => cells[column].chars().count(),
+                    _ => 0,
+                })
+                .max()
+                .unwrap_or(0);
+            for line_widths in &mut widths[start..end] {
+                line_widths[column] = width + 1;
+            }
+            start = end;
+        }
+    }
+    for (line, widths) in lines.iter().zip(widths) {
+        match line {
+            Line::Blank => out.push('\n'),
+            Line::Comment(comment) => writeln!(out, "{indent}// {comment}").unwrap(),
+            Line::Cells(cells) => {
+                out.push_str(indent);
+                for (cell, width) in cells.iter().zip(widths) {
+                    write!(out, "{cell:width$}").unwrap();
+                }
+                out.push_str(cells.last().unwrap());
+                out.push('\n');
+            }
+        }
+    }
+}
+
+fn generate_const(out: &mut String, constant: &SchemeConst) {
+    let name = renamed(&constant.annotations, "go")
+        .map_or_else(|| go_name(&constant.name), str::to_string);
+    deprecated_comment(out, "", &constant.annotations);
+    writeln!(
+        out,
+        "const {name} {} = {}",
+        builtin_type(constant.ty),
+        value(
+            &constant.value,
+            &AssemblyTypeRef::Builtin(constant.ty),
+            &mut Uses::default()
+        )
+    )
+    .unwrap();
+}
+
+fn generate_struct(out: &mut String, ty: &AssemblyType, uses: &mut Uses) {
+    deprecated_comment(out, "", &ty.annotations);
+    writeln!(out, "type {}{} struct {{", type_name(ty), generics(ty)).unwrap();
+    let mut lines = Vec::new();
+    let inherited = match &ty.base {
+        Some(base) => {
+            // Fields of embedded structs are promoted, in Go and in JSON.
+            let base = base.borrow();
+            lines.push(Line::Cells(vec![type_name(&base)]));
+            base.fields.len()
+        }
+        None => 0,
+    };
+    for field in &ty.fields[inherited..] {
+        // Blank lines keep the embedded struct and the comments out of the aligned columns.
+        let after_base = lines.len() == 1 && inherited > 0;
+        if let Some(reason) = deprecation(&field.annotations) {
+            if !lines.is_empty() {
+                lines.push(Line::Blank);
+            }
+            lines.push(Line::Comment(deprecated_text(reason)));
+        } else if after_base {
+            lines.push(Line::Blank);
+        }
+        let mut cells = vec![
+            field_name(field),
+            field_type(&ty.name, &field.ty, field.optional, uses),
+        ];
+        // Channels and synced values only exist at runtime, they are not sent as JSON.
+        if field.ty.kind == AssemblyFieldTypeKind::Normal {
+            let omitempty = if field.optional { ",omitempty" } else { "" };
+            cells.push(format!("`json:\"{}{omitempty}\"`", field.name));
+        } else {
+            cells.push("`json:\"-\"`".to_string());
+        }
+        lines.push(Line::Cells(cells));
+    }
+    write_aligned(out, "\t", &lines);
+    out.push_str("}\n");
+}
+
+/// `NewX` returns an `X` holding the default values of its fields. Decoding JSON into it keeps
+/// them for the absent fields.
+fn generate_constructor(out: &mut String, ty: &AssemblyType, uses: &mut Uses) {
+    let defaults = ty
+        .fields
+        .iter()
+        .filter_map(|field| Some((field, field.default.as_ref()?)))
+        .collect::<Vec<_>>();
+    if defaults.is_empty() {
+        return;
+    }
+    let type_name = type_name(ty);
+    let args = if ty.params.is_empty() {
+        String::new()
+    } else {
+        format!("[{}]", ty.params.join(", "))
+    };
+    writeln!(
+        out,
+        "\n// New{type_name} returns a {type_name} holding the default values of its fields.\n\
+         func New{type_name}{}() {type_name}{args} {{\n\
+         \tvar value {type_name}{args}",
+        generics(ty)
+    )
+    .unwrap();
+    for (field, default) in defaults {
+        let default = match field.ty.kind {
+            AssemblyFieldTypeKind::Sync => format!(
+                "NewSyncValue[{}]({})",
+                type_ref(&ty.name, &field.ty.ty_ref, false, uses),
+                value(default, &field.ty.ty_ref, uses)
+            ),
+            _ => value(default, &field.ty.ty_ref, uses),
+        };
+        writeln!(out, "\tvalue.{} = {default}", field_name(field)).unwrap();
+    }
+    out.push_str("\treturn value\n}\n");
+}
+
+/// Servers implement the interface of each service, one method per remote function.
+fn generate_service(out: &mut String, service: &AssemblyService, uses: &mut Uses) {
+    uses.imports.insert("context");
+    deprecated_comment(out, "", &service.annotations);
+    writeln!(
+        out,
+        "type {} interface {{",
+        renamed(&service.annotations, "go").map_or_else(|| exported(&service.name), str::to_string)
+    )
+    .unwrap();
+    for function in &service.functions {
+        deprecated_comment(out, "\t", &function.annotations);
+        let params = function
+            .params
+            .iter()
+            .map(|param| {
+                format!(
+                    ", {} {}",
+                    escape(param.name.clone()),
+                    type_ref("", &param.ty, false, uses)
+                )
+            })
+            .collect::<String>();
+        let returns = match &function.returns {
+            Some(ty) => format!("({}, error)", field_type("", ty, false, uses)),
+            None => "error".to_string(),
+        };
+        writeln!(
+            out,
+            "\t{}(ctx context.Context{params}) {returns}",
+            renamed(&function.annotations, "go")
+                .map_or_else(|| go_name(&function.name), str::to_string)
+        )
+        .unwrap();
+    }
+    out.push_str("}\n");
+}
+
+fn deprecated_comment(out: &mut String, indent: &str, annotations: &[SchemeAnnotation]) {
+    if let Some(reason) = deprecation(annotations) {
+        writeln!(out, "{indent}// {}", deprecated_text(reason)).unwrap();
+    }
+}
+
+/// Deprecation notice recognized by Go tools.
+fn deprecated_text(reason: &str) -> String {
+    match reason {
+        "" => "Deprecated: do not use.".to_string(),
+        reason => format!("Deprecated: {reason}"),
+    }
+}
+
+/// Exported names start with a capital letter.
+fn exported(name: &str) -> String {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// `displayName` -> `DisplayName`, `userId` -> `UserID`.
+fn go_name(name: &str) -> String {
+    snake_case(name)
+        .split('_')
+        .map(|word| {
+            let upper = word.to_uppercase();
+            if INITIALISMS.contains(&upper.as_str()) {
+                upper
+            } else {
+                exported(word)
+            }
+        })
+        .collect()
+}
+
+/// Names that are Go keywords get a trailing `_`.
+fn escape(name: String) -> String {
+    if KEYWORDS.contains(&name.as_str()) {
+        format!("{name}_")
+    } else {
+        name
+    }
+}
+
+fn type_name(ty: &AssemblyType) -> String {
+    renamed(&ty.annotations, "go").map_or_else(|| exported(&ty.name), str::to_string)
+}
+
+/// `[K comparable, V any]` for generic types, empty otherwise. Parameters used as map keys must be
+/// comparable.
+fn generics(ty: &AssemblyType) -> String {
+    if ty.params.is_empty() {
+        return String::new();
+    }
+    let params = ty
+        .params
+        .iter()
+        .map(|param| {
+            let is_key = ty
+                .fields
+                .iter()
+                .any(|field| is_map_key(&field.ty.ty_ref, param));
+            format!("{param} {}", if is_key { "comparable" } else { "any" })
+        })
+        .collect::<Vec<_>>();
+    format!("[{}]", params.join(", "))
+}
+
+fn field_name(field: &AssemblyField) -> String {
+    renamed(&field.annotations, "go").map_or_else(|| go_name(&field.name), str::to_string)
+}
+
+/// Fields that may be absent are pointers, `nil` when they are. `stream` and `sync` values can
+/// already be `nil`.
+fn field_type(owner: &str, ty: &AssemblyFieldType, optional: bool, uses: &mut Uses) -> String {
+    match ty.kind {
+        AssemblyFieldTypeKind::Normal => {
+            let inner = type_ref(owner, &ty.ty_ref, true, uses);
+            if optional && !matches!(ty.ty_ref, AssemblyTypeRef::Optional(_)) {
+                format!("*{}", inner.trim_start_matches('*'))
+            } else {
+                inner
+            }
+        }
+        AssemblyFieldTypeKind::Stream => {
+            format!("<-chan {}", type_ref(owner, &ty.ty_ref, false, uses))
+        }
+        AssemblyFieldTypeKind::Sync => {
+            format!("*SyncValue[{}]", type_ref(owner, &ty.ty_ref, false, uses))
+        }
+    }
+}
+
+/// `inline` is true while the value would be stored inside `owner` itself, where a reference back
+/// to `owner` needs a pointer to keep the struct finite.
+fn type_ref(owner: &str, ty_ref: &AssemblyTypeRef, inline: bool, uses: &mut Uses) -> String {
+    match ty_ref {
+        AssemblyTypeRef::Builtin(builtin) => builtin_type(*builtin).to_string(),
+        AssemblyTypeRef::Custom(ty) | AssemblyTypeRef::Instance(ty, _) => {
+            let mut name = type_name(&ty.borrow());
+            if let AssemblyTypeRef::Instance(_, args) = ty_ref {
+                let args = args
+                    .iter()
+                    .map(|arg| type_ref(owner, arg, false, uses))
+                    .collect::<Vec<_>>();
+                write!(name, "[{}]", args.join(", ")).unwrap();
+            }
+            if inline && contains_inline(ty_ref, owner) {
+                format!("*{name}")
+            } else {
+                name
+            }
+        }
+        AssemblyTypeRef::Param(name) => name.clone(),
+        AssemblyTypeRef::Array(item) => format!("[]{}", type_ref(owner, item, false, uses)),
+        AssemblyTypeRef::Optional(item) => {
+            let item = type_ref(owner, item, inline, uses);
+            // Cyclic references are pointers already.
+            if item.starts_with('*') {
+                item
+            } else {
+                format!("*{item}")
+            }
+        }
+        AssemblyTypeRef::Map(key, value) => {
+            let value = type_ref(owner, value, false, uses);
+            match &**key {
+                AssemblyTypeRef::Builtin(BuiltinType::String | BuiltinType::Integer) => {
+                    format!("map[{}]{value}", type_ref(owner, key, false, uses))
+                }
+                AssemblyTypeRef::Builtin(BuiltinType::Float) => {
+                    uses.float_map = true;
+                    format!("FloatMap[{value}]")
+                }
+                key => {
+                    uses.pairs = true;
+                    format!("Pairs[{}, {value}]", type_ref(owner, key, false, uses))
+                }
+            }
+        }
+    }
+}
+
+fn builtin_type(builtin: BuiltinType) -> &'static str {
+    match builtin {
+        BuiltinType::Integer => "int64",
+        BuiltinType::Float => "float64",
+        BuiltinType::String => "string",
+        BuiltinType::Boolean => "bool",
+    }
+}
+
+/// Interpreted string literal.
+fn string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn value(value: &SchemeValue, ty_ref: &AssemblyTypeRef, uses: &mut Uses) -> String {
+    match (value, ty_ref) {
+        (SchemeValue::Null, _) => "nil".to_string(),
+        (value, AssemblyTypeRef::Optional(item)) => {
+            uses.ptr = true;
+            format!(
+                "ptr[{}]({})",
+                type_ref("", item, false, uses),
+                self::value(value, item, uses)
+            )
+        }
+        (SchemeValue::Integer(n), _) => n.to_string(),
+        (SchemeValue::Float(x), _) => format!("{x:?}"),
+        (SchemeValue::String(s), _) => string(s),
+        (SchemeValue::Boolean(b), _) => b.to_string(),
+        (SchemeValue::Array(items), AssemblyTypeRef::Array(item)) => {
+            let items = items
+                .iter()
+                .map(|v| self::value(v, item, uses))
+                .collect::<Vec<_>>();
+            format!(
+                "{}{{{}}}",
+                type_ref("", ty_ref, false, uses),
+                items.join(", ")
+            )
+        }
+        (SchemeValue::Map(entries), AssemblyTypeRef::Map(key, item)) => {
+            let entries = entries
+                .iter()
+                .map(|(k, v)| {
+                    format!(
+                        "{}: {}",
+                        self::value(k, key, uses),
+                        self::value(v, item, uses)
+                    )
+                })
+                .collect::<Vec<_>>();
+            format!(
+                "{}{{{}}}",
+                type_ref("", ty_ref, false, uses),
+                entries.join(", ")
+            )
+        }
+        _ => unreachable!("default values are checked against the field type"),
+    }
+}
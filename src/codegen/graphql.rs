@@ -0,0 +1,319 @@
+//! GraphQL backend: SDL with an object type per type, the remote functions being the fields of
+//! `Query`, or of `Subscription` when they return `stream` or `sync` values. Fields are non-null
+//! unless they are `Optional` or may be absent. GraphQL has no generics and no maps: each
+//! instance of a generic type gets a type of its own, e.g. `PageUser` for `Page<User>`, and maps
+//! are lists of `key`/`value` entries. Arguments get input types, named after the type with an
+//! `Input` suffix. `integer` is the custom scalar `Long`, as GraphQL limits `Int` to 32 bits.
+//! Schemas need a `Query` type, which holds a field that is always null when no function belongs
+//! to it. `stream` and `sync` fields of types have their plain type, and a description telling
+//! their kind, which `rgc import graphql` reads back.
+
+use super::{
+    camel_case, deprecation, flat_name, renamed, substitute, written, written_instance, Names,
+    TypeArgs,
+};
+use crate::json::Json;
+use crate::schema::assembly::*;
+use crate::schema::module::{BuiltinType, SchemeValue};
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::rc::Rc;
+
+/// Scalar of `integer` values, which do not fit in the 32 bits of `Int`.
+const LONG: &str = "Long";
+const LONG_DESCRIPTION: &str = "A 64-bit integer, as `Int` only holds 32 bits.";
+/// Description of the field of `Query` in schemas without queries.
+const NO_QUERIES: &str = "Always null, as the schema has no queries.";
+/// Descriptions of `sync` subscriptions and fields, and of `stream` fields.
+const SYNC: &str = "The current value, then each new value.";
+const STREAM: &str = "Each new value, in turn.";
+
+/// Fails if generated type names clash.
+pub fn generate(assembly: &Assembly) -> Result<String, String> {
+    let mut generator = Generator {
+        types: Vec::new(),
+        pending: Vec::new(),
+        seen: HashSet::new(),
+        names: Names::default(),
+        uses_long: false,
+    };
+    for ty in &assembly.types {
+        if ty.borrow().params.is_empty() {
+            let name = type_name(&ty.borrow());
+            generator.seen.insert(name.clone());
+            let owner = format!("`{}`", ty.borrow().name);
+            generator.names.claim(&name, owner);
+            let object = generator.object(&ty.borrow(), &name, &TypeArgs::new(), false);
+            generator.types.push(object);
+        }
+    }
+    let mut query = String::new();
+    let mut subscription = String::new();
+    for service in &assembly.services {
+        for function in &service.functions {
+            let root = match &function.returns {
+                Some(returns) if returns.kind != AssemblyFieldTypeKind::Normal => &mut subscription,
+                _ => &mut query,
+            };
+            generator.root_field(root, service, function);
+        }
+    }
+    // Types are discovered while generating others, including pending ones.
+    let mut next = 0;
+    while next < generator.pending.len() {
+        let (name, ty, args, input) = generator.pending[next].clone();
+        let object = generator.object(&ty.borrow(), &name, &args, input);
+        generator.types.push(object);
+        next += 1;
+    }
+
+    let mut out = String::from("# Generated by rgc. Do not edit.\n");
+    if generator.uses_long {
+        generator.names.claim(LONG, "the scalar of `integer`");
+        write!(out, "\n{}\nscalar {LONG}\n", string(LONG_DESCRIPTION)).unwrap();
+    }
+    for ty in &generator.types {
+        out.push('\n');
+        out.push_str(ty);
+    }
+    if query.is_empty() {
+        writeln!(query, "  {}\n  _: Boolean", string(NO_QUERIES)).unwrap();
+    }
+    for (name, fields) in [("Query", query), ("Subscription", subscription)] {
+        if !fields.is_empty() {
+            let owner = format!("the {} root type", name.to_lowercase());
+            generator.names.claim(name, owner);
+            write!(out, "\ntype {name} {{\n{fields}}}\n").unwrap();
+        }
+    }
+    generator.names.check()?;
+    Ok(out)
+}
+
+struct Generator {
+    types: Vec<String>,
+    /// Instances of generic types and input types to generate: name, type, arguments of its
+    /// parameters, and whether it is an input type.
+    pending: Vec<(String, Rc<RefCell<AssemblyType>>, TypeArgs, bool)>,
+    /// Names of the types already generated or pending.
+    seen: HashSet<String>,
+    names: Names,
+    /// Whether `integer` values are used, which need the `Long` scalar to be declared.
+    uses_long: bool,
+}
+
+impl Generator {
+    /// An object type, or an input type if `input`.
+    fn object(&mut self, ty: &AssemblyType, name: &str, args: &TypeArgs, input: bool) -> String {
+        let mut out = String::new();
+        let reason = deprecation(&ty.annotations);
+        // Types cannot be marked `@deprecated`.
+        if let Some(reason) = reason {
+            let description = match reason {
+                "" => "Deprecated.".to_string(),
+                reason => format!("Deprecated: {reason}"),
+            };
+            writeln!(out, "{}", string(&description)).unwrap();
+        }
+        let keyword = if input { "input" } else { "type" };
+        writeln!(out, "{keyword} {name} {{").unwrap();
+        for field in &ty.fields {
+            let ty_ref = substitute(&field.ty.ty_ref, args);
+            let mut field_type = self.type_ref(&ty_ref, input);
+            if field.optional {
+                field_type = nullable(field_type);
+            }
+            let name = renamed(&field.annotations, "graphql").unwrap_or(&field.name);
+            match field.ty.kind {
+                AssemblyFieldTypeKind::Normal => {}
+                AssemblyFieldTypeKind::Stream => writeln!(out, "  {}", string(STREAM)).unwrap(),
+                AssemblyFieldTypeKind::Sync => writeln!(out, "  {}", string(SYNC)).unwrap(),
+            }
+            write!(out, "  {name}: {field_type}").unwrap();
+            match &field.default {
+                Some(default) if input => write!(out, " = {}", literal(default)).unwrap(),
+                _ => {}
+            }
+            // Input fields that must be given cannot be deprecated.
+            if !input {
+                out.push_str(&deprecated(deprecation(&field.annotations)));
+            }
+            out.push('\n');
+        }
+        // Types need at least one field.
+        if ty.fields.is_empty() {
+            out.push_str("  \"Always null, as the type has no fields.\"\n  _: Boolean\n");
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    fn root_field(
+        &mut self,
+        out: &mut String,
+        service: &AssemblyService,
+        function: &AssemblyFunction,
+    ) {
+        let name = camel_case(&format!("{}_{}", service.name, function.name));
+        let field_type = match &function.returns {
+            Some(returns) => {
+                if returns.kind == AssemblyFieldTypeKind::Sync {
+                    writeln!(out, "  {}", string(SYNC)).unwrap();
+                }
+                self.type_ref(&returns.ty_ref, false)
+            }
+            None => {
+                writeln!(out, "  {}", string("Returns nothing, always null.")).unwrap();
+                "Boolean".to_string()
+            }
+        };
+        let params = function
+            .params
+            .iter()
+            .map(|param| format!("{}: {}", param.name, self.type_ref(&param.ty, true)))
+            .collect::<Vec<_>>();
+        let params = match params.is_empty() {
+            true => String::new(),
+            false => format!("({})", params.join(", ")),
+        };
+        let reason = deprecation(&function.annotations).or(deprecation(&service.annotations));
+        writeln!(out, "  {name}{params}: {field_type}{}", deprecated(reason)).unwrap();
+    }
+
+    /// GraphQL type of values of `ty_ref`, in input types and arguments if `input`.
+    fn type_ref(&mut self, ty_ref: &AssemblyTypeRef, input: bool) -> String {
+        let named = match ty_ref {
+            AssemblyTypeRef::Optional(item) => return nullable(self.type_ref(item, input)),
+            AssemblyTypeRef::Builtin(builtin) => {
+                self.uses_long |= *builtin == BuiltinType::Integer;
+                builtin_type(*builtin).to_string()
+            }
+            AssemblyTypeRef::Custom(ty) => {
+                let name = type_name(&ty.borrow());
+                match input {
+                    true => self.queue(format!("{name}Input"), ty, TypeArgs::new(), true),
+                    false => {
+                        self.names.claim(&name, format!("`{}`", ty.borrow().name));
+                        name
+                    }
+                }
+            }
+            AssemblyTypeRef::Instance(ty, args) => {
+                let name = flat_name(ty_ref, "graphql");
+                let name = if input { format!("{name}Input") } else { name };
+                let params = ty.borrow().params.clone();
+                let type_args = params.into_iter().zip(args.iter().cloned()).collect();
+                self.queue(name, ty, type_args, input)
+            }
+            AssemblyTypeRef::Array(item) => format!("[{}]", self.type_ref(item, input)),
+            AssemblyTypeRef::Map(key, value) => format!("[{}!]", self.entry(key, value, input)),
+            AssemblyTypeRef::Param(_) => unreachable!("type parameters are substituted"),
+        };
+        format!("{named}!")
+    }
+
+    /// Queues the type `name` to be generated from `ty`, unless it already is.
+    fn queue(
+        &mut self,
+        name: String,
+        ty: &Rc<RefCell<AssemblyType>>,
+        args: TypeArgs,
+        input: bool,
+    ) -> String {
+        let written = written_instance(&ty.borrow(), &args);
+        let owner = match input {
+            true => format!("the input type of `{written}`"),
+            false => format!("`{written}`"),
+        };
+        self.names.claim(&name, owner);
+        if self.seen.insert(name.clone()) {
+            self.pending.push((name.clone(), ty.clone(), args, input));
+        }
+        name
+    }
+
+    /// Type of the entries of maps from `key` to `value`.
+    fn entry(&mut self, key: &AssemblyTypeRef, value: &AssemblyTypeRef, input: bool) -> String {
+        let suffix = if input { "Input" } else { "" };
+        let keyword = if input { "input" } else { "type" };
+        let name = format!(
+            "{}{}Entry{suffix}",
+            flat_name(key, "graphql"),
+            flat_name(value, "graphql")
+        );
+        let map = format!("Map<{}, {}>", written(key), written(value));
+        let owner = match input {
+            true => format!("the input entries of `{map}`"),
+            false => format!("the entries of `{map}`"),
+        };
+        self.names.claim(&name, owner);
+        if self.seen.insert(name.clone()) {
+            let key = self.type_ref(key, input);
+            let value = self.type_ref(value, input);
+            let entry = format!("{keyword} {name} {{\n  key: {key}\n  value: {value}\n}}\n");
+            self.types.push(entry);
+        }
+        name
+    }
+}
+
+fn type_name(ty: &AssemblyType) -> String {
+    renamed(&ty.annotations, "graphql")
+        .unwrap_or(&ty.name)
+        .to_string()
+}
+
+fn builtin_type(builtin: BuiltinType) -> &'static str {
+    match builtin {
+        BuiltinType::Integer => LONG,
+        BuiltinType::Float => "Float",
+        BuiltinType::String => "String",
+        BuiltinType::Boolean => "Boolean",
+    }
+}
+
+/// `ty` without its non-null marker.
+fn nullable(ty: String) -> String {
+    match ty.strip_suffix('!') {
+        Some(ty) => ty.to_string(),
+        None => ty,
+    }
+}
+
+/// ` @deprecated(...)` for deprecated declarations.
+fn deprecated(reason: Option<&str>) -> String {
+    match reason {
+        None => String::new(),
+        Some("") => " @deprecated".to_string(),
+        Some(reason) => format!(" @deprecated(reason: {})", string(reason)),
+    }
+}
+
+/// A string literal, whose escapes are those of JSON.
+fn string(text: &str) -> String {
+    Json::from(text).to_string()
+}
+
+/// A default value. Maps are lists of entries.
+fn literal(value: &SchemeValue) -> String {
+    match value {
+        SchemeValue::Integer(n) => n.to_string(),
+        SchemeValue::Float(x) => format!("{x:?}"),
+        SchemeValue::String(text) => string(text),
+        SchemeValue::Boolean(b) => b.to_string(),
+        SchemeValue::Null => "null".to_string(),
+        SchemeValue::Array(items) => {
+            let items = items.iter().map(literal).collect::<Vec<_>>();
+            format!("[{}]", items.join(", "))
+        }
+        SchemeValue::Map(entries) => {
+            let entries = entries
+                .iter()
+                .map(|(key, value)| format!("{{key: {}, value: {}}}", literal(key), literal(value)))
+                .collect::<Vec<_>>();
+            format!("[{}]", entries.join(", "))
+        }
+    }
+}
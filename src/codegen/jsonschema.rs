@@ -0,0 +1,461 @@
+//! JSON Schema backend: a draft 2020-12 document with a definition per type under `$defs`, so
+//! that payloads can be validated without rgc. Custom types are linked with `$ref`, which also
+//! covers recursive types. Each instance of a generic type gets a definition of its own, e.g.
+//! `Page<User>`, and the generic definition accepts any value for the type parameters. `stream`
+//! and `sync` fields are put together by the transport, so they are left out of the definitions.
+
+use super::{constraints, deprecation, Constraint};
+use crate::json::Json;
+use crate::schema::assembly::*;
+use crate::schema::module::{BuiltinType, SchemeValue};
+use crate::schema::regex;
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::rc::Rc;
+
+const DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// Instances nested deeper than this, e.g. through generic types referencing each other with
+/// growing arguments, link to the generic definition instead.
+const MAX_INSTANCE_DEPTH: usize = 8;
+
+pub fn generate(assembly: &Assembly) -> String {
+    let definitions = Definitions::new(assembly, Dialect::JsonSchema);
+    Json::object([
+        ("$schema", Json::from(DIALECT)),
+        ("$defs", Json::Object(definitions.finish())),
+    ])
+    .pretty()
+}
+
+/// Documents embedding the definitions, which decides where they are and how they are named.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Dialect {
+    /// Under `$defs`, instances of generic types being named as in rgc, e.g. `Page<User>`.
+    JsonSchema,
+    /// Under `components/schemas`, whose names cannot have `<`, e.g. `Page_User`.
+    OpenApi,
+}
+
+impl Dialect {
+    fn key(self, name: &str) -> String {
+        match self {
+            Dialect::JsonSchema => name.to_string(),
+            Dialect::OpenApi => name
+                .chars()
+                .filter_map(|c| match c {
+                    '<' | ',' => Some('_'),
+                    ' ' | '>' => None,
+                    c if c.is_ascii_alphanumeric() || "-._".contains(c) => Some(c),
+                    _ => Some('_'),
+                })
+                .collect(),
+        }
+    }
+
+    /// `$ref` to the definition `name`, escaped as a URI fragment.
+    fn reference(self, name: &str) -> Json {
+        let mut pointer = String::from(match self {
+            Dialect::JsonSchema => "#/$defs/",
+            Dialect::OpenApi => "#/components/schemas/",
+        });
+        for byte in self.key(name).bytes() {
+            match byte {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' => {
+                    pointer.push(byte as char)
+                }
+                byte => pointer.push_str(&format!("%{byte:02X}")),
+            }
+        }
+        Json::object([("$ref", Json::from(pointer))])
+    }
+}
+
+/// Definitions of the types of an assembly, and of the instances of generic types they use.
+pub(crate) struct Definitions {
+    dialect: Dialect,
+    defs: Vec<(String, Json)>,
+    instances: Vec<Instance>,
+    /// Names of the instances already in `instances`.
+    seen: HashSet<String>,
+}
+
+impl Definitions {
+    pub(crate) fn new(assembly: &Assembly, dialect: Dialect) -> Self {
+        let mut definitions = Definitions {
+            dialect,
+            defs: Vec::new(),
+            instances: Vec::new(),
+            seen: HashSet::new(),
+        };
+        for ty in &assembly.types {
+            let ty = ty.borrow();
+            let args = ty
+                .params
+                .iter()
+                .map(|param| Arg {
+                    name: param.clone(),
+                    schema: Json::Object(vec![]),
+                })
+                .collect::<Vec<_>>();
+            let scope = Scope {
+                owner: &ty.name,
+                instance: None,
+                params: &ty.params,
+                args: &args,
+                depth: 0,
+            };
+            let schema = definitions.type_schema(&ty, &scope);
+            definitions.defs.push((dialect.key(&ty.name), schema));
+        }
+        definitions
+    }
+
+    /// Schema of the values of `ty_ref`, written outside of any type.
+    pub(crate) fn schema(&mut self, ty_ref: &AssemblyTypeRef) -> Json {
+        let scope = Scope {
+            owner: "",
+            instance: None,
+            params: &[],
+            args: &[],
+            depth: 0,
+        };
+        self.ref_schema(ty_ref, &scope)
+    }
+
+    /// The definitions by name, including the instances used so far.
+    pub(crate) fn finish(mut self) -> Vec<(String, Json)> {
+        // Instances are discovered while generating definitions, including other instances.
+        let mut next = 0;
+        while next < self.instances.len() {
+            let Instance {
+                name,
+                ty,
+                args,
+                depth,
+            } = self.instances[next].clone();
+            let ty = ty.borrow();
+            let scope = Scope {
+                owner: &ty.name,
+                instance: Some(&name),
+                params: &ty.params,
+                args: &args,
+                depth,
+            };
+            let schema = self.type_schema(&ty, &scope);
+            self.defs.push((self.dialect.key(&name), schema));
+            next += 1;
+        }
+        self.defs
+    }
+}
+
+/// A generic type given its type arguments, whose definition is generated under `name`.
+#[derive(Clone)]
+struct Instance {
+    name: String,
+    ty: Rc<RefCell<AssemblyType>>,
+    args: Vec<Arg>,
+    depth: usize,
+}
+
+/// A type argument, spelled as in rgc schemas for the names of instances.
+#[derive(Clone)]
+struct Arg {
+    name: String,
+    schema: Json,
+}
+
+/// The definition being generated. Type parameters stand for `args`, which accept any value in
+/// the definition of a generic type itself.
+struct Scope<'a> {
+    owner: &'a str,
+    /// Name of the instance of `owner`, `None` for the definition of the type itself.
+    instance: Option<&'a str>,
+    params: &'a [String],
+    args: &'a [Arg],
+    depth: usize,
+}
+
+impl Scope<'_> {
+    fn arg(&self, param: &str) -> &Arg {
+        let index = self.params.iter().position(|p| p == param);
+        &self.args[index.expect("type parameters are checked")]
+    }
+}
+
+impl Definitions {
+    /// `$ref` to the definition `name`.
+    fn reference(&self, name: &str) -> Json {
+        self.dialect.reference(name)
+    }
+
+    fn type_schema(&mut self, ty: &AssemblyType, scope: &Scope) -> Json {
+        let mut schema = Json::object([("type", Json::from("object"))]);
+        if let Some(reason) = deprecation(&ty.annotations) {
+            if !reason.is_empty() {
+                schema.insert("description", Json::from(reason));
+            }
+            schema.insert("deprecated", Json::from(true));
+        }
+        let fields = ty
+            .fields
+            .iter()
+            .filter(|field| field.ty.kind == AssemblyFieldTypeKind::Normal)
+            .collect::<Vec<_>>();
+        if !fields.is_empty() {
+            let properties = fields
+                .iter()
+                .map(|field| (field.name.clone(), self.field_schema(field, scope)))
+                .collect();
+            schema.insert("properties", Json::Object(properties));
+        }
+        // Fields that may be absent or have a default value can be left out of payloads.
+        let required = fields
+            .iter()
+            .filter(|field| !field.optional && field.default.is_none())
+            .map(|field| Json::from(field.name.as_str()))
+            .collect::<Vec<_>>();
+        if !required.is_empty() {
+            schema.insert("required", Json::Array(required));
+        }
+        schema
+    }
+
+    fn field_schema(&mut self, field: &AssemblyField, scope: &Scope) -> Json {
+        let mut schema = self.constrained(&field.ty.ty_ref, field, scope);
+        if let Some(default) = &field.default {
+            schema.insert("default", value(default, &field.ty.ty_ref));
+        }
+        if let Some(reason) = deprecation(&field.annotations) {
+            if !reason.is_empty() {
+                schema.insert("description", Json::from(reason));
+            }
+            schema.insert("deprecated", Json::from(true));
+        }
+        schema
+    }
+
+    /// Schema of `ty_ref` with the constraints of `field`, which apply to present values.
+    fn constrained(
+        &mut self,
+        ty_ref: &AssemblyTypeRef,
+        field: &AssemblyField,
+        scope: &Scope,
+    ) -> Json {
+        if let AssemblyTypeRef::Optional(item) = ty_ref {
+            let item = self.constrained(item, field, scope);
+            return nullable(item);
+        }
+        let mut schema = self.ref_schema(ty_ref, scope);
+        for constraint in constraints(field) {
+            match constraint {
+                Constraint::Range { min, max } => {
+                    if let Some(min) = min {
+                        schema.insert("minimum", number(min));
+                    }
+                    if let Some(max) = max {
+                        schema.insert("maximum", number(max));
+                    }
+                }
+                Constraint::Pattern(regex) => {
+                    schema.insert("pattern", Json::from(regex::javascript(regex)))
+                }
+                Constraint::MinLen(len) => schema.insert("minLength", Json::from(len)),
+                Constraint::MaxLen(len) => schema.insert("maxLength", Json::from(len)),
+                Constraint::MinItems(count) => schema.insert("minItems", Json::from(count)),
+                Constraint::MaxItems(count) => schema.insert("maxItems", Json::from(count)),
+            }
+        }
+        schema
+    }
+
+    fn ref_schema(&mut self, ty_ref: &AssemblyTypeRef, scope: &Scope) -> Json {
+        match ty_ref {
+            AssemblyTypeRef::Builtin(builtin) => {
+                Json::object([("type", Json::from(builtin_type(*builtin)))])
+            }
+            AssemblyTypeRef::Custom(ty) => self.reference(&ty.borrow().name),
+            AssemblyTypeRef::Instance(ty, args) => {
+                let generic = ty.borrow().name.clone();
+                // The arguments are not known in the definition of a generic type.
+                let unknown = scope.instance.is_none() && args.iter().any(mentions_param);
+                if unknown {
+                    return self.reference(&generic);
+                }
+                let args = args
+                    .iter()
+                    .map(|arg| Arg {
+                        name: type_name(arg, scope),
+                        schema: self.ref_schema(arg, scope),
+                    })
+                    .collect::<Vec<_>>();
+                let names = args.iter().map(|arg| arg.name.as_str());
+                let name = format!("{generic}<{}>", names.collect::<Vec<_>>().join(", "));
+                let nested = generic == scope.owner && scope.instance != Some(name.as_str());
+                if nested || scope.depth >= MAX_INSTANCE_DEPTH {
+                    return self.reference(&generic);
+                }
+                if self.seen.insert(name.clone()) {
+                    self.instances.push(Instance {
+                        name: name.clone(),
+                        ty: ty.clone(),
+                        args,
+                        depth: scope.depth + 1,
+                    });
+                }
+                self.reference(&name)
+            }
+            AssemblyTypeRef::Param(param) => scope.arg(param).schema.clone(),
+            AssemblyTypeRef::Array(item) => Json::object([
+                ("type", Json::from("array")),
+                ("items", self.ref_schema(item, scope)),
+            ]),
+            AssemblyTypeRef::Optional(item) => {
+                let item = self.ref_schema(item, scope);
+                nullable(item)
+            }
+            AssemblyTypeRef::Map(key, value) if is_object_key(key) => {
+                let mut schema = Json::object([("type", Json::from("object"))]);
+                if let AssemblyTypeRef::Builtin(BuiltinType::Integer | BuiltinType::Float) = **key {
+                    let pattern = match **key {
+                        AssemblyTypeRef::Builtin(BuiltinType::Integer) => "^-?[0-9]+$",
+                        _ => "^-?[0-9]+(\\.[0-9]+)?([eE][-+]?[0-9]+)?$",
+                    };
+                    let names = Json::object([("pattern", Json::from(pattern))]);
+                    schema.insert("propertyNames", names);
+                }
+                schema.insert("additionalProperties", self.ref_schema(value, scope));
+                schema
+            }
+            // Other maps are sent as arrays of `[key, value]` pairs.
+            AssemblyTypeRef::Map(key, value) => {
+                let pair = Json::object([
+                    ("type", Json::from("array")),
+                    (
+                        "prefixItems",
+                        Json::Array(vec![
+                            self.ref_schema(key, scope),
+                            self.ref_schema(value, scope),
+                        ]),
+                    ),
+                    ("minItems", Json::from(2_i64)),
+                    ("maxItems", Json::from(2_i64)),
+                ]);
+                Json::object([("type", Json::from("array")), ("items", pair)])
+            }
+        }
+    }
+}
+
+/// Whether maps with this key type are sent as JSON objects.
+fn is_object_key(key: &AssemblyTypeRef) -> bool {
+    matches!(
+        key,
+        AssemblyTypeRef::Builtin(BuiltinType::String | BuiltinType::Integer | BuiltinType::Float)
+    )
+}
+
+fn mentions_param(ty_ref: &AssemblyTypeRef) -> bool {
+    match ty_ref {
+        AssemblyTypeRef::Builtin(_) | AssemblyTypeRef::Custom(_) => false,
+        AssemblyTypeRef::Param(_) => true,
+        AssemblyTypeRef::Instance(_, args) => args.iter().any(mentions_param),
+        AssemblyTypeRef::Array(item) | AssemblyTypeRef::Optional(item) => mentions_param(item),
+        AssemblyTypeRef::Map(key, value) => mentions_param(key) || mentions_param(value),
+    }
+}
+
+/// `ty_ref` as written in rgc schemas, with the type parameters replaced by their arguments.
+fn type_name(ty_ref: &AssemblyTypeRef, scope: &Scope) -> String {
+    match ty_ref {
+        AssemblyTypeRef::Builtin(builtin) => builtin_name(*builtin).to_string(),
+        AssemblyTypeRef::Custom(ty) => ty.borrow().name.clone(),
+        AssemblyTypeRef::Instance(ty, args) => {
+            let args = args.iter().map(|arg| type_name(arg, scope));
+            format!(
+                "{}<{}>",
+                ty.borrow().name,
+                args.collect::<Vec<_>>().join(", ")
+            )
+        }
+        AssemblyTypeRef::Param(param) => scope.arg(param).name.clone(),
+        AssemblyTypeRef::Array(item) => format!("Array<{}>", type_name(item, scope)),
+        AssemblyTypeRef::Optional(item) => format!("Optional<{}>", type_name(item, scope)),
+        AssemblyTypeRef::Map(key, value) => {
+            format!(
+                "Map<{}, {}>",
+                type_name(key, scope),
+                type_name(value, scope)
+            )
+        }
+    }
+}
+
+fn builtin_name(builtin: BuiltinType) -> &'static str {
+    match builtin {
+        BuiltinType::Integer => "integer",
+        BuiltinType::Float => "float",
+        BuiltinType::String => "string",
+        BuiltinType::Boolean => "boolean",
+    }
+}
+
+fn builtin_type(builtin: BuiltinType) -> &'static str {
+    match builtin {
+        BuiltinType::Integer => "integer",
+        BuiltinType::Float => "number",
+        BuiltinType::String => "string",
+        BuiltinType::Boolean => "boolean",
+    }
+}
+
+fn nullable(schema: Json) -> Json {
+    let null = Json::object([("type", Json::from("null"))]);
+    Json::object([("anyOf", Json::Array(vec![schema, null]))])
+}
+
+fn number(value: &SchemeValue) -> Json {
+    match value {
+        SchemeValue::Integer(n) => Json::from(*n),
+        SchemeValue::Float(x) => Json::from(*x),
+        _ => unreachable!("bounds are checked against the field type"),
+    }
+}
+
+/// `value` as sent in payloads.
+fn value(value: &SchemeValue, ty_ref: &AssemblyTypeRef) -> Json {
+    match (value, ty_ref) {
+        (SchemeValue::Null, _) => Json::Null,
+        (value, AssemblyTypeRef::Optional(item)) => self::value(value, item),
+        (SchemeValue::Integer(n), _) => Json::from(*n),
+        (SchemeValue::Float(x), _) => Json::from(*x),
+        (SchemeValue::String(s), _) => Json::from(s.as_str()),
+        (SchemeValue::Boolean(b), _) => Json::from(*b),
+        (SchemeValue::Array(items), AssemblyTypeRef::Array(item)) => {
+            Json::Array(items.iter().map(|v| self::value(v, item)).collect())
+        }
+        (SchemeValue::Map(entries), AssemblyTypeRef::Map(key, item)) if is_object_key(key) => {
+            Json::Object(
+                entries
+                    .iter()
+                    .map(|(k, v)| {
+                        let k = match self::value(k, key) {
+                            Json::String(s) => s,
+                            k => k.to_string(),
+                        };
+                        (k, self::value(v, item))
+                    })
+                    .collect(),
+            )
+        }
+        (SchemeValue::Map(entries), AssemblyTypeRef::Map(key, item)) => Json::Array(
+            entries
+                .iter()
+                .map(|(k, v)| Json::Array(vec![self::value(k, key), self::value(v, item)]))
+                .collect(),
+        ),
+        _ => unreachable!("default values are checked against the field type"),
+    }
+}
@@ -0,0 +1,372 @@
+//! Kotlin backend: a data class per type, serialized with kotlinx.serialization. Inherited fields
+//! are copied into the classes extending a type. Services get an interface, implemented by
+//! servers or by client stubs.
+
+use super::{camel_case, deprecation, live_types, renamed};
+use crate::schema::assembly::*;
+use crate::schema::module::{BuiltinType, SchemeAnnotation, SchemeConst, SchemeValue};
+
+use std::collections::{BTreeSet, HashSet};
+use std::fmt::Write;
+
+const KEYWORDS: &[&str] = &[
+    "as",
+    "break",
+    "class",
+    "continue",
+    "do",
+    "else",
+    "false",
+    "for",
+    "fun",
+    "if",
+    "in",
+    "interface",
+    "is",
+    "null",
+    "object",
+    "package",
+    "return",
+    "super",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typealias",
+    "typeof",
+    "val",
+    "var",
+    "when",
+    "while",
+];
+
+const PAIRS_PRELUDE: &str = r#"
+/** Serializes maps as arrays of `[key, value]` pairs, their keys not being strings or numbers. */
+class PairsSerializer<K, V>(
+    private val keySerializer: KSerializer<K>,
+    private val valueSerializer: KSerializer<V>,
+) : KSerializer<Map<K, V>> {
+    private val pairs = ListSerializer(JsonArray.serializer())
+
+    override val descriptor: SerialDescriptor = pairs.descriptor
+
+    override fun serialize(encoder: Encoder, value: Map<K, V>) {
+        val json = (encoder as JsonEncoder).json
+        val items = value.map { (key, item) ->
+            JsonArray(
+                listOf(
+                    json.encodeToJsonElement(keySerializer, key),
+                    json.encodeToJsonElement(valueSerializer, item),
+                ),
+            )
+        }
+        pairs.serialize(encoder, items)
+    }
+
+    override fun deserialize(decoder: Decoder): Map<K, V> {
+        val json = (decoder as JsonDecoder).json
+        return pairs.deserialize(decoder).associate { (key, item) ->
+            val decodedKey = json.decodeFromJsonElement(keySerializer, key)
+            decodedKey to json.decodeFromJsonElement(valueSerializer, item)
+        }
+    }
+}
+"#;
+
+const PAIRS_IMPORTS: &[&str] = &[
+    "kotlinx.serialization.KSerializer",
+    "kotlinx.serialization.builtins.ListSerializer",
+    "kotlinx.serialization.descriptors.SerialDescriptor",
+    "kotlinx.serialization.encoding.Decoder",
+    "kotlinx.serialization.encoding.Encoder",
+    "kotlinx.serialization.json.JsonArray",
+    "kotlinx.serialization.json.JsonDecoder",
+    "kotlinx.serialization.json.JsonEncoder",
+];
+
+/// Imports and helpers used by the generated code, collected while generating it.
+#[derive(Default)]
+struct Uses {
+    imports: BTreeSet<&'static str>,
+    pairs: bool,
+}
+
+pub fn generate(assembly: &Assembly) -> String {
+    let live = live_types(assembly);
+    let mut uses = Uses::default();
+    let mut body = String::new();
+    if !assembly.consts.is_empty() {
+        body.push('\n');
+        for constant in &assembly.consts {
+            generate_const(&mut body, constant);
+        }
+    }
+    for ty in &assembly.types {
+        body.push('\n');
+        generate_class(&mut body, &ty.borrow(), &live, &mut uses);
+    }
+    for service in &assembly.services {
+        body.push('\n');
+        generate_service(&mut body, service, &mut uses);
+    }
+
+    let mut out = String::from("// Generated by rgc. Do not edit.\n");
+    if uses.pairs {
+        uses.imports.extend(PAIRS_IMPORTS);
+    }
+    if !uses.imports.is_empty() {
+        out.push('\n');
+        for import in &uses.imports {
+            writeln!(out, "import {import}").unwrap();
+        }
+    }
+    if uses.pairs {
+        out.push_str(PAIRS_PRELUDE);
+    }
+    out.push_str(&body);
+    out
+}
+
+fn generate_const(out: &mut String, constant: &SchemeConst) {
+    deprecated_annotation(out, "", &constant.annotations);
+    let ty = AssemblyTypeRef::Builtin(constant.ty);
+    writeln!(
+        out,
+        "const val {}: {} = {}",
+        renamed(&constant.annotations, "kotlin").unwrap_or(&constant.name),
+        builtin_type(constant.ty),
+        value(&constant.value, &ty)
+    )
+    .unwrap();
+}
+
+fn generate_class(out: &mut String, ty: &AssemblyType, live: &HashSet<String>, uses: &mut Uses) {
+    // Live types only exist at runtime, they are not serialized.
+    let serializable = !live.contains(&ty.name);
+    deprecated_annotation(out, "", &ty.annotations);
+    if serializable {
+        uses.imports.insert("kotlinx.serialization.Serializable");
+        out.push_str("@Serializable\n");
+    }
+    let name = format!("{}{}", type_name(ty), generics(ty));
+    // Data classes need at least one property.
+    if ty.fields.is_empty() {
+        writeln!(out, "class {name}").unwrap();
+        return;
+    }
+    writeln!(out, "data class {name}(").unwrap();
+    for field in &ty.fields {
+        deprecated_annotation(out, "    ", &field.annotations);
+        let name = field_name(field);
+        if serializable && name.trim_matches('`') != field.name {
+            uses.imports.insert("kotlinx.serialization.SerialName");
+            writeln!(out, "    @SerialName({})", string(&field.name)).unwrap();
+        }
+        let mut field_type = field_type(&field.ty, uses);
+        if field.optional && !field_type.ends_with('?') {
+            field_type.push('?');
+        }
+        // Absent fields get their default value, `null` for the ones that may be absent.
+        let default = match (&field.default, &field.ty.kind) {
+            _ if field.optional => " = null".to_string(),
+            (Some(default), AssemblyFieldTypeKind::Sync) => {
+                uses.imports
+                    .insert("kotlinx.coroutines.flow.MutableStateFlow");
+                format!(" = MutableStateFlow({})", value(default, &field.ty.ty_ref))
+            }
+            (Some(default), _) => format!(" = {}", value(default, &field.ty.ty_ref)),
+            (None, _) => String::new(),
+        };
+        writeln!(out, "    val {name}: {field_type}{default},").unwrap();
+    }
+    out.push_str(")\n");
+}
+
+/// Servers implement the interface of each service, one method per remote function.
+fn generate_service(out: &mut String, service: &AssemblyService, uses: &mut Uses) {
+    deprecated_annotation(out, "", &service.annotations);
+    writeln!(
+        out,
+        "interface {} {{",
+        renamed(&service.annotations, "kotlin").unwrap_or(&service.name)
+    )
+    .unwrap();
+    for function in &service.functions {
+        deprecated_annotation(out, "    ", &function.annotations);
+        let params = function
+            .params
+            .iter()
+            .map(|param| {
+                format!(
+                    "{}: {}",
+                    escape(camel_case(&param.name)),
+                    type_ref(&param.ty, uses)
+                )
+            })
+            .collect::<Vec<_>>();
+        let name = escape(
+            renamed(&function.annotations, "kotlin")
+                .map_or_else(|| camel_case(&function.name), str::to_string),
+        );
+        // `stream` and `sync` values are handed out at once and then updated by the transport.
+        let (modifier, returns) = match &function.returns {
+            Some(ty) if ty.kind != AssemblyFieldTypeKind::Normal => {
+                ("", format!(": {}", field_type(ty, uses)))
+            }
+            Some(ty) => ("suspend ", format!(": {}", field_type(ty, uses))),
+            None => ("suspend ", String::new()),
+        };
+        writeln!(
+            out,
+            "    {modifier}fun {name}({}){returns}",
+            params.join(", ")
+        )
+        .unwrap();
+    }
+    out.push_str("}\n");
+}
+
+fn deprecated_annotation(out: &mut String, indent: &str, annotations: &[SchemeAnnotation]) {
+    if let Some(reason) = deprecation(annotations) {
+        writeln!(out, "{indent}@Deprecated({})", string(reason)).unwrap();
+    }
+}
+
+fn type_name(ty: &AssemblyType) -> &str {
+    renamed(&ty.annotations, "kotlin").unwrap_or(&ty.name)
+}
+
+/// `<T, U>` for generic types, empty otherwise.
+fn generics(ty: &AssemblyType) -> String {
+    if ty.params.is_empty() {
+        String::new()
+    } else {
+        format!("<{}>", ty.params.join(", "))
+    }
+}
+
+fn field_name(field: &AssemblyField) -> String {
+    escape(
+        renamed(&field.annotations, "kotlin")
+            .map_or_else(|| camel_case(&field.name), str::to_string),
+    )
+}
+
+/// Names that are Kotlin keywords are quoted with backticks.
+fn escape(name: String) -> String {
+    if KEYWORDS.contains(&name.as_str()) {
+        format!("`{name}`")
+    } else {
+        name
+    }
+}
+
+fn field_type(ty: &AssemblyFieldType, uses: &mut Uses) -> String {
+    let inner = type_ref(&ty.ty_ref, uses);
+    match ty.kind {
+        AssemblyFieldTypeKind::Normal => inner,
+        AssemblyFieldTypeKind::Stream => {
+            uses.imports.insert("kotlinx.coroutines.flow.Flow");
+            format!("Flow<{inner}>")
+        }
+        AssemblyFieldTypeKind::Sync => {
+            uses.imports.insert("kotlinx.coroutines.flow.StateFlow");
+            format!("StateFlow<{inner}>")
+        }
+    }
+}
+
+fn type_ref(ty_ref: &AssemblyTypeRef, uses: &mut Uses) -> String {
+    match ty_ref {
+        AssemblyTypeRef::Builtin(builtin) => builtin_type(*builtin).to_string(),
+        AssemblyTypeRef::Custom(ty) => type_name(&ty.borrow()).to_string(),
+        AssemblyTypeRef::Instance(ty, args) => {
+            let args = args
+                .iter()
+                .map(|arg| type_ref(arg, uses))
+                .collect::<Vec<_>>();
+            format!("{}<{}>", type_name(&ty.borrow()), args.join(", "))
+        }
+        AssemblyTypeRef::Param(name) => name.clone(),
+        AssemblyTypeRef::Array(item) => format!("List<{}>", type_ref(item, uses)),
+        AssemblyTypeRef::Optional(item) => {
+            let item = type_ref(item, uses);
+            if item.ends_with('?') {
+                item
+            } else {
+                format!("{item}?")
+            }
+        }
+        AssemblyTypeRef::Map(key, value) => {
+            let map = format!("Map<{}, {}>", type_ref(key, uses), type_ref(value, uses));
+            match **key {
+                // Written as JSON object keys by kotlinx.serialization.
+                AssemblyTypeRef::Builtin(
+                    BuiltinType::String | BuiltinType::Integer | BuiltinType::Float,
+                ) => map,
+                _ => {
+                    uses.pairs = true;
+                    uses.imports.insert("kotlinx.serialization.Serializable");
+                    format!("@Serializable(with = PairsSerializer::class) {map}")
+                }
+            }
+        }
+    }
+}
+
+fn builtin_type(builtin: BuiltinType) -> &'static str {
+    match builtin {
+        BuiltinType::Integer => "Long",
+        BuiltinType::Float => "Double",
+        BuiltinType::String => "String",
+        BuiltinType::Boolean => "Boolean",
+    }
+}
+
+/// String literal, `$` starting string templates.
+fn string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '$' => out.push_str("\\$"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn value(value: &SchemeValue, ty_ref: &AssemblyTypeRef) -> String {
+    match (value, ty_ref) {
+        (SchemeValue::Null, _) => "null".to_string(),
+        (value, AssemblyTypeRef::Optional(item)) => self::value(value, item),
+        (SchemeValue::Integer(n), _) => format!("{n}L"),
+        (SchemeValue::Float(x), _) => format!("{x:?}"),
+        (SchemeValue::String(s), _) => string(s),
+        (SchemeValue::Boolean(b), _) => b.to_string(),
+        (SchemeValue::Array(items), _) if items.is_empty() => "emptyList()".to_string(),
+        (SchemeValue::Array(items), AssemblyTypeRef::Array(item)) => {
+            let items = items
+                .iter()
+                .map(|v| self::value(v, item))
+                .collect::<Vec<_>>();
+            format!("listOf({})", items.join(", "))
+        }
+        (SchemeValue::Map(entries), _) if entries.is_empty() => "emptyMap()".to_string(),
+        (SchemeValue::Map(entries), AssemblyTypeRef::Map(key, item)) => {
+            let entries = entries
+                .iter()
+                .map(|(k, v)| format!("{} to {}", self::value(k, key), self::value(v, item)))
+                .collect::<Vec<_>>();
+            format!("mapOf({})", entries.join(", "))
+        }
+        _ => unreachable!("default values are checked against the field type"),
+    }
+}
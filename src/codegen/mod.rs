@@ -1,2 +1,612 @@
+pub mod avro;
+pub mod csharp;
+pub mod go;
+pub mod graphql;
+pub mod jsonschema;
+pub mod kotlin;
+pub mod openapi;
+pub mod protobuf;
+pub mod python;
 pub mod rust;
-pub mod typescript;
\ No newline at end of file
+pub mod swift;
+pub mod typescript;
+
+use crate::schema::assembly::*;
+use crate::schema::module::{
+    find_annotation, BuiltinType, SchemeAnnotation, SchemeTypeRef, SchemeValue,
+};
+
+use std::collections::{HashMap, HashSet};
+
+/// Output languages of `rgc gen`, the JSON Schema and OpenAPI documents of payloads, protobuf and
+/// GraphQL definitions, and Avro schemas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    Rust,
+    TypeScript,
+    Python,
+    Go,
+    Kotlin,
+    Swift,
+    CSharp,
+    JsonSchema,
+    OpenApi,
+    Protobuf,
+    GraphQl,
+    Avro,
+}
+
+/// Settings of `rgc gen` that only apply to some targets.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Options {
+    pub python: python::Flavor,
+    /// Go package of the generated code, [`go::DEFAULT_PACKAGE`] if `None`.
+    pub go_package: Option<String>,
+    /// C# namespace of the generated code, [`csharp::DEFAULT_NAMESPACE`] if `None`.
+    pub csharp_namespace: Option<String>,
+    /// Title of the OpenAPI document, [`openapi::DEFAULT_TITLE`] if `None`.
+    pub openapi_title: Option<String>,
+    /// Protobuf package of the definitions, [`protobuf::DEFAULT_PACKAGE`] if `None`.
+    pub proto_package: Option<String>,
+}
+
+impl Target {
+    pub const ALL: &'static [Target] = &[
+        Target::Rust,
+        Target::TypeScript,
+        Target::Python,
+        Target::Go,
+        Target::Kotlin,
+        Target::Swift,
+        Target::CSharp,
+        Target::JsonSchema,
+        Target::OpenApi,
+        Target::Protobuf,
+        Target::GraphQl,
+        Target::Avro,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Target::Rust => "rust",
+            Target::TypeScript => "typescript",
+            Target::Python => "python",
+            Target::Go => "go",
+            Target::Kotlin => "kotlin",
+            Target::Swift => "swift",
+            Target::CSharp => "csharp",
+            Target::JsonSchema => "jsonschema",
+            Target::OpenApi => "openapi",
+            Target::Protobuf => "protobuf",
+            Target::GraphQl => "graphql",
+            Target::Avro => "avro",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Target> {
+        Target::ALL
+            .iter()
+            .copied()
+            .find(|target| target.name() == name)
+    }
+
+    /// Fails with a message if the assembly cannot be represented in the target.
+    pub fn generate(self, assembly: &Assembly, options: &Options) -> Result<String, String> {
+        Ok(match self {
+            Target::Rust => rust::generate(assembly),
+            Target::TypeScript => typescript::generate(assembly),
+            Target::Python => python::generate(assembly, options.python),
+            Target::Go => go::generate(
+                assembly,
+                options.go_package.as_deref().unwrap_or(go::DEFAULT_PACKAGE),
+            ),
+            Target::Kotlin => kotlin::generate(assembly),
+            Target::Swift => swift::generate(assembly),
+            Target::CSharp => csharp::generate(
+                assembly,
+                options
+                    .csharp_namespace
+                    .as_deref()
+                    .unwrap_or(csharp::DEFAULT_NAMESPACE),
+            ),
+            Target::JsonSchema => jsonschema::generate(assembly),
+            Target::OpenApi => openapi::generate(
+                assembly,
+                options
+                    .openapi_title
+                    .as_deref()
+                    .unwrap_or(openapi::DEFAULT_TITLE),
+            ),
+            Target::Protobuf => protobuf::generate(
+                assembly,
+                options
+                    .proto_package
+                    .as_deref()
+                    .unwrap_or(protobuf::DEFAULT_PACKAGE),
+            )?,
+            Target::GraphQl => graphql::generate(assembly)?,
+            Target::Avro => avro::generate(assembly)?,
+        })
+    }
+}
+
+/// `createdAt` -> `created_at`.
+pub(crate) fn snake_case(name: &str) -> String {
+    let mut out = String::with_capacity(name.len() + 4);
+    let mut previous_lower = false;
+    for c in name.chars() {
+        if c.is_uppercase() {
+            if previous_lower {
+                out.push('_');
+            }
+            out.extend(c.to_lowercase());
+            previous_lower = false;
+        } else {
+            out.push(c);
+            previous_lower = c.is_lowercase() || c.is_ascii_digit();
+        }
+    }
+    out
+}
+
+/// `created_at` -> `createdAt`.
+pub(crate) fn camel_case(name: &str) -> String {
+    let snake = snake_case(name);
+    let mut words = snake.split('_').filter(|word| !word.is_empty());
+    let mut out = words.next().unwrap_or_default().to_string();
+    for word in words {
+        let mut chars = word.chars();
+        out.extend(chars.next().into_iter().flat_map(char::to_uppercase));
+        out.extend(chars);
+    }
+    out
+}
+
+/// Whether any field or remote function of the assembly has the given kind.
+pub(crate) fn uses_kind(assembly: &Assembly, kind: AssemblyFieldTypeKind) -> bool {
+    assembly
+        .types
+        .iter()
+        .any(|ty| ty.borrow().fields.iter().any(|field| field.ty.kind == kind))
+        || assembly.services.iter().any(|service| {
+            service
+                .functions
+                .iter()
+                .any(|function| function.returns.as_ref().is_some_and(|ty| ty.kind == kind))
+        })
+}
+
+/// Names of the types that contain a `stream` or `sync` field, directly or through the types
+/// they reference. Such types only exist at runtime and cannot be written as plain data.
+pub(crate) fn live_types(assembly: &Assembly) -> HashSet<String> {
+    let mut live = HashSet::new();
+    loop {
+        let before = live.len();
+        for ty in &assembly.types {
+            let ty = ty.borrow();
+            let is_live = ty.fields.iter().any(|field| {
+                field.ty.kind != AssemblyFieldTypeKind::Normal
+                    || references(&field.ty.ty_ref)
+                        .iter()
+                        .any(|name| live.contains(name))
+            });
+            if is_live {
+                live.insert(ty.name.clone());
+            }
+        }
+        if live.len() == before {
+            return live;
+        }
+    }
+}
+
+/// Type of the values held by `field` in generated code: a field that may be absent holds an
+/// `Optional` value.
+pub(crate) fn value_type(field: &AssemblyField) -> AssemblyTypeRef {
+    if field.optional {
+        AssemblyTypeRef::Optional(Box::new(field.ty.ty_ref.clone()))
+    } else {
+        field.ty.ty_ref.clone()
+    }
+}
+
+/// Whether a value of type `ty_ref` is or stores a value of type `target` without any heap
+/// indirection. A reference back to `target` from such a place needs a `Box` or a pointer to keep
+/// the type finite. Values of type parameters are stored inline, so the arguments of instances
+/// count, e.g. `Wrap<Node>` stores a `Node` if `Wrap<T>` has a field of type `T`.
+pub(crate) fn contains_inline(ty_ref: &AssemblyTypeRef, target: &str) -> bool {
+    stores_inline(ty_ref, &[], target, &mut HashSet::new())
+}
+
+/// [`contains_inline`] inside a generic type whose parameters `carriers` stand for values storing
+/// `target`. Types are visited once for each set of such parameters.
+fn stores_inline(
+    ty_ref: &AssemblyTypeRef,
+    carriers: &[String],
+    target: &str,
+    visited: &mut HashSet<(String, Vec<String>)>,
+) -> bool {
+    let mut ty_ref = ty_ref;
+    while let AssemblyTypeRef::Optional(item) = ty_ref {
+        ty_ref = item;
+    }
+    let (ty, carriers) = match ty_ref {
+        AssemblyTypeRef::Param(name) => return carriers.contains(name),
+        AssemblyTypeRef::Custom(ty) => (ty.borrow(), vec![]),
+        AssemblyTypeRef::Instance(ty, args) => {
+            let ty = ty.borrow();
+            let carriers = ty
+                .params
+                .iter()
+                .zip(args)
+                .filter(|(_, arg)| stores_inline(arg, carriers, target, visited))
+                .map(|(param, _)| param.clone())
+                .collect();
+            (ty, carriers)
+        }
+        _ => return false,
+    };
+    if ty.name == target {
+        return true;
+    }
+    if !visited.insert((ty.name.clone(), carriers.clone())) {
+        return false;
+    }
+    ty.fields.iter().any(|field| {
+        field.ty.kind == AssemblyFieldTypeKind::Normal
+            && stores_inline(&field.ty.ty_ref, &carriers, target, visited)
+    })
+}
+
+/// Whether the type parameter `param` is used as the key of a map in `ty_ref`.
+pub(crate) fn is_map_key(ty_ref: &AssemblyTypeRef, param: &str) -> bool {
+    match ty_ref {
+        AssemblyTypeRef::Builtin(_) | AssemblyTypeRef::Custom(_) | AssemblyTypeRef::Param(_) => {
+            false
+        }
+        AssemblyTypeRef::Instance(_, args) => args.iter().any(|arg| is_map_key(arg, param)),
+        AssemblyTypeRef::Array(item) | AssemblyTypeRef::Optional(item) => is_map_key(item, param),
+        AssemblyTypeRef::Map(key, value) => {
+            matches!(&**key, AssemblyTypeRef::Param(name) if name == param)
+                || is_map_key(key, param)
+                || is_map_key(value, param)
+        }
+    }
+}
+
+/// Names of the custom types mentioned by `ty_ref`.
+pub(crate) fn references(ty_ref: &AssemblyTypeRef) -> Vec<String> {
+    match ty_ref {
+        AssemblyTypeRef::Builtin(_) | AssemblyTypeRef::Param(_) => vec![],
+        AssemblyTypeRef::Custom(ty) => vec![ty.borrow().name.clone()],
+        AssemblyTypeRef::Instance(ty, args) => {
+            let mut names = vec![ty.borrow().name.clone()];
+            names.extend(args.iter().flat_map(references));
+            names
+        }
+        AssemblyTypeRef::Array(item) | AssemblyTypeRef::Optional(item) => references(item),
+        AssemblyTypeRef::Map(key, value) => {
+            let mut names = references(key);
+            names.extend(references(value));
+            names
+        }
+    }
+}
+
+/// Arguments of the type parameters of a generic type, by parameter name.
+pub(crate) type TypeArgs = HashMap<String, AssemblyTypeRef>;
+
+/// `ty_ref` with the type parameters replaced by their arguments.
+pub(crate) fn substitute(ty_ref: &AssemblyTypeRef, args: &TypeArgs) -> AssemblyTypeRef {
+    match ty_ref {
+        AssemblyTypeRef::Param(param) => args[param].clone(),
+        AssemblyTypeRef::Builtin(_) | AssemblyTypeRef::Custom(_) => ty_ref.clone(),
+        AssemblyTypeRef::Instance(ty, params) => AssemblyTypeRef::Instance(
+            ty.clone(),
+            params.iter().map(|param| substitute(param, args)).collect(),
+        ),
+        AssemblyTypeRef::Array(item) => AssemblyTypeRef::Array(Box::new(substitute(item, args))),
+        AssemblyTypeRef::Optional(item) => {
+            AssemblyTypeRef::Optional(Box::new(substitute(item, args)))
+        }
+        AssemblyTypeRef::Map(key, value) => AssemblyTypeRef::Map(
+            Box::new(substitute(key, args)),
+            Box::new(substitute(value, args)),
+        ),
+    }
+}
+
+/// Name of the type generated for `ty_ref` by targets without generics, with the names given by
+/// `@rename` for the language `key`: `PageUser` for `Page<User>`, `StringList` for
+/// `Array<string>`.
+pub(crate) fn flat_name(ty_ref: &AssemblyTypeRef, key: &str) -> String {
+    let name = |ty: &AssemblyType| {
+        renamed(&ty.annotations, key)
+            .unwrap_or(&ty.name)
+            .to_string()
+    };
+    match ty_ref {
+        AssemblyTypeRef::Builtin(builtin) => match builtin {
+            BuiltinType::Integer => "Integer",
+            BuiltinType::Float => "Float",
+            BuiltinType::String => "String",
+            BuiltinType::Boolean => "Boolean",
+        }
+        .to_string(),
+        AssemblyTypeRef::Custom(ty) => name(&ty.borrow()),
+        AssemblyTypeRef::Instance(ty, args) => {
+            let args = args.iter().map(|arg| flat_name(arg, key));
+            format!("{}{}", name(&ty.borrow()), args.collect::<String>())
+        }
+        AssemblyTypeRef::Array(item) => format!("{}List", flat_name(item, key)),
+        AssemblyTypeRef::Optional(item) => format!("Optional{}", flat_name(item, key)),
+        AssemblyTypeRef::Map(key_ref, value) => {
+            format!("{}{}Map", flat_name(key_ref, key), flat_name(value, key))
+        }
+        AssemblyTypeRef::Param(_) => unreachable!("type parameters are substituted"),
+    }
+}
+
+/// `ty_ref` the way it is written in a schema, e.g. `Page<User>`.
+pub(crate) fn written(ty_ref: &AssemblyTypeRef) -> String {
+    match ty_ref {
+        AssemblyTypeRef::Builtin(builtin) => SchemeTypeRef::Builtin(*builtin).to_string(),
+        AssemblyTypeRef::Custom(ty) => ty.borrow().name.clone(),
+        AssemblyTypeRef::Instance(ty, args) => {
+            let args = args.iter().map(written).collect::<Vec<_>>();
+            format!("{}<{}>", ty.borrow().name, args.join(", "))
+        }
+        AssemblyTypeRef::Param(name) => name.clone(),
+        AssemblyTypeRef::Array(item) => format!("Array<{}>", written(item)),
+        AssemblyTypeRef::Optional(item) => format!("Optional<{}>", written(item)),
+        AssemblyTypeRef::Map(key, value) => format!("Map<{}, {}>", written(key), written(value)),
+    }
+}
+
+/// `ty` given the arguments of its parameters, the way it is written in a schema.
+pub(crate) fn written_instance(ty: &AssemblyType, args: &TypeArgs) -> String {
+    if ty.params.is_empty() {
+        return ty.name.clone();
+    }
+    let args = ty.params.iter().map(|param| written(&args[param]));
+    format!("{}<{}>", ty.name, args.collect::<Vec<_>>().join(", "))
+}
+
+/// Names of the declarations of targets without generics, with what each is generated for, e.g.
+/// `` `Page<User>` `` for `PageUser`. Names made up from other names, see [`flat_name`], may be
+/// taken already: such clashes are collected rather than generating two declarations of a name.
+#[derive(Default)]
+pub(crate) struct Names {
+    owners: HashMap<String, String>,
+    clashes: Vec<String>,
+}
+
+impl Names {
+    /// Records that `name` is declared for `owner`, described for error messages.
+    pub(crate) fn claim(&mut self, name: &str, owner: impl Into<String>) {
+        let owner = owner.into();
+        match self.owners.get(name) {
+            None => {
+                self.owners.insert(name.to_string(), owner);
+            }
+            Some(other) if *other != owner => {
+                let clash = format!("`{name}` would name both {other} and {owner}");
+                if !self.clashes.contains(&clash) {
+                    self.clashes.push(clash);
+                }
+            }
+            Some(_) => {}
+        }
+    }
+
+    /// Fails if a name was claimed for two declarations.
+    pub(crate) fn check(&self) -> Result<(), String> {
+        match self.clashes.is_empty() {
+            true => Ok(()),
+            false => Err(format!(
+                "generated names clash, give types other names with `@rename`: {}",
+                self.clashes.join(", ")
+            )),
+        }
+    }
+}
+
+/// Name given by `@rename` for the language `key` (e.g. `ts`), or for every language.
+pub(crate) fn renamed<'a>(annotations: &'a [SchemeAnnotation], key: &str) -> Option<&'a str> {
+    let rename = find_annotation(annotations, "rename")?;
+    match rename.arg(key).or_else(|| rename.arg("name"))? {
+        SchemeValue::String(name) => Some(name),
+        _ => None,
+    }
+}
+
+/// `Some(reason)` for `@deprecated` declarations, the reason being empty if none is given.
+pub(crate) fn deprecation(annotations: &[SchemeAnnotation]) -> Option<&str> {
+    let deprecated = find_annotation(annotations, "deprecated")?;
+    match deprecated.arg("reason") {
+        Some(SchemeValue::String(reason)) => Some(reason),
+        _ => Some(""),
+    }
+}
+
+/// A constraint annotation of a field, see [`crate::schema::annotation::ConstraintSubject`].
+pub(crate) enum Constraint<'a> {
+    Range {
+        min: Option<&'a SchemeValue>,
+        max: Option<&'a SchemeValue>,
+    },
+    Pattern(&'a str),
+    MinLen(i64),
+    MaxLen(i64),
+    MinItems(i64),
+    MaxItems(i64),
+}
+
+impl Constraint<'_> {
+    /// Annotation name, reported in validation errors.
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Constraint::Range { .. } => "range",
+            Constraint::Pattern(_) => "pattern",
+            Constraint::MinLen(_) => "min_len",
+            Constraint::MaxLen(_) => "max_len",
+            Constraint::MinItems(_) => "min_items",
+            Constraint::MaxItems(_) => "max_items",
+        }
+    }
+}
+
+pub(crate) fn constraints(field: &AssemblyField) -> Vec<Constraint<'_>> {
+    let integer = |annotation: &SchemeAnnotation, arg: &str| match annotation.arg(arg) {
+        Some(SchemeValue::Integer(n)) => *n,
+        _ => 0,
+    };
+    field
+        .annotations
+        .iter()
+        .filter_map(|annotation| {
+            Some(match annotation.name.as_str() {
+                "range" => Constraint::Range {
+                    min: annotation.arg("min"),
+                    max: annotation.arg("max"),
+                },
+                "pattern" => match annotation.arg("regex") {
+                    Some(SchemeValue::String(regex)) => Constraint::Pattern(regex),
+                    _ => return None,
+                },
+                "min_len" => Constraint::MinLen(integer(annotation, "len")),
+                "max_len" => Constraint::MaxLen(integer(annotation, "len")),
+                "min_items" => Constraint::MinItems(integer(annotation, "count")),
+                "max_items" => Constraint::MaxItems(integer(annotation, "count")),
+                _ => return None,
+            })
+        })
+        .collect()
+}
+
+/// Names of the types whose values can break a constraint, directly or through the values of
+/// other types they contain. Only these types get a validator. A generic type is also validated
+/// when one of its instances holds validated values as those of a type parameter, e.g.
+/// `Page<T>` for `Page<Post>`: its validator is then given one for the values of each parameter
+/// in [`validated_params`].
+pub(crate) fn validated_types(assembly: &Assembly) -> HashSet<String> {
+    let mut validated = HashSet::new();
+    loop {
+        let before = validated.len();
+        for ty in &assembly.types {
+            let ty = ty.borrow();
+            // Values of type parameters are validated by validated generic types only.
+            let params = match validated.contains(&ty.name) {
+                true => validated_params(&ty),
+                false => vec![],
+            };
+            let mut instances = Vec::new();
+            let mut is_validated = false;
+            for field in &ty.fields {
+                is_validated |= !constraints(field).is_empty();
+                if field.ty.kind == AssemblyFieldTypeKind::Normal {
+                    let value_type = value_type(field);
+                    is_validated |= contains_validated(&value_type, &validated, &params);
+                    validated_instances(&value_type, &validated, &params, &mut instances);
+                }
+            }
+            if is_validated {
+                validated.insert(ty.name.clone());
+            }
+            validated.extend(instances);
+        }
+        if validated.len() == before {
+            return validated;
+        }
+    }
+}
+
+/// Adds to `instances` the generic types of the instances in `ty_ref` that hold validated values
+/// as those of one of their parameters.
+fn validated_instances(
+    ty_ref: &AssemblyTypeRef,
+    validated: &HashSet<String>,
+    params: &[String],
+    instances: &mut Vec<String>,
+) {
+    match ty_ref {
+        AssemblyTypeRef::Builtin(_) | AssemblyTypeRef::Custom(_) | AssemblyTypeRef::Param(_) => {}
+        AssemblyTypeRef::Instance(ty, args) => {
+            let held = validated_params(&ty.borrow());
+            let ty_params = ty.borrow().params.clone();
+            for (param, arg) in ty_params.iter().zip(args) {
+                if held.contains(param) && contains_validated(arg, validated, params) {
+                    instances.push(ty.borrow().name.clone());
+                }
+                validated_instances(arg, validated, params, instances);
+            }
+        }
+        AssemblyTypeRef::Array(item) | AssemblyTypeRef::Optional(item) => {
+            validated_instances(item, validated, params, instances)
+        }
+        AssemblyTypeRef::Map(key, value) => {
+            validated_instances(key, validated, params, instances);
+            validated_instances(value, validated, params, instances);
+        }
+    }
+}
+
+/// Whether a value of type `ty_ref` contains values of validated types, or values of the type
+/// parameters `params`.
+pub(crate) fn contains_validated(
+    ty_ref: &AssemblyTypeRef,
+    validated: &HashSet<String>,
+    params: &[String],
+) -> bool {
+    match ty_ref {
+        AssemblyTypeRef::Builtin(_) => false,
+        AssemblyTypeRef::Param(name) => params.contains(name),
+        AssemblyTypeRef::Custom(ty) | AssemblyTypeRef::Instance(ty, _) => {
+            validated.contains(&ty.borrow().name)
+        }
+        AssemblyTypeRef::Array(item) | AssemblyTypeRef::Optional(item) => {
+            contains_validated(item, validated, params)
+        }
+        // Only the values of maps are validated.
+        AssemblyTypeRef::Map(_, value) => contains_validated(value, validated, params),
+    }
+}
+
+/// Parameters of the generic type `ty` whose values are held by its fields, outside of map keys.
+/// The validator of `ty` checks these values with validators given for each of them.
+pub(crate) fn validated_params(ty: &AssemblyType) -> Vec<String> {
+    ty.params
+        .iter()
+        .filter(|param| holds_param(ty, param, &mut HashSet::new()))
+        .cloned()
+        .collect()
+}
+
+fn holds_param(ty: &AssemblyType, param: &str, visiting: &mut HashSet<String>) -> bool {
+    if !visiting.insert(ty.name.clone()) {
+        return false;
+    }
+    let holds = ty.fields.iter().any(|field| {
+        field.ty.kind == AssemblyFieldTypeKind::Normal
+            && mentions_param(&value_type(field), param, visiting)
+    });
+    visiting.remove(&ty.name);
+    holds
+}
+
+fn mentions_param(ty_ref: &AssemblyTypeRef, param: &str, visiting: &mut HashSet<String>) -> bool {
+    match ty_ref {
+        AssemblyTypeRef::Builtin(_) | AssemblyTypeRef::Custom(_) => false,
+        AssemblyTypeRef::Param(name) => name == param,
+        AssemblyTypeRef::Instance(ty, args) => {
+            let ty = ty.borrow();
+            ty.params.iter().zip(args).any(|(ty_param, arg)| {
+                mentions_param(arg, param, visiting) && holds_param(&ty, ty_param, visiting)
+            })
+        }
+        AssemblyTypeRef::Array(item) | AssemblyTypeRef::Optional(item) => {
+            mentions_param(item, param, visiting)
+        }
+        AssemblyTypeRef::Map(_, value) => mentions_param(value, param, visiting),
+    }
+}
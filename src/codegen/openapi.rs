@@ -0,0 +1,153 @@
+//! OpenAPI 3.1 backend. Each remote function is an operation `POST /{Service}/{function}`, whose
+//! request body is an object of the arguments by parameter name, as sent by generated clients.
+//! Bodies reference the schemas of the types under `components/schemas`, see
+//! [`super::jsonschema`]. `stream` and `sync` returns are sent as server-sent events or as
+//! newline-delimited JSON, one value per event or line.
+
+use super::deprecation;
+use super::jsonschema::{Definitions, Dialect};
+use crate::json::Json;
+use crate::schema::assembly::*;
+use crate::schema::module::{find_annotation, SchemeAnnotation, SchemeValue};
+
+pub const DEFAULT_TITLE: &str = "rgc";
+
+const STREAM_TYPES: &[&str] = &["text/event-stream", "application/x-ndjson"];
+
+pub fn generate(assembly: &Assembly, title: &str) -> String {
+    let mut definitions = Definitions::new(assembly, Dialect::OpenApi);
+    let mut paths = Vec::new();
+    for service in &assembly.services {
+        for function in &service.functions {
+            let operation = operation(&mut definitions, service, function);
+            let path = format!("/{}/{}", service.name, function.name);
+            paths.push((path, Json::object([("post", operation)])));
+        }
+    }
+    let info = Json::object([
+        ("title", Json::from(title)),
+        ("version", Json::from(version(assembly).to_string())),
+    ]);
+    let mut document = Json::object([
+        ("openapi", Json::from("3.1.0")),
+        ("info", info),
+        ("paths", Json::Object(paths)),
+    ]);
+    let schemas = definitions.finish();
+    if !schemas.is_empty() {
+        let components = Json::object([("schemas", Json::Object(schemas))]);
+        document.insert("components", components);
+    }
+    document.pretty()
+}
+
+fn operation(
+    definitions: &mut Definitions,
+    service: &AssemblyService,
+    function: &AssemblyFunction,
+) -> Json {
+    let mut operation = Json::object([
+        (
+            "operationId",
+            Json::from(format!("{}.{}", service.name, function.name)),
+        ),
+        ("tags", Json::Array(vec![Json::from(service.name.as_str())])),
+    ]);
+    let reason = deprecation(&function.annotations).or(deprecation(&service.annotations));
+    if let Some(reason) = reason {
+        if !reason.is_empty() {
+            operation.insert("description", Json::from(reason));
+        }
+        operation.insert("deprecated", Json::from(true));
+    }
+    if !function.params.is_empty() {
+        let properties = function
+            .params
+            .iter()
+            .map(|param| (param.name.clone(), definitions.schema(&param.ty)))
+            .collect();
+        // Arguments are always sent, `Optional` ones as `null`.
+        let required = function
+            .params
+            .iter()
+            .map(|param| Json::from(param.name.as_str()))
+            .collect();
+        let schema = Json::object([
+            ("type", Json::from("object")),
+            ("properties", Json::Object(properties)),
+            ("required", Json::Array(required)),
+        ]);
+        let body = Json::object([
+            ("required", Json::from(true)),
+            ("content", content(&["application/json"], schema)),
+        ]);
+        operation.insert("requestBody", body);
+    }
+    let response = match &function.returns {
+        None => (
+            "204",
+            Json::object([("description", Json::from("No content"))]),
+        ),
+        Some(returns) => {
+            let schema = definitions.schema(&returns.ty_ref);
+            let (description, types) = match returns.kind {
+                AssemblyFieldTypeKind::Normal => ("The returned value", &["application/json"][..]),
+                AssemblyFieldTypeKind::Stream => {
+                    ("Each item of the stream, as it comes", STREAM_TYPES)
+                }
+                AssemblyFieldTypeKind::Sync => (
+                    "The current value, then each new value until the call ends",
+                    STREAM_TYPES,
+                ),
+            };
+            let response = Json::object([
+                ("description", Json::from(description)),
+                ("content", content(types, schema)),
+            ]);
+            ("200", response)
+        }
+    };
+    operation.insert("responses", Json::object([response]));
+    operation
+}
+
+/// Content of a body in each of the media `types`. Streamed bodies are a sequence of values of
+/// `schema`.
+fn content(types: &[&str], schema: Json) -> Json {
+    Json::object(
+        types
+            .iter()
+            .map(|ty| (*ty, Json::object([("schema", schema.clone())]))),
+    )
+}
+
+/// Version of the API: the last version declared by `@since`, 1 if there is none.
+fn version(assembly: &Assembly) -> i64 {
+    let types = assembly.types.iter().flat_map(|ty| {
+        let ty = ty.borrow();
+        let fields = ty.fields.iter().map(|field| since(&field.annotations));
+        fields.chain([since(&ty.annotations)]).collect::<Vec<_>>()
+    });
+    let services = assembly.services.iter().flat_map(|service| {
+        let functions = service.functions.iter();
+        let functions = functions.map(|function| since(&function.annotations));
+        functions.chain([since(&service.annotations)])
+    });
+    let consts = assembly
+        .consts
+        .iter()
+        .map(|constant| since(&constant.annotations));
+    types
+        .chain(services)
+        .chain(consts)
+        .flatten()
+        .max()
+        .unwrap_or(1)
+}
+
+fn since(annotations: &[SchemeAnnotation]) -> Option<i64> {
+    match find_annotation(annotations, "since")?.arg("version")? {
+        SchemeValue::Integer(version) => Some(*version),
+        _ => None,
+    }
+}
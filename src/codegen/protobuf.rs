@@ -0,0 +1,523 @@
+//! Protobuf backend: a proto3 message per type, with the field numbers given by `@tag` so that
+//! regenerating never renumbers fields. Protobuf has no generics, so each instance of a generic
+//! type gets a message of its own, e.g. `PageUser` for `Page<User>`. Nesting protobuf cannot
+//! express, such as arrays of arrays or nullable map values, goes through wrapper messages.
+//! Services get a gRPC service, `stream` and `sync` returns being server-streaming RPCs, and the
+//! parameters of a function are the fields of its request message, numbered by `@tag` too.
+
+use super::{
+    deprecation, flat_name, live_types, references, renamed, snake_case, substitute, written,
+    Names, TypeArgs,
+};
+use crate::schema::assembly::*;
+use crate::schema::module::{find_annotation, BuiltinType, SchemeAnnotation, SchemeValue};
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::path::{Component, Path};
+use std::rc::Rc;
+
+pub const DEFAULT_PACKAGE: &str = "schema";
+
+const EMPTY: &str = "google.protobuf.Empty";
+
+/// Fails if fields or parameters have no `@tag` or an invalid one, or if generated message names
+/// clash.
+pub fn generate(assembly: &Assembly, package: &str) -> Result<String, String> {
+    // Numbers given in order would change as fields are added, breaking encoded messages.
+    let untagged = untagged(assembly);
+    if !untagged.is_empty() {
+        return Err(format!(
+            "fields and parameters need a number, given with `@tag(n)`: {}",
+            untagged.join(", ")
+        ));
+    }
+    let invalid = invalid_tags(assembly);
+    if !invalid.is_empty() {
+        return Err(invalid.join("; "));
+    }
+    let mut generator = Generator {
+        live: live_types(assembly),
+        messages: Vec::new(),
+        instances: Vec::new(),
+        seen: HashSet::new(),
+        names: Names::default(),
+        uses_empty: false,
+    };
+    for ty in &assembly.types {
+        let ty = ty.borrow();
+        if ty.params.is_empty() && !generator.live.contains(&ty.name) {
+            let name = message_name(&ty);
+            generator.names.claim(&name, format!("`{}`", ty.name));
+            let message = generator.message(&ty, &name, &HashMap::new());
+            generator.messages.push(message);
+        }
+    }
+    let services = assembly
+        .services
+        .iter()
+        .map(|service| generator.service(service))
+        .collect::<Vec<_>>();
+    // Instances are discovered while generating messages, including other instances.
+    let mut next = 0;
+    while next < generator.instances.len() {
+        let (name, ty, args) = generator.instances[next].clone();
+        let message = generator.message(&ty.borrow(), &name, &args);
+        generator.messages.push(message);
+        next += 1;
+    }
+    generator.names.check()?;
+
+    let mut out = String::from("// Generated by rgc. Do not edit.\n\nsyntax = \"proto3\";\n\n");
+    writeln!(out, "package {package};").unwrap();
+    if generator.uses_empty {
+        out.push_str("\nimport \"google/protobuf/empty.proto\";\n");
+    }
+    for message in generator.messages.iter().chain(&services) {
+        out.push('\n');
+        out.push_str(message);
+    }
+    Ok(out)
+}
+
+/// The fields without `@tag` of the types that get a message, as `Type.field`, and the
+/// parameters without `@tag`, as `Service.function(param)`.
+fn untagged(assembly: &Assembly) -> Vec<String> {
+    let live = live_types(assembly);
+    let mut untagged = Vec::new();
+    for ty in &assembly.types {
+        let ty = ty.borrow();
+        if live.contains(&ty.name) {
+            continue;
+        }
+        for field in &ty.fields {
+            if tag(&field.annotations).is_none() {
+                untagged.push(format!("{}.{}", ty.name, field.name));
+            }
+        }
+    }
+    for service in &assembly.services {
+        for function in &service.functions {
+            for param in &function.params {
+                if tag(&param.annotations).is_none() {
+                    untagged.push(format!(
+                        "{}.{}({})",
+                        service.name, function.name, param.name
+                    ));
+                }
+            }
+        }
+    }
+    untagged
+}
+
+/// Largest protobuf field number.
+const MAX_TAG: i64 = (1 << 29) - 1;
+/// Field numbers reserved by protobuf.
+const RESERVED_TAGS: std::ops::RangeInclusive<i64> = 19000..=19999;
+
+/// Numbers given by `@tag` that are not valid protobuf field numbers, or that are given to
+/// another field of the same message. Duplicates among inherited fields are reported for the
+/// base type only.
+fn invalid_tags(assembly: &Assembly) -> Vec<String> {
+    let live = live_types(assembly);
+    let mut invalid = Vec::new();
+    for ty in &assembly.types {
+        let ty = ty.borrow();
+        if live.contains(&ty.name) {
+            continue;
+        }
+        // Inherited fields are copied in front of the fields of the type.
+        let inherited = ty
+            .base
+            .as_ref()
+            .map_or(0, |base| base.borrow().fields.len());
+        let mut tags = HashMap::new();
+        for (i, field) in ty.fields.iter().enumerate() {
+            let name = format!("{}.{}", ty.name, field.name);
+            if let Some(error) = check_tag(&field.annotations, &name, &mut tags) {
+                if i >= inherited {
+                    invalid.push(error);
+                }
+            }
+        }
+    }
+    for service in &assembly.services {
+        for function in &service.functions {
+            // Parameters are the fields of the request message of the function.
+            let mut tags = HashMap::new();
+            for param in &function.params {
+                let name = format!("{}.{}({})", service.name, function.name, param.name);
+                invalid.extend(check_tag(&param.annotations, &name, &mut tags));
+            }
+        }
+    }
+    invalid
+}
+
+/// Checks that the number given to the field or parameter `name` is a valid protobuf field
+/// number that `tags` does not hold yet, and adds it.
+fn check_tag(
+    annotations: &[SchemeAnnotation],
+    name: &str,
+    tags: &mut HashMap<i64, String>,
+) -> Option<String> {
+    let tag = tag(annotations)?;
+    if !(1..=MAX_TAG).contains(&tag) || RESERVED_TAGS.contains(&tag) {
+        Some(format!(
+            "field number {tag} of {name} is out of range, it must be between 1 and {MAX_TAG} \
+             and not between {} and {}",
+            RESERVED_TAGS.start(),
+            RESERVED_TAGS.end()
+        ))
+    } else if let Some(other) = tags.get(&tag) {
+        Some(format!(
+            "field number {tag} of {name} is already given to {other}"
+        ))
+    } else {
+        tags.insert(tag, name.to_string());
+        None
+    }
+}
+
+/// Package of the schema file at `path`: its directories and name, without the extension, in
+/// snake_case (`billing/invoice-items.rgc` -> `billing.invoice_items`). Only the file name is
+/// used for paths that are not relative to the working directory.
+pub fn package(path: &Path) -> String {
+    let path = path.with_extension("");
+    let relative = path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+    let segments = if relative {
+        path.components()
+            .filter_map(|component| match component {
+                Component::Normal(segment) => Some(segment.to_string_lossy().into_owned()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    } else {
+        path.file_name()
+            .map(|name| vec![name.to_string_lossy().into_owned()])
+            .unwrap_or_default()
+    };
+    let segments = segments
+        .iter()
+        .map(|segment| package_segment(segment))
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>();
+    if segments.is_empty() {
+        DEFAULT_PACKAGE.to_string()
+    } else {
+        segments.join(".")
+    }
+}
+
+fn package_segment(segment: &str) -> String {
+    let segment = snake_case(segment)
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    if segment.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{segment}")
+    } else {
+        segment
+    }
+}
+
+/// Whether `name` is a valid package, dotted identifiers.
+pub fn is_package(name: &str) -> bool {
+    name.split('.').all(|segment| {
+        let mut chars = segment.chars();
+        chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
+struct Generator {
+    live: HashSet<String>,
+    messages: Vec<String>,
+    /// Instances of generic types and wrapper messages, by message name.
+    instances: Vec<(String, Rc<RefCell<AssemblyType>>, TypeArgs)>,
+    /// Names of the instances and wrappers already generated or queued.
+    seen: HashSet<String>,
+    names: Names,
+    uses_empty: bool,
+}
+
+/// How a value is held by a field: `label type`.
+struct FieldType {
+    label: &'static str,
+    ty: String,
+}
+
+impl Generator {
+    fn message(&mut self, ty: &AssemblyType, name: &str, args: &TypeArgs) -> String {
+        let mut out = String::new();
+        deprecated_comment(&mut out, "", &ty.annotations);
+        writeln!(out, "message {name} {{").unwrap();
+        if deprecation(&ty.annotations).is_some() {
+            out.push_str("  option deprecated = true;\n");
+        }
+        for field in &ty.fields {
+            let number = tag(&field.annotations).expect("fields are checked for `@tag`");
+            let ty_ref = substitute(&field.ty.ty_ref, args);
+            let FieldType { label, ty } = self.field_type(&ty_ref, field.optional);
+            deprecated_comment(&mut out, "  ", &field.annotations);
+            let name = renamed(&field.annotations, "proto")
+                .map(str::to_string)
+                .unwrap_or_else(|| snake_case(&field.name));
+            let options = match deprecation(&field.annotations) {
+                Some(_) => " [deprecated = true]",
+                None => "",
+            };
+            writeln!(out, "  {label}{ty} {name} = {number}{options};").unwrap();
+        }
+        out.push_str("}\n");
+        out.replace("{\n}", "{}")
+    }
+
+    /// Label and type of a field holding values of `ty_ref`. `optional` fields may be absent.
+    fn field_type(&mut self, ty_ref: &AssemblyTypeRef, optional: bool) -> FieldType {
+        let (label, ty) = match ty_ref {
+            AssemblyTypeRef::Builtin(builtin) => (
+                if optional { "optional " } else { "" },
+                builtin_type(*builtin).to_string(),
+            ),
+            AssemblyTypeRef::Optional(item) => return self.field_type(item, true),
+            // Messages can always be absent.
+            AssemblyTypeRef::Custom(_) | AssemblyTypeRef::Instance(..) => {
+                ("", self.element(ty_ref))
+            }
+            // Absent and empty arrays and maps cannot be told apart, except through a wrapper.
+            AssemblyTypeRef::Array(_) | AssemblyTypeRef::Map(..) if optional => {
+                ("", self.element(ty_ref))
+            }
+            AssemblyTypeRef::Array(item) => ("repeated ", self.element(item)),
+            AssemblyTypeRef::Map(key, value) => match map_key(key) {
+                Some(key) => ("", format!("map<{key}, {}>", self.element(value))),
+                None => ("repeated ", self.entry(key, value)),
+            },
+            AssemblyTypeRef::Param(_) => unreachable!("type parameters are substituted"),
+        };
+        FieldType {
+            label,
+            ty: ty.to_string(),
+        }
+    }
+
+    /// Type of the items of a `repeated` field or the values of a `map`, which cannot be labeled
+    /// themselves.
+    fn element(&mut self, ty_ref: &AssemblyTypeRef) -> String {
+        match ty_ref {
+            AssemblyTypeRef::Builtin(builtin) => builtin_type(*builtin).to_string(),
+            AssemblyTypeRef::Custom(ty) => {
+                let name = message_name(&ty.borrow());
+                self.names.claim(&name, format!("`{}`", ty.borrow().name));
+                name
+            }
+            AssemblyTypeRef::Instance(ty, args) => {
+                let name = flat_name(ty_ref, "proto");
+                self.names.claim(&name, format!("`{}`", written(ty_ref)));
+                if self.seen.insert(name.clone()) {
+                    let ty_ref = ty.borrow();
+                    let type_args = ty_ref.params.iter().cloned().zip(args.iter().cloned());
+                    self.instances
+                        .push((name.clone(), ty.clone(), type_args.collect()));
+                }
+                name
+            }
+            AssemblyTypeRef::Array(_) | AssemblyTypeRef::Map(..) | AssemblyTypeRef::Optional(_) => {
+                let name = flat_name(ty_ref, "proto");
+                self.names.claim(&name, format!("`{}`", written(ty_ref)));
+                if self.seen.insert(name.clone()) {
+                    let wrapper = self.wrapper(&name, ty_ref);
+                    self.messages.push(wrapper);
+                }
+                name
+            }
+            AssemblyTypeRef::Param(_) => unreachable!("type parameters are substituted"),
+        }
+    }
+
+    /// Message holding a single value of `ty_ref`, for values that cannot be elements.
+    fn wrapper(&mut self, name: &str, ty_ref: &AssemblyTypeRef) -> String {
+        let (FieldType { label, ty }, field) = match ty_ref {
+            AssemblyTypeRef::Optional(item) => (self.field_type(item, true), "value"),
+            AssemblyTypeRef::Map(..) => (self.field_type(ty_ref, false), "entries"),
+            _ => (self.field_type(ty_ref, false), "items"),
+        };
+        format!("message {name} {{\n  {label}{ty} {field} = 1;\n}}\n")
+    }
+
+    /// Message of the entries of a map whose keys cannot be protobuf map keys.
+    fn entry(&mut self, key: &AssemblyTypeRef, value: &AssemblyTypeRef) -> String {
+        let name = format!(
+            "{}{}Entry",
+            flat_name(key, "proto"),
+            flat_name(value, "proto")
+        );
+        let owner = format!("the entries of `Map<{}, {}>`", written(key), written(value));
+        self.names.claim(&name, owner);
+        if self.seen.insert(name.clone()) {
+            let key = self.field_type(key, false);
+            let value = self.field_type(value, false);
+            let message = format!(
+                "message {name} {{\n  {}{} key = 1;\n  {}{} value = 2;\n}}\n",
+                key.label, key.ty, value.label, value.ty
+            );
+            self.messages.push(message);
+        }
+        name
+    }
+
+    fn service(&mut self, service: &AssemblyService) -> String {
+        let mut messages = String::new();
+        let mut out = String::new();
+        deprecated_comment(&mut out, "", &service.annotations);
+        writeln!(out, "service {} {{", service.name).unwrap();
+        let owner = format!("service `{}`", service.name);
+        self.names.claim(&service.name, owner);
+        if deprecation(&service.annotations).is_some() {
+            out.push_str("  option deprecated = true;\n");
+        }
+        for function in &service.functions {
+            let name = pascal_case(&function.name);
+            let mentioned = function
+                .params
+                .iter()
+                .map(|param| &param.ty)
+                .chain(function.returns.iter().map(|returns| &returns.ty_ref));
+            if let Some(live) = mentioned
+                .flat_map(references)
+                .find(|name| self.live.contains(name))
+            {
+                writeln!(
+                    out,
+                    "  // `{}` is left out: `{live}` has `stream` or `sync` fields.",
+                    function.name
+                )
+                .unwrap();
+                continue;
+            }
+            let prefix = format!("{}{name}", service.name);
+            let request = match function.params.is_empty() {
+                true => {
+                    self.uses_empty = true;
+                    EMPTY.to_string()
+                }
+                false => {
+                    let request = format!("{prefix}Request");
+                    let owner = format!("the request of `{}.{}`", service.name, function.name);
+                    self.names.claim(&request, owner);
+                    writeln!(messages, "\nmessage {request} {{").unwrap();
+                    for param in &function.params {
+                        let number =
+                            tag(&param.annotations).expect("params are checked for `@tag`");
+                        let FieldType { label, ty } = self.field_type(&param.ty, false);
+                        let param = snake_case(&param.name);
+                        writeln!(messages, "  {label}{ty} {param} = {number};").unwrap();
+                    }
+                    messages.push_str("}\n");
+                    request
+                }
+            };
+            let response = match &function.returns {
+                None => {
+                    self.uses_empty = true;
+                    EMPTY.to_string()
+                }
+                Some(returns) => match &returns.ty_ref {
+                    ty_ref @ (AssemblyTypeRef::Custom(_) | AssemblyTypeRef::Instance(..)) => {
+                        self.element(ty_ref)
+                    }
+                    ty_ref => {
+                        let response = format!("{prefix}Response");
+                        let owner = format!("the response of `{}.{}`", service.name, function.name);
+                        self.names.claim(&response, owner);
+                        let FieldType { label, ty } = self.field_type(ty_ref, false);
+                        writeln!(
+                            messages,
+                            "\nmessage {response} {{\n  {label}{ty} value = 1;\n}}"
+                        )
+                        .unwrap();
+                        response
+                    }
+                },
+            };
+            let stream = match &function.returns {
+                Some(returns) if returns.kind == AssemblyFieldTypeKind::Sync => {
+                    out.push_str("  // Sends the current value, then each new value.\n");
+                    "stream "
+                }
+                Some(returns) if returns.kind == AssemblyFieldTypeKind::Stream => "stream ",
+                _ => "",
+            };
+            deprecated_comment(&mut out, "  ", &function.annotations);
+            let body = match deprecation(&function.annotations) {
+                Some(_) => " {\n    option deprecated = true;\n  }",
+                None => ";",
+            };
+            writeln!(
+                out,
+                "  rpc {name}({request}) returns ({stream}{response}){body}"
+            )
+            .unwrap();
+        }
+        out.push_str("}\n");
+        out.push_str(&messages);
+        out
+    }
+}
+
+/// Number given to a field or parameter by `@tag`.
+fn tag(annotations: &[SchemeAnnotation]) -> Option<i64> {
+    match find_annotation(annotations, "tag")?.arg("n")? {
+        SchemeValue::Integer(n) => Some(*n),
+        _ => None,
+    }
+}
+
+fn message_name(ty: &AssemblyType) -> String {
+    renamed(&ty.annotations, "proto")
+        .unwrap_or(&ty.name)
+        .to_string()
+}
+
+/// `get_user` -> `GetUser`.
+fn pascal_case(name: &str) -> String {
+    let mut out = String::new();
+    for word in snake_case(name).split('_') {
+        let mut chars = word.chars();
+        out.extend(chars.next().into_iter().flat_map(char::to_uppercase));
+        out.extend(chars);
+    }
+    out
+}
+
+/// Type of the keys of a protobuf map with keys of type `key`, if they can be map keys.
+fn map_key(key: &AssemblyTypeRef) -> Option<&'static str> {
+    match key {
+        AssemblyTypeRef::Builtin(BuiltinType::Float) => None,
+        AssemblyTypeRef::Builtin(builtin) => Some(builtin_type(*builtin)),
+        _ => None,
+    }
+}
+
+fn builtin_type(builtin: BuiltinType) -> &'static str {
+    match builtin {
+        BuiltinType::Integer => "int64",
+        BuiltinType::Float => "double",
+        BuiltinType::String => "string",
+        BuiltinType::Boolean => "bool",
+    }
+}
+
+fn deprecated_comment(out: &mut String, indent: &str, annotations: &[SchemeAnnotation]) {
+    match deprecation(annotations) {
+        Some("") | None => {}
+        Some(reason) => writeln!(out, "{indent}// Deprecated: {reason}").unwrap(),
+    }
+}
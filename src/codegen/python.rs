@@ -0,0 +1,763 @@
+//! Python backend: a dataclass per type, with `from_json` and `to_json` methods converting JSON
+//! payloads, or a pydantic model per type with [`Flavor::Pydantic`]. Constraints are only checked
+//! by pydantic models.
+
+use super::{constraints, deprecation, live_types, references, renamed, snake_case, Constraint};
+use crate::json::Json;
+use crate::schema::assembly::*;
+use crate::schema::module::{BuiltinType, SchemeAnnotation, SchemeConst, SchemeValue};
+
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashSet};
+use std::fmt::Write;
+use std::rc::Rc;
+
+const KEYWORDS: &[&str] = &[
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue",
+    "def", "del", "elif", "else", "except", "finally", "for", "from", "global", "if", "import",
+    "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while",
+    "with", "yield",
+];
+
+/// Kind of classes generated for the types of a schema.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Flavor {
+    /// Standard library dataclasses, converted with their `from_json` and `to_json` methods.
+    #[default]
+    Dataclasses,
+    /// pydantic models, converted with `model_validate` and `model_dump(by_alias=True)`.
+    Pydantic,
+}
+
+const OBSERVABLE_PRELUDE: &str = r#"_T = TypeVar("_T")
+
+
+class Observable(Generic[_T]):
+    """Current value of a `sync` field, kept up to date by the transport."""
+
+    def __init__(self, value: _T) -> None:
+        self._value = value
+        self._listeners: list[Callable[[_T], None]] = []
+
+    @property
+    def value(self) -> _T:
+        return self._value
+
+    def set(self, value: _T) -> None:
+        """Replaces the value and notifies the listeners, called by the transport."""
+        self._value = value
+        for listener in list(self._listeners):
+            listener(value)
+
+    def subscribe(self, listener: Callable[[_T], None]) -> Callable[[], None]:
+        """Calls `listener` on every change, until the returned function is called."""
+        self._listeners.append(listener)
+        return lambda: self._listeners.remove(listener)
+"#;
+
+const PAIRS_PRELUDE: &str = r#"def _from_pairs(value: Any) -> Any:
+    return dict(value) if isinstance(value, list) else value
+
+
+def _to_pairs(value: dict[Any, Any]) -> list[Any]:
+    return [[key, item] for key, item in value.items()]
+"#;
+
+/// Modules to import names from, collected while generating the code using them.
+#[derive(Default)]
+struct Imports {
+    flavor: Flavor,
+    typing: BTreeSet<&'static str>,
+    pydantic: BTreeSet<&'static str>,
+    /// Whether pydantic models convert maps from and to `[key, value]` pairs.
+    pairs: bool,
+}
+
+pub fn generate(assembly: &Assembly, flavor: Flavor) -> String {
+    let live = live_types(assembly);
+    let mut imports = Imports {
+        flavor,
+        ..Imports::default()
+    };
+    // Top-level statements, separated by two blank lines.
+    let mut sections = Vec::new();
+    if uses_sync(assembly) {
+        imports.typing.extend(["Callable", "Generic", "TypeVar"]);
+        sections.push(OBSERVABLE_PRELUDE.to_string());
+    }
+    let mut params = Vec::new();
+    for ty in &assembly.types {
+        for param in &ty.borrow().params {
+            if !params.contains(param) {
+                params.push(param.clone());
+            }
+        }
+    }
+    if !params.is_empty() {
+        imports.typing.insert("TypeVar");
+        let mut section = String::new();
+        for param in &params {
+            writeln!(section, "{param} = TypeVar({param:?})").unwrap();
+        }
+        sections.push(section);
+    }
+    if !assembly.consts.is_empty() {
+        imports.typing.insert("Final");
+        let mut section = String::new();
+        for constant in &assembly.consts {
+            generate_const(&mut section, constant);
+        }
+        sections.push(section);
+    }
+    let types = base_first(&assembly.types);
+    for ty in &types {
+        let mut section = String::new();
+        let ty = ty.borrow();
+        match flavor {
+            Flavor::Dataclasses => generate_dataclass(&mut section, &ty, &live, &mut imports),
+            Flavor::Pydantic => generate_model(&mut section, &ty, &live, &mut imports),
+        }
+        sections.push(section);
+    }
+    if flavor == Flavor::Pydantic {
+        // Models referring to types declared after them resolve those once all are declared.
+        let mut declared = HashSet::new();
+        let mut section = String::new();
+        for ty in &types {
+            let ty = ty.borrow();
+            declared.insert(ty.name.clone());
+            let refers_forward = ty.fields.iter().any(|field| {
+                references(&field.ty.ty_ref)
+                    .iter()
+                    .any(|name| *name == ty.name || !declared.contains(name))
+            });
+            if refers_forward {
+                writeln!(section, "{}.model_rebuild()", class_name(&ty)).unwrap();
+            }
+        }
+        if !section.is_empty() {
+            sections.push(section);
+        }
+    }
+
+    if imports.pairs {
+        imports.typing.insert("Any");
+        let index = usize::from(uses_sync(assembly));
+        sections.insert(index, PAIRS_PRELUDE.to_string());
+    }
+
+    // Annotations are only evaluated once every class is declared, when they are at all.
+    let mut header = String::from("from __future__ import annotations\n\n");
+    if flavor == Flavor::Dataclasses && !types.is_empty() {
+        header.push_str("import dataclasses\n");
+    }
+    for (module, names) in [("pydantic", &imports.pydantic), ("typing", &imports.typing)] {
+        if !names.is_empty() {
+            let names = names.iter().copied().collect::<Vec<_>>();
+            writeln!(header, "from {module} import {}", names.join(", ")).unwrap();
+        }
+    }
+    sections.insert(0, header.trim_end().to_string() + "\n");
+    format!(
+        "# Generated by rgc. Do not edit.\n\n{}",
+        sections.join("\n\n")
+    )
+}
+
+fn uses_sync(assembly: &Assembly) -> bool {
+    assembly.types.iter().any(|ty| {
+        ty.borrow()
+            .fields
+            .iter()
+            .any(|field| field.ty.kind == AssemblyFieldTypeKind::Sync)
+    })
+}
+
+/// Types in declaration order, except that base types come before the types extending them:
+/// Python needs base classes to be defined first.
+fn base_first(types: &[Rc<RefCell<AssemblyType>>]) -> Vec<Rc<RefCell<AssemblyType>>> {
+    fn visit(
+        ty: &Rc<RefCell<AssemblyType>>,
+        done: &mut HashSet<String>,
+        order: &mut Vec<Rc<RefCell<AssemblyType>>>,
+    ) {
+        if done.contains(&ty.borrow().name) {
+            return;
+        }
+        if let Some(base) = &ty.borrow().base {
+            visit(base, done, order);
+        }
+        done.insert(ty.borrow().name.clone());
+        order.push(ty.clone());
+    }
+    let mut done = HashSet::new();
+    let mut order = Vec::new();
+    for ty in types {
+        visit(ty, &mut done, &mut order);
+    }
+    order
+}
+
+fn generate_const(out: &mut String, constant: &SchemeConst) {
+    deprecated_comment(out, "", &constant.annotations);
+    writeln!(
+        out,
+        "{}: Final[{}] = {}",
+        renamed(&constant.annotations, "py").unwrap_or(&constant.name),
+        builtin_type(constant.ty),
+        value(&constant.value, &AssemblyTypeRef::Builtin(constant.ty))
+    )
+    .unwrap();
+}
+
+/// Header of a class: `class Name(Base, Generic[T]):`, with its docstring.
+fn class_header(out: &mut String, ty: &AssemblyType, root: Option<&str>, imports: &mut Imports) {
+    let mut bases = Vec::new();
+    match &ty.base {
+        Some(base) => bases.push(class_name(&base.borrow()).to_string()),
+        None => bases.extend(root.map(str::to_string)),
+    }
+    if !ty.params.is_empty() {
+        imports.typing.insert("Generic");
+        bases.push(format!("Generic[{}]", ty.params.join(", ")));
+    }
+    if bases.is_empty() {
+        writeln!(out, "class {}:", class_name(ty)).unwrap();
+    } else {
+        writeln!(out, "class {}({}):", class_name(ty), bases.join(", ")).unwrap();
+    }
+    match deprecation(&ty.annotations) {
+        Some("") => out.push_str("    \"\"\"Deprecated.\"\"\"\n\n"),
+        Some(reason) => writeln!(out, "    \"\"\"Deprecated: {reason}\"\"\"\n").unwrap(),
+        None => {}
+    }
+}
+
+fn generate_dataclass(
+    out: &mut String,
+    ty: &AssemblyType,
+    live: &HashSet<String>,
+    imports: &mut Imports,
+) {
+    // Keyword-only fields may be declared in any order, even after inherited ones with defaults.
+    out.push_str("@dataclasses.dataclass(kw_only=True)\n");
+    class_header(out, ty, None, imports);
+    let inherited = ty
+        .base
+        .as_ref()
+        .map_or(0, |base| base.borrow().fields.len());
+    for field in &ty.fields[inherited..] {
+        deprecated_comment(out, "    ", &field.annotations);
+        let field_type = field_type(&field.ty, field.optional, imports);
+        let default = match (&field.default, field.optional) {
+            (_, true) => " = None".to_string(),
+            // Each instance observes a value of its own.
+            (Some(default), false) if field.ty.kind == AssemblyFieldTypeKind::Sync => format!(
+                " = dataclasses.field(default_factory=lambda: Observable({}))",
+                value(default, &field.ty.ty_ref)
+            ),
+            (Some(default), false) => match (default, &field.ty.ty_ref) {
+                // Mutable values are created anew for every instance.
+                (SchemeValue::Array(items), _) if items.is_empty() => {
+                    " = dataclasses.field(default_factory=list)".to_string()
+                }
+                (SchemeValue::Map(entries), _) if entries.is_empty() => {
+                    " = dataclasses.field(default_factory=dict)".to_string()
+                }
+                (SchemeValue::Array(_) | SchemeValue::Map(_), ty_ref) => format!(
+                    " = dataclasses.field(default_factory=lambda: {})",
+                    value(default, ty_ref)
+                ),
+                (default, ty_ref) => format!(" = {}", value(default, ty_ref)),
+            },
+            (None, false) => String::new(),
+        };
+        writeln!(out, "    {}: {field_type}{default}", field_name(field)).unwrap();
+    }
+    generate_from_json(out, ty, live, imports);
+    generate_to_json(out, ty, live, imports);
+}
+
+/// Whether `field` holds values that only exist at runtime: those of a `stream` or `sync` field
+/// or of a type containing one. They are not part of the JSON of a dataclass.
+fn is_live_field(field: &AssemblyField, live: &HashSet<String>) -> bool {
+    field.ty.kind != AssemblyFieldTypeKind::Normal
+        || references(&field.ty.ty_ref)
+            .iter()
+            .any(|name| live.contains(name))
+}
+
+fn generate_from_json(
+    out: &mut String,
+    ty: &AssemblyType,
+    live: &HashSet<String>,
+    imports: &mut Imports,
+) {
+    imports.typing.insert("Any");
+    let mut params = ty
+        .params
+        .iter()
+        .map(|param| {
+            imports.typing.insert("Callable");
+            format!(", decode_{}: Callable[[Any], {param}]", snake_case(param))
+        })
+        .collect::<String>();
+    // Live values are put together by the transport, which passes them along with the JSON.
+    let live_fields = ty
+        .fields
+        .iter()
+        .filter(|field| is_live_field(field, live))
+        .collect::<Vec<_>>();
+    if !live_fields.is_empty() {
+        params.push_str(", *");
+    }
+    for field in &live_fields {
+        let field_type = field_type(&field.ty, field.optional, imports);
+        write!(params, ", {}: {field_type}", field_name(field)).unwrap();
+    }
+    writeln!(
+        out,
+        "\n    @classmethod\n    def from_json(cls, data: Any{params}) -> {}{}:",
+        class_name(ty),
+        generics(ty)
+    )
+    .unwrap();
+    if ty.fields.is_empty() {
+        out.push_str("        return cls()\n");
+        return;
+    }
+    out.push_str("        return cls(\n");
+    for field in &ty.fields {
+        if is_live_field(field, live) {
+            let name = field_name(field);
+            writeln!(out, "            {name}={name},").unwrap();
+            continue;
+        }
+        let access = format!("data[{}]", Json::from(field.name.as_str()));
+        let decoded = decode(&access, &field.ty.ty_ref, 0);
+        let absent = match (&field.default, field.optional) {
+            (_, true) => Some("None".to_string()),
+            (Some(default), false) => Some(value(default, &field.ty.ty_ref)),
+            (None, false) => None,
+        };
+        let expr = match (decoded, absent) {
+            (Some(decoded), Some(absent)) => format!(
+                "{decoded} if {} in data else {absent}",
+                Json::from(field.name.as_str())
+            ),
+            (None, Some(absent)) if absent == "None" => {
+                format!("data.get({})", Json::from(field.name.as_str()))
+            }
+            (None, Some(absent)) => {
+                format!("data.get({}, {absent})", Json::from(field.name.as_str()))
+            }
+            (decoded, None) => decoded.unwrap_or(access),
+        };
+        writeln!(out, "            {}={expr},", field_name(field)).unwrap();
+    }
+    out.push_str("        )\n");
+}
+
+fn generate_to_json(
+    out: &mut String,
+    ty: &AssemblyType,
+    live: &HashSet<String>,
+    imports: &mut Imports,
+) {
+    let params = ty
+        .params
+        .iter()
+        .map(|param| {
+            imports.typing.insert("Callable");
+            format!(", encode_{}: Callable[[{param}], Any]", snake_case(param))
+        })
+        .collect::<String>();
+    writeln!(out, "\n    def to_json(self{params}) -> Any:").unwrap();
+    let (present, optional): (Vec<_>, Vec<_>) = ty
+        .fields
+        .iter()
+        .filter(|field| !is_live_field(field, live))
+        .partition(|field| !field.optional);
+    if optional.is_empty() {
+        out.push_str("        return {\n");
+    } else {
+        out.push_str("        data: dict[str, Any] = {\n");
+    }
+    for field in present {
+        let access = format!("self.{}", field_name(field));
+        let encoded = encode(&access, &field.ty.ty_ref, 0).unwrap_or(access);
+        writeln!(
+            out,
+            "            {}: {encoded},",
+            Json::from(field.name.as_str())
+        )
+        .unwrap();
+    }
+    out.push_str("        }\n");
+    if optional.is_empty() {
+        return;
+    }
+    // Fields that may be absent are left out when they are `None`.
+    for field in optional {
+        let access = format!("self.{}", field_name(field));
+        let encoded = encode(&access, &field.ty.ty_ref, 0).unwrap_or_else(|| access.clone());
+        writeln!(
+            out,
+            "        if {access} is not None:\n            data[{}] = {encoded}",
+            Json::from(field.name.as_str())
+        )
+        .unwrap();
+    }
+    out.push_str("        return data\n");
+}
+
+fn generate_model(
+    out: &mut String,
+    ty: &AssemblyType,
+    live: &HashSet<String>,
+    imports: &mut Imports,
+) {
+    if ty.base.is_none() {
+        imports.pydantic.insert("BaseModel");
+    }
+    class_header(out, ty, Some("BaseModel"), imports);
+    let mut config = Vec::new();
+    if ty.base.is_none() {
+        // Models may be created with the Python names of their fields as well.
+        config.push("populate_by_name=True");
+    }
+    // `stream` fields and `Observable`s are not validated by pydantic.
+    let base_live = ty
+        .base
+        .as_ref()
+        .is_some_and(|base| live.contains(&base.borrow().name));
+    if live.contains(&ty.name) && !base_live {
+        config.push("arbitrary_types_allowed=True");
+    }
+    if !config.is_empty() {
+        imports.pydantic.insert("ConfigDict");
+        writeln!(out, "    model_config = ConfigDict({})", config.join(", ")).unwrap();
+    }
+    let inherited = ty
+        .base
+        .as_ref()
+        .map_or(0, |base| base.borrow().fields.len());
+    for field in &ty.fields[inherited..] {
+        deprecated_comment(out, "    ", &field.annotations);
+        let name = field_name(field);
+        let field_type = field_type(&field.ty, field.optional, imports);
+        let mut args = Vec::new();
+        // Each instance observes a value of its own.
+        let factory = field.ty.kind == AssemblyFieldTypeKind::Sync && !field.optional;
+        if field.optional {
+            args.push("None".to_string());
+        } else if let Some(default) = &field.default {
+            match factory {
+                true => args.push(format!(
+                    "default_factory=lambda: Observable({})",
+                    value(default, &field.ty.ty_ref)
+                )),
+                false => args.push(value(default, &field.ty.ty_ref)),
+            }
+        }
+        if name != field.name {
+            args.push(format!("alias={}", Json::from(field.name.as_str())));
+        }
+        for constraint in constraints(field) {
+            match constraint {
+                Constraint::Range { min, max } => {
+                    args.extend(min.map(|min| format!("ge={}", number(min))));
+                    args.extend(max.map(|max| format!("le={}", number(max))));
+                }
+                Constraint::Pattern(regex) => {
+                    args.push(format!("pattern={}", Json::from(regex)));
+                }
+                Constraint::MinLen(len) | Constraint::MinItems(len) => {
+                    args.push(format!("min_length={len}"));
+                }
+                Constraint::MaxLen(len) | Constraint::MaxItems(len) => {
+                    args.push(format!("max_length={len}"));
+                }
+            }
+        }
+        let has_default = field.optional || field.default.is_some();
+        let default = match args.len() {
+            0 => String::new(),
+            1 if has_default && !factory => format!(" = {}", args[0]),
+            _ => {
+                imports.pydantic.insert("Field");
+                format!(" = Field({})", args.join(", "))
+            }
+        };
+        writeln!(out, "    {name}: {field_type}{default}").unwrap();
+    }
+    if config.is_empty() && ty.fields.len() == inherited && deprecation(&ty.annotations).is_none() {
+        out.push_str("    pass\n");
+    }
+}
+
+/// Expression converting the JSON value `expr` to `ty_ref`, or `None` if it can be used as is.
+/// `depth` keeps the names of nested comprehension variables apart.
+fn decode(expr: &str, ty_ref: &AssemblyTypeRef, depth: usize) -> Option<String> {
+    match ty_ref {
+        AssemblyTypeRef::Builtin(_) => None,
+        AssemblyTypeRef::Custom(ty) => {
+            Some(format!("{}.from_json({expr})", class_name(&ty.borrow())))
+        }
+        AssemblyTypeRef::Instance(ty, args) => {
+            let decoders = args
+                .iter()
+                .map(|arg| format!(", {}", decoder(arg, depth)))
+                .collect::<String>();
+            Some(format!(
+                "{}.from_json({expr}{decoders})",
+                class_name(&ty.borrow())
+            ))
+        }
+        AssemblyTypeRef::Param(name) => Some(format!("decode_{}({expr})", snake_case(name))),
+        AssemblyTypeRef::Optional(item) => {
+            let decoded = decode(expr, item, depth)?;
+            Some(format!("None if {expr} is None else {decoded}"))
+        }
+        AssemblyTypeRef::Array(item) => {
+            let name = format!("item{depth}");
+            let decoded = decode(&name, item, depth + 1)?;
+            Some(format!("[{decoded} for {name} in {expr}]"))
+        }
+        AssemblyTypeRef::Map(key, item) => {
+            let (k, v) = (format!("key{depth}"), format!("value{depth}"));
+            if is_object_key(key) {
+                // JSON object keys are strings, numbers are parsed back.
+                let decoded_key = match **key {
+                    AssemblyTypeRef::Builtin(BuiltinType::Integer) => Some(format!("int({k})")),
+                    AssemblyTypeRef::Builtin(BuiltinType::Float) => Some(format!("float({k})")),
+                    _ => None,
+                };
+                let decoded = decode(&v, item, depth + 1);
+                if decoded_key.is_none() && decoded.is_none() {
+                    return None;
+                }
+                Some(format!(
+                    "{{{}: {} for {k}, {v} in {expr}.items()}}",
+                    decoded_key.unwrap_or_else(|| k.clone()),
+                    decoded.unwrap_or_else(|| v.clone())
+                ))
+            } else {
+                // Other keys cannot be JSON object keys, such maps are sent as `[key, value]` pairs.
+                let decoded_key = decode(&k, key, depth + 1).unwrap_or_else(|| k.clone());
+                let decoded = decode(&v, item, depth + 1).unwrap_or_else(|| v.clone());
+                Some(format!(
+                    "{{{decoded_key}: {decoded} for {k}, {v} in {expr}}}"
+                ))
+            }
+        }
+    }
+}
+
+/// Function converting JSON values to `ty_ref`, given to the decoders of generic types.
+fn decoder(ty_ref: &AssemblyTypeRef, depth: usize) -> String {
+    match ty_ref {
+        AssemblyTypeRef::Custom(ty) => format!("{}.from_json", class_name(&ty.borrow())),
+        AssemblyTypeRef::Param(name) => format!("decode_{}", snake_case(name)),
+        ty_ref => {
+            let name = format!("json{depth}");
+            let decoded = decode(&name, ty_ref, depth + 1).unwrap_or_else(|| name.clone());
+            format!("lambda {name}: {decoded}")
+        }
+    }
+}
+
+/// Expression converting `expr`, a value of type `ty_ref`, to JSON, or `None` if it can be used
+/// as is.
+fn encode(expr: &str, ty_ref: &AssemblyTypeRef, depth: usize) -> Option<String> {
+    match ty_ref {
+        AssemblyTypeRef::Builtin(_) => None,
+        AssemblyTypeRef::Custom(_) => Some(format!("{expr}.to_json()")),
+        AssemblyTypeRef::Instance(_, args) => {
+            let encoders = args
+                .iter()
+                .map(|arg| encoder(arg, depth))
+                .collect::<Vec<_>>();
+            Some(format!("{expr}.to_json({})", encoders.join(", ")))
+        }
+        AssemblyTypeRef::Param(name) => Some(format!("encode_{}({expr})", snake_case(name))),
+        AssemblyTypeRef::Optional(item) => {
+            let encoded = encode(expr, item, depth)?;
+            Some(format!("None if {expr} is None else {encoded}"))
+        }
+        AssemblyTypeRef::Array(item) => {
+            let name = format!("item{depth}");
+            let encoded = encode(&name, item, depth + 1)?;
+            Some(format!("[{encoded} for {name} in {expr}]"))
+        }
+        AssemblyTypeRef::Map(key, item) => {
+            let (k, v) = (format!("key{depth}"), format!("value{depth}"));
+            if is_object_key(key) {
+                // `json.dumps` turns number keys into strings.
+                let encoded = encode(&v, item, depth + 1)?;
+                Some(format!("{{{k}: {encoded} for {k}, {v} in {expr}.items()}}"))
+            } else {
+                let encoded_key = encode(&k, key, depth + 1).unwrap_or_else(|| k.clone());
+                let encoded = encode(&v, item, depth + 1).unwrap_or_else(|| v.clone());
+                Some(format!(
+                    "[[{encoded_key}, {encoded}] for {k}, {v} in {expr}.items()]"
+                ))
+            }
+        }
+    }
+}
+
+/// Function converting values of type `ty_ref` to JSON, given to the encoders of generic types.
+fn encoder(ty_ref: &AssemblyTypeRef, depth: usize) -> String {
+    match ty_ref {
+        AssemblyTypeRef::Param(name) => format!("encode_{}", snake_case(name)),
+        ty_ref => {
+            let name = format!("value{depth}");
+            let encoded = encode(&name, ty_ref, depth + 1).unwrap_or_else(|| name.clone());
+            format!("lambda {name}: {encoded}")
+        }
+    }
+}
+
+/// Whether maps with this key type are JSON objects.
+fn is_object_key(key: &AssemblyTypeRef) -> bool {
+    matches!(
+        key,
+        AssemblyTypeRef::Builtin(BuiltinType::String | BuiltinType::Integer | BuiltinType::Float)
+    )
+}
+
+fn deprecated_comment(out: &mut String, indent: &str, annotations: &[SchemeAnnotation]) {
+    match deprecation(annotations) {
+        Some("") => writeln!(out, "{indent}# Deprecated."),
+        Some(reason) => writeln!(out, "{indent}# Deprecated: {reason}"),
+        None => return,
+    }
+    .unwrap();
+}
+
+fn class_name(ty: &AssemblyType) -> &str {
+    renamed(&ty.annotations, "py").unwrap_or(&ty.name)
+}
+
+/// `[T, U]` for generic types, empty otherwise.
+fn generics(ty: &AssemblyType) -> String {
+    if ty.params.is_empty() {
+        String::new()
+    } else {
+        format!("[{}]", ty.params.join(", "))
+    }
+}
+
+fn field_name(field: &AssemblyField) -> String {
+    let name =
+        renamed(&field.annotations, "py").map_or_else(|| snake_case(&field.name), str::to_string);
+    if KEYWORDS.contains(&name.as_str()) {
+        format!("{name}_")
+    } else {
+        name
+    }
+}
+
+/// Fields that may be absent are `None` when they are, like `null` ones.
+fn field_type(ty: &AssemblyFieldType, optional: bool, imports: &mut Imports) -> String {
+    let inner = type_ref(&ty.ty_ref, imports);
+    let field_type = match ty.kind {
+        AssemblyFieldTypeKind::Normal => inner,
+        AssemblyFieldTypeKind::Stream => {
+            imports.typing.insert("AsyncIterator");
+            format!("AsyncIterator[{inner}]")
+        }
+        AssemblyFieldTypeKind::Sync => format!("Observable[{inner}]"),
+    };
+    if optional && !matches!(ty.ty_ref, AssemblyTypeRef::Optional(_)) {
+        imports.typing.insert("Optional");
+        format!("Optional[{field_type}]")
+    } else {
+        field_type
+    }
+}
+
+fn type_ref(ty_ref: &AssemblyTypeRef, imports: &mut Imports) -> String {
+    match ty_ref {
+        AssemblyTypeRef::Builtin(builtin) => builtin_type(*builtin).to_string(),
+        AssemblyTypeRef::Custom(ty) => class_name(&ty.borrow()).to_string(),
+        AssemblyTypeRef::Instance(ty, args) => {
+            let args = args
+                .iter()
+                .map(|arg| type_ref(arg, imports))
+                .collect::<Vec<_>>();
+            format!("{}[{}]", class_name(&ty.borrow()), args.join(", "))
+        }
+        AssemblyTypeRef::Param(name) => name.clone(),
+        AssemblyTypeRef::Array(item) => format!("list[{}]", type_ref(item, imports)),
+        AssemblyTypeRef::Optional(item) => {
+            imports.typing.insert("Optional");
+            format!("Optional[{}]", type_ref(item, imports))
+        }
+        AssemblyTypeRef::Map(key, value) => {
+            let map = format!(
+                "dict[{}, {}]",
+                type_ref(key, imports),
+                type_ref(value, imports)
+            );
+            if imports.flavor == Flavor::Pydantic && !is_object_key(key) {
+                // Sent as `[key, value]` pairs, like by `to_json` in dataclasses.
+                imports.pairs = true;
+                imports.typing.insert("Annotated");
+                imports
+                    .pydantic
+                    .extend(["BeforeValidator", "PlainSerializer"]);
+                format!(
+                    "Annotated[{map}, BeforeValidator(_from_pairs), PlainSerializer(_to_pairs)]"
+                )
+            } else {
+                map
+            }
+        }
+    }
+}
+
+fn builtin_type(builtin: BuiltinType) -> &'static str {
+    match builtin {
+        BuiltinType::Integer => "int",
+        BuiltinType::Float => "float",
+        BuiltinType::String => "str",
+        BuiltinType::Boolean => "bool",
+    }
+}
+
+fn number(value: &SchemeValue) -> String {
+    match value {
+        SchemeValue::Integer(n) => n.to_string(),
+        SchemeValue::Float(x) => format!("{x:?}"),
+        _ => unreachable!("range bounds are numbers"),
+    }
+}
+
+fn value(value: &SchemeValue, ty_ref: &AssemblyTypeRef) -> String {
+    match (value, ty_ref) {
+        (SchemeValue::Null, _) => "None".to_string(),
+        (value, AssemblyTypeRef::Optional(item)) => self::value(value, item),
+        (SchemeValue::Integer(n), _) => n.to_string(),
+        (SchemeValue::Float(x), _) => format!("{x:?}"),
+        (SchemeValue::String(s), _) => Json::from(s.as_str()).to_string(),
+        (SchemeValue::Boolean(true), _) => "True".to_string(),
+        (SchemeValue::Boolean(false), _) => "False".to_string(),
+        (SchemeValue::Array(items), AssemblyTypeRef::Array(item)) => {
+            let items = items
+                .iter()
+                .map(|v| self::value(v, item))
+                .collect::<Vec<_>>();
+            format!("[{}]", items.join(", "))
+        }
+        (SchemeValue::Map(entries), AssemblyTypeRef::Map(key, item)) => {
+            let entries = entries
+                .iter()
+                .map(|(k, v)| format!("{}: {}", self::value(k, key), self::value(v, item)))
+                .collect::<Vec<_>>();
+            format!("{{{}}}", entries.join(", "))
+        }
+        _ => unreachable!("default values are checked against the field type"),
+    }
+}
@@ -0,0 +1,769 @@
+//! Rust backend: one struct per type, (de)serialized with serde.
+
+use super::{
+    constraints, contains_inline, contains_validated, deprecation, live_types, renamed, snake_case,
+    uses_kind, validated_params, validated_types, value_type, Constraint,
+};
+use crate::schema::assembly::*;
+use crate::schema::module::{BuiltinType, SchemeAnnotation, SchemeConst, SchemeValue};
+
+use std::collections::HashSet;
+use std::fmt::Write;
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "dyn", "else", "enum", "extern", "false",
+    "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref",
+    "return", "static", "struct", "trait", "true", "type", "unsafe", "use", "where", "while",
+    "abstract", "become", "box", "do", "final", "macro", "override", "priv", "try", "typeof",
+    "unsized", "virtual", "yield",
+];
+
+/// Keywords that cannot be raw identifiers either.
+const PATH_KEYWORDS: &[&str] = &["self", "Self", "crate", "super"];
+
+pub fn generate(assembly: &Assembly) -> String {
+    let live = live_types(assembly);
+    let mut out = String::from("// Generated by rgc. Do not edit.\n");
+    if assembly
+        .types
+        .iter()
+        .any(|ty| !live.contains(&ty.borrow().name))
+    {
+        out.push_str("\nuse serde::{Deserialize, Serialize};\n");
+    }
+    if uses_kind(assembly, AssemblyFieldTypeKind::Stream) {
+        out.push_str(
+            "\n/// Items of a `stream` field, produced over time by the transport.\n\
+             pub type Stream<T> = std::pin::Pin<Box<dyn futures_core::Stream<Item = T> + Send>>;\n",
+        );
+    }
+    if uses_kind(assembly, AssemblyFieldTypeKind::Sync) {
+        out.push_str(
+            "\n/// Current value of a `sync` field, kept up to date by the transport.\n\
+             pub type SyncValue<T> = tokio::sync::watch::Receiver<T>;\n",
+        );
+    }
+    // Absent `Optional` fields are `None`, but `null` ones are `Some(None)`.
+    let keeps_null = assembly.types.iter().any(|ty| {
+        let ty = ty.borrow();
+        !live.contains(&ty.name)
+            && ty.fields.iter().any(|field| {
+                field.optional && matches!(field.ty.ty_ref, AssemblyTypeRef::Optional(_))
+            })
+    });
+    if keeps_null {
+        out.push_str(
+            "\n/// Deserializes a field that may be absent, so that only an absent field is `None`.\n\
+             fn deserialize_present<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>\n\
+             where\n    \
+                 T: Deserialize<'de>,\n    \
+                 D: serde::Deserializer<'de>,\n\
+             {\n    \
+                 T::deserialize(deserializer).map(Some)\n\
+             }\n",
+        );
+    }
+    let validated = validated_types(assembly);
+    if !validated.is_empty() {
+        out.push_str(VALIDATION_PRELUDE);
+    }
+    if !assembly.consts.is_empty() {
+        out.push('\n');
+        for constant in &assembly.consts {
+            generate_const(&mut out, constant);
+        }
+    }
+    for ty in &assembly.types {
+        out.push('\n');
+        generate_struct(&mut out, &ty.borrow(), &live);
+        if validated.contains(&ty.borrow().name) {
+            out.push('\n');
+            generate_validator(&mut out, &ty.borrow(), &validated);
+        }
+    }
+    for service in &assembly.services {
+        out.push('\n');
+        generate_service(&mut out, service);
+    }
+    out
+}
+
+const VALIDATION_PRELUDE: &str = r#"
+/// A value that breaks a constraint of the schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    /// Location of the value, e.g. `posts[0].title`.
+    pub path: String,
+    /// Name of the broken constraint, e.g. `max_len`.
+    pub constraint: &'static str,
+    pub message: String,
+}
+
+pub trait Validate {
+    /// Appends the constraint violations of `self` to `errors`. `path` is prepended to the
+    /// location of every error.
+    fn validate_at(&self, path: &str, errors: &mut Vec<ValidationError>);
+
+    fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        let mut errors = Vec::new();
+        self.validate_at("", &mut errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+"#;
+
+fn generate_struct(out: &mut String, ty: &AssemblyType, live: &HashSet<String>) {
+    let is_live = live.contains(&ty.name);
+    let type_name = type_name(ty);
+    let generics = generics(ty);
+    deprecated_attribute(out, "", &ty.annotations);
+    if !is_live {
+        out.push_str("#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]\n");
+    }
+    writeln!(out, "pub struct {type_name}{generics} {{").unwrap();
+    for field in &ty.fields {
+        let name = field_name(field);
+        deprecated_attribute(out, "    ", &field.annotations);
+        if !is_live {
+            let mut attributes = Vec::new();
+            if name.trim_start_matches("r#") != field.name {
+                attributes.push(format!("rename = {:?}", field.name));
+            }
+            if field.optional {
+                attributes.push("default".to_string());
+                attributes.push("skip_serializing_if = \"Option::is_none\"".to_string());
+                if matches!(field.ty.ty_ref, AssemblyTypeRef::Optional(_)) {
+                    attributes.push("deserialize_with = \"deserialize_present\"".to_string());
+                }
+            }
+            if field.default.is_some() {
+                attributes.push(format!(
+                    "default = \"{type_name}{}::default_{}\"",
+                    match generics.as_str() {
+                        "" => String::new(),
+                        generics => format!("::{generics}"),
+                    },
+                    name.trim_start_matches("r#")
+                ));
+            }
+            if !attributes.is_empty() {
+                writeln!(out, "    #[serde({})]", attributes.join(", ")).unwrap();
+            }
+        }
+        let field_type = field_type(&ty.name, &field.ty, true);
+        if field.optional {
+            writeln!(out, "    pub {name}: Option<{field_type}>,").unwrap();
+        } else {
+            writeln!(out, "    pub {name}: {field_type},").unwrap();
+        }
+    }
+    out.push_str("}\n");
+
+    let defaults = ty
+        .fields
+        .iter()
+        .filter_map(|field| Some((field, field.default.as_ref()?)))
+        .collect::<Vec<_>>();
+    if defaults.is_empty() {
+        return;
+    }
+    // The generated code itself should not warn about deprecated types and fields.
+    let allow_deprecated = if deprecation(&ty.annotations).is_some()
+        || ty
+            .fields
+            .iter()
+            .any(|field| deprecation(&field.annotations).is_some())
+    {
+        "#[allow(deprecated)]\n"
+    } else {
+        ""
+    };
+    writeln!(
+        out,
+        "\n{allow_deprecated}impl{generics} {type_name}{generics} {{"
+    )
+    .unwrap();
+    for (i, (field, default)) in defaults.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        writeln!(
+            out,
+            "    pub fn default_{}() -> {} {{\n        {}\n    }}",
+            field_name(field).trim_start_matches("r#"),
+            field_type(&ty.name, &field.ty, true),
+            default_value(field, default)
+        )
+        .unwrap();
+    }
+    out.push_str("}\n");
+
+    // Fields without a default value must have an obvious empty value to implement `Default`.
+    let implements_default = !is_live
+        && ty.fields.iter().all(|field| {
+            field.default.is_some()
+                || field.optional
+                || !matches!(
+                    field.ty.ty_ref,
+                    AssemblyTypeRef::Custom(_)
+                        | AssemblyTypeRef::Instance(..)
+                        | AssemblyTypeRef::Param(_)
+                )
+        });
+    if !implements_default {
+        return;
+    }
+    writeln!(
+        out,
+        "\n{allow_deprecated}impl{generics} Default for {type_name}{generics} {{\n    fn default() -> Self {{\n        Self {{"
+    )
+    .unwrap();
+    for field in &ty.fields {
+        let name = field_name(field);
+        match field.default {
+            Some(_) => writeln!(
+                out,
+                "            {name}: Self::default_{}(),",
+                name.trim_start_matches("r#")
+            ),
+            None => writeln!(out, "            {name}: Default::default(),"),
+        }
+        .unwrap();
+    }
+    out.push_str("        }\n    }\n}\n");
+}
+
+/// Default value of `field`. The value of a `sync` field comes from a channel whose sender is
+/// dropped, so it stays the default until the transport hands out another one.
+fn default_value(field: &AssemblyField, default: &SchemeValue) -> String {
+    let value = value(default, &field.ty.ty_ref);
+    match field.ty.kind {
+        AssemblyFieldTypeKind::Sync => format!("tokio::sync::watch::channel({value}).1"),
+        _ => value,
+    }
+}
+
+fn generate_validator(out: &mut String, ty: &AssemblyType, validated: &HashSet<String>) {
+    let generics = generics(ty);
+    let params = validated_params(ty);
+    if params.is_empty() {
+        writeln!(
+            out,
+            "impl{generics} Validate for {}{generics} {{",
+            type_name(ty)
+        )
+        .unwrap();
+        out.push_str(
+            "    fn validate_at(&self, path: &str, errors: &mut Vec<ValidationError>) {\n",
+        );
+    } else {
+        // Values of type parameters are checked by validators given for them.
+        let validators = params
+            .iter()
+            .map(|param| format!("`{}` for `{param}`", param_validator(param)))
+            .collect::<Vec<_>>();
+        writeln!(
+            out,
+            "impl{generics} {}{generics} {{\n    \
+             /// Validates `self` like [`Validate::validate_at`], checking the values of type \
+             parameters\n    \
+             /// with {}.\n    \
+             pub fn validate_with(\n        \
+                 &self,\n        \
+                 path: &str,\n        \
+                 errors: &mut Vec<ValidationError>,",
+            type_name(ty),
+            validators.join(", ")
+        )
+        .unwrap();
+        for param in &params {
+            writeln!(
+                out,
+                "        {}: &dyn Fn(&{param}, &str, &mut Vec<ValidationError>),",
+                param_validator(param)
+            )
+            .unwrap();
+        }
+        out.push_str("    ) {\n");
+    }
+    for field in &ty.fields {
+        let access = format!("self.{}", field_name(field));
+        let constraints = constraints(field);
+        if !constraints.is_empty() {
+            // Constraints apply to the value inside `Optional`s, when there is one.
+            let value_type = value_type(field);
+            let mut ty_ref = &value_type;
+            let mut expr = access.clone();
+            let mut indent = "        ".to_string();
+            let mut depth = 0;
+            while let AssemblyTypeRef::Optional(item) = ty_ref {
+                writeln!(out, "{indent}if let Some(value{depth}) = &{expr} {{").unwrap();
+                expr = format!("(*value{depth})");
+                indent.push_str("    ");
+                depth += 1;
+                ty_ref = item;
+            }
+            for constraint in &constraints {
+                check(out, &indent, &expr, &field.name, constraint);
+            }
+            while depth > 0 {
+                indent.truncate(indent.len() - 4);
+                writeln!(out, "{indent}}}").unwrap();
+                depth -= 1;
+            }
+        }
+        if field.ty.kind == AssemblyFieldTypeKind::Normal {
+            nested(
+                out,
+                "        ",
+                &access,
+                &value_type(field),
+                &field.name,
+                0,
+                validated,
+                &params,
+            );
+        }
+    }
+    out.push_str("    }\n}\n");
+    if !params.is_empty() {
+        writeln!(
+            out,
+            "\nimpl{generics} Validate for {}{generics} {{\n    \
+             fn validate_at(&self, path: &str, errors: &mut Vec<ValidationError>) {{\n        \
+                 self.validate_with(path, errors{});\n    \
+             }}\n\
+             }}",
+            type_name(ty),
+            ", &|_, _, _| {}".repeat(params.len())
+        )
+        .unwrap();
+    }
+}
+
+/// Name of the argument of `validate_with` that validates values of the type parameter `param`.
+fn param_validator(param: &str) -> String {
+    format!("validate_{}", snake_case(param))
+}
+
+fn check(out: &mut String, indent: &str, expr: &str, path: &str, constraint: &Constraint) {
+    let (condition, message) = match constraint {
+        Constraint::Range { min, max } => {
+            if let Some(min) = min {
+                let min = number(min);
+                push_error(
+                    out,
+                    indent,
+                    &format!("{expr} < {min}"),
+                    path,
+                    constraint,
+                    &format!("must be at least {min}"),
+                );
+            }
+            if let Some(max) = max {
+                let max = number(max);
+                push_error(
+                    out,
+                    indent,
+                    &format!("{expr} > {max}"),
+                    path,
+                    constraint,
+                    &format!("must be at most {max}"),
+                );
+            }
+            return;
+        }
+        Constraint::Pattern(regex) => {
+            // Compiled once, on first use.
+            let name = format!("{}_pattern", snake_case(path));
+            writeln!(
+                out,
+                "{indent}static {}: std::sync::OnceLock<regex::Regex> = std::sync::OnceLock::new();\n\
+                 {indent}let {name} = {}.get_or_init(|| regex::Regex::new({regex:?}).unwrap());",
+                name.to_uppercase(),
+                name.to_uppercase()
+            )
+            .unwrap();
+            (
+                format!("!{name}.is_match(&{expr})"),
+                format!("must match the pattern {regex}"),
+            )
+        }
+        Constraint::MinLen(len) => (
+            format!("{expr}.chars().count() < {len}"),
+            format!("must have at least {len} characters"),
+        ),
+        Constraint::MaxLen(len) => (
+            format!("{expr}.chars().count() > {len}"),
+            format!("must have at most {len} characters"),
+        ),
+        Constraint::MinItems(count) => (
+            format!("{expr}.len() < {count}"),
+            format!("must have at least {count} items"),
+        ),
+        Constraint::MaxItems(count) => (
+            format!("{expr}.len() > {count}"),
+            format!("must have at most {count} items"),
+        ),
+    };
+    push_error(out, indent, &condition, path, constraint, &message);
+}
+
+fn push_error(
+    out: &mut String,
+    indent: &str,
+    condition: &str,
+    path: &str,
+    constraint: &Constraint,
+    message: &str,
+) {
+    writeln!(
+        out,
+        "{indent}if {condition} {{\n\
+         {indent}    errors.push(ValidationError {{\n\
+         {indent}        path: format!(\"{{path}}{path}\"),\n\
+         {indent}        constraint: {:?},\n\
+         {indent}        message: {message:?}.to_string(),\n\
+         {indent}    }});\n\
+         {indent}}}",
+        constraint.name()
+    )
+    .unwrap();
+}
+
+fn number(value: &SchemeValue) -> String {
+    match value {
+        SchemeValue::Float(x) => format!("{x:?}"),
+        SchemeValue::Integer(n) => n.to_string(),
+        _ => unreachable!("bounds are checked against the field type"),
+    }
+}
+
+/// Validates the values of validated types found in `expr`, a place of type `ty_ref` located at
+/// `path`, and the values of the type parameters `params`.
+#[allow(clippy::too_many_arguments)]
+fn nested(
+    out: &mut String,
+    indent: &str,
+    expr: &str,
+    ty_ref: &AssemblyTypeRef,
+    path: &str,
+    depth: usize,
+    validated: &HashSet<String>,
+    params: &[String],
+) {
+    if !contains_validated(ty_ref, validated, params) {
+        return;
+    }
+    let inner = format!("{indent}    ");
+    match ty_ref {
+        AssemblyTypeRef::Instance(ty, args) if !validated_params(&ty.borrow()).is_empty() => {
+            let ty = ty.borrow();
+            let held = validated_params(&ty);
+            let mut validators = Vec::new();
+            for (param, arg) in ty.params.iter().zip(args) {
+                if !held.contains(param) {
+                    continue;
+                }
+                validators.push(match arg {
+                    AssemblyTypeRef::Param(name) if params.contains(name) => param_validator(name),
+                    arg if !contains_validated(arg, validated, params) => {
+                        "&|_, _, _| {}".to_string()
+                    }
+                    arg => {
+                        // Paths given to validators of parameters locate the value itself.
+                        let mut closure = "&|value, path, errors| {\n".to_string();
+                        let body = format!("{inner}    ");
+                        nested(&mut closure, &body, "value", arg, "", 0, validated, params);
+                        write!(closure, "{inner}}}").unwrap();
+                        closure
+                    }
+                });
+            }
+            let path = format!("&format!(\"{{path}}{path}.\")");
+            if validators.iter().any(|validator| validator.contains('\n')) {
+                writeln!(
+                    out,
+                    "{indent}{expr}.validate_with(\n{inner}{path},\n{inner}errors,"
+                )
+                .unwrap();
+                for validator in validators {
+                    writeln!(out, "{inner}{validator},").unwrap();
+                }
+                writeln!(out, "{indent});").unwrap();
+            } else {
+                writeln!(
+                    out,
+                    "{indent}{expr}.validate_with({path}, errors, {});",
+                    validators.join(", ")
+                )
+                .unwrap();
+            }
+        }
+        AssemblyTypeRef::Custom(_) | AssemblyTypeRef::Instance(..) => {
+            writeln!(
+                out,
+                "{indent}{expr}.validate_at(&format!(\"{{path}}{path}.\"), errors);"
+            )
+            .unwrap();
+        }
+        AssemblyTypeRef::Param(name) => {
+            // Fields are places, other expressions are bound to references.
+            let value = match expr.starts_with("self.") {
+                true => format!("&{expr}"),
+                false => expr.to_string(),
+            };
+            writeln!(
+                out,
+                "{indent}{}({value}, &format!(\"{{path}}{path}\"), errors);",
+                param_validator(name)
+            )
+            .unwrap();
+        }
+        AssemblyTypeRef::Optional(item) => {
+            let value = format!("value{depth}");
+            writeln!(out, "{indent}if let Some({value}) = &{expr} {{").unwrap();
+            nested(
+                out,
+                &inner,
+                &value,
+                item,
+                path,
+                depth + 1,
+                validated,
+                params,
+            );
+            writeln!(out, "{indent}}}").unwrap();
+        }
+        AssemblyTypeRef::Array(item) => {
+            let (i, value) = (format!("i{depth}"), format!("item{depth}"));
+            writeln!(
+                out,
+                "{indent}for ({i}, {value}) in {expr}.iter().enumerate() {{"
+            )
+            .unwrap();
+            nested(
+                out,
+                &inner,
+                &value,
+                item,
+                &format!("{path}[{{{i}}}]"),
+                depth + 1,
+                validated,
+                params,
+            );
+            writeln!(out, "{indent}}}").unwrap();
+        }
+        AssemblyTypeRef::Map(_, item) => {
+            let (key, value) = (format!("key{depth}"), format!("value{depth}"));
+            writeln!(out, "{indent}for ({key}, {value}) in {expr}.iter() {{").unwrap();
+            nested(
+                out,
+                &inner,
+                &value,
+                item,
+                &format!("{path}[{{{key}:?}}]"),
+                depth + 1,
+                validated,
+                params,
+            );
+            writeln!(out, "{indent}}}").unwrap();
+        }
+        AssemblyTypeRef::Builtin(_) => {}
+    }
+}
+
+fn generate_const(out: &mut String, constant: &SchemeConst) {
+    deprecated_attribute(out, "", &constant.annotations);
+    let ty = match constant.ty {
+        BuiltinType::String => "&str",
+        ty => builtin_type(ty),
+    };
+    let value = match &constant.value {
+        SchemeValue::String(s) => format!("{s:?}"),
+        value => self::value(value, &AssemblyTypeRef::Builtin(constant.ty)),
+    };
+    writeln!(
+        out,
+        "pub const {}: {ty} = {value};",
+        renamed(&constant.annotations, "rust").unwrap_or(&constant.name)
+    )
+    .unwrap();
+}
+
+/// Servers implement the trait of each service, one method per remote function.
+fn generate_service(out: &mut String, service: &AssemblyService) {
+    deprecated_attribute(out, "", &service.annotations);
+    writeln!(
+        out,
+        "pub trait {}: Send + Sync {{",
+        renamed(&service.annotations, "rust").unwrap_or(&service.name)
+    )
+    .unwrap();
+    for (i, function) in service.functions.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        deprecated_attribute(out, "    ", &function.annotations);
+        let params = function
+            .params
+            .iter()
+            .map(|param| {
+                format!(
+                    "{}: {}",
+                    escape(snake_case(&param.name)),
+                    type_ref("", &param.ty, false)
+                )
+            })
+            .collect::<Vec<_>>();
+        let name = escape(
+            renamed(&function.annotations, "rust")
+                .map_or_else(|| snake_case(&function.name), str::to_string),
+        );
+        // `stream` and `sync` values are handed out at once and then updated by the transport.
+        let returns = match &function.returns {
+            Some(ty) if ty.kind != AssemblyFieldTypeKind::Normal => field_type("", ty, false),
+            returns => format!(
+                "impl std::future::Future<Output = {}> + Send",
+                returns
+                    .as_ref()
+                    .map_or_else(|| "()".to_string(), |ty| field_type("", ty, false))
+            ),
+        };
+        writeln!(
+            out,
+            "    fn {name}(&self{}) -> {returns};",
+            params
+                .iter()
+                .map(|param| format!(", {param}"))
+                .collect::<String>()
+        )
+        .unwrap();
+    }
+    out.push_str("}\n");
+}
+
+fn deprecated_attribute(out: &mut String, indent: &str, annotations: &[SchemeAnnotation]) {
+    match deprecation(annotations) {
+        Some("") => writeln!(out, "{indent}#[deprecated]"),
+        Some(reason) => writeln!(out, "{indent}#[deprecated(note = {reason:?})]"),
+        None => return,
+    }
+    .unwrap();
+}
+
+fn type_name(ty: &AssemblyType) -> String {
+    renamed(&ty.annotations, "rust")
+        .unwrap_or(&ty.name)
+        .to_string()
+}
+
+/// `<T, U>` for generic types, empty otherwise.
+fn generics(ty: &AssemblyType) -> String {
+    if ty.params.is_empty() {
+        String::new()
+    } else {
+        format!("<{}>", ty.params.join(", "))
+    }
+}
+
+fn field_name(field: &AssemblyField) -> String {
+    escape(
+        renamed(&field.annotations, "rust").map_or_else(|| snake_case(&field.name), str::to_string),
+    )
+}
+
+/// Raw identifier for names that are Rust keywords, or the name followed by `_` for those that
+/// cannot be raw identifiers.
+fn escape(name: String) -> String {
+    if PATH_KEYWORDS.contains(&name.as_str()) {
+        format!("{name}_")
+    } else if KEYWORDS.contains(&name.as_str()) {
+        format!("r#{name}")
+    } else {
+        name
+    }
+}
+
+fn field_type(owner: &str, ty: &AssemblyFieldType, inline: bool) -> String {
+    let inner = type_ref(owner, &ty.ty_ref, inline);
+    match ty.kind {
+        AssemblyFieldTypeKind::Normal => inner,
+        AssemblyFieldTypeKind::Stream => format!("Stream<{inner}>"),
+        AssemblyFieldTypeKind::Sync => format!("SyncValue<{inner}>"),
+    }
+}
+
+/// `inline` is true while the value would be stored inside `owner` itself, where a reference back
+/// to `owner` needs a `Box` to keep the struct finite.
+fn type_ref(owner: &str, ty_ref: &AssemblyTypeRef, inline: bool) -> String {
+    match ty_ref {
+        AssemblyTypeRef::Builtin(builtin) => builtin_type(*builtin).to_string(),
+        AssemblyTypeRef::Custom(ty) | AssemblyTypeRef::Instance(ty, _) => {
+            let mut name = type_name(&ty.borrow());
+            if let AssemblyTypeRef::Instance(_, args) = ty_ref {
+                let args = args
+                    .iter()
+                    .map(|arg| type_ref(owner, arg, false))
+                    .collect::<Vec<_>>();
+                write!(name, "<{}>", args.join(", ")).unwrap();
+            }
+            if inline && contains_inline(ty_ref, owner) {
+                format!("Box<{name}>")
+            } else {
+                name
+            }
+        }
+        AssemblyTypeRef::Param(name) => name.clone(),
+        AssemblyTypeRef::Array(item) => format!("Vec<{}>", type_ref(owner, item, false)),
+        AssemblyTypeRef::Optional(item) => format!("Option<{}>", type_ref(owner, item, inline)),
+        AssemblyTypeRef::Map(key, value) => format!(
+            "std::collections::HashMap<{}, {}>",
+            type_ref(owner, key, false),
+            type_ref(owner, value, false)
+        ),
+    }
+}
+
+fn builtin_type(builtin: BuiltinType) -> &'static str {
+    match builtin {
+        BuiltinType::Integer => "i64",
+        BuiltinType::Float => "f64",
+        BuiltinType::String => "String",
+        BuiltinType::Boolean => "bool",
+    }
+}
+
+fn value(value: &SchemeValue, ty_ref: &AssemblyTypeRef) -> String {
+    match (value, ty_ref) {
+        (SchemeValue::Null, _) => "None".to_string(),
+        (value, AssemblyTypeRef::Optional(item)) => format!("Some({})", self::value(value, item)),
+        (SchemeValue::Integer(n), _) => n.to_string(),
+        (SchemeValue::Float(x), _) => format!("{x:?}"),
+        (SchemeValue::String(s), _) => format!("{s:?}.to_string()"),
+        (SchemeValue::Boolean(b), _) => b.to_string(),
+        (SchemeValue::Array(items), _) if items.is_empty() => "Vec::new()".to_string(),
+        (SchemeValue::Array(items), AssemblyTypeRef::Array(item)) => {
+            let items = items
+                .iter()
+                .map(|v| self::value(v, item))
+                .collect::<Vec<_>>();
+            format!("vec![{}]", items.join(", "))
+        }
+        (SchemeValue::Map(entries), _) if entries.is_empty() => {
+            "std::collections::HashMap::new()".to_string()
+        }
+        (SchemeValue::Map(entries), AssemblyTypeRef::Map(key, item)) => {
+            let entries = entries
+                .iter()
+                .map(|(k, v)| format!("({}, {})", self::value(k, key), self::value(v, item)))
+                .collect::<Vec<_>>();
+            format!("std::collections::HashMap::from([{}])", entries.join(", "))
+        }
+        _ => unreachable!("default values are checked against the field type"),
+    }
+}
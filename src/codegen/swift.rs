@@ -0,0 +1,608 @@
+//! Swift backend: a Codable struct per type, or a final class for types that contain themselves,
+//! since structs cannot. Inherited fields are copied into the types extending a type. Services get
+//! a protocol, implemented by servers or by client stubs.
+
+use super::{camel_case, contains_inline, deprecation, is_map_key, live_types, renamed, uses_kind};
+use crate::schema::assembly::*;
+use crate::schema::module::{BuiltinType, SchemeAnnotation, SchemeConst, SchemeValue};
+
+use std::collections::HashSet;
+use std::fmt::Write;
+
+const KEYWORDS: &[&str] = &[
+    "as",
+    "associatedtype",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "continue",
+    "default",
+    "defer",
+    "deinit",
+    "do",
+    "else",
+    "enum",
+    "extension",
+    "fallthrough",
+    "false",
+    "fileprivate",
+    "for",
+    "func",
+    "guard",
+    "if",
+    "import",
+    "in",
+    "init",
+    "inout",
+    "internal",
+    "is",
+    "let",
+    "nil",
+    "operator",
+    "private",
+    "protocol",
+    "public",
+    "repeat",
+    "rethrows",
+    "return",
+    "self",
+    "static",
+    "struct",
+    "subscript",
+    "super",
+    "switch",
+    "throw",
+    "throws",
+    "true",
+    "try",
+    "typealias",
+    "var",
+    "where",
+    "while",
+];
+
+const SYNCED_PRELUDE: &str = r#"
+/// Current value of a `sync` field, kept up to date by the transport.
+@propertyWrapper
+final class Synced<Value> {
+    private var listeners: [Int: (Value) -> Void] = [:]
+    private var nextListener = 0
+
+    var wrappedValue: Value {
+        didSet {
+            for listener in listeners.values {
+                listener(wrappedValue)
+            }
+        }
+    }
+
+    var projectedValue: Synced<Value> { self }
+
+    init(wrappedValue: Value) {
+        self.wrappedValue = wrappedValue
+    }
+
+    /// Calls `listener` on every change, until the returned function is called.
+    func subscribe(_ listener: @escaping (Value) -> Void) -> () -> Void {
+        let id = nextListener
+        nextListener += 1
+        listeners[id] = listener
+        return { [weak self] in self?.listeners[id] = nil }
+    }
+}
+"#;
+
+const PAIRS_PRELUDE: &str = r#"
+/// A dictionary sent as an array of `[key, value]` pairs, its keys not being strings or numbers.
+struct Pairs<Key: Hashable, Value>: ExpressibleByDictionaryLiteral {
+    var dictionary: [Key: Value]
+
+    init(_ dictionary: [Key: Value] = [:]) {
+        self.dictionary = dictionary
+    }
+
+    init(dictionaryLiteral elements: (Key, Value)...) {
+        dictionary = Dictionary(uniqueKeysWithValues: elements)
+    }
+
+    subscript(key: Key) -> Value? {
+        get { dictionary[key] }
+        set { dictionary[key] = newValue }
+    }
+}
+
+extension Pairs: Codable where Key: Codable, Value: Codable {
+    init(from decoder: Decoder) throws {
+        var pairs = try decoder.unkeyedContainer()
+        dictionary = [:]
+        while !pairs.isAtEnd {
+            var pair = try pairs.nestedUnkeyedContainer()
+            let key = try pair.decode(Key.self)
+            dictionary[key] = try pair.decode(Value.self)
+        }
+    }
+
+    func encode(to encoder: Encoder) throws {
+        var pairs = encoder.unkeyedContainer()
+        for (key, value) in dictionary {
+            var pair = pairs.nestedUnkeyedContainer()
+            try pair.encode(key)
+            try pair.encode(value)
+        }
+    }
+}
+"#;
+
+const FLOAT_KEYS_PRELUDE: &str = r#"
+/// A dictionary with `Double` keys, sent as an object. Codable only writes `String` and `Int`
+/// keys as object keys.
+struct FloatKeys<Value>: ExpressibleByDictionaryLiteral {
+    var dictionary: [Double: Value]
+
+    init(_ dictionary: [Double: Value] = [:]) {
+        self.dictionary = dictionary
+    }
+
+    init(dictionaryLiteral elements: (Double, Value)...) {
+        dictionary = Dictionary(uniqueKeysWithValues: elements)
+    }
+
+    subscript(key: Double) -> Value? {
+        get { dictionary[key] }
+        set { dictionary[key] = newValue }
+    }
+}
+
+extension FloatKeys: Codable where Value: Codable {
+    init(from decoder: Decoder) throws {
+        dictionary = [:]
+        for (key, value) in try [String: Value](from: decoder) {
+            guard let number = Double(key) else {
+                throw DecodingError.dataCorrupted(DecodingError.Context(
+                    codingPath: decoder.codingPath,
+                    debugDescription: "Expected a number key, found \(key)"
+                ))
+            }
+            dictionary[number] = value
+        }
+    }
+
+    func encode(to encoder: Encoder) throws {
+        let object = dictionary.map { (key, value) in (String(key), value) }
+        try Dictionary(uniqueKeysWithValues: object).encode(to: encoder)
+    }
+}
+"#;
+
+/// Helpers used by the generated code, collected while generating it.
+#[derive(Default)]
+struct Uses {
+    pairs: bool,
+    float_keys: bool,
+}
+
+pub fn generate(assembly: &Assembly) -> String {
+    let live = live_types(assembly);
+    let mut uses = Uses::default();
+    let mut body = String::new();
+    if !assembly.consts.is_empty() {
+        body.push('\n');
+        for constant in &assembly.consts {
+            generate_const(&mut body, constant);
+        }
+    }
+    for ty in &assembly.types {
+        let ty = ty.borrow();
+        body.push('\n');
+        generate_type(&mut body, &ty, &live, &mut uses);
+    }
+    for service in &assembly.services {
+        body.push('\n');
+        generate_service(&mut body, service, &mut uses);
+    }
+
+    let mut out = String::from("// Generated by rgc. Do not edit.\n");
+    if uses_kind(assembly, AssemblyFieldTypeKind::Sync) {
+        out.push_str(SYNCED_PRELUDE);
+    }
+    if uses.pairs {
+        out.push_str(PAIRS_PRELUDE);
+    }
+    if uses.float_keys {
+        out.push_str(FLOAT_KEYS_PRELUDE);
+    }
+    out.push_str(&body);
+    out
+}
+
+fn generate_const(out: &mut String, constant: &SchemeConst) {
+    deprecated_attribute(out, "", &constant.annotations);
+    let ty = AssemblyTypeRef::Builtin(constant.ty);
+    writeln!(
+        out,
+        "let {}: {} = {}",
+        escape(
+            renamed(&constant.annotations, "swift")
+                .unwrap_or(&constant.name)
+                .to_string()
+        ),
+        builtin_type(constant.ty),
+        value(&constant.value, &ty)
+    )
+    .unwrap();
+}
+
+fn generate_type(out: &mut String, ty: &AssemblyType, live: &HashSet<String>, uses: &mut Uses) {
+    // Live types only exist at runtime, they are not encoded.
+    let codable = !live.contains(&ty.name);
+    // A struct stored inside itself would be infinite, a class is a reference.
+    let is_class = ty.fields.iter().any(|field| {
+        field.ty.kind == AssemblyFieldTypeKind::Normal
+            && contains_inline(&field.ty.ty_ref, &ty.name)
+    });
+    let name = type_name(ty);
+    // A generic struct is only Codable when the values of its parameters are, which an extension
+    // declares. The decoder of a class is a designated initializer, which must be in the class.
+    let conditional = codable && !is_class && !ty.params.is_empty();
+    let header_codable = codable && !conditional;
+    // Parameters used as dictionary keys must be hashable.
+    let params = ty
+        .params
+        .iter()
+        .map(|param| {
+            let is_key = ty
+                .fields
+                .iter()
+                .any(|field| is_map_key(&field.ty.ty_ref, param));
+            match (is_key, header_codable) {
+                (true, true) => format!("{param}: Hashable & Codable"),
+                (true, false) => format!("{param}: Hashable"),
+                (false, true) => format!("{param}: Codable"),
+                (false, false) => param.clone(),
+            }
+        })
+        .collect::<Vec<_>>();
+    let generics = if params.is_empty() {
+        String::new()
+    } else {
+        format!("<{}>", params.join(", "))
+    };
+    deprecated_attribute(out, "", &ty.annotations);
+    writeln!(
+        out,
+        "{} {name}{generics}{} {{",
+        if is_class { "final class" } else { "struct" },
+        if header_codable { ": Codable" } else { "" }
+    )
+    .unwrap();
+
+    let fields = ty
+        .fields
+        .iter()
+        .map(|field| {
+            let field_type = field_type(&field.ty, field.optional, uses);
+            let default = match &field.default {
+                Some(default) => Some(value(default, &field.ty.ty_ref)),
+                // Optionals default to `nil`, in the memberwise initializer of structs too.
+                None if field_type.ends_with('?') => Some("nil".to_string()),
+                None => None,
+            };
+            (field, field_name(field), field_type, default)
+        })
+        .collect::<Vec<_>>();
+    for (field, name, field_type, default) in &fields {
+        deprecated_attribute(out, "    ", &field.annotations);
+        let wrapper = if field.ty.kind == AssemblyFieldTypeKind::Sync {
+            "@Synced "
+        } else {
+            ""
+        };
+        let default = match default {
+            Some(default) if !is_class => format!(" = {default}"),
+            _ => String::new(),
+        };
+        writeln!(out, "    {wrapper}var {name}: {field_type}{default}").unwrap();
+    }
+
+    let renames = fields
+        .iter()
+        .any(|(field, name, ..)| name.trim_matches('`') != field.name);
+    let has_defaults = fields.iter().any(|(field, ..)| field.default.is_some());
+    let mut coding_keys = String::new();
+    if codable && (renames || has_defaults) {
+        coding_keys.push_str("    enum CodingKeys: String, CodingKey {\n");
+        for (field, name, ..) in &fields {
+            if name.trim_matches('`') == field.name {
+                writeln!(coding_keys, "        case {name}").unwrap();
+            } else {
+                writeln!(coding_keys, "        case {name} = {}", string(&field.name)).unwrap();
+            }
+        }
+        coding_keys.push_str("    }\n");
+    }
+    if !conditional && !coding_keys.is_empty() {
+        out.push('\n');
+        out.push_str(&coding_keys);
+    }
+
+    if is_class {
+        // Classes have no memberwise initializer.
+        let params = fields
+            .iter()
+            .map(|(_, name, field_type, default)| match default {
+                Some(default) => format!("{name}: {field_type} = {default}"),
+                None => format!("{name}: {field_type}"),
+            })
+            .collect::<Vec<_>>();
+        let params = params.join(", ");
+        if params.len() > 80 {
+            writeln!(
+                out,
+                "\n    init(\n        {}\n    ) {{",
+                params.replace(", ", ",\n        ")
+            )
+        } else {
+            writeln!(out, "\n    init({params}) {{")
+        }
+        .unwrap();
+        for (_, name, ..) in &fields {
+            writeln!(out, "        self.{} = {name}", name.trim_matches('`')).unwrap();
+        }
+        out.push_str("    }\n");
+    }
+
+    // Absent fields get their default value, which the synthesized decoder does not know about.
+    let decoder = if codable && has_defaults {
+        let mut decoder = String::from(
+            "    init(from decoder: Decoder) throws {\n        \
+                 let container = try decoder.container(keyedBy: CodingKeys.self)\n",
+        );
+        for (field, name, field_type, default) in &fields {
+            let property = name.trim_matches('`');
+            let decoded_type = field_type.trim_end_matches('?');
+            match (&field.default, default) {
+                (Some(_), Some(default)) => writeln!(
+                    decoder,
+                    "        self.{property} = try container.decodeIfPresent({decoded_type}.self, forKey: .{property}) ?? {default}"
+                ),
+                (None, Some(_)) => writeln!(
+                    decoder,
+                    "        self.{property} = try container.decodeIfPresent({decoded_type}.self, forKey: .{property})"
+                ),
+                _ => writeln!(
+                    decoder,
+                    "        self.{property} = try container.decode({field_type}.self, forKey: .{property})"
+                ),
+            }
+            .unwrap();
+        }
+        decoder.push_str("    }\n");
+        Some(decoder)
+    } else {
+        None
+    };
+    match decoder {
+        _ if conditional => {
+            let bounds = ty
+                .params
+                .iter()
+                .map(|param| format!("{param}: Codable"))
+                .collect::<Vec<_>>();
+            write!(
+                out,
+                "}}\n\nextension {name}: Codable where {} {{",
+                bounds.join(", ")
+            )
+            .unwrap();
+            let members = [coding_keys, decoder.unwrap_or_default()];
+            let members = members.into_iter().filter(|member| !member.is_empty());
+            match members.collect::<Vec<_>>().join("\n") {
+                members if members.is_empty() => out.push_str("}\n"),
+                members => {
+                    out.push('\n');
+                    out.push_str(&members);
+                    out.push_str("}\n");
+                }
+            }
+        }
+        // Inside the class, initializers of classes cannot be declared in extensions.
+        Some(decoder) if is_class => {
+            out.push('\n');
+            out.push_str(&decoder);
+            out.push_str("}\n");
+        }
+        // In an extension, the struct keeps its memberwise initializer.
+        Some(decoder) => {
+            writeln!(out, "}}\n\nextension {name} {{").unwrap();
+            out.push_str(&decoder);
+            out.push_str("}\n");
+        }
+        None => out.push_str("}\n"),
+    }
+}
+
+/// Servers implement the protocol of each service, one method per remote function.
+fn generate_service(out: &mut String, service: &AssemblyService, uses: &mut Uses) {
+    deprecated_attribute(out, "", &service.annotations);
+    writeln!(
+        out,
+        "protocol {} {{",
+        renamed(&service.annotations, "swift").unwrap_or(&service.name)
+    )
+    .unwrap();
+    for function in &service.functions {
+        deprecated_attribute(out, "    ", &function.annotations);
+        let params = function
+            .params
+            .iter()
+            .map(|param| {
+                format!(
+                    "{}: {}",
+                    escape(camel_case(&param.name)),
+                    type_ref(&param.ty, uses)
+                )
+            })
+            .collect::<Vec<_>>();
+        let name = escape(
+            renamed(&function.annotations, "swift")
+                .map_or_else(|| camel_case(&function.name), str::to_string),
+        );
+        // `stream` and `sync` values are handed out at once and then updated by the transport.
+        let returns = match &function.returns {
+            Some(ty) if ty.kind == AssemblyFieldTypeKind::Sync => {
+                format!(" -> Synced<{}>", type_ref(&ty.ty_ref, uses))
+            }
+            Some(ty) if ty.kind == AssemblyFieldTypeKind::Stream => {
+                format!(" -> {}", field_type(ty, false, uses))
+            }
+            Some(ty) => format!(" async throws -> {}", field_type(ty, false, uses)),
+            None => " async throws".to_string(),
+        };
+        writeln!(out, "    func {name}({}){returns}", params.join(", ")).unwrap();
+    }
+    out.push_str("}\n");
+}
+
+fn deprecated_attribute(out: &mut String, indent: &str, annotations: &[SchemeAnnotation]) {
+    match deprecation(annotations) {
+        Some("") => writeln!(out, "{indent}@available(*, deprecated)"),
+        Some(reason) => writeln!(
+            out,
+            "{indent}@available(*, deprecated, message: {})",
+            string(reason)
+        ),
+        None => return,
+    }
+    .unwrap();
+}
+
+fn type_name(ty: &AssemblyType) -> &str {
+    renamed(&ty.annotations, "swift").unwrap_or(&ty.name)
+}
+
+fn field_name(field: &AssemblyField) -> String {
+    escape(
+        renamed(&field.annotations, "swift")
+            .map_or_else(|| camel_case(&field.name), str::to_string),
+    )
+}
+
+/// Names that are Swift keywords are quoted with backticks.
+fn escape(name: String) -> String {
+    if KEYWORDS.contains(&name.as_str()) {
+        format!("`{name}`")
+    } else {
+        name
+    }
+}
+
+/// Fields that may be absent are optionals, `nil` when they are. `sync` fields hold the value,
+/// wrapped by `@Synced`.
+fn field_type(ty: &AssemblyFieldType, optional: bool, uses: &mut Uses) -> String {
+    let inner = type_ref(&ty.ty_ref, uses);
+    let field_type = match ty.kind {
+        AssemblyFieldTypeKind::Normal | AssemblyFieldTypeKind::Sync => inner,
+        AssemblyFieldTypeKind::Stream => format!("AsyncStream<{inner}>"),
+    };
+    if optional && !field_type.ends_with('?') {
+        format!("{field_type}?")
+    } else {
+        field_type
+    }
+}
+
+fn type_ref(ty_ref: &AssemblyTypeRef, uses: &mut Uses) -> String {
+    match ty_ref {
+        AssemblyTypeRef::Builtin(builtin) => builtin_type(*builtin).to_string(),
+        AssemblyTypeRef::Custom(ty) => type_name(&ty.borrow()).to_string(),
+        AssemblyTypeRef::Instance(ty, args) => {
+            let args = args
+                .iter()
+                .map(|arg| type_ref(arg, uses))
+                .collect::<Vec<_>>();
+            format!("{}<{}>", type_name(&ty.borrow()), args.join(", "))
+        }
+        AssemblyTypeRef::Param(name) => name.clone(),
+        AssemblyTypeRef::Array(item) => format!("[{}]", type_ref(item, uses)),
+        AssemblyTypeRef::Optional(item) => {
+            let item = type_ref(item, uses);
+            if item.ends_with('?') {
+                item
+            } else {
+                format!("{item}?")
+            }
+        }
+        AssemblyTypeRef::Map(key, value) => {
+            let value = type_ref(value, uses);
+            match &**key {
+                // Written as JSON object keys by Codable.
+                AssemblyTypeRef::Builtin(BuiltinType::String | BuiltinType::Integer) => {
+                    format!("[{}: {value}]", type_ref(key, uses))
+                }
+                AssemblyTypeRef::Builtin(BuiltinType::Float) => {
+                    uses.float_keys = true;
+                    format!("FloatKeys<{value}>")
+                }
+                key => {
+                    uses.pairs = true;
+                    format!("Pairs<{}, {value}>", type_ref(key, uses))
+                }
+            }
+        }
+    }
+}
+
+fn builtin_type(builtin: BuiltinType) -> &'static str {
+    match builtin {
+        BuiltinType::Integer => "Int",
+        BuiltinType::Float => "Double",
+        BuiltinType::String => "String",
+        BuiltinType::Boolean => "Bool",
+    }
+}
+
+fn string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => write!(out, "\\u{{{:x}}}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn value(value: &SchemeValue, ty_ref: &AssemblyTypeRef) -> String {
+    match (value, ty_ref) {
+        (SchemeValue::Null, _) => "nil".to_string(),
+        (value, AssemblyTypeRef::Optional(item)) => self::value(value, item),
+        (SchemeValue::Integer(n), _) => n.to_string(),
+        (SchemeValue::Float(x), _) => format!("{x:?}"),
+        (SchemeValue::String(s), _) => string(s),
+        (SchemeValue::Boolean(b), _) => b.to_string(),
+        (SchemeValue::Array(items), AssemblyTypeRef::Array(item)) => {
+            let items = items
+                .iter()
+                .map(|v| self::value(v, item))
+                .collect::<Vec<_>>();
+            format!("[{}]", items.join(", "))
+        }
+        (SchemeValue::Map(entries), _) if entries.is_empty() => "[:]".to_string(),
+        (SchemeValue::Map(entries), AssemblyTypeRef::Map(key, item)) => {
+            let entries = entries
+                .iter()
+                .map(|(k, v)| format!("{}: {}", self::value(k, key), self::value(v, item)))
+                .collect::<Vec<_>>();
+            format!("[{}]", entries.join(", "))
+        }
+        _ => unreachable!("default values are checked against the field type"),
+    }
+}
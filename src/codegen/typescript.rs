@@ -0,0 +1,716 @@
+//! TypeScript backend: an interface per type, plus a `decode` function applying defaults to JSON
+//! payloads, a `create` factory for types with default values and a `validate` function for
+//! types with constraints. Services get a client class calling their remote functions.
+
+use super::{
+    constraints, contains_validated, deprecation, renamed, uses_kind, validated_params,
+    validated_types, value_type, Constraint,
+};
+use crate::json::Json;
+use crate::schema::assembly::*;
+use crate::schema::module::{BuiltinType, SchemeAnnotation, SchemeValue};
+use crate::schema::regex;
+
+use std::collections::HashSet;
+use std::fmt::Write;
+
+pub fn generate(assembly: &Assembly) -> String {
+    let mut out = String::from("// Generated by rgc. Do not edit.\n");
+    if uses_kind(assembly, AssemblyFieldTypeKind::Sync) {
+        out.push_str(
+            "\n/** Current value of a `sync` field, kept up to date by the transport. */\n\
+             export interface Sync<T> {\n    \
+                 readonly value: T;\n    \
+                 subscribe(listener: (value: T) => void): () => void;\n\
+             }\n",
+        );
+    }
+    let validated = validated_types(assembly);
+    if !validated.is_empty() {
+        out.push_str(
+            "\n/** A value that breaks a constraint of the schema. */\n\
+             export interface ValidationError {\n    \
+                 /** Location of the value, e.g. `posts[0].title`. */\n    \
+                 path: string;\n    \
+                 /** Name of the broken constraint, e.g. `max_len`. */\n    \
+                 constraint: string;\n    \
+                 message: string;\n\
+             }\n",
+        );
+    }
+    if !assembly.consts.is_empty() {
+        out.push('\n');
+        for constant in &assembly.consts {
+            doc_comment(&mut out, "", &constant.annotations);
+            writeln!(
+                out,
+                "export const {}: {} = {};",
+                renamed(&constant.annotations, "ts").unwrap_or(&constant.name),
+                builtin_type(constant.ty),
+                value(&constant.value, &AssemblyTypeRef::Builtin(constant.ty))
+            )
+            .unwrap();
+        }
+    }
+    for ty in &assembly.types {
+        let ty = ty.borrow();
+        out.push('\n');
+        generate_interface(&mut out, &ty);
+        generate_factory(&mut out, &ty);
+        generate_decoder(&mut out, &ty);
+        if validated.contains(&ty.name) {
+            generate_validator(&mut out, &ty, &validated);
+        }
+    }
+    if !assembly.services.is_empty() {
+        generate_transport(&mut out, assembly);
+    }
+    for service in &assembly.services {
+        out.push('\n');
+        generate_client(&mut out, service);
+    }
+    out
+}
+
+/// Interface the clients send their calls through, implemented by the application.
+fn generate_transport(out: &mut String, assembly: &Assembly) {
+    let args = "service: string, method: string, args: Record<string, unknown>";
+    write!(
+        out,
+        "\n/** Sends the calls of service clients to the remote functions. */\n\
+         export interface Transport {{\n    \
+             call({args}): Promise<any>;\n"
+    )
+    .unwrap();
+    if uses_kind(assembly, AssemblyFieldTypeKind::Stream) {
+        writeln!(out, "    stream({args}): AsyncIterable<any>;").unwrap();
+    }
+    if uses_kind(assembly, AssemblyFieldTypeKind::Sync) {
+        writeln!(out, "    sync({args}): Sync<any>;").unwrap();
+    }
+    out.push_str("}\n");
+}
+
+fn generate_client(out: &mut String, service: &AssemblyService) {
+    doc_comment(out, "", &service.annotations);
+    writeln!(
+        out,
+        "export class {}Client {{\n    constructor(private readonly transport: Transport) {{}}",
+        renamed(&service.annotations, "ts").unwrap_or(&service.name)
+    )
+    .unwrap();
+    for function in &service.functions {
+        out.push('\n');
+        doc_comment(out, "    ", &function.annotations);
+        let name = renamed(&function.annotations, "ts").unwrap_or(&function.name);
+        let params = function
+            .params
+            .iter()
+            .map(|param| format!("{}: {}", param.name, type_ref(&param.ty)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let args = match function.params.is_empty() {
+            true => "{}".to_string(),
+            false => {
+                let names = function.params.iter().map(|param| param.name.as_str());
+                format!("{{ {} }}", names.collect::<Vec<_>>().join(", "))
+            }
+        };
+        let call = |method: &str| {
+            format!(
+                "this.transport.{method}({}, {}, {args})",
+                Json::from(service.name.as_str()),
+                Json::from(function.name.as_str())
+            )
+        };
+        let Some(returns) = &function.returns else {
+            writeln!(
+                out,
+                "    async {name}({params}): Promise<void> {{\n        await {};\n    }}",
+                call("call")
+            )
+            .unwrap();
+            continue;
+        };
+        let ty = type_ref(&returns.ty_ref);
+        match returns.kind {
+            AssemblyFieldTypeKind::Normal => match decode("json", &returns.ty_ref, 0) {
+                Some(decoded) => writeln!(
+                    out,
+                    "    async {name}({params}): Promise<{ty}> {{\n        \
+                         const json = await {};\n        \
+                         return {decoded};\n    \
+                     }}",
+                    call("call")
+                ),
+                None => writeln!(
+                    out,
+                    "    {name}({params}): Promise<{ty}> {{\n        return {};\n    }}",
+                    call("call")
+                ),
+            },
+            AssemblyFieldTypeKind::Stream => match decode("item", &returns.ty_ref, 0) {
+                Some(decoded) => writeln!(
+                    out,
+                    "    async *{name}({params}): AsyncIterable<{ty}> {{\n        \
+                         for await (const item of {}) {{\n            \
+                             yield {decoded};\n        \
+                         }}\n    \
+                     }}",
+                    call("stream")
+                ),
+                None => writeln!(
+                    out,
+                    "    {name}({params}): AsyncIterable<{ty}> {{\n        return {};\n    }}",
+                    call("stream")
+                ),
+            },
+            // Like `sync` fields, the value is kept up to date by the transport as is.
+            AssemblyFieldTypeKind::Sync => writeln!(
+                out,
+                "    {name}({params}): Sync<{ty}> {{\n        return {};\n    }}",
+                call("sync")
+            ),
+        }
+        .unwrap();
+    }
+    out.push_str("}\n");
+}
+
+fn generate_interface(out: &mut String, ty: &AssemblyType) {
+    doc_comment(out, "", &ty.annotations);
+    // Inherited fields come first and are declared by the base interface.
+    let inherited = match &ty.base {
+        Some(base) => {
+            let base = base.borrow();
+            writeln!(
+                out,
+                "export interface {}{} extends {} {{",
+                type_name(ty),
+                generics(ty),
+                type_name(&base)
+            )
+            .unwrap();
+            base.fields.len()
+        }
+        None => {
+            writeln!(out, "export interface {}{} {{", type_name(ty), generics(ty)).unwrap();
+            0
+        }
+    };
+    for field in &ty.fields[inherited..] {
+        doc_comment(out, "    ", &field.annotations);
+        let optional = if field.optional { "?" } else { "" };
+        writeln!(
+            out,
+            "    {}{optional}: {};",
+            field_name(field),
+            field_type(&field.ty)
+        )
+        .unwrap();
+    }
+    out.push_str("}\n");
+}
+
+fn doc_comment(out: &mut String, indent: &str, annotations: &[SchemeAnnotation]) {
+    match deprecation(annotations) {
+        Some("") => writeln!(out, "{indent}/** @deprecated */"),
+        Some(reason) => writeln!(out, "{indent}/** @deprecated {reason} */"),
+        None => return,
+    }
+    .unwrap();
+}
+
+fn type_name(ty: &AssemblyType) -> &str {
+    renamed(&ty.annotations, "ts").unwrap_or(&ty.name)
+}
+
+/// `<T, U>` for generic types, empty otherwise.
+fn generics(ty: &AssemblyType) -> String {
+    if ty.params.is_empty() {
+        String::new()
+    } else {
+        format!("<{}>", ty.params.join(", "))
+    }
+}
+
+fn field_name(field: &AssemblyField) -> &str {
+    renamed(&field.annotations, "ts").unwrap_or(&field.name)
+}
+
+/// Default value of `field`, held by a `Sync` that never changes for `sync` fields.
+fn default_value(field: &AssemblyField, default: &SchemeValue) -> String {
+    let value = value(default, &field.ty.ty_ref);
+    match field.ty.kind {
+        AssemblyFieldTypeKind::Sync => {
+            format!("{{ value: {value}, subscribe: () => () => {{}} }}")
+        }
+        _ => value,
+    }
+}
+
+/// `createT(fields)` takes every field, but those with a default value may be left out.
+fn generate_factory(out: &mut String, ty: &AssemblyType) {
+    let defaults = ty
+        .fields
+        .iter()
+        .filter_map(|field| Some((field, field.default.as_ref()?)))
+        .collect::<Vec<_>>();
+    if defaults.is_empty() {
+        return;
+    }
+    let keys = defaults
+        .iter()
+        .map(|(field, _)| Json::from(field_name(field)).to_string())
+        .collect::<Vec<_>>()
+        .join(" | ");
+    writeln!(
+        out,
+        "\nexport function create{name}{generics}(fields: Omit<{name}{generics}, {keys}> & Partial<Pick<{name}{generics}, {keys}>>): {name}{generics} {{\n    return {{",
+        name = type_name(ty),
+        generics = generics(ty)
+    )
+    .unwrap();
+    for (field, default) in defaults {
+        writeln!(
+            out,
+            "        {}: {},",
+            field_name(field),
+            default_value(field, default)
+        )
+        .unwrap();
+    }
+    out.push_str("        ...fields,\n    };\n}\n");
+}
+
+/// `decodeT(json)` converts a parsed JSON payload, filling in absent fields with their default.
+/// Decoders of generic types also take a decoder for each type parameter.
+fn generate_decoder(out: &mut String, ty: &AssemblyType) {
+    let params = ty
+        .params
+        .iter()
+        .map(|param| format!(", decode{param}: (json: any) => {param}"))
+        .collect::<String>();
+    writeln!(
+        out,
+        "\nexport function decode{name}{generics}(json: any{params}): {name}{generics} {{\n    return {{",
+        name = type_name(ty),
+        generics = generics(ty)
+    )
+    .unwrap();
+    for field in &ty.fields {
+        let access = format!("json.{}", field.name);
+        // `stream` and `sync` fields are attached by the transport and passed through as is.
+        let decoded = match field.ty.kind {
+            AssemblyFieldTypeKind::Normal => decode(&access, &field.ty.ty_ref, 0),
+            _ => None,
+        }
+        .map(|decoded| match field.optional {
+            // Absent fields stay absent, only present ones are decoded.
+            true => format!("{access} === undefined ? undefined : {decoded}"),
+            false => decoded,
+        })
+        .unwrap_or_else(|| access.clone());
+        match &field.default {
+            Some(default) => writeln!(
+                out,
+                "        {}: {access} === undefined ? {} : {decoded},",
+                field_name(field),
+                default_value(field, default)
+            ),
+            None => writeln!(out, "        {}: {decoded},", field_name(field)),
+        }
+        .unwrap();
+    }
+    out.push_str("    };\n}\n");
+}
+
+/// Expression converting the JSON value `expr` to `ty_ref`, or `None` if it can be used as is.
+/// `depth` keeps the names of nested callback parameters apart.
+fn decode(expr: &str, ty_ref: &AssemblyTypeRef, depth: usize) -> Option<String> {
+    match ty_ref {
+        AssemblyTypeRef::Builtin(_) => None,
+        AssemblyTypeRef::Custom(ty) => Some(format!("decode{}({expr})", type_name(&ty.borrow()))),
+        AssemblyTypeRef::Instance(ty, args) => {
+            let decoders = args
+                .iter()
+                .map(|arg| format!(", {}", decoder(arg, depth)))
+                .collect::<String>();
+            Some(format!(
+                "decode{}({expr}{decoders})",
+                type_name(&ty.borrow())
+            ))
+        }
+        AssemblyTypeRef::Param(name) => Some(format!("decode{name}({expr})")),
+        AssemblyTypeRef::Optional(item) => {
+            let decoded = decode(expr, item, depth)?;
+            Some(format!("{expr} == null ? null : {decoded}"))
+        }
+        AssemblyTypeRef::Array(item) => {
+            let name = format!("item{depth}");
+            let decoded = decode(&name, item, depth + 1)?;
+            Some(format!("{expr}.map(({name}: any) => {decoded})"))
+        }
+        AssemblyTypeRef::Map(key, item) => {
+            let (k, v) = (format!("key{depth}"), format!("value{depth}"));
+            if is_record_key(key) {
+                let decoded = decode(&v, item, depth + 1)?;
+                Some(format!(
+                    "Object.fromEntries(Object.entries({expr}).map(([{k}, {v}]: [string, any]) => [{k}, {decoded}]))"
+                ))
+            } else {
+                // Other keys cannot be JSON object keys, such maps are sent as `[key, value]` pairs.
+                let decoded_key = decode(&k, key, depth + 1).unwrap_or_else(|| k.clone());
+                let decoded = decode(&v, item, depth + 1).unwrap_or_else(|| v.clone());
+                Some(format!(
+                    "new Map({expr}.map(([{k}, {v}]: [any, any]) => [{decoded_key}, {decoded}]))"
+                ))
+            }
+        }
+    }
+}
+
+/// `validateT(value)` lists the constraint violations of a value, located relative to `path`.
+fn generate_validator(out: &mut String, ty: &AssemblyType, validated: &HashSet<String>) {
+    // Values of type parameters are checked by validators given for them.
+    let params = validated_params(ty);
+    let validators = params
+        .iter()
+        .map(|param| {
+            format!(
+                ", validate{param}: (value: {param}, path: string) => ValidationError[] = () => []"
+            )
+        })
+        .collect::<String>();
+    writeln!(
+        out,
+        "\nexport function validate{name}{generics}(value: {name}{generics}, path: string = \"\"{validators}): ValidationError[] {{\n    \
+         const errors: ValidationError[] = [];",
+        name = type_name(ty),
+        generics = generics(ty)
+    )
+    .unwrap();
+    for field in &ty.fields {
+        let access = format!("value.{}", field_name(field));
+        let constraints = constraints(field);
+        if !constraints.is_empty() {
+            // Constraints apply to the value inside `Optional`s, when there is one.
+            let optional = matches!(value_type(field), AssemblyTypeRef::Optional(_));
+            let indent = if optional { "        " } else { "    " };
+            if optional {
+                writeln!(out, "    if ({access} != null) {{").unwrap();
+            }
+            for constraint in &constraints {
+                check(out, indent, &access, &field.name, constraint);
+            }
+            if optional {
+                out.push_str("    }\n");
+            }
+        }
+        if field.ty.kind == AssemblyFieldTypeKind::Normal {
+            nested(
+                out,
+                "    ",
+                &access,
+                &value_type(field),
+                &field.name,
+                0,
+                validated,
+                &params,
+            );
+        }
+    }
+    out.push_str("    return errors;\n}\n");
+}
+
+fn check(out: &mut String, indent: &str, expr: &str, path: &str, constraint: &Constraint) {
+    let (condition, message) = match constraint {
+        Constraint::Range { min, max } => {
+            if let Some(min) = min {
+                let min = number(min);
+                push_error(
+                    out,
+                    indent,
+                    &format!("{expr} < {min}"),
+                    path,
+                    constraint,
+                    &format!("must be at least {min}"),
+                );
+            }
+            if let Some(max) = max {
+                let max = number(max);
+                push_error(
+                    out,
+                    indent,
+                    &format!("{expr} > {max}"),
+                    path,
+                    constraint,
+                    &format!("must be at most {max}"),
+                );
+            }
+            return;
+        }
+        Constraint::Pattern(regex) => (
+            format!(
+                "!new RegExp({}, \"u\").test({expr})",
+                Json::from(regex::javascript(regex))
+            ),
+            format!("must match the pattern {regex}"),
+        ),
+        // Spreading counts code points, like the Rust validators do.
+        Constraint::MinLen(len) => (
+            format!("[...{expr}].length < {len}"),
+            format!("must have at least {len} characters"),
+        ),
+        Constraint::MaxLen(len) => (
+            format!("[...{expr}].length > {len}"),
+            format!("must have at most {len} characters"),
+        ),
+        Constraint::MinItems(count) => (
+            format!("{expr}.length < {count}"),
+            format!("must have at least {count} items"),
+        ),
+        Constraint::MaxItems(count) => (
+            format!("{expr}.length > {count}"),
+            format!("must have at most {count} items"),
+        ),
+    };
+    push_error(out, indent, &condition, path, constraint, &message);
+}
+
+fn push_error(
+    out: &mut String,
+    indent: &str,
+    condition: &str,
+    path: &str,
+    constraint: &Constraint,
+    message: &str,
+) {
+    writeln!(
+        out,
+        "{indent}if ({condition}) {{\n\
+         {indent}    errors.push({{ path: `${{path}}{path}`, constraint: {}, message: {} }});\n\
+         {indent}}}",
+        Json::from(constraint.name()),
+        Json::from(message)
+    )
+    .unwrap();
+}
+
+fn number(value: &SchemeValue) -> String {
+    match value {
+        SchemeValue::Float(x) => format!("{x:?}"),
+        SchemeValue::Integer(n) => n.to_string(),
+        _ => unreachable!("bounds are checked against the field type"),
+    }
+}
+
+/// Validates the values of validated types found in `expr`, a value of type `ty_ref` located at
+/// `path`, and the values of the type parameters `params`.
+#[allow(clippy::too_many_arguments)]
+fn nested(
+    out: &mut String,
+    indent: &str,
+    expr: &str,
+    ty_ref: &AssemblyTypeRef,
+    path: &str,
+    depth: usize,
+    validated: &HashSet<String>,
+    params: &[String],
+) {
+    if !contains_validated(ty_ref, validated, params) {
+        return;
+    }
+    let inner = format!("{indent}    ");
+    match ty_ref {
+        AssemblyTypeRef::Instance(ty, args) if !validated_params(&ty.borrow()).is_empty() => {
+            let ty = ty.borrow();
+            let held = validated_params(&ty);
+            let mut validators = Vec::new();
+            for (param, arg) in ty.params.iter().zip(args) {
+                if !held.contains(param) {
+                    continue;
+                }
+                validators.push(match arg {
+                    AssemblyTypeRef::Param(name) if params.contains(name) => {
+                        format!("validate{name}")
+                    }
+                    arg if !contains_validated(arg, validated, params) => "() => []".to_string(),
+                    arg => {
+                        // Paths given to validators of parameters locate the value itself.
+                        let body = format!("{inner}    ");
+                        let mut function = format!(
+                            "(value, path) => {{\n{body}const errors: ValidationError[] = [];\n"
+                        );
+                        nested(&mut function, &body, "value", arg, "", 0, validated, params);
+                        write!(function, "{body}return errors;\n{inner}}}").unwrap();
+                        function
+                    }
+                });
+            }
+            let name = type_name(&ty);
+            let path = format!("`${{path}}{path}.`");
+            if validators.iter().any(|validator| validator.contains('\n')) {
+                writeln!(
+                    out,
+                    "{indent}errors.push(...validate{name}(\n{inner}{expr},\n{inner}{path},"
+                )
+                .unwrap();
+                for validator in validators {
+                    writeln!(out, "{inner}{validator},").unwrap();
+                }
+                writeln!(out, "{indent}));").unwrap();
+            } else {
+                writeln!(
+                    out,
+                    "{indent}errors.push(...validate{name}({expr}, {path}, {}));",
+                    validators.join(", ")
+                )
+                .unwrap();
+            }
+        }
+        AssemblyTypeRef::Param(name) => {
+            writeln!(
+                out,
+                "{indent}errors.push(...validate{name}({expr}, `${{path}}{path}`));"
+            )
+            .unwrap();
+        }
+        AssemblyTypeRef::Custom(ty) | AssemblyTypeRef::Instance(ty, _) => {
+            writeln!(
+                out,
+                "{indent}errors.push(...validate{}({expr}, `${{path}}{path}.`));",
+                type_name(&ty.borrow())
+            )
+            .unwrap();
+        }
+        AssemblyTypeRef::Optional(item) => {
+            writeln!(out, "{indent}if ({expr} != null) {{").unwrap();
+            nested(out, &inner, expr, item, path, depth, validated, params);
+            writeln!(out, "{indent}}}").unwrap();
+        }
+        AssemblyTypeRef::Array(item) => {
+            let (i, value) = (format!("i{depth}"), format!("item{depth}"));
+            writeln!(out, "{indent}{expr}.forEach(({value}, {i}) => {{").unwrap();
+            nested(
+                out,
+                &inner,
+                &value,
+                item,
+                &format!("{path}[${{{i}}}]"),
+                depth + 1,
+                validated,
+                params,
+            );
+            writeln!(out, "{indent}}});").unwrap();
+        }
+        AssemblyTypeRef::Map(key, item) => {
+            let (k, v) = (format!("key{depth}"), format!("value{depth}"));
+            let path = format!("{path}[${{JSON.stringify({k})}}]");
+            if is_record_key(key) {
+                writeln!(
+                    out,
+                    "{indent}for (const [{k}, {v}] of Object.entries({expr})) {{"
+                )
+                .unwrap();
+            } else {
+                writeln!(out, "{indent}for (const [{k}, {v}] of {expr}) {{").unwrap();
+            }
+            nested(out, &inner, &v, item, &path, depth + 1, validated, params);
+            writeln!(out, "{indent}}}").unwrap();
+        }
+        AssemblyTypeRef::Builtin(_) => {}
+    }
+}
+
+/// Function converting JSON values to `ty_ref`, given to the decoders of generic types.
+fn decoder(ty_ref: &AssemblyTypeRef, depth: usize) -> String {
+    match ty_ref {
+        AssemblyTypeRef::Custom(ty) => format!("decode{}", type_name(&ty.borrow())),
+        AssemblyTypeRef::Param(name) => format!("decode{name}"),
+        ty_ref => {
+            let name = format!("json{depth}");
+            let decoded = decode(&name, ty_ref, depth + 1).unwrap_or_else(|| name.clone());
+            format!("({name}: any) => {decoded}")
+        }
+    }
+}
+
+fn field_type(ty: &AssemblyFieldType) -> String {
+    let inner = type_ref(&ty.ty_ref);
+    match ty.kind {
+        AssemblyFieldTypeKind::Normal => inner,
+        AssemblyFieldTypeKind::Stream => format!("AsyncIterable<{inner}>"),
+        AssemblyFieldTypeKind::Sync => format!("Sync<{inner}>"),
+    }
+}
+
+fn type_ref(ty_ref: &AssemblyTypeRef) -> String {
+    match ty_ref {
+        AssemblyTypeRef::Builtin(builtin) => builtin_type(*builtin).to_string(),
+        AssemblyTypeRef::Custom(ty) => type_name(&ty.borrow()).to_string(),
+        AssemblyTypeRef::Instance(ty, args) => {
+            let args = args.iter().map(type_ref).collect::<Vec<_>>();
+            format!("{}<{}>", type_name(&ty.borrow()), args.join(", "))
+        }
+        AssemblyTypeRef::Param(name) => name.clone(),
+        AssemblyTypeRef::Array(item) => format!("Array<{}>", type_ref(item)),
+        AssemblyTypeRef::Optional(item) => format!("{} | null", type_ref(item)),
+        AssemblyTypeRef::Map(key, value) if is_record_key(key) => {
+            format!("Record<{}, {}>", type_ref(key), type_ref(value))
+        }
+        AssemblyTypeRef::Map(key, value) => format!("Map<{}, {}>", type_ref(key), type_ref(value)),
+    }
+}
+
+/// Whether maps with this key type are plain objects.
+fn is_record_key(key: &AssemblyTypeRef) -> bool {
+    matches!(
+        key,
+        AssemblyTypeRef::Builtin(BuiltinType::String | BuiltinType::Integer | BuiltinType::Float)
+    )
+}
+
+fn builtin_type(builtin: BuiltinType) -> &'static str {
+    match builtin {
+        BuiltinType::Integer | BuiltinType::Float => "number",
+        BuiltinType::String => "string",
+        BuiltinType::Boolean => "boolean",
+    }
+}
+
+fn value(value: &SchemeValue, ty_ref: &AssemblyTypeRef) -> String {
+    match (value, ty_ref) {
+        (SchemeValue::Null, _) => "null".to_string(),
+        (value, AssemblyTypeRef::Optional(item)) => self::value(value, item),
+        (SchemeValue::Integer(n), _) => n.to_string(),
+        (SchemeValue::Float(x), _) => format!("{x:?}"),
+        (SchemeValue::String(s), _) => Json::from(s.as_str()).to_string(),
+        (SchemeValue::Boolean(b), _) => b.to_string(),
+        (SchemeValue::Array(items), AssemblyTypeRef::Array(item)) => {
+            let items = items
+                .iter()
+                .map(|v| self::value(v, item))
+                .collect::<Vec<_>>();
+            format!("[{}]", items.join(", "))
+        }
+        (SchemeValue::Map(entries), AssemblyTypeRef::Map(key, item)) if is_record_key(key) => {
+            if entries.is_empty() {
+                return "{}".to_string();
+            }
+            let entries = entries
+                .iter()
+                .map(|(k, v)| format!("{}: {}", self::value(k, key), self::value(v, item)))
+                .collect::<Vec<_>>();
+            format!("{{ {} }}", entries.join(", "))
+        }
+        (SchemeValue::Map(entries), AssemblyTypeRef::Map(key, item)) => {
+            let entries = entries
+                .iter()
+                .map(|(k, v)| format!("[{}, {}]", self::value(k, key), self::value(v, item)))
+                .collect::<Vec<_>>();
+            format!("new Map([{}])", entries.join(", "))
+        }
+        _ => unreachable!("default values are checked against the field type"),
+    }
+}
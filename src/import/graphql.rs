@@ -0,0 +1,1205 @@
+//! GraphQL importer, for schemas written in SDL. Object, interface and input types become types,
+//! and the fields of the root types (`Query`, `Mutation` and `Subscription`) become the remote
+//! functions of a service per root type, subscriptions returning `stream` values. Types
+//! implementing an interface extend it. rgc has neither enums nor unions: enums are imported as
+//! `string`, and unions as a type with a field per member, which may be absent. Custom scalars are
+//! imported as `string`, but for `Long`, a 64-bit `integer`. Nullable types are `Optional`.
+//! The input types, map entries, root fields and `stream` or `sync` fields written by
+//! `rgc gen --target graphql` are read back as the types, maps, functions and fields they were
+//! generated from.
+
+use super::*;
+use crate::codegen::camel_case;
+
+use std::collections::HashMap;
+
+/// Builtin scalars, and the rgc types they are imported as.
+const SCALARS: &[(&str, &str)] = &[
+    ("Int", "integer"),
+    ("Float", "float"),
+    ("String", "string"),
+    ("Boolean", "boolean"),
+    ("ID", "string"),
+];
+
+/// Descriptions given by `rgc gen` to what GraphQL cannot express otherwise.
+const NO_RESULT: &str = "Returns nothing, always null.";
+const SYNC: &str = "The current value, then each new value.";
+const STREAM: &str = "Each new value, in turn.";
+const NO_FIELDS: &str = "Always null, as the type has no fields.";
+const NO_QUERIES: &str = "Always null, as the schema has no queries.";
+
+/// Custom scalar of the 64-bit integers written by `rgc gen`, and by other tools.
+const LONG: &str = "Long";
+
+/// Imports the GraphQL schema `source`. Syntax errors are returned as `line:column: message`.
+pub fn import(source: &str) -> Result<Imported, String> {
+    let mut parser = Parser {
+        tokens: Tokens::new(tokenize(source)?),
+        definitions: Vec::new(),
+        roots: Vec::new(),
+        warnings: Vec::new(),
+    };
+    parser.document()?;
+    let Parser {
+        definitions,
+        roots,
+        warnings,
+        ..
+    } = parser;
+    let mut importer = Importer {
+        definitions: &definitions,
+        roots,
+        names: HashMap::new(),
+        inputs: HashMap::new(),
+        warnings,
+    };
+    let blocks = importer.blocks();
+    let mut warnings = importer.warnings;
+    warnings.sort_by_key(|(position, _)| *position);
+    warnings.dedup();
+    Ok(Imported {
+        source: print(blocks),
+        warnings: warnings.into_iter().map(|(_, warning)| warning).collect(),
+    })
+}
+
+fn is_name_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut lexer = Lexer::new(source);
+    let mut tokens = Vec::<Token>::new();
+    let mut comments = Vec::new();
+    while let Some(c) = lexer.peek(0) {
+        let position = lexer.position();
+        // Commas are insignificant, like whitespace.
+        if c.is_whitespace() || c == ',' || c == '\u{feff}' {
+            lexer.bump();
+            continue;
+        }
+        let (kind, text) = match c {
+            '#' => {
+                lexer.bump();
+                let text = lexer.take_while(|c| c != '\n' && c != '\r');
+                let lines = vec![format!("// {}", text.trim()).trim_end().to_string()];
+                attach_comments(&mut tokens, &mut comments, lines, position);
+                continue;
+            }
+            c if is_name_start(c) => {
+                let text = lexer.take_while(|c| c.is_ascii_alphanumeric() || c == '_');
+                (Kind::Identifier, text)
+            }
+            c if c.is_ascii_digit() || c == '-' => {
+                let mut text = String::new();
+                if c == '-' {
+                    text.push(c);
+                    lexer.bump();
+                }
+                while let Some(c) = lexer.peek(0) {
+                    let exponent = matches!(c, '+' | '-') && text.ends_with(['e', 'E']);
+                    if !(c.is_ascii_alphanumeric() || c == '.' || exponent) {
+                        break;
+                    }
+                    text.push(c);
+                    lexer.bump();
+                }
+                if text.parse::<i64>().is_ok() {
+                    (Kind::Integer, text)
+                } else if text.parse::<f64>().is_ok() && !text.ends_with('.') {
+                    (Kind::Float, text)
+                } else {
+                    return Err(error(position, &format!("invalid number `{text}`")));
+                }
+            }
+            '"' if lexer.peek(1) == Some('"') && lexer.peek(2) == Some('"') => {
+                lexer.bump();
+                lexer.bump();
+                lexer.bump();
+                (Kind::String, block_string(&mut lexer, position)?)
+            }
+            '"' => {
+                lexer.bump();
+                (Kind::String, string_literal(&mut lexer, position)?)
+            }
+            '.' if lexer.peek(1) == Some('.') && lexer.peek(2) == Some('.') => {
+                lexer.bump();
+                lexer.bump();
+                lexer.bump();
+                (Kind::Symbol, "...".to_string())
+            }
+            c if "!$&():=@[]{|}".contains(c) => {
+                lexer.bump();
+                (Kind::Symbol, c.to_string())
+            }
+            c => return Err(error(position, &format!("unexpected character `{c}`"))),
+        };
+        tokens.push(Token {
+            kind,
+            text,
+            position,
+            comments: std::mem::take(&mut comments),
+            trailing: Vec::new(),
+        });
+    }
+    Ok(tokens)
+}
+
+/// The rest of a string literal after its opening quote, unescaped.
+fn string_literal(lexer: &mut Lexer, start: Position) -> Result<String, String> {
+    let mut out = String::new();
+    loop {
+        let position = lexer.position();
+        let c = match lexer.bump() {
+            None | Some('\n' | '\r') => return Err(error(start, "unterminated string")),
+            Some('"') => return Ok(out),
+            Some('\\') => match lexer.bump() {
+                Some('n') => '\n',
+                Some('r') => '\r',
+                Some('t') => '\t',
+                Some('b') => '\x08',
+                Some('f') => '\x0c',
+                Some('u') => {
+                    let digits = match lexer.peek(0) {
+                        Some('{') => {
+                            lexer.bump();
+                            let digits = lexer.take_while(|c| c.is_ascii_hexdigit());
+                            if lexer.bump() != Some('}') {
+                                return Err(error(position, "invalid escape sequence"));
+                            }
+                            digits
+                        }
+                        _ => (0..4).filter_map(|_| lexer.bump()).collect(),
+                    };
+                    u32::from_str_radix(&digits, 16)
+                        .ok()
+                        .and_then(char::from_u32)
+                        .ok_or_else(|| error(position, "invalid escape sequence"))?
+                }
+                Some(c @ ('"' | '\\' | '/')) => c,
+                _ => return Err(error(position, "invalid escape sequence")),
+            },
+            Some(c) => c,
+        };
+        out.push(c);
+    }
+}
+
+/// The rest of a block string after its opening `"""`, with the indentation common to its lines
+/// removed, as well as leading and trailing blank lines.
+fn block_string(lexer: &mut Lexer, start: Position) -> Result<String, String> {
+    let mut raw = String::new();
+    loop {
+        match (lexer.peek(0), lexer.peek(1), lexer.peek(2), lexer.peek(3)) {
+            (Some('"'), Some('"'), Some('"'), _) => {
+                lexer.bump();
+                lexer.bump();
+                lexer.bump();
+                break;
+            }
+            (Some('\\'), Some('"'), Some('"'), Some('"')) => {
+                for _ in 0..4 {
+                    lexer.bump();
+                }
+                raw.push_str("\"\"\"");
+            }
+            (Some(_), ..) => raw.extend(lexer.bump()),
+            (None, ..) => return Err(error(start, "unterminated string")),
+        }
+    }
+    let lines = raw.lines().collect::<Vec<_>>();
+    let indent = lines
+        .iter()
+        .skip(1)
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let lines = lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| match i {
+            0 => line.to_string(),
+            _ => line.get(indent..).unwrap_or_default().to_string(),
+        })
+        .collect::<Vec<_>>();
+    Ok(lines.join("\n").trim_matches('\n').trim_end().to_string())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DefinitionKind {
+    Object,
+    Interface,
+    Input,
+    Enum,
+    Union,
+    Scalar,
+}
+
+struct Definition {
+    kind: DefinitionKind,
+    name: String,
+    position: Position,
+    description: Option<String>,
+    comments: Vec<String>,
+    interfaces: Vec<String>,
+    /// Fields of object, interface and input types.
+    fields: Vec<FieldDefinition>,
+    /// Values of enums, or members of unions.
+    values: Vec<String>,
+    directives: Vec<Directive>,
+}
+
+/// A field, or an argument.
+struct FieldDefinition {
+    name: String,
+    position: Position,
+    description: Option<String>,
+    comments: Vec<String>,
+    args: Vec<FieldDefinition>,
+    ty: TypeRef,
+    default: Option<Value>,
+    directives: Vec<Directive>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TypeRef {
+    Named(String),
+    List(Box<TypeRef>),
+    NonNull(Box<TypeRef>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Integer(i64),
+    Float(f64),
+    String(String),
+    Boolean(bool),
+    Null,
+    Enum(String),
+    List(Vec<Value>),
+    Object(Vec<(String, Value)>),
+    Variable,
+}
+
+struct Directive {
+    name: String,
+    position: Position,
+    args: Vec<(String, Value)>,
+}
+
+struct Parser {
+    tokens: Tokens,
+    definitions: Vec<Definition>,
+    /// Root operation types given by `schema { ... }`, by operation.
+    roots: Vec<(String, String)>,
+    warnings: Vec<(Position, Warning)>,
+}
+
+impl Parser {
+    fn warn(&mut self, position: Position, message: impl Into<String>) {
+        let warning = Warning {
+            location: format!("{}:{}", position.0, position.1),
+            message: message.into(),
+        };
+        self.warnings.push((position, warning));
+    }
+
+    fn name(&mut self) -> Result<Token, String> {
+        self.tokens.expect_kind(Kind::Identifier, "a name")
+    }
+
+    fn description(&mut self) -> Option<String> {
+        let token = self.tokens.peek(0)?;
+        if token.kind != Kind::String {
+            return None;
+        }
+        let text = token.text.clone();
+        self.tokens.next().ok()?;
+        Some(text)
+    }
+
+    fn document(&mut self) -> Result<(), String> {
+        while let Some(token) = self.tokens.peek(0).cloned() {
+            let description = self.description();
+            let keyword = self.tokens.peek(0).cloned().unwrap_or(token.clone());
+            let comments = match description {
+                Some(_) => token.comments.clone(),
+                None => keyword.comments.clone(),
+            };
+            match keyword.text.as_str() {
+                "extend" => {
+                    self.tokens.next()?;
+                    let extended = self.definition(None, Vec::new())?;
+                    self.extend(extended);
+                }
+                "directive" => self.directive_definition()?,
+                "schema" => {
+                    self.tokens.next()?;
+                    self.directives()?;
+                    self.tokens.expect("{")?;
+                    while !self.tokens.eat("}") {
+                        let operation = self.name()?.text;
+                        self.tokens.expect(":")?;
+                        let ty = self.name()?.text;
+                        self.roots.push((operation, ty));
+                    }
+                }
+                _ => {
+                    let definition = self.definition(description, comments)?;
+                    if let Some(definition) = definition {
+                        self.definitions.push(definition);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds the fields, values and interfaces of `extension` to the definition it extends.
+    fn extend(&mut self, extension: Option<Definition>) {
+        let Some(extension) = extension else {
+            return;
+        };
+        let extended = self
+            .definitions
+            .iter_mut()
+            .find(|definition| definition.name == extension.name);
+        match extended {
+            Some(definition) if definition.kind == extension.kind => {
+                definition.interfaces.extend(extension.interfaces);
+                definition.fields.extend(extension.fields);
+                definition.values.extend(extension.values);
+                definition.directives.extend(extension.directives);
+            }
+            _ => self.warn(
+                extension.position,
+                format!(
+                    "`{}` is extended but not defined, the extension is ignored",
+                    extension.name
+                ),
+            ),
+        }
+    }
+
+    /// `directive @name(args) on LOCATIONS`, which is not imported.
+    fn directive_definition(&mut self) -> Result<(), String> {
+        self.tokens.next()?;
+        self.tokens.expect("@")?;
+        self.name()?;
+        if self.tokens.is("(") {
+            self.arguments_definition()?;
+        }
+        self.tokens.eat("repeatable");
+        self.tokens.expect("on")?;
+        self.tokens.eat("|");
+        self.name()?;
+        while self.tokens.eat("|") {
+            self.name()?;
+        }
+        Ok(())
+    }
+
+    /// A type definition, `None` for `schema` extensions.
+    fn definition(
+        &mut self,
+        description: Option<String>,
+        comments: Vec<String>,
+    ) -> Result<Option<Definition>, String> {
+        let keyword = self.tokens.peek(0).cloned();
+        let kind = match keyword.as_ref().map(|token| token.text.as_str()) {
+            Some("type") => DefinitionKind::Object,
+            Some("interface") => DefinitionKind::Interface,
+            Some("input") => DefinitionKind::Input,
+            Some("enum") => DefinitionKind::Enum,
+            Some("union") => DefinitionKind::Union,
+            Some("scalar") => DefinitionKind::Scalar,
+            Some("schema") => {
+                self.tokens.next()?;
+                self.directives()?;
+                if self.tokens.is("{") {
+                    self.tokens.next()?;
+                    while !self.tokens.eat("}") {
+                        self.name()?;
+                        self.tokens.expect(":")?;
+                        self.name()?;
+                    }
+                }
+                return Ok(None);
+            }
+            _ => return Err(self.tokens.unexpected("a definition")),
+        };
+        let keyword = self.tokens.next()?;
+        let mut definition = Definition {
+            kind,
+            name: self.name()?.text,
+            position: keyword.position,
+            description,
+            comments,
+            interfaces: Vec::new(),
+            fields: Vec::new(),
+            values: Vec::new(),
+            directives: Vec::new(),
+        };
+        if self.tokens.eat("implements") {
+            self.tokens.eat("&");
+            definition.interfaces.push(self.name()?.text);
+            while self.tokens.eat("&") {
+                definition.interfaces.push(self.name()?.text);
+            }
+        }
+        definition.directives = self.directives()?;
+        match kind {
+            DefinitionKind::Object | DefinitionKind::Interface | DefinitionKind::Input => {
+                if self.tokens.eat("{") {
+                    while !self.tokens.eat("}") {
+                        let field = self.field(kind != DefinitionKind::Input)?;
+                        definition.fields.push(field);
+                    }
+                }
+            }
+            DefinitionKind::Enum => {
+                if self.tokens.eat("{") {
+                    while !self.tokens.eat("}") {
+                        self.description();
+                        definition.values.push(self.name()?.text);
+                        self.directives()?;
+                    }
+                }
+            }
+            DefinitionKind::Union => {
+                if self.tokens.eat("=") {
+                    self.tokens.eat("|");
+                    definition.values.push(self.name()?.text);
+                    while self.tokens.eat("|") {
+                        definition.values.push(self.name()?.text);
+                    }
+                }
+            }
+            DefinitionKind::Scalar => {}
+        }
+        Ok(Some(definition))
+    }
+
+    /// A field definition, with arguments if `with_args`, or an input value.
+    fn field(&mut self, with_args: bool) -> Result<FieldDefinition, String> {
+        let first = self
+            .tokens
+            .peek(0)
+            .cloned()
+            .ok_or_else(|| self.tokens.unexpected("a field"))?;
+        let description = self.description();
+        let name = self.name()?;
+        let args = match with_args && self.tokens.is("(") {
+            true => self.arguments_definition()?,
+            false => Vec::new(),
+        };
+        self.tokens.expect(":")?;
+        let ty = self.type_ref()?;
+        let default = match self.tokens.eat("=") {
+            true => Some(self.value()?),
+            false => None,
+        };
+        let directives = self.directives()?;
+        let mut comments = first.comments;
+        // Comments on the line of the field trail its last token.
+        comments.extend(
+            self.tokens
+                .last()
+                .into_iter()
+                .flat_map(|token| token.trailing.clone()),
+        );
+        Ok(FieldDefinition {
+            name: name.text,
+            position: name.position,
+            description,
+            comments,
+            args,
+            ty,
+            default,
+            directives,
+        })
+    }
+
+    fn arguments_definition(&mut self) -> Result<Vec<FieldDefinition>, String> {
+        self.tokens.expect("(")?;
+        let mut args = Vec::new();
+        while !self.tokens.eat(")") {
+            args.push(self.field(false)?);
+        }
+        Ok(args)
+    }
+
+    fn type_ref(&mut self) -> Result<TypeRef, String> {
+        let ty = match self.tokens.eat("[") {
+            true => {
+                let item = self.type_ref()?;
+                self.tokens.expect("]")?;
+                TypeRef::List(Box::new(item))
+            }
+            false => TypeRef::Named(self.name()?.text),
+        };
+        Ok(match self.tokens.eat("!") {
+            true => TypeRef::NonNull(Box::new(ty)),
+            false => ty,
+        })
+    }
+
+    fn directives(&mut self) -> Result<Vec<Directive>, String> {
+        let mut directives = Vec::new();
+        while self.tokens.is("@") {
+            let at = self.tokens.next()?;
+            let name = self.name()?.text;
+            let mut args = Vec::new();
+            if self.tokens.eat("(") {
+                while !self.tokens.eat(")") {
+                    let name = self.name()?.text;
+                    self.tokens.expect(":")?;
+                    args.push((name, self.value()?));
+                }
+            }
+            directives.push(Directive {
+                name,
+                position: at.position,
+                args,
+            });
+        }
+        Ok(directives)
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        let token = self.tokens.next()?;
+        Ok(match (token.kind, token.text.as_str()) {
+            (Kind::Integer, text) => Value::Integer(text.parse().unwrap_or_default()),
+            (Kind::Float, text) => Value::Float(text.parse().unwrap_or_default()),
+            (Kind::String, text) => Value::String(text.to_string()),
+            (Kind::Identifier, "true") => Value::Boolean(true),
+            (Kind::Identifier, "false") => Value::Boolean(false),
+            (Kind::Identifier, "null") => Value::Null,
+            (Kind::Identifier, text) => Value::Enum(text.to_string()),
+            (Kind::Symbol, "$") => {
+                self.name()?;
+                Value::Variable
+            }
+            (Kind::Symbol, "[") => {
+                let mut items = Vec::new();
+                while !self.tokens.eat("]") {
+                    items.push(self.value()?);
+                }
+                Value::List(items)
+            }
+            (Kind::Symbol, "{") => {
+                let mut fields = Vec::new();
+                while !self.tokens.eat("}") {
+                    let name = self.name()?.text;
+                    self.tokens.expect(":")?;
+                    fields.push((name, self.value()?));
+                }
+                Value::Object(fields)
+            }
+            _ => {
+                return Err(error(
+                    token.position,
+                    &format!("expected a value, found `{}`", token.text),
+                ))
+            }
+        })
+    }
+}
+
+struct Importer<'a> {
+    definitions: &'a [Definition],
+    roots: Vec<(String, String)>,
+    /// Names of the imported types, by name of their definition. Input types generated from a
+    /// type are given the name of that type.
+    names: HashMap<String, String>,
+    /// Input types generated from a type, by name of the type. They hold its default values.
+    inputs: HashMap<String, &'a Definition>,
+    warnings: Vec<(Position, Warning)>,
+}
+
+impl<'a> Importer<'a> {
+    fn warn(&mut self, position: Position, message: impl Into<String>) {
+        let warning = Warning {
+            location: format!("{}:{}", position.0, position.1),
+            message: message.into(),
+        };
+        self.warnings.push((position, warning));
+    }
+
+    fn definition(&self, name: &str) -> Option<&'a Definition> {
+        self.definitions
+            .iter()
+            .find(|definition| definition.name == name)
+    }
+
+    /// Name of the root type of `operation`: `query`, `mutation` or `subscription`.
+    fn root(&self, operation: &str) -> String {
+        match self.roots.iter().find(|(op, _)| op == operation) {
+            Some((_, ty)) => ty.clone(),
+            None if self.roots.is_empty() => {
+                let mut name = operation.to_string();
+                name[..1].make_ascii_uppercase();
+                name
+            }
+            None => String::new(),
+        }
+    }
+
+    fn blocks(&mut self) -> Vec<ASTRootBlock> {
+        let definitions = self.definitions;
+        let roots = ["query", "mutation", "subscription"].map(|operation| self.root(operation));
+        let is_root = |name: &str| roots.iter().any(|root| root == name);
+        let aliases = self.input_aliases();
+        for (input, ty) in &aliases {
+            let input = self.definition(input).unwrap();
+            self.inputs.insert(ty.clone(), input);
+        }
+        let entries = self.entries();
+        let mut names = Names::default();
+        for definition in definitions {
+            let imported = match definition.kind {
+                DefinitionKind::Object => !is_root(&definition.name),
+                DefinitionKind::Interface | DefinitionKind::Union => true,
+                DefinitionKind::Input => !aliases.contains_key(&definition.name),
+                DefinitionKind::Enum | DefinitionKind::Scalar => false,
+            };
+            if imported && !entries.contains(&definition.name) {
+                let name = names.claim(&definition.name);
+                self.names.insert(definition.name.clone(), name);
+            }
+        }
+        for (input, ty) in &aliases {
+            if let Some(name) = self.names.get(ty).cloned() {
+                self.names.insert(input.clone(), name);
+            }
+        }
+
+        let mut blocks = Vec::new();
+        for definition in definitions {
+            let name = match self.names.get(&definition.name) {
+                Some(name) if !aliases.contains_key(&definition.name) => name.clone(),
+                _ => {
+                    self.unsupported(definition);
+                    continue;
+                }
+            };
+            let type_def = match definition.kind {
+                DefinitionKind::Union => self.union(definition, name),
+                _ => self.type_def(definition, name),
+            };
+            blocks.push(ASTRootBlock::TypeDef(type_def));
+        }
+        for (i, root) in roots.iter().enumerate() {
+            let Some(definition) = self.definition(root) else {
+                continue;
+            };
+            // The `Query` rgc writes for schemas without queries.
+            let placeholder = matches!(&definition.fields[..], [field] if field.name == "_"
+                && field.description.as_deref() == Some(NO_QUERIES));
+            if definition.kind == DefinitionKind::Object && !placeholder {
+                let name = names.claim(root);
+                blocks.push(ASTRootBlock::Service(self.service(
+                    definition,
+                    name,
+                    i == 2,
+                )));
+            }
+        }
+        blocks
+    }
+
+    /// Warns about the definitions that are not imported as types.
+    fn unsupported(&mut self, definition: &Definition) {
+        match definition.kind {
+            DefinitionKind::Enum => self.warn(
+                definition.position,
+                format!(
+                    "rgc has no enums, `{}` is imported as `string`",
+                    definition.name
+                ),
+            ),
+            DefinitionKind::Scalar if definition.name != LONG => self.warn(
+                definition.position,
+                format!(
+                    "custom scalar `{}` is imported as `string`",
+                    definition.name
+                ),
+            ),
+            _ => {}
+        }
+    }
+
+    /// Input types `XInput` with the fields of the object type `X`, as generated by rgc for
+    /// arguments, by name of the input type.
+    fn input_aliases(&self) -> HashMap<String, String> {
+        let mut aliases = self
+            .definitions
+            .iter()
+            .filter(|definition| definition.kind == DefinitionKind::Input)
+            .filter_map(|input| {
+                let ty = input.name.strip_suffix("Input")?;
+                let ty = self.definition(ty)?;
+                matches!(ty.kind, DefinitionKind::Object | DefinitionKind::Interface)
+                    .then(|| (input.name.clone(), ty.name.clone()))
+            })
+            .collect::<HashMap<_, _>>();
+        // Aliases whose fields refer to other inputs depend on these being aliases too.
+        loop {
+            let mismatched = aliases
+                .iter()
+                .find(|(input, ty)| {
+                    let input = self.definition(input).unwrap();
+                    let ty = self.definition(ty).unwrap();
+                    input.description.is_some()
+                        || input.fields.len() != ty.fields.len()
+                        || input
+                            .fields
+                            .iter()
+                            .zip(&ty.fields)
+                            .any(|(a, b)| a.name != b.name || aliased(&a.ty, &aliases) != b.ty)
+                })
+                .map(|(input, _)| input.clone());
+            match mismatched {
+                Some(input) => aliases.remove(&input),
+                None => return aliases,
+            };
+        }
+    }
+
+    /// Types of the entries of maps, as generated by rgc: `key` and `value` fields.
+    fn entries(&self) -> HashSet<String> {
+        let mut entries = self
+            .definitions
+            .iter()
+            .filter(|definition| {
+                matches!(
+                    definition.kind,
+                    DefinitionKind::Object | DefinitionKind::Input
+                ) && (definition.name.ends_with("Entry") || definition.name.ends_with("EntryInput"))
+                    && definition.interfaces.is_empty()
+                    && definition.description.is_none()
+                    && matches!(&definition.fields[..], [key, value]
+                        if key.name == "key"
+                            && value.name == "value"
+                            && matches!(key.ty, TypeRef::NonNull(_)))
+            })
+            .map(|definition| definition.name.clone())
+            .collect::<HashSet<_>>();
+        // Entries referenced other than as the items of a list are types of their own.
+        let mut types = Vec::new();
+        for definition in self.definitions {
+            for field in &definition.fields {
+                types.push(&field.ty);
+                types.extend(field.args.iter().map(|arg| &arg.ty));
+            }
+        }
+        for ty in types {
+            let mut ty = ty;
+            loop {
+                match ty {
+                    TypeRef::NonNull(inner) => ty = inner,
+                    TypeRef::List(item) => match &**item {
+                        TypeRef::NonNull(inner) if matches!(&**inner, TypeRef::Named(_)) => break,
+                        item => ty = item,
+                    },
+                    TypeRef::Named(name) => {
+                        entries.remove(name);
+                        break;
+                    }
+                }
+            }
+        }
+        entries
+    }
+
+    fn type_def(&mut self, definition: &Definition, name: String) -> ASTTypeDef {
+        let mut type_def = type_def(name);
+        let description = definition.description.as_deref();
+        (type_def.comments, type_def.annotations) =
+            documentation(description, &definition.directives);
+        type_def
+            .comments
+            .splice(0..0, definition.comments.iter().cloned());
+        self.directives(&definition.directives);
+        // Inherited fields are not repeated.
+        let mut inherited = Vec::new();
+        for (i, interface) in definition.interfaces.iter().enumerate() {
+            let base = self
+                .definition(interface)
+                .filter(|base| base.kind == DefinitionKind::Interface);
+            match base {
+                Some(base) if i == 0 => {
+                    type_def.extends = Some(type_name(&self.names[&base.name], vec![]));
+                    inherited = base
+                        .fields
+                        .iter()
+                        .map(|field| field.name.as_str())
+                        .collect();
+                }
+                Some(_) => self.warn(
+                    definition.position,
+                    format!(
+                        "rgc types extend a single type, `{}` only extends `{}` and repeats the \
+                         fields of `{interface}`",
+                        definition.name, definition.interfaces[0]
+                    ),
+                ),
+                None => self.warn(
+                    definition.position,
+                    format!(
+                        "`{interface}` is not an interface defined in this schema and is ignored"
+                    ),
+                ),
+            }
+        }
+        if definition.kind == DefinitionKind::Interface {
+            let implementations = self
+                .definitions
+                .iter()
+                .filter(|other| other.interfaces.contains(&definition.name))
+                .map(|other| format!("`{}`", other.name))
+                .collect::<Vec<_>>();
+            self.warn(
+                definition.position,
+                format!(
+                    "rgc has no interfaces, `{}` is imported as a type, which the types \
+                     implementing it extend",
+                    definition.name
+                ),
+            );
+            if !implementations.is_empty() {
+                type_def
+                    .comments
+                    .push(format!("// Implemented by {}", implementations.join(", ")));
+            }
+        }
+        for field in &definition.fields {
+            if inherited.contains(&field.name.as_str()) {
+                continue;
+            }
+            // The field rgc adds to types without fields.
+            if field.name == "_" && field.description.as_deref() == Some(NO_FIELDS) {
+                continue;
+            }
+            if !field.args.is_empty() {
+                self.warn(
+                    field.position,
+                    format!(
+                        "arguments of `{}` are not supported and are ignored",
+                        field.name
+                    ),
+                );
+            }
+            // Objects have no default values, unlike the input types generated from them.
+            let default = self.inputs.get(&definition.name).and_then(|input| {
+                let input = input.fields.iter().find(|input| input.name == field.name)?;
+                input.default.as_ref()
+            });
+            type_def
+                .fields
+                .push(self.field(field, default.or(field.default.as_ref())));
+        }
+        type_def
+    }
+
+    fn field(&mut self, field: &FieldDefinition, default: Option<&Value>) -> ASTField {
+        let name = identifier(&field.name);
+        if name != field.name {
+            self.warn(
+                field.position,
+                format!(
+                    "`{}` is not a valid field name, imported as `{name}` which changes its name \
+                     in payloads",
+                    field.name
+                ),
+            );
+        }
+        let (ty, note) = self.type_of(&field.ty, field.position);
+        let mut imported = super::field(name, ty);
+        let description = field.description.as_deref();
+        imported.ty.kind = match description {
+            Some(SYNC) => ASTTypeKind::Sync,
+            Some(STREAM) => ASTTypeKind::Stream,
+            _ => ASTTypeKind::Normal,
+        };
+        let description = description.filter(|text| ![SYNC, STREAM].contains(text));
+        (imported.comments, imported.annotations) = documentation(description, &field.directives);
+        imported
+            .comments
+            .splice(0..0, field.comments.iter().cloned());
+        imported.comments.extend(note);
+        self.directives(&field.directives);
+        if let Some(default) = default {
+            imported.default = literal(default, &imported.ty.name);
+            if imported.default.is_none() {
+                self.warn(
+                    field.position,
+                    "the default value does not match the type of the field and is ignored",
+                );
+            }
+        }
+        imported
+    }
+
+    /// Warns about the directives that are not imported.
+    fn directives(&mut self, directives: &[Directive]) {
+        for directive in directives
+            .iter()
+            .filter(|directive| directive.name != "deprecated")
+        {
+            let message = format!(
+                "directive `@{}` is not supported and is ignored",
+                directive.name
+            );
+            self.warn(directive.position, message);
+        }
+    }
+
+    /// A union as a type with a field per member, which may be absent.
+    fn union(&mut self, definition: &Definition, name: String) -> ASTTypeDef {
+        self.warn(
+            definition.position,
+            format!(
+                "rgc has no unions, `{}` is imported as a type with a field per member, which may \
+                 be absent",
+                definition.name
+            ),
+        );
+        let mut type_def = type_def(name);
+        let description = definition.description.as_deref();
+        (type_def.comments, type_def.annotations) =
+            documentation(description, &definition.directives);
+        type_def
+            .comments
+            .splice(0..0, definition.comments.iter().cloned());
+        self.directives(&definition.directives);
+        let fields = definition
+            .values
+            .iter()
+            .map(|member| identifier(&camel_case(member)))
+            .collect::<Vec<_>>();
+        for (i, (member, field_name)) in definition.values.iter().zip(&fields).enumerate() {
+            let (ty, _) = self.named(member, definition.position);
+            let mut field = field(field_name.clone(), ty);
+            field.optional = true;
+            if i == 0 {
+                let fields = fields
+                    .iter()
+                    .map(|field| format!("`{field}`"))
+                    .collect::<Vec<_>>();
+                field
+                    .comments
+                    .push(format!("// Exactly one of {} is set", fields.join(", ")));
+            }
+            type_def.fields.push(field);
+        }
+        type_def
+    }
+
+    /// A service with a remote function per field of the root type `definition`.
+    fn service(&mut self, definition: &Definition, name: String, subscription: bool) -> ASTService {
+        let description = definition.description.as_deref();
+        let (mut comments, annotations) = documentation(description, &definition.directives);
+        comments.splice(0..0, definition.comments.iter().cloned());
+        self.directives(&definition.directives);
+        let mut functions = Vec::new();
+        for field in &definition.fields {
+            let params = field
+                .args
+                .iter()
+                .map(|arg| {
+                    if arg.default.is_some() {
+                        let message = format!("the default value of `{}` is ignored", arg.name);
+                        self.warn(arg.position, message);
+                    }
+                    self.directives(&arg.directives);
+                    ASTParam {
+                        name: identifier(&arg.name),
+                        name_span: Span::default(),
+                        ty: self.type_of(&arg.ty, arg.position).0,
+                        annotations: vec![],
+                    }
+                })
+                .collect();
+            let description = field.description.as_deref();
+            let kind = match (subscription, description) {
+                (true, Some(SYNC)) => ASTTypeKind::Sync,
+                (true, _) => ASTTypeKind::Stream,
+                (false, _) => ASTTypeKind::Normal,
+            };
+            let returns = match description {
+                Some(NO_RESULT) if !subscription => None,
+                _ => Some(ASTType {
+                    kind,
+                    name: self.type_of(&field.ty, field.position).0,
+                }),
+            };
+            let description = description.filter(|text| ![SYNC, NO_RESULT].contains(text));
+            let (mut comments, annotations) = documentation(description, &field.directives);
+            comments.splice(0..0, field.comments.iter().cloned());
+            self.directives(&field.directives);
+            functions.push(ASTFunction {
+                name: identifier(&field.name),
+                name_span: Span::default(),
+                params,
+                returns,
+                annotations,
+                comments,
+                trailing_comment: None,
+            });
+        }
+        ASTService {
+            name,
+            name_span: Span::default(),
+            functions,
+            annotations,
+            comments,
+            trailing_comments: Vec::new(),
+            closing_comment: None,
+        }
+    }
+
+    /// The type of values of `ty`, and the `// One of: ...` comment of enums.
+    fn type_of(&mut self, ty: &TypeRef, position: Position) -> (ASTTypeName, Option<String>) {
+        match ty {
+            TypeRef::NonNull(inner) => self.non_null(inner, position),
+            ty => {
+                let (ty, note) = self.non_null(ty, position);
+                (type_name("Optional", vec![ty]), note)
+            }
+        }
+    }
+
+    fn non_null(&mut self, ty: &TypeRef, position: Position) -> (ASTTypeName, Option<String>) {
+        match ty {
+            TypeRef::NonNull(inner) => self.non_null(inner, position),
+            TypeRef::Named(name) => self.named(name, position),
+            TypeRef::List(item) => {
+                if let TypeRef::NonNull(inner) = &**item {
+                    if let TypeRef::Named(name) = &**inner {
+                        if let Some(entry) = self.map_entry(name) {
+                            let (key, _) = self.type_of(&entry.fields[0].ty, position);
+                            let (value, note) = self.type_of(&entry.fields[1].ty, position);
+                            return (type_name("Map", vec![key, value]), note);
+                        }
+                    }
+                }
+                let (item, note) = self.type_of(item, position);
+                (type_name("Array", vec![item]), note)
+            }
+        }
+    }
+
+    /// The definition of the map entries named `name`, which are not imported as a type.
+    fn map_entry(&self, name: &str) -> Option<&'a Definition> {
+        let definition = self.definition(name)?;
+        let is_entry = matches!(
+            definition.kind,
+            DefinitionKind::Object | DefinitionKind::Input
+        ) && !self.names.contains_key(name)
+            && definition.fields.len() == 2;
+        is_entry.then_some(definition)
+    }
+
+    fn named(&mut self, name: &str, position: Position) -> (ASTTypeName, Option<String>) {
+        if let Some((_, ty)) = SCALARS.iter().find(|(scalar, _)| *scalar == name) {
+            return (type_name(ty, vec![]), None);
+        }
+        if let Some(imported) = self.names.get(name) {
+            return (type_name(imported, vec![]), None);
+        }
+        match self.definition(name) {
+            Some(definition) if definition.kind == DefinitionKind::Enum => {
+                let values = definition.values.iter().map(|value| format!("{value:?}"));
+                let note = format!("// One of: {}", values.collect::<Vec<_>>().join(", "));
+                (type_name("string", vec![]), Some(note))
+            }
+            Some(definition) if definition.kind == DefinitionKind::Scalar => match name {
+                LONG => (type_name("integer", vec![]), None),
+                _ => (type_name("string", vec![]), None),
+            },
+            _ => {
+                self.warn(
+                    position,
+                    format!("`{name}` is not defined in this schema, imported as `string`"),
+                );
+                (type_name("string", vec![]), None)
+            }
+        }
+    }
+}
+
+/// `ty` with the input types named after the types they alias.
+fn aliased(ty: &TypeRef, aliases: &HashMap<String, String>) -> TypeRef {
+    match ty {
+        TypeRef::Named(name) => TypeRef::Named(aliases.get(name).unwrap_or(name).clone()),
+        TypeRef::List(item) => TypeRef::List(Box::new(aliased(item, aliases))),
+        TypeRef::NonNull(inner) => TypeRef::NonNull(Box::new(aliased(inner, aliases))),
+    }
+}
+
+/// Comments for a description, and `@deprecated` for `@deprecated` directives or descriptions
+/// of deprecated types as written by rgc (`Deprecated: reason`).
+fn documentation(
+    description: Option<&str>,
+    directives: &[Directive],
+) -> (Vec<String>, Vec<ASTAnnotation>) {
+    let deprecated = directives
+        .iter()
+        .find(|directive| directive.name == "deprecated");
+    if let Some(deprecated) = deprecated {
+        let reason = deprecated.args.iter().find(|(name, _)| name == "reason");
+        let args = match reason {
+            Some((_, Value::String(reason))) => vec![(None, string(reason))],
+            _ => vec![],
+        };
+        let comments = description.map(comments).unwrap_or_default();
+        return (comments, vec![annotation("deprecated", args)]);
+    }
+    match description {
+        Some("Deprecated.") => (vec![], vec![annotation("deprecated", vec![])]),
+        Some(text) => match text.strip_prefix("Deprecated: ") {
+            Some(reason) if !reason.contains('\n') => (
+                vec![],
+                vec![annotation("deprecated", vec![(None, string(reason))])],
+            ),
+            _ => (comments(text), vec![]),
+        },
+        None => (vec![], vec![]),
+    }
+}
+
+/// A default value as a literal of type `ty`. Enum values are strings, and lists of `key` and
+/// `value` objects are maps.
+fn literal(value: &Value, ty: &ASTTypeName) -> Option<ASTValue> {
+    let param = |i: usize| ty.params.get(i);
+    Some(match (value, ty.path[0].as_str()) {
+        (Value::Null, "Optional") => super::value(ASTValueKind::Null),
+        (value, "Optional") => literal(value, param(0)?)?,
+        (Value::Integer(n), "integer") => integer(*n),
+        (Value::Integer(n), "float") => float(*n as f64),
+        (Value::Float(x), "float") => float(*x),
+        (Value::String(text) | Value::Enum(text), "string") => string(text),
+        (Value::Boolean(b), "boolean") => super::value(ASTValueKind::Boolean(*b)),
+        (Value::List(items), "Array") => {
+            let items = items.iter().map(|item| literal(item, param(0)?));
+            super::value(ASTValueKind::Array(items.collect::<Option<_>>()?))
+        }
+        (Value::List(entries), "Map") => {
+            let entries = entries.iter().map(|entry| {
+                let Value::Object(fields) = entry else {
+                    return None;
+                };
+                let field = |name: &str| fields.iter().find(|(key, _)| key == name).map(|(_, v)| v);
+                Some((
+                    literal(field("key")?, param(0)?)?,
+                    literal(field("value")?, param(1)?)?,
+                ))
+            });
+            super::value(ASTValueKind::Map(entries.collect::<Option<_>>()?))
+        }
+        _ => return None,
+    })
+}
@@ -0,0 +1,771 @@
+//! JSON Schema importer. Object schemas under `$defs` (or `definitions`) become types, and so does
+//! the root schema when it describes an object. `$ref` links to these types; other referenced
+//! schemas are inlined, with a warning for the other definitions. rgc has neither enums nor
+//! unions: `enum` and `const` keep the type of their values, and `oneOf`/`anyOf` with several
+//! alternatives become a type with a field per alternative, which may be absent, like the unions
+//! of [`super::graphql`]. A `null` alternative makes the value `Optional<T>`.
+//! The maps and pairs written by `rgc gen --target jsonschema` are read back as `Map`s.
+
+use super::*;
+use crate::codegen::camel_case;
+use crate::json::Json;
+
+use std::collections::HashSet;
+
+/// Keywords that only document a schema.
+const ANNOTATIONS: &[&str] = &[
+    "$schema",
+    "$id",
+    "$comment",
+    "title",
+    "description",
+    "examples",
+    "$defs",
+    "definitions",
+];
+
+/// Keywords understood by the importer, besides [`ANNOTATIONS`].
+const KEYWORDS: &[&str] = &[
+    "type",
+    "$ref",
+    "properties",
+    "required",
+    "additionalProperties",
+    "items",
+    "enum",
+    "const",
+    "oneOf",
+    "anyOf",
+    "allOf",
+    "default",
+    "deprecated",
+    "minimum",
+    "maximum",
+    "pattern",
+    "minLength",
+    "maxLength",
+    "minItems",
+    "maxItems",
+    "propertyNames",
+];
+
+/// Number of `$ref` followed to find the constraints of a field.
+const MAX_REFERENCES: usize = 16;
+
+/// Imports the JSON Schema document `source`. `name` is the name of the type of the root schema
+/// when it has no `title`, usually the name of the file.
+pub fn import(source: &str, name: &str) -> Result<Imported, String> {
+    let root = Json::parse(source)?;
+    let mut importer = Importer {
+        root: &root,
+        names: Names::default(),
+        types: Vec::new(),
+        blocks: Vec::new(),
+        inlining: Vec::new(),
+        inlined: HashSet::new(),
+        warnings: Vec::new(),
+    };
+    // Types are named first, so that references can be resolved in any order.
+    let mut types = Vec::new();
+    if is_object(&root) {
+        let title = root.get("title").and_then(Json::as_str).unwrap_or(name);
+        types.push(("#".to_string(), importer.names.claim(title), &root));
+    }
+    for key in ["$defs", "definitions"] {
+        let Some(Json::Object(defs)) = root.get(key) else {
+            continue;
+        };
+        for (def, schema) in defs {
+            if is_object(schema) {
+                let pointer = format!("#/{key}/{}", escape(def));
+                types.push((pointer, importer.names.claim(def), schema));
+            }
+        }
+    }
+    importer.types = types
+        .iter()
+        .map(|(pointer, name, _)| (pointer.clone(), name.clone()))
+        .collect();
+    for (pointer, name, schema) in types {
+        importer.check_keywords(schema, &pointer);
+        importer.type_def(name, schema, &pointer);
+    }
+    // Other definitions have no type of their own.
+    for key in ["$defs", "definitions"] {
+        let Some(Json::Object(defs)) = root.get(key) else {
+            continue;
+        };
+        for (def, schema) in defs {
+            let pointer = format!("#/{key}/{}", escape(def));
+            if is_object(schema) {
+                continue;
+            }
+            let message = match importer.inlined.contains(&pointer) {
+                true => format!("`{def}` is not an object, it is inlined where it is referenced"),
+                false => format!("`{def}` is neither an object nor referenced, it is not imported"),
+            };
+            importer.warn(&pointer, message);
+        }
+    }
+    Ok(Imported {
+        source: print(importer.blocks),
+        warnings: importer.warnings,
+    })
+}
+
+/// Whether `schema` describes objects with known properties, imported as a type.
+fn is_object(schema: &Json) -> bool {
+    match schema.get("type") {
+        Some(ty) if ty.as_str() != Some("object") => false,
+        Some(_) => {
+            schema.get("properties").is_some() || schema.get("additionalProperties").is_none()
+        }
+        None => schema.get("properties").is_some() || schema.get("allOf").is_some(),
+    }
+}
+
+/// `key` as a JSON pointer token.
+fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+struct Importer<'a> {
+    root: &'a Json,
+    names: Names,
+    /// Names of the types, by JSON pointer to their schema.
+    types: Vec<(String, String)>,
+    blocks: Vec<ASTRootBlock>,
+    /// Pointers of the referenced schemas being inlined, to detect cycles.
+    inlining: Vec<String>,
+    /// Pointers of all the referenced schemas inlined so far.
+    inlined: HashSet<String>,
+    warnings: Vec<Warning>,
+}
+
+impl<'a> Importer<'a> {
+    fn warn(&mut self, location: &str, message: impl Into<String>) {
+        let warning = Warning {
+            location: location.to_string(),
+            message: message.into(),
+        };
+        // Schemas inlined at each of their references would report the same problems again.
+        if !self.warnings.contains(&warning) {
+            self.warnings.push(warning);
+        }
+    }
+
+    /// Warns about the keywords of `schema` that are not imported.
+    fn check_keywords(&mut self, schema: &Json, pointer: &str) {
+        let Json::Object(pairs) = schema else {
+            return;
+        };
+        for (key, _) in pairs {
+            if !ANNOTATIONS.contains(&key.as_str()) && !KEYWORDS.contains(&key.as_str()) {
+                self.warn(pointer, format!("`{key}` is not supported and is ignored"));
+            }
+        }
+    }
+
+    fn type_def(&mut self, name: String, schema: &'a Json, pointer: &str) {
+        let mut type_def = type_def(name.clone());
+        (type_def.comments, type_def.annotations) = documentation(schema);
+        // `allOf` is read as inheritance: a reference to the base type, and schemas adding
+        // properties.
+        let mut parts = vec![(schema, pointer.to_string())];
+        if let Some(all_of) = schema.get("allOf").and_then(Json::as_array) {
+            for (i, part) in all_of.iter().enumerate() {
+                let part_pointer = format!("{pointer}/allOf/{i}");
+                let base = part.get("$ref").and_then(Json::as_str).map(percent_decode);
+                match base.and_then(|base| self.type_of_pointer(&base)) {
+                    Some(base) if type_def.extends.is_none() => {
+                        type_def.extends = Some(type_name(&base, vec![]));
+                    }
+                    Some(_) => self.warn(
+                        &part_pointer,
+                        "rgc types extend a single type, the properties of this one are ignored",
+                    ),
+                    None => parts.push((part, part_pointer)),
+                }
+            }
+        }
+        // Properties may be required by another part than the one defining them.
+        let required = parts
+            .iter()
+            .flat_map(|(part, _)| part.get("required").and_then(Json::as_array))
+            .flatten()
+            .filter_map(Json::as_str)
+            .collect::<Vec<_>>();
+        let mut nested = Vec::new();
+        for (i, (part, part_pointer)) in parts.into_iter().enumerate() {
+            // The keywords of `schema` itself are checked by the caller.
+            if i > 0 {
+                self.check_keywords(part, &part_pointer);
+            }
+            if let Some(schema) = part.get("additionalProperties") {
+                if schema != &Json::Bool(false) {
+                    self.warn(
+                        &part_pointer,
+                        "`additionalProperties` is not supported alongside `properties`",
+                    );
+                }
+            }
+            let Some(Json::Object(properties)) = part.get("properties") else {
+                continue;
+            };
+            for (property, schema) in properties {
+                let field_pointer = format!("{part_pointer}/properties/{}", escape(property));
+                let hint = format!("{name}_{property}");
+                let required = required.contains(&property.as_str());
+                let field = self.field(
+                    property,
+                    schema,
+                    required,
+                    &hint,
+                    &field_pointer,
+                    &mut nested,
+                );
+                type_def.fields.push(field);
+            }
+        }
+        self.blocks.push(ASTRootBlock::TypeDef(type_def));
+        self.blocks.extend(nested);
+    }
+
+    fn field(
+        &mut self,
+        property: &str,
+        schema: &'a Json,
+        required: bool,
+        hint: &str,
+        pointer: &str,
+        nested: &mut Vec<ASTRootBlock>,
+    ) -> ASTField {
+        let name = identifier(property);
+        if name != property {
+            self.warn(
+                pointer,
+                format!(
+                    "`{property}` is not a valid field name, imported as `{name}` which changes \
+                     its name in payloads"
+                ),
+            );
+        }
+        let ty = self.type_of(schema, hint, pointer, nested);
+        let mut field = field(name, ty);
+        (field.comments, field.annotations) = documentation(schema);
+        if let Some(values) = schema.get("enum").and_then(Json::as_array) {
+            let values = values.iter().map(Json::to_string).collect::<Vec<_>>();
+            field
+                .comments
+                .push(format!("// One of: {}", values.join(", ")));
+        }
+        field.annotations.extend(self.constraints(schema, 0));
+        match schema.get("default") {
+            Some(default) => match self.value(default, &field.ty.name, pointer) {
+                Some(default) => field.default = Some(default),
+                None => field.optional = !required,
+            },
+            // Fields with a default value may be absent, but `?` fields cannot have one.
+            None => field.optional = !required,
+        }
+        field
+    }
+
+    /// Annotations for the validation keywords of `schema`, of the non-null alternative of a
+    /// nullable schema, or of the schema it references when that one is not a type. `depth`
+    /// counts the references followed, which may form a cycle.
+    fn constraints(&self, schema: &Json, depth: usize) -> Vec<ASTAnnotation> {
+        let mut annotations = Vec::new();
+        let number = |value: &Json| match value {
+            Json::Number(n) if n.fract() == 0.0 && n.abs() < 1e15 => Some(integer(*n as i64)),
+            Json::Number(x) => Some(float(*x)),
+            _ => None,
+        };
+        let min = schema.get("minimum").and_then(number);
+        let max = schema.get("maximum").and_then(number);
+        if min.is_some() || max.is_some() {
+            let args = [("min", min), ("max", max)]
+                .into_iter()
+                .filter_map(|(name, value)| Some((Some(name), value?)))
+                .collect();
+            annotations.push(annotation("range", args));
+        }
+        if let Some(regex) = schema.get("pattern").and_then(Json::as_str) {
+            annotations.push(annotation("pattern", vec![(None, string(regex))]));
+        }
+        for (keyword, name) in [
+            ("minLength", "min_len"),
+            ("maxLength", "max_len"),
+            ("minItems", "min_items"),
+            ("maxItems", "max_items"),
+        ] {
+            if let Some(n) = schema.get(keyword).and_then(Json::as_u64) {
+                annotations.push(annotation(name, vec![(None, integer(n as i64))]));
+            }
+        }
+        if !annotations.is_empty() || depth > MAX_REFERENCES {
+            return annotations;
+        }
+        for key in ["anyOf", "oneOf"] {
+            let alternatives = schema.get(key).and_then(Json::as_array).unwrap_or_default();
+            if let [item] = non_null(alternatives)[..] {
+                return self.constraints(item, depth);
+            }
+        }
+        let reference = schema
+            .get("$ref")
+            .and_then(Json::as_str)
+            .map(percent_decode);
+        match reference {
+            Some(reference) if self.type_of_pointer(&reference).is_none() => {
+                match resolve(self.root, &reference) {
+                    Some(target) => self.constraints(target, depth + 1),
+                    None => annotations,
+                }
+            }
+            _ => annotations,
+        }
+    }
+
+    /// Name of the type imported from the schema at `pointer`, if it is one.
+    fn type_of_pointer(&self, pointer: &str) -> Option<String> {
+        let (_, name) = self.types.iter().find(|(p, _)| p == pointer)?;
+        Some(name.clone())
+    }
+
+    /// The type of values matching `schema`. Inline object schemas become types named after
+    /// `hint`, added to `nested`.
+    fn type_of(
+        &mut self,
+        schema: &'a Json,
+        hint: &str,
+        pointer: &str,
+        nested: &mut Vec<ASTRootBlock>,
+    ) -> ASTTypeName {
+        if !matches!(schema, Json::Object(_)) {
+            self.warn(
+                pointer,
+                "schemas accepting any value are not supported, imported as `string`",
+            );
+            return type_name("string", vec![]);
+        }
+        self.check_keywords(schema, pointer);
+        if let Some(reference) = schema.get("$ref").and_then(Json::as_str) {
+            return self.reference(reference, hint, pointer, nested);
+        }
+        for key in ["oneOf", "anyOf"] {
+            let Some(alternatives) = schema.get(key).and_then(Json::as_array) else {
+                continue;
+            };
+            let items = non_null(alternatives);
+            let Some(&first) = items.first() else {
+                self.warn(
+                    pointer,
+                    format!("`{key}` without alternatives, imported as `string`"),
+                );
+                return type_name("string", vec![]);
+            };
+            let ty = if items.len() > 1 {
+                self.union(schema, key, hint, pointer, nested)
+            } else {
+                let index = alternatives
+                    .iter()
+                    .position(|item| std::ptr::eq(item, first));
+                let item_pointer = format!("{pointer}/{key}/{}", index.unwrap_or_default());
+                self.type_of(first, hint, &item_pointer, nested)
+            };
+            return match items.len() < alternatives.len() {
+                true => type_name("Optional", vec![ty]),
+                false => ty,
+            };
+        }
+        if let Some(values) = schema.get("enum").and_then(Json::as_array) {
+            self.warn(
+                pointer,
+                "rgc has no enums, the values of `enum` are not checked",
+            );
+            return self.type_of_values(values, pointer);
+        }
+        if let Some(value) = schema.get("const") {
+            self.warn(
+                pointer,
+                "`const` is not supported, the value is not checked",
+            );
+            return self.type_of_values(std::slice::from_ref(value), pointer);
+        }
+        let types = match schema.get("type") {
+            Some(Json::String(ty)) => vec![ty.as_str()],
+            Some(Json::Array(types)) => types.iter().filter_map(Json::as_str).collect(),
+            _ if schema.get("properties").is_some() => vec!["object"],
+            _ => vec![],
+        };
+        let nullable = types.contains(&"null");
+        let types = types
+            .into_iter()
+            .filter(|ty| *ty != "null")
+            .collect::<Vec<_>>();
+        let Some(&ty) = types.first() else {
+            self.warn(
+                pointer,
+                "schemas without a type are not supported, imported as `string`",
+            );
+            return type_name("string", vec![]);
+        };
+        if types.len() > 1 {
+            self.warn(pointer, format!("rgc has no unions, imported as `{ty}`"));
+        }
+        let ty = match ty {
+            "string" => type_name("string", vec![]),
+            "integer" => type_name("integer", vec![]),
+            "number" => type_name("float", vec![]),
+            "boolean" => type_name("boolean", vec![]),
+            "array" => self.array(schema, hint, pointer, nested),
+            "object" => self.object(schema, hint, pointer, nested),
+            ty => {
+                self.warn(
+                    pointer,
+                    format!("unknown type `{ty}`, imported as `string`"),
+                );
+                type_name("string", vec![])
+            }
+        };
+        match nullable {
+            true => type_name("Optional", vec![ty]),
+            false => ty,
+        }
+    }
+
+    /// The alternatives of `oneOf` or `anyOf` as a type named after `hint`, with a field per
+    /// alternative other than `null`, which may be absent.
+    fn union(
+        &mut self,
+        schema: &'a Json,
+        key: &str,
+        hint: &str,
+        pointer: &str,
+        nested: &mut Vec<ASTRootBlock>,
+    ) -> ASTTypeName {
+        let name = self.names.claim(hint);
+        self.warn(
+            pointer,
+            format!(
+                "rgc has no unions, `{key}` is imported as `{name}`, a type with a field per \
+                 alternative, which may be absent"
+            ),
+        );
+        let mut type_def = type_def(name.clone());
+        (type_def.comments, type_def.annotations) = documentation(schema);
+        let alternatives = schema.get(key).and_then(Json::as_array).unwrap_or_default();
+        let mut members = Vec::new();
+        for (i, item) in alternatives.iter().enumerate() {
+            if non_null(std::slice::from_ref(item)).is_empty() {
+                continue;
+            }
+            let mut member = identifier(&camel_case(&alternative_name(item, i)));
+            if members.iter().any(|(other, _)| *other == member) {
+                member = format!("{member}{}", i + 1);
+            }
+            let item_pointer = format!("{pointer}/{key}/{i}");
+            let ty = self.type_of(item, &format!("{name}_{member}"), &item_pointer, nested);
+            members.push((member, ty));
+        }
+        let names = members
+            .iter()
+            .map(|(member, _)| format!("`{member}`"))
+            .collect::<Vec<_>>();
+        for (i, (member, ty)) in members.into_iter().enumerate() {
+            let mut field = field(member, ty);
+            field.optional = true;
+            if i == 0 {
+                field
+                    .comments
+                    .push(format!("// Exactly one of {} is set", names.join(", ")));
+            }
+            type_def.fields.push(field);
+        }
+        nested.push(ASTRootBlock::TypeDef(type_def));
+        type_name(&name, vec![])
+    }
+
+    fn reference(
+        &mut self,
+        reference: &str,
+        hint: &str,
+        pointer: &str,
+        nested: &mut Vec<ASTRootBlock>,
+    ) -> ASTTypeName {
+        let reference = &percent_decode(reference);
+        if let Some(name) = self.type_of_pointer(reference) {
+            return type_name(&name, vec![]);
+        }
+        let target = resolve(self.root, reference);
+        match target {
+            Some(target) if !self.inlining.iter().any(|p| p == reference) => {
+                self.inlining.push(reference.to_string());
+                self.inlined.insert(reference.to_string());
+                let ty = self.type_of(target, hint, reference, nested);
+                self.inlining.pop();
+                ty
+            }
+            Some(_) => {
+                self.warn(
+                    pointer,
+                    format!("`{reference}` is recursive but not an object, imported as `string`"),
+                );
+                type_name("string", vec![])
+            }
+            None => {
+                self.warn(
+                    pointer,
+                    format!("`{reference}` cannot be resolved, imported as `string`"),
+                );
+                type_name("string", vec![])
+            }
+        }
+    }
+
+    fn array(
+        &mut self,
+        schema: &'a Json,
+        hint: &str,
+        pointer: &str,
+        nested: &mut Vec<ASTRootBlock>,
+    ) -> ASTTypeName {
+        let Some(items) = schema.get("items") else {
+            self.warn(
+                pointer,
+                "arrays without `items` are not supported, imported as `Array<string>`",
+            );
+            return type_name("Array", vec![type_name("string", vec![])]);
+        };
+        // `[key, value]` pairs, the encoding of maps whose keys are not strings or numbers.
+        if let Some([key, value]) = items.get("prefixItems").and_then(Json::as_array) {
+            if items.get("minItems").and_then(Json::as_u64) == Some(2)
+                && items.get("maxItems").and_then(Json::as_u64) == Some(2)
+            {
+                let items_pointer = format!("{pointer}/items/prefixItems");
+                let key = self.type_of(
+                    key,
+                    &format!("{hint}_key"),
+                    &format!("{items_pointer}/0"),
+                    nested,
+                );
+                let value = self.type_of(
+                    value,
+                    &format!("{hint}_value"),
+                    &format!("{items_pointer}/1"),
+                    nested,
+                );
+                return type_name("Map", vec![key, value]);
+            }
+        }
+        let item = self.type_of(
+            items,
+            &format!("{hint}_item"),
+            &format!("{pointer}/items"),
+            nested,
+        );
+        type_name("Array", vec![item])
+    }
+
+    fn object(
+        &mut self,
+        schema: &'a Json,
+        hint: &str,
+        pointer: &str,
+        nested: &mut Vec<ASTRootBlock>,
+    ) -> ASTTypeName {
+        if is_object(schema) {
+            let name = self.names.claim(hint);
+            let blocks = std::mem::take(&mut self.blocks);
+            self.type_def(name.clone(), schema, pointer);
+            nested.extend(std::mem::replace(&mut self.blocks, blocks));
+            return type_name(&name, vec![]);
+        }
+        // Integer keys are written by `rgc gen` with a pattern on the property names.
+        let key = match schema
+            .get("propertyNames")
+            .and_then(|names| names.get("pattern"))
+        {
+            Some(Json::String(pattern)) if pattern == "^-?[0-9]+$" => "integer",
+            Some(Json::String(pattern)) if pattern.starts_with("^-?[0-9]+(") => "float",
+            None => "string",
+            Some(_) => {
+                self.warn(pointer, "`propertyNames` is not supported and is ignored");
+                "string"
+            }
+        };
+        let value = match schema.get("additionalProperties") {
+            Some(value @ Json::Object(_)) => {
+                let value_pointer = format!("{pointer}/additionalProperties");
+                self.type_of(value, &format!("{hint}_value"), &value_pointer, nested)
+            }
+            _ => {
+                self.warn(
+                    pointer,
+                    "objects with any properties are not supported, imported as \
+                     `Map<string, string>`",
+                );
+                type_name("string", vec![])
+            }
+        };
+        type_name("Map", vec![type_name(key, vec![]), value])
+    }
+
+    /// Type of the values of an `enum`.
+    fn type_of_values(&mut self, values: &[Json], pointer: &str) -> ASTTypeName {
+        let ty = |value: &Json| match value {
+            Json::String(_) => Some("string"),
+            Json::Number(n) if n.fract() == 0.0 => Some("integer"),
+            Json::Number(_) => Some("float"),
+            Json::Bool(_) => Some("boolean"),
+            _ => None,
+        };
+        let nullable = values.contains(&Json::Null);
+        let mut types = values
+            .iter()
+            .filter(|value| **value != Json::Null)
+            .map(ty)
+            .collect::<Option<Vec<_>>>()
+            .unwrap_or_default();
+        types.sort_unstable();
+        types.dedup();
+        let ty = match types[..] {
+            [ty] => Some(ty),
+            ["float", "integer"] => Some("float"),
+            _ => None,
+        };
+        let Some(ty) = ty else {
+            self.warn(
+                pointer,
+                "values of different types are not supported, imported as `string`",
+            );
+            return type_name("string", vec![]);
+        };
+        match nullable {
+            true => type_name("Optional", vec![type_name(ty, vec![])]),
+            false => type_name(ty, vec![]),
+        }
+    }
+
+    /// `value` as a literal of type `ty`, `None` (with a warning) if it does not fit.
+    fn value(&mut self, value: &Json, ty: &ASTTypeName, pointer: &str) -> Option<ASTValue> {
+        let converted = literal(value, ty);
+        if converted.is_none() {
+            self.warn(
+                pointer,
+                format!(
+                    "default value {value} does not match the type of the field and is ignored"
+                ),
+            );
+        }
+        converted
+    }
+}
+
+fn literal(value: &Json, ty: &ASTTypeName) -> Option<ASTValue> {
+    let param = |i: usize| ty.params.get(i);
+    Some(match (value, ty.path[0].as_str()) {
+        (Json::Null, "Optional") => super::value(ASTValueKind::Null),
+        (value, "Optional") => literal(value, param(0)?)?,
+        (Json::String(s), "string") => string(s),
+        (Json::Number(n), "integer") if n.fract() == 0.0 && n.abs() < 1e15 => integer(*n as i64),
+        (Json::Number(x), "float") => float(*x),
+        (Json::Bool(b), "boolean") => super::value(ASTValueKind::Boolean(*b)),
+        (Json::Array(items), "Array") => {
+            let items = items.iter().map(|item| literal(item, param(0)?));
+            super::value(ASTValueKind::Array(items.collect::<Option<_>>()?))
+        }
+        (Json::Object(pairs), "Map") => {
+            let key_type = param(0)?;
+            let entries = pairs.iter().map(|(key, value)| {
+                let key = match key_type.path[0].as_str() {
+                    "string" => Json::from(key.as_str()),
+                    _ => Json::parse(key).ok()?,
+                };
+                Some((literal(&key, key_type)?, literal(value, param(1)?)?))
+            });
+            super::value(ASTValueKind::Map(entries.collect::<Option<_>>()?))
+        }
+        (Json::Array(pairs), "Map") => {
+            let entries = pairs.iter().map(|pair| match pair.as_array()? {
+                [key, value] => Some((literal(key, param(0)?)?, literal(value, param(1)?)?)),
+                _ => None,
+            });
+            super::value(ASTValueKind::Map(entries.collect::<Option<_>>()?))
+        }
+        _ => return None,
+    })
+}
+
+/// Comments and `@deprecated` annotation for the description and deprecation of `schema`. A
+/// one-line description of a deprecated schema is taken as the reason, as written by
+/// `rgc gen --target jsonschema`.
+fn documentation(schema: &Json) -> (Vec<String>, Vec<ASTAnnotation>) {
+    let description = schema.get("description").and_then(Json::as_str);
+    if schema.get("deprecated").and_then(Json::as_bool) != Some(true) {
+        return (description.map(comments).unwrap_or_default(), vec![]);
+    }
+    match description {
+        Some(reason) if !reason.trim().contains('\n') => {
+            let reason = string(reason.trim());
+            (vec![], vec![annotation("deprecated", vec![(None, reason)])])
+        }
+        description => {
+            let comments = description.map(comments).unwrap_or_default();
+            (comments, vec![annotation("deprecated", vec![])])
+        }
+    }
+}
+
+/// Name of the field holding the alternative `item`, the `i`-th of a union: the name of the
+/// referenced definition, or else the title or type of the alternative.
+fn alternative_name(item: &Json, i: usize) -> String {
+    let reference = item.get("$ref").and_then(Json::as_str).map(percent_decode);
+    if let Some(name) = reference.as_deref().and_then(|r| r.rsplit('/').next()) {
+        return name.to_string();
+    }
+    match (item.get("title"), item.get("type")) {
+        (Some(Json::String(title)), _) | (_, Some(Json::String(title))) => title.clone(),
+        _ => format!("option{}", i + 1),
+    }
+}
+
+/// Alternatives of `oneOf` or `anyOf` other than `null`.
+fn non_null(alternatives: &[Json]) -> Vec<&Json> {
+    let null = Json::object([("type", Json::from("null"))]);
+    alternatives.iter().filter(|item| **item != null).collect()
+}
+
+/// The schema at `pointer`, a JSON pointer into the document.
+fn resolve<'a>(root: &'a Json, pointer: &str) -> Option<&'a Json> {
+    let path = pointer.strip_prefix('#')?;
+    let mut schema = root;
+    for token in path.split('/').skip(1) {
+        let token = token.replace("~1", "/").replace("~0", "~");
+        schema = match schema {
+            Json::Array(items) => items.get(token.parse::<usize>().ok()?)?,
+            schema => schema.get(&token)?,
+        };
+    }
+    Some(schema)
+}
+
+fn percent_decode(text: &str) -> String {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut rest = text.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let hex = tail.get(..2).and_then(|hex| std::str::from_utf8(hex).ok());
+        match hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+            Some(decoded) if byte == b'%' => {
+                bytes.push(decoded);
+                rest = &tail[2..];
+            }
+            _ => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
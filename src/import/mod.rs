@@ -0,0 +1,356 @@
+//! Conversion of schemas written in other languages to rgc. Importers build an AST, which the
+//! formatter prints as `.rgc` source. Constructs that rgc cannot express are approximated and
+//! reported as warnings.
+
+pub mod graphql;
+pub mod jsonschema;
+pub mod protobuf;
+
+use crate::schema::ast::*;
+use crate::schema::format::format;
+use crate::schema::tokenize::{tokenize, Token as SchemeToken, TokenType};
+use crate::schema::{FormatOptions, Span};
+
+use std::collections::HashSet;
+use std::fmt;
+
+/// Result of an import: `.rgc` source, and what could not be converted faithfully.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Imported {
+    pub source: String,
+    pub warnings: Vec<Warning>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    /// Where the construct is in the imported schema, in terms of its language, e.g. a JSON
+    /// pointer.
+    pub location: String,
+    pub message: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: warning: {}", self.location, self.message)
+    }
+}
+
+/// Line and column, both starting at 1.
+pub(crate) type Position = (usize, usize);
+
+/// A syntax error in an imported schema, `line:column: message`.
+pub(crate) fn error((line, column): Position, message: &str) -> String {
+    format!("{line}:{column}: {message}")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Kind {
+    Identifier,
+    Integer,
+    Float,
+    String,
+    Symbol,
+}
+
+/// A token of an imported schema, for the importers of languages that are not JSON.
+#[derive(Debug, Clone)]
+pub(crate) struct Token {
+    pub kind: Kind,
+    /// Text of the token, unescaped for strings.
+    pub text: String,
+    pub position: Position,
+    /// Comments on the lines before the token.
+    pub comments: Vec<String>,
+    /// Comments after the token, on the same line.
+    pub trailing: Vec<String>,
+}
+
+pub(crate) struct Lexer {
+    chars: Vec<char>,
+    i: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Lexer {
+    pub(crate) fn new(source: &str) -> Lexer {
+        Lexer {
+            chars: source.chars().collect(),
+            i: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    pub(crate) fn position(&self) -> Position {
+        (self.line, self.column)
+    }
+
+    pub(crate) fn peek(&self, n: usize) -> Option<char> {
+        self.chars.get(self.i + n).copied()
+    }
+
+    pub(crate) fn bump(&mut self) -> Option<char> {
+        let c = self.peek(0)?;
+        self.i += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    /// Characters while `f` holds.
+    pub(crate) fn take_while(&mut self, f: impl Fn(char) -> bool) -> String {
+        let mut out = String::new();
+        while let Some(c) = self.peek(0).filter(|c| f(*c)) {
+            out.push(c);
+            self.bump();
+        }
+        out
+    }
+}
+
+/// Adds the comment `lines` found at `position`: after the last token if it is on the same line,
+/// otherwise to the `pending` comments of the next token.
+pub(crate) fn attach_comments(
+    tokens: &mut [Token],
+    pending: &mut Vec<String>,
+    lines: Vec<String>,
+    position: Position,
+) {
+    match tokens.last_mut() {
+        Some(last) if last.position.0 == position.0 && pending.is_empty() => {
+            last.trailing.extend(lines);
+        }
+        _ => pending.extend(lines),
+    }
+}
+
+/// Tokens being parsed.
+pub(crate) struct Tokens {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Tokens {
+    pub(crate) fn new(tokens: Vec<Token>) -> Tokens {
+        Tokens { tokens, pos: 0 }
+    }
+
+    pub(crate) fn peek(&self, n: usize) -> Option<&Token> {
+        self.tokens.get(self.pos + n)
+    }
+
+    /// Whether the next token is the keyword or symbol `text`.
+    pub(crate) fn is(&self, text: &str) -> bool {
+        self.peek(0).is_some_and(|token| {
+            matches!(token.kind, Kind::Identifier | Kind::Symbol) && token.text == text
+        })
+    }
+
+    /// The token last taken.
+    pub(crate) fn last(&self) -> Option<&Token> {
+        self.tokens[..self.pos].last()
+    }
+
+    pub(crate) fn next(&mut self) -> Result<Token, String> {
+        let token = self
+            .peek(0)
+            .cloned()
+            .ok_or_else(|| self.unexpected("a token"))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    pub(crate) fn eat(&mut self, text: &str) -> bool {
+        let found = self.is(text);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    pub(crate) fn expect(&mut self, text: &str) -> Result<Token, String> {
+        match self.is(text) {
+            true => self.next(),
+            false => Err(self.unexpected(&format!("`{text}`"))),
+        }
+    }
+
+    pub(crate) fn expect_kind(&mut self, kind: Kind, expected: &str) -> Result<Token, String> {
+        match self.peek(0) {
+            Some(token) if token.kind == kind => self.next(),
+            _ => Err(self.unexpected(expected)),
+        }
+    }
+
+    pub(crate) fn unexpected(&self, expected: &str) -> String {
+        match self.peek(0) {
+            Some(token) => error(
+                token.position,
+                &format!("expected {expected}, found `{}`", token.text),
+            ),
+            None => format!("expected {expected}, found the end of the file"),
+        }
+    }
+}
+
+pub(crate) fn print(blocks: Vec<ASTRootBlock>) -> String {
+    format(&ASTRoot { blocks }, &FormatOptions::default())
+}
+
+/// Names given to imported declarations, kept unique.
+#[derive(Default)]
+pub(crate) struct Names {
+    used: HashSet<String>,
+}
+
+impl Names {
+    /// `name` as a type name, suffixed with a number if it is already taken.
+    pub(crate) fn claim(&mut self, name: &str) -> String {
+        let base = pascal_case(name);
+        let mut name = base.clone();
+        let mut n = 2;
+        while !self.used.insert(name.clone()) {
+            name = format!("{base}{n}");
+            n += 1;
+        }
+        name
+    }
+}
+
+/// `invoice-items` -> `InvoiceItems`, always a valid type name.
+pub(crate) fn pascal_case(name: &str) -> String {
+    let mut out = String::new();
+    for word in name.split(|c: char| !c.is_alphanumeric()) {
+        let mut chars = word.chars();
+        out.extend(chars.next().into_iter().flat_map(char::to_uppercase));
+        out.extend(chars);
+    }
+    identifier(&out)
+}
+
+/// `name` if it can be written as an identifier, otherwise a close identifier.
+pub(crate) fn identifier(name: &str) -> String {
+    if is_identifier(name) {
+        return name.to_string();
+    }
+    let mut out = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    if !out.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+        out.insert(0, '_');
+    }
+    if !is_identifier(&out) {
+        // Keywords and `true` or `false`.
+        out.push('_');
+    }
+    out
+}
+
+fn is_identifier(name: &str) -> bool {
+    let (tokens, diagnostics) = tokenize(name);
+    let single = |token: &SchemeToken| token.ty == TokenType::Identifier && token.value == name;
+    diagnostics.is_empty() && matches!(&tokens[..], [token] if single(token))
+}
+
+pub(crate) fn type_name(name: &str, params: Vec<ASTTypeName>) -> ASTTypeName {
+    ASTTypeName {
+        path: vec![name.to_string()],
+        span: Span::default(),
+        params,
+    }
+}
+
+pub(crate) fn type_def(name: String) -> ASTTypeDef {
+    ASTTypeDef {
+        name,
+        name_span: Span::default(),
+        params: vec![],
+        extends: None,
+        fields: vec![],
+        annotations: vec![],
+        comments: vec![],
+        trailing_comments: vec![],
+        closing_comment: None,
+    }
+}
+
+pub(crate) fn field(name: String, ty: ASTTypeName) -> ASTField {
+    ASTField {
+        name,
+        name_span: Span::default(),
+        ty: ASTType {
+            kind: ASTTypeKind::Normal,
+            name: ty,
+        },
+        optional: false,
+        default: None,
+        annotations: vec![],
+        comments: vec![],
+        trailing_comment: None,
+    }
+}
+
+/// `@name(args)`, with `name = value` arguments where a name is given.
+pub(crate) fn annotation(name: &str, args: Vec<(Option<&str>, ASTValue)>) -> ASTAnnotation {
+    ASTAnnotation {
+        name: name.to_string(),
+        name_span: Span::default(),
+        args: args
+            .into_iter()
+            .map(|(name, value)| ASTAnnotationArg {
+                name: name.map(str::to_string),
+                value,
+                span: Span::default(),
+            })
+            .collect(),
+    }
+}
+
+/// `// text` lines, one per line of `text`.
+pub(crate) fn comments(text: &str) -> Vec<String> {
+    text.trim()
+        .lines()
+        .map(|line| format!("// {line}").trim_end().to_string())
+        .collect()
+}
+
+pub(crate) fn value(kind: ASTValueKind) -> ASTValue {
+    ASTValue {
+        kind,
+        span: Span::default(),
+    }
+}
+
+/// A string literal, escaped as the lexer expects.
+pub(crate) fn string(text: &str) -> ASTValue {
+    let mut raw = String::from('"');
+    for c in text.chars() {
+        match c {
+            '"' => raw.push_str("\\\""),
+            '\\' => raw.push_str("\\\\"),
+            '\n' => raw.push_str("\\n"),
+            '\r' => raw.push_str("\\r"),
+            '\t' => raw.push_str("\\t"),
+            '\0' => raw.push_str("\\0"),
+            c if c.is_control() => raw.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => raw.push(c),
+        }
+    }
+    raw.push('"');
+    value(ASTValueKind::String(raw))
+}
+
+pub(crate) fn integer(n: i64) -> ASTValue {
+    value(ASTValueKind::Integer(n.to_string()))
+}
+
+/// A float literal, which always has a fractional part or an exponent.
+pub(crate) fn float(x: f64) -> ASTValue {
+    value(ASTValueKind::Float(format!("{x:?}")))
+}
@@ -0,0 +1,1199 @@
+//! Protobuf importer, for `.proto` files in proto2 or proto3 syntax. Messages become types whose
+//! fields keep their number as `@tag`, and services become services. Fields are named as in the
+//! JSON mapping of protobuf, in lowerCamelCase. rgc has neither enums nor unions: enums are
+//! imported as `string`, their values as in JSON, and the fields of a `oneof` as fields that may
+//! be absent. Request messages only used as the input of RPCs are unpacked as parameters, which
+//! keep their numbers as `@tag`, and responses holding a single `value` are unwrapped, which
+//! reverses `rgc gen --target protobuf`.
+
+use super::*;
+use crate::codegen::{camel_case, snake_case};
+
+/// Scalar types of protobuf, and the rgc types they are imported as.
+const SCALARS: &[(&str, &str)] = &[
+    ("double", "float"),
+    ("float", "float"),
+    ("int32", "integer"),
+    ("int64", "integer"),
+    ("uint32", "integer"),
+    ("uint64", "integer"),
+    ("sint32", "integer"),
+    ("sint64", "integer"),
+    ("fixed32", "integer"),
+    ("fixed64", "integer"),
+    ("sfixed32", "integer"),
+    ("sfixed64", "integer"),
+    ("bool", "boolean"),
+    ("string", "string"),
+    ("bytes", "string"),
+];
+
+/// Imports the protobuf definitions `source`. Syntax errors are returned as
+/// `line:column: message`.
+pub fn import(source: &str) -> Result<Imported, String> {
+    let mut parser = Parser {
+        tokens: Tokens::new(tokenize(source)?),
+        file: File::default(),
+        warnings: Vec::new(),
+    };
+    parser.file()?;
+    let Parser { file, warnings, .. } = parser;
+    let mut importer = Importer {
+        file: &file,
+        names: Vec::new(),
+        warnings,
+    };
+    let blocks = importer.blocks();
+    let mut warnings = importer.warnings;
+    warnings.sort_by_key(|(position, _)| *position);
+    warnings.dedup();
+    Ok(Imported {
+        source: print(blocks),
+        warnings: warnings.into_iter().map(|(_, warning)| warning).collect(),
+    })
+}
+
+fn is_word_start(c: Option<char>) -> bool {
+    c.is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut lexer = Lexer::new(source);
+    let mut tokens = Vec::<Token>::new();
+    let mut comments = Vec::new();
+    while let Some(c) = lexer.peek(0) {
+        let position = lexer.position();
+        if c.is_whitespace() {
+            lexer.bump();
+            continue;
+        }
+        let (kind, text) = match (c, lexer.peek(1)) {
+            ('/', Some('/' | '*')) => {
+                lexer.bump();
+                let lines = match lexer.bump() {
+                    Some('/') => {
+                        let text = lexer.take_while(|c| c != '\n');
+                        vec![format!("// {}", text.trim()).trim_end().to_string()]
+                    }
+                    _ => {
+                        let mut text = String::new();
+                        loop {
+                            match (lexer.bump(), lexer.peek(0)) {
+                                (Some('*'), Some('/')) => break,
+                                (Some(c), _) => text.push(c),
+                                (None, _) => return Err(error(position, "unterminated comment")),
+                            }
+                        }
+                        lexer.bump();
+                        let lines = text.lines().map(|line| {
+                            let line = line.trim();
+                            line.strip_prefix('*').unwrap_or(line).trim()
+                        });
+                        super::comments(&lines.collect::<Vec<_>>().join("\n"))
+                    }
+                };
+                attach_comments(&mut tokens, &mut comments, lines, position);
+                continue;
+            }
+            (c, next) if is_word_start(Some(c)) || (c == '.' && is_word_start(next)) => {
+                let mut text = String::new();
+                if c == '.' {
+                    text.push(c);
+                    lexer.bump();
+                }
+                loop {
+                    text.push_str(&lexer.take_while(|c| c.is_ascii_alphanumeric() || c == '_'));
+                    if lexer.peek(0) != Some('.') || !is_word_start(lexer.peek(1)) {
+                        break;
+                    }
+                    text.push('.');
+                    lexer.bump();
+                }
+                (Kind::Identifier, text)
+            }
+            (c, next)
+                if c.is_ascii_digit() || (c == '.' && next.is_some_and(|c| c.is_ascii_digit())) =>
+            {
+                let mut text = String::new();
+                while let Some(c) = lexer.peek(0) {
+                    let exponent = matches!(c, '+' | '-')
+                        && text.ends_with(['e', 'E'])
+                        && !text.starts_with("0x")
+                        && !text.starts_with("0X");
+                    if !(c.is_ascii_alphanumeric() || c == '.' || exponent) {
+                        break;
+                    }
+                    text.push(c);
+                    lexer.bump();
+                }
+                if parse_integer(&text).is_some() {
+                    (Kind::Integer, text)
+                } else if text.parse::<f64>().is_ok() {
+                    (Kind::Float, text)
+                } else {
+                    return Err(error(position, &format!("invalid number `{text}`")));
+                }
+            }
+            (quote @ ('"' | '\''), _) => {
+                lexer.bump();
+                (Kind::String, string_literal(&mut lexer, quote, position)?)
+            }
+            (c, _) if "{}[]()<>;,=-+:".contains(c) => {
+                lexer.bump();
+                (Kind::Symbol, c.to_string())
+            }
+            (c, _) => return Err(error(position, &format!("unexpected character `{c}`"))),
+        };
+        tokens.push(Token {
+            kind,
+            text,
+            position,
+            comments: std::mem::take(&mut comments),
+            trailing: Vec::new(),
+        });
+    }
+    Ok(tokens)
+}
+
+/// The rest of a string literal after its opening `quote`, unescaped.
+fn string_literal(lexer: &mut Lexer, quote: char, start: Position) -> Result<String, String> {
+    let mut out = String::new();
+    loop {
+        let position = lexer.position();
+        let c = match lexer.bump() {
+            None | Some('\n') => return Err(error(start, "unterminated string")),
+            Some(c) if c == quote => return Ok(out),
+            Some('\\') => match lexer.bump() {
+                Some('n') => '\n',
+                Some('r') => '\r',
+                Some('t') => '\t',
+                Some('a') => '\x07',
+                Some('b') => '\x08',
+                Some('f') => '\x0c',
+                Some('v') => '\x0b',
+                Some('x' | 'X') => {
+                    let digits = lexer.take_while(|c| c.is_ascii_hexdigit());
+                    let digits = &digits[..digits.len().min(2)];
+                    char_code(digits, 16, position)?
+                }
+                Some('u' | 'U') => {
+                    let digits = lexer.take_while(|c| c.is_ascii_hexdigit());
+                    char_code(&digits, 16, position)?
+                }
+                Some(c @ '0'..='7') => {
+                    let mut digits = c.to_string();
+                    while digits.len() < 3
+                        && lexer.peek(0).is_some_and(|c| ('0'..='7').contains(&c))
+                    {
+                        digits.extend(lexer.bump());
+                    }
+                    char_code(&digits, 8, position)?
+                }
+                Some(c) => c,
+                None => return Err(error(start, "unterminated string")),
+            },
+            Some(c) => c,
+        };
+        out.push(c);
+    }
+}
+
+fn char_code(digits: &str, radix: u32, position: Position) -> Result<char, String> {
+    u32::from_str_radix(digits, radix)
+        .ok()
+        .and_then(char::from_u32)
+        .ok_or_else(|| error(position, "invalid escape sequence"))
+}
+
+/// A decimal, hexadecimal (`0x`) or octal (`0`) integer literal.
+fn parse_integer(text: &str) -> Option<i64> {
+    if let Some(hex) = text.strip_prefix("0x").or(text.strip_prefix("0X")) {
+        i64::from_str_radix(hex, 16).ok()
+    } else if text.len() > 1 && text.starts_with('0') {
+        i64::from_str_radix(&text[1..], 8).ok()
+    } else {
+        text.parse().ok()
+    }
+}
+
+#[derive(Default)]
+struct File {
+    package: String,
+    /// Messages in the order of their declaration, nested ones after their parent.
+    messages: Vec<Message>,
+    enums: Vec<Enum>,
+    services: Vec<Service>,
+}
+
+struct Message {
+    /// Name within the package, e.g. `Outer.Inner`.
+    name: String,
+    comments: Vec<String>,
+    trailing_comments: Vec<String>,
+    fields: Vec<Field>,
+    oneofs: Vec<Oneof>,
+    options: Vec<ProtoOption>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Label {
+    None,
+    Optional,
+    Repeated,
+    Required,
+}
+
+enum FieldType {
+    Named(String),
+    Map(String, String),
+}
+
+struct Field {
+    name: String,
+    position: Position,
+    comments: Vec<String>,
+    label: Label,
+    ty: FieldType,
+    number: i64,
+    options: Vec<ProtoOption>,
+    /// Name of the `oneof` the field belongs to.
+    oneof: Option<String>,
+}
+
+struct Oneof {
+    name: String,
+    position: Position,
+}
+
+struct Enum {
+    /// Name within the package, e.g. `Outer.Status`.
+    name: String,
+    position: Position,
+    values: Vec<String>,
+}
+
+struct Service {
+    name: String,
+    comments: Vec<String>,
+    trailing_comments: Vec<String>,
+    rpcs: Vec<Rpc>,
+    options: Vec<ProtoOption>,
+}
+
+struct Rpc {
+    name: String,
+    position: Position,
+    comments: Vec<String>,
+    input: String,
+    client_streaming: bool,
+    output: String,
+    server_streaming: bool,
+    options: Vec<ProtoOption>,
+}
+
+/// `name = value`, in brackets after a field or in an `option` statement.
+struct ProtoOption {
+    /// Custom options are written in parentheses: `(my.option).field`.
+    name: String,
+    position: Position,
+    value: OptionValue,
+}
+
+enum OptionValue {
+    Identifier(String),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    /// Messages in text format, which are not read.
+    Other,
+}
+
+struct Parser {
+    tokens: Tokens,
+    file: File,
+    warnings: Vec<(Position, Warning)>,
+}
+
+impl Parser {
+    fn identifier(&mut self) -> Result<Token, String> {
+        self.tokens.expect_kind(Kind::Identifier, "a name")
+    }
+
+    /// Adjacent string literals, which are concatenated.
+    fn string(&mut self) -> Result<String, String> {
+        let mut out = self.tokens.expect_kind(Kind::String, "a string")?.text;
+        while self
+            .tokens
+            .peek(0)
+            .is_some_and(|token| token.kind == Kind::String)
+        {
+            out.push_str(&self.tokens.next()?.text);
+        }
+        Ok(out)
+    }
+
+    fn integer(&mut self) -> Result<i64, String> {
+        let negative = self.tokens.eat("-");
+        let token = self.tokens.expect_kind(Kind::Integer, "an integer")?;
+        let n = parse_integer(&token.text)
+            .ok_or_else(|| error(token.position, "integer out of range"))?;
+        Ok(if negative { -n } else { n })
+    }
+
+    fn warn(&mut self, position: Position, message: impl Into<String>) {
+        let warning = Warning {
+            location: format!("{}:{}", position.0, position.1),
+            message: message.into(),
+        };
+        self.warnings.push((position, warning));
+    }
+
+    /// Skips to the end of the current statement, including its `;`.
+    fn skip_statement(&mut self) -> Result<(), String> {
+        while !self.tokens.eat(";") {
+            if self.tokens.is("{") {
+                self.skip_block()?;
+            } else {
+                self.tokens.next()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Skips a block in braces.
+    fn skip_block(&mut self) -> Result<(), String> {
+        self.tokens.expect("{")?;
+        let mut depth = 1;
+        while depth > 0 {
+            match self.tokens.next()?.text.as_str() {
+                "{" => depth += 1,
+                "}" => depth -= 1,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn file(&mut self) -> Result<(), String> {
+        while let Some(token) = self.tokens.peek(0).cloned() {
+            if token.kind == Kind::Symbol && token.text == ";" {
+                self.tokens.next()?;
+                continue;
+            }
+            if token.kind != Kind::Identifier {
+                return Err(self.tokens.unexpected("a declaration"));
+            }
+            match token.text.as_str() {
+                "syntax" | "edition" => {
+                    self.tokens.next()?;
+                    self.tokens.expect("=")?;
+                    self.string()?;
+                    self.tokens.expect(";")?;
+                }
+                "package" => {
+                    self.tokens.next()?;
+                    self.file.package = self.identifier()?.text;
+                    self.tokens.expect(";")?;
+                }
+                // Types from imported files are not defined here and reported where used.
+                "import" => {
+                    self.tokens.next()?;
+                    if !self.tokens.eat("weak") {
+                        self.tokens.eat("public");
+                    }
+                    self.string()?;
+                    self.tokens.expect(";")?;
+                }
+                "option" => {
+                    // File options are about generated code.
+                    self.tokens.next()?;
+                    self.option()?;
+                    self.tokens.expect(";")?;
+                }
+                "message" => self.message("")?,
+                "enum" => self.enumeration("")?,
+                "service" => self.service()?,
+                "extend" => self.extend()?,
+                _ => return Err(self.tokens.unexpected("a declaration")),
+            }
+        }
+        Ok(())
+    }
+
+    fn extend(&mut self) -> Result<(), String> {
+        let keyword = self.tokens.next()?;
+        self.warn(keyword.position, "`extend` is not supported and is ignored");
+        self.identifier()?;
+        self.skip_block()
+    }
+
+    /// `message Name { ... }`, nested in the message named `parent` if not empty.
+    fn message(&mut self, parent: &str) -> Result<(), String> {
+        let keyword = self.tokens.next()?;
+        let name = self.identifier()?.text;
+        let name = match parent {
+            "" => name,
+            parent => format!("{parent}.{name}"),
+        };
+        let index = self.file.messages.len();
+        self.file.messages.push(Message {
+            name: name.clone(),
+            comments: keyword.comments,
+            trailing_comments: Vec::new(),
+            fields: Vec::new(),
+            oneofs: Vec::new(),
+            options: Vec::new(),
+        });
+        self.tokens.expect("{")?;
+        loop {
+            let token = self
+                .tokens
+                .peek(0)
+                .cloned()
+                .ok_or_else(|| self.tokens.unexpected("`}`"))?;
+            match token.text.as_str() {
+                "}" if token.kind == Kind::Symbol => {
+                    self.tokens.next()?;
+                    self.file.messages[index].trailing_comments = token.comments;
+                    return Ok(());
+                }
+                ";" if token.kind == Kind::Symbol => {
+                    self.tokens.next()?;
+                }
+                "message" => self.message(&name)?,
+                "enum" => self.enumeration(&name)?,
+                "extend" => self.extend()?,
+                "option" => {
+                    self.tokens.next()?;
+                    let option = self.option()?;
+                    self.tokens.expect(";")?;
+                    self.file.messages[index].options.push(option);
+                }
+                "reserved" | "extensions" => {
+                    self.tokens.next()?;
+                    self.skip_statement()?;
+                }
+                "oneof" => {
+                    self.tokens.next()?;
+                    let oneof = self.identifier()?.text;
+                    self.file.messages[index].oneofs.push(Oneof {
+                        name: oneof.clone(),
+                        position: token.position,
+                    });
+                    self.tokens.expect("{")?;
+                    while !self.tokens.eat("}") {
+                        if self.tokens.eat(";") {
+                            continue;
+                        }
+                        if self.tokens.eat("option") {
+                            self.option()?;
+                            self.tokens.expect(";")?;
+                            continue;
+                        }
+                        if let Some(field) = self.field(Some(&oneof))? {
+                            self.file.messages[index].fields.push(field);
+                        }
+                    }
+                }
+                _ => {
+                    if let Some(field) = self.field(None)? {
+                        self.file.messages[index].fields.push(field);
+                    }
+                }
+            }
+        }
+    }
+
+    /// `[label] type name = number [options];`, `None` for groups which are not imported.
+    fn field(&mut self, oneof: Option<&str>) -> Result<Option<Field>, String> {
+        let first = self
+            .tokens
+            .peek(0)
+            .cloned()
+            .ok_or_else(|| self.tokens.unexpected("a field"))?;
+        let label = match first.text.as_str() {
+            "optional" => Label::Optional,
+            "repeated" => Label::Repeated,
+            "required" => Label::Required,
+            _ => Label::None,
+        };
+        if label != Label::None {
+            self.tokens.next()?;
+        }
+        let ty = match self.tokens.is("map")
+            && self.tokens.peek(1).is_some_and(|token| token.text == "<")
+        {
+            true => {
+                self.tokens.next()?;
+                self.tokens.expect("<")?;
+                let key = self.identifier()?.text;
+                self.tokens.expect(",")?;
+                let value = self.identifier()?.text;
+                self.tokens.expect(">")?;
+                FieldType::Map(key, value)
+            }
+            false => FieldType::Named(self.identifier()?.text),
+        };
+        let name = self.identifier()?;
+        self.tokens.expect("=")?;
+        let number = self.integer()?;
+        let mut options = Vec::new();
+        if self.tokens.eat("[") {
+            loop {
+                options.push(self.option()?);
+                if self.tokens.eat("]") {
+                    break;
+                }
+                self.tokens.expect(",")?;
+            }
+        }
+        if matches!(&ty, FieldType::Named(ty) if ty == "group") {
+            self.warn(first.position, "groups are not supported and are ignored");
+            self.skip_block()?;
+            return Ok(None);
+        }
+        let end = self.tokens.expect(";")?;
+        let mut comments = first.comments;
+        comments.extend(end.trailing);
+        Ok(Some(Field {
+            name: name.text,
+            position: first.position,
+            comments,
+            label,
+            ty,
+            number,
+            options,
+            oneof: oneof.map(str::to_string),
+        }))
+    }
+
+    /// `name = value`, after `option` or in brackets.
+    fn option(&mut self) -> Result<ProtoOption, String> {
+        let position = self
+            .tokens
+            .peek(0)
+            .map(|token| token.position)
+            .unwrap_or_default();
+        let name =
+            if self.tokens.eat("(") {
+                let mut name = format!("({})", self.identifier()?.text);
+                self.tokens.expect(")")?;
+                // `(my.option).field`: the lexer reads `.field` as an identifier.
+                while self.tokens.peek(0).is_some_and(|token| {
+                    token.kind == Kind::Identifier && token.text.starts_with('.')
+                }) {
+                    name.push_str(&self.tokens.next()?.text);
+                }
+                name
+            } else {
+                self.identifier()?.text
+            };
+        self.tokens.expect("=")?;
+        let value = self.option_value()?;
+        Ok(ProtoOption {
+            name,
+            position,
+            value,
+        })
+    }
+
+    fn option_value(&mut self) -> Result<OptionValue, String> {
+        if self.tokens.is("{") {
+            self.skip_block()?;
+            return Ok(OptionValue::Other);
+        }
+        let negative = self.tokens.eat("-");
+        let token = self
+            .tokens
+            .peek(0)
+            .cloned()
+            .ok_or_else(|| self.tokens.unexpected("a value"))?;
+        let value = match token.kind {
+            Kind::Integer => match parse_integer(&token.text) {
+                Some(n) if negative => OptionValue::Integer(-n),
+                Some(n) => OptionValue::Integer(n),
+                None => OptionValue::Other,
+            },
+            Kind::Float => {
+                let x = token.text.parse::<f64>().unwrap_or_default();
+                OptionValue::Float(if negative { -x } else { x })
+            }
+            Kind::String => return Ok(OptionValue::String(self.string()?)),
+            Kind::Identifier if negative => OptionValue::Other,
+            Kind::Identifier => OptionValue::Identifier(token.text),
+            Kind::Symbol => return Err(self.tokens.unexpected("a value")),
+        };
+        self.tokens.next()?;
+        Ok(value)
+    }
+
+    fn enumeration(&mut self, parent: &str) -> Result<(), String> {
+        let keyword = self.tokens.next()?;
+        let name = self.identifier()?.text;
+        let name = match parent {
+            "" => name,
+            parent => format!("{parent}.{name}"),
+        };
+        self.tokens.expect("{")?;
+        let mut values = Vec::new();
+        while !self.tokens.eat("}") {
+            if self.tokens.eat(";") {
+                continue;
+            }
+            if self.tokens.eat("option") || self.tokens.eat("reserved") {
+                self.skip_statement()?;
+                continue;
+            }
+            values.push(self.identifier()?.text);
+            self.tokens.expect("=")?;
+            self.integer()?;
+            if self.tokens.eat("[") {
+                loop {
+                    self.option()?;
+                    if self.tokens.eat("]") {
+                        break;
+                    }
+                    self.tokens.expect(",")?;
+                }
+            }
+            self.tokens.expect(";")?;
+        }
+        self.file.enums.push(Enum {
+            name,
+            position: keyword.position,
+            values,
+        });
+        Ok(())
+    }
+
+    fn service(&mut self) -> Result<(), String> {
+        let keyword = self.tokens.next()?;
+        let mut service = Service {
+            name: self.identifier()?.text,
+            comments: keyword.comments,
+            trailing_comments: Vec::new(),
+            rpcs: Vec::new(),
+            options: Vec::new(),
+        };
+        self.tokens.expect("{")?;
+        loop {
+            if self.tokens.is("}") {
+                service.trailing_comments = self.tokens.next()?.comments;
+                break;
+            }
+            if self.tokens.eat(";") {
+                continue;
+            }
+            if self.tokens.eat("option") {
+                service.options.push(self.option()?);
+                self.tokens.expect(";")?;
+                continue;
+            }
+            let keyword = self.tokens.expect("rpc")?;
+            let name = self.identifier()?.text;
+            self.tokens.expect("(")?;
+            let (client_streaming, input) = self.rpc_type()?;
+            self.tokens.expect(")")?;
+            self.tokens.expect("returns")?;
+            self.tokens.expect("(")?;
+            let (server_streaming, output) = self.rpc_type()?;
+            self.tokens.expect(")")?;
+            let mut options = Vec::new();
+            if self.tokens.eat("{") {
+                while !self.tokens.eat("}") {
+                    if self.tokens.eat(";") {
+                        continue;
+                    }
+                    self.tokens.expect("option")?;
+                    options.push(self.option()?);
+                    self.tokens.expect(";")?;
+                }
+            } else {
+                self.tokens.expect(";")?;
+            }
+            service.rpcs.push(Rpc {
+                name,
+                position: keyword.position,
+                comments: keyword.comments,
+                input,
+                client_streaming,
+                output,
+                server_streaming,
+                options,
+            });
+        }
+        self.file.services.push(service);
+        Ok(())
+    }
+
+    /// `[stream] Type`, in the parentheses of an RPC.
+    fn rpc_type(&mut self) -> Result<(bool, String), String> {
+        let stream =
+            self.tokens.is("stream") && self.tokens.peek(1).is_some_and(|token| token.text != ")");
+        if stream {
+            self.tokens.next()?;
+        }
+        Ok((stream, self.identifier()?.text))
+    }
+}
+
+/// What a type name refers to.
+enum Resolved {
+    Scalar(&'static str),
+    Message(usize),
+    Enum(usize),
+    /// A type of `google.protobuf`, such as `Timestamp`.
+    WellKnown(String),
+    Unknown,
+}
+
+/// An imported type, and how it is held.
+struct Ty {
+    name: ASTTypeName,
+    /// Singular message fields may be absent.
+    message: bool,
+    /// `// One of: ...` for enums.
+    note: Option<String>,
+}
+
+struct Importer<'a> {
+    file: &'a File,
+    /// Names of the imported types, by index of their message. `None` for the messages imported
+    /// as the parameters or the result of functions.
+    names: Vec<Option<String>>,
+    warnings: Vec<(Position, Warning)>,
+}
+
+impl<'a> Importer<'a> {
+    fn warn(&mut self, position: Position, message: impl Into<String>) {
+        let warning = Warning {
+            location: format!("{}:{}", position.0, position.1),
+            message: message.into(),
+        };
+        self.warnings.push((position, warning));
+    }
+
+    fn blocks(&mut self) -> Vec<ASTRootBlock> {
+        let file = self.file;
+        let mut referenced = vec![false; file.messages.len()];
+        let mut inputs = vec![false; file.messages.len()];
+        let mut outputs = vec![false; file.messages.len()];
+        for message in &file.messages {
+            for field in &message.fields {
+                let types = match &field.ty {
+                    FieldType::Named(ty) => vec![ty],
+                    FieldType::Map(key, value) => vec![key, value],
+                };
+                for ty in types {
+                    if let Resolved::Message(i) = self.resolve(ty, &message.name) {
+                        referenced[i] = true;
+                    }
+                }
+            }
+        }
+        for rpc in file.services.iter().flat_map(|service| &service.rpcs) {
+            if let Resolved::Message(i) = self.resolve(&rpc.input, "") {
+                inputs[i] = true;
+            }
+            if let Resolved::Message(i) = self.resolve(&rpc.output, "") {
+                outputs[i] = true;
+            }
+        }
+        let mut names = Names::default();
+        for (i, message) in file.messages.iter().enumerate() {
+            let plain = !referenced[i] && inputs[i] != outputs[i] && self.is_plain(message);
+            let unpacked = plain && inputs[i];
+            let value = |field: &Field| field.name == "value" && field.number == 1;
+            let unwrapped =
+                plain && outputs[i] && matches!(&message.fields[..], [field] if value(field));
+            self.names.push(match unpacked || unwrapped {
+                true => None,
+                false => Some(names.claim(&message.name)),
+            });
+        }
+
+        for enumeration in &file.enums {
+            let message = format!(
+                "rgc has no enums, `{}` is imported as `string`",
+                enumeration.name
+            );
+            self.warn(enumeration.position, message);
+        }
+        let mut blocks = Vec::new();
+        for (i, message) in file.messages.iter().enumerate() {
+            if let Some(name) = self.names[i].clone() {
+                blocks.push(ASTRootBlock::TypeDef(self.type_def(message, name)));
+            }
+        }
+        for service in &file.services {
+            let name = names.claim(&service.name);
+            blocks.push(ASTRootBlock::Service(self.service(service, name)));
+        }
+        blocks
+    }
+
+    /// Whether the message has nothing besides its fields, so that they can be imported as
+    /// parameters or as a result.
+    fn is_plain(&self, message: &Message) -> bool {
+        let prefix = format!("{}.", message.name);
+        let nested = self.file.messages.iter().map(|message| &message.name);
+        let nested = nested.chain(self.file.enums.iter().map(|enumeration| &enumeration.name));
+        message.oneofs.is_empty()
+            && message.options.is_empty()
+            && message
+                .fields
+                .iter()
+                .all(|field| field.label != Label::Optional && field.options.is_empty())
+            && !nested.into_iter().any(|name| name.starts_with(&prefix))
+    }
+
+    /// Resolves the type `name` as written in the message `scope`, `""` at the top level.
+    fn resolve(&self, name: &str, scope: &str) -> Resolved {
+        let candidates = match name.strip_prefix('.') {
+            Some(absolute) => vec![absolute.to_string()],
+            None => {
+                if let Some((_, ty)) = SCALARS.iter().find(|(scalar, _)| *scalar == name) {
+                    return Resolved::Scalar(ty);
+                }
+                // Innermost scope first.
+                let scope = [self.file.package.as_str(), scope]
+                    .into_iter()
+                    .filter(|part| !part.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(".");
+                let parts = scope
+                    .split('.')
+                    .filter(|part| !part.is_empty())
+                    .collect::<Vec<_>>();
+                (0..=parts.len())
+                    .rev()
+                    .map(|i| match i {
+                        0 => name.to_string(),
+                        i => format!("{}.{name}", parts[..i].join(".")),
+                    })
+                    .collect()
+            }
+        };
+        let full = |name: &str| match self.file.package.as_str() {
+            "" => name.to_string(),
+            package => format!("{package}.{name}"),
+        };
+        for candidate in &candidates {
+            let mut messages = self.file.messages.iter();
+            if let Some(i) = messages.position(|message| full(&message.name) == *candidate) {
+                return Resolved::Message(i);
+            }
+            let mut enums = self.file.enums.iter();
+            if let Some(i) = enums.position(|enumeration| full(&enumeration.name) == *candidate) {
+                return Resolved::Enum(i);
+            }
+        }
+        match candidates
+            .last()
+            .and_then(|name| name.strip_prefix("google.protobuf."))
+        {
+            Some(name) => Resolved::WellKnown(name.to_string()),
+            None => Resolved::Unknown,
+        }
+    }
+
+    fn type_of(&mut self, name: &str, scope: &str, position: Position) -> Ty {
+        let ty = |name: &str, message: bool| Ty {
+            name: type_name(name, vec![]),
+            message,
+            note: None,
+        };
+        match self.resolve(name, scope) {
+            Resolved::Scalar(scalar) => {
+                if name == "bytes" {
+                    self.warn(
+                        position,
+                        "`bytes` are imported as `string`, holding base64 as in JSON",
+                    );
+                }
+                ty(scalar, false)
+            }
+            Resolved::Message(i) => {
+                let name = self.names[i].clone().unwrap_or_default();
+                ty(&name, true)
+            }
+            Resolved::Enum(i) => {
+                let values = self.file.enums[i].values.iter();
+                let values = values.map(|value| format!("{value:?}")).collect::<Vec<_>>();
+                Ty {
+                    note: Some(format!("// One of: {}", values.join(", "))),
+                    ..ty("string", false)
+                }
+            }
+            // Wrappers are messages so that scalars may be absent.
+            Resolved::WellKnown(name) => match name.as_str() {
+                "DoubleValue" | "FloatValue" => ty("float", true),
+                "Int64Value" | "UInt64Value" | "Int32Value" | "UInt32Value" => ty("integer", true),
+                "BoolValue" => ty("boolean", true),
+                "StringValue" => ty("string", true),
+                "BytesValue" => {
+                    self.warn(
+                        position,
+                        "`bytes` are imported as `string`, holding base64 as in JSON",
+                    );
+                    ty("string", true)
+                }
+                "Timestamp" | "Duration" | "FieldMask" => {
+                    self.warn(
+                        position,
+                        format!(
+                            "`google.protobuf.{name}` is imported as `string`, as written in JSON"
+                        ),
+                    );
+                    ty("string", true)
+                }
+                _ => {
+                    self.warn(
+                        position,
+                        format!("`google.protobuf.{name}` is not supported, imported as `string`"),
+                    );
+                    ty("string", true)
+                }
+            },
+            Resolved::Unknown => {
+                self.warn(
+                    position,
+                    format!("`{name}` is not defined in this file, imported as `string`"),
+                );
+                ty("string", false)
+            }
+        }
+    }
+
+    fn type_def(&mut self, message: &Message, name: String) -> ASTTypeDef {
+        let mut type_def = type_def(name);
+        type_def.comments = message.comments.clone();
+        type_def.trailing_comments = message.trailing_comments.clone();
+        type_def.annotations = self.annotations(&message.options, &mut type_def.comments);
+        for oneof in &message.oneofs {
+            self.warn(
+                oneof.position,
+                format!(
+                    "rgc has no unions, the fields of `oneof {}` are imported as fields that may \
+                     be absent",
+                    oneof.name
+                ),
+            );
+        }
+        let mut oneofs = Vec::new();
+        for field in &message.fields {
+            let mut imported = self.field(field, &message.name);
+            if let Some(oneof) = field.oneof.as_ref().filter(|oneof| !oneofs.contains(oneof)) {
+                let members = message
+                    .fields
+                    .iter()
+                    .filter(|member| member.oneof.as_ref() == Some(oneof))
+                    .map(|member| format!("`{}`", field_name(member)))
+                    .collect::<Vec<_>>();
+                let note = format!(
+                    "// At most one of {} is set (`{oneof}`)",
+                    members.join(", ")
+                );
+                imported.comments.push(note);
+                oneofs.push(oneof);
+            }
+            type_def.fields.push(imported);
+        }
+        type_def
+    }
+
+    fn field(&mut self, field: &Field, scope: &str) -> ASTField {
+        let ty = match &field.ty {
+            FieldType::Named(name) => {
+                let ty = self.type_of(name, scope, field.position);
+                match field.label {
+                    Label::Repeated => Ty {
+                        name: type_name("Array", vec![ty.name]),
+                        message: false,
+                        note: ty.note,
+                    },
+                    _ => ty,
+                }
+            }
+            FieldType::Map(key, value) => {
+                let key = self.type_of(key, scope, field.position);
+                let value = self.type_of(value, scope, field.position);
+                Ty {
+                    name: type_name("Map", vec![key.name, value.name]),
+                    message: false,
+                    note: value.note,
+                }
+            }
+        };
+        let name = field_name(field);
+        if let Some(OptionValue::String(json)) = option(&field.options, "json_name") {
+            if name != *json {
+                self.warn(
+                    field.position,
+                    format!(
+                        "`{json}` is not a valid field name, imported as `{name}` which changes \
+                         its name in JSON"
+                    ),
+                );
+            }
+        }
+        let mut imported = super::field(name.clone(), ty.name);
+        imported.comments = field.comments.clone();
+        imported.comments.extend(ty.note);
+        imported.annotations = self.annotations(&field.options, &mut imported.comments);
+        if snake_case(&name) != field.name {
+            let rename = vec![(Some("proto"), string(&field.name))];
+            imported.annotations.push(annotation("rename", rename));
+        }
+        let tag = vec![(None, integer(field.number))];
+        imported.annotations.push(annotation("tag", tag));
+        if let Some(default) = option(&field.options, "default") {
+            imported.default = literal(default, &imported.ty.name);
+            if imported.default.is_none() {
+                self.warn(
+                    field.position,
+                    "the default value does not match the type of the field and is ignored",
+                );
+            }
+        }
+        // Fields with a default value may be absent, but `?` fields cannot have one.
+        imported.optional = imported.default.is_none()
+            && (field.oneof.is_some()
+                || field.label == Label::Optional
+                || (ty.message && field.label == Label::None));
+        imported
+    }
+
+    /// `@deprecated` for `deprecated = true`, with the reason found in a `// Deprecated: ...`
+    /// comment, as generated by rgc. Custom options are reported as ignored.
+    fn annotations(
+        &mut self,
+        options: &[ProtoOption],
+        comments: &mut Vec<String>,
+    ) -> Vec<ASTAnnotation> {
+        for option in options.iter().filter(|option| option.name.starts_with('(')) {
+            let message = format!("option `{}` is not supported and is ignored", option.name);
+            self.warn(option.position, message);
+        }
+        match option(options, "deprecated") {
+            Some(OptionValue::Identifier(value)) if value == "true" => {}
+            _ => return vec![],
+        }
+        let reason = comments
+            .iter()
+            .position(|comment| comment.starts_with("// Deprecated: "));
+        match reason {
+            Some(i) => {
+                let reason = comments.remove(i);
+                let reason = string(&reason["// Deprecated: ".len()..]);
+                vec![annotation("deprecated", vec![(None, reason)])]
+            }
+            None => vec![annotation("deprecated", vec![])],
+        }
+    }
+
+    fn service(&mut self, service: &Service, name: String) -> ASTService {
+        let file = self.file;
+        let mut comments = service.comments.clone();
+        let annotations = self.annotations(&service.options, &mut comments);
+        let mut functions = Vec::new();
+        for rpc in &service.rpcs {
+            if rpc.client_streaming {
+                self.warn(
+                    rpc.position,
+                    format!(
+                        "client streaming is not supported, `{}` is imported as taking a single \
+                         request",
+                        rpc.name
+                    ),
+                );
+            }
+            let params = match self.resolve(&rpc.input, "") {
+                Resolved::WellKnown(name) if name == "Empty" => vec![],
+                Resolved::Message(i) if self.names[i].is_none() => {
+                    let message = &file.messages[i];
+                    message
+                        .fields
+                        .iter()
+                        .map(|field| {
+                            let field = self.field(field, &message.name);
+                            let tag = field.annotations.into_iter().filter(|a| a.name == "tag");
+                            ASTParam {
+                                name: field.name,
+                                name_span: Span::default(),
+                                ty: field.ty.name,
+                                annotations: tag.collect(),
+                            }
+                        })
+                        .collect()
+                }
+                _ => vec![ASTParam {
+                    name: "request".to_string(),
+                    name_span: Span::default(),
+                    ty: self.type_of(&rpc.input, "", rpc.position).name,
+                    annotations: vec![annotation("tag", vec![(None, integer(1))])],
+                }],
+            };
+            let returns = match self.resolve(&rpc.output, "") {
+                Resolved::WellKnown(name) if name == "Empty" => None,
+                Resolved::Message(i) if self.names[i].is_none() => {
+                    let message = &file.messages[i];
+                    Some(self.field(&message.fields[0], &message.name).ty.name)
+                }
+                _ => Some(self.type_of(&rpc.output, "", rpc.position).name),
+            };
+            let kind = match rpc.server_streaming {
+                true => ASTTypeKind::Stream,
+                false => ASTTypeKind::Normal,
+            };
+            let mut comments = rpc.comments.clone();
+            let annotations = self.annotations(&rpc.options, &mut comments);
+            functions.push(ASTFunction {
+                name: identifier(&camel_case(&rpc.name)),
+                name_span: Span::default(),
+                params,
+                returns: returns.map(|name| ASTType { kind, name }),
+                annotations,
+                comments,
+                trailing_comment: None,
+            });
+        }
+        ASTService {
+            name,
+            name_span: Span::default(),
+            functions,
+            annotations,
+            comments,
+            trailing_comments: service.trailing_comments.clone(),
+            closing_comment: None,
+        }
+    }
+}
+
+/// Name of the imported field: its name in JSON, `json_name` or the name in lowerCamelCase.
+fn field_name(field: &Field) -> String {
+    match option(&field.options, "json_name") {
+        Some(OptionValue::String(name)) => identifier(name),
+        _ => identifier(&camel_case(&field.name)),
+    }
+}
+
+fn option<'o>(options: &'o [ProtoOption], name: &str) -> Option<&'o OptionValue> {
+    let option = options.iter().rev().find(|option| option.name == name)?;
+    Some(&option.value)
+}
+
+/// A `default` value as a literal of type `ty`. Enum values are strings.
+fn literal(value: &OptionValue, ty: &ASTTypeName) -> Option<ASTValue> {
+    Some(match (value, ty.path[0].as_str()) {
+        (OptionValue::Integer(n), "integer") => integer(*n),
+        (OptionValue::Integer(n), "float") => float(*n as f64),
+        (OptionValue::Float(x), "float") => float(*x),
+        (OptionValue::Identifier(b), "boolean") if b == "true" || b == "false" => {
+            super::value(ASTValueKind::Boolean(b == "true"))
+        }
+        (OptionValue::String(s), "string") => string(s),
+        (OptionValue::Identifier(v), "string") => string(v),
+        _ => return None,
+    })
+}
@@ -0,0 +1,505 @@
+pub mod transport;
+pub mod workspace;
+
+use crate::json::Json;
+use crate::schema::annotation::KNOWN_ANNOTATIONS;
+use crate::schema::ast::ASTTypeDef;
+use crate::schema::diagnostic::{Diagnostic, Severity, Span};
+use crate::schema::format::{format_field_type, format_type_def};
+use crate::schema::module;
+use crate::schema::tokenize::{Lexer, TokenType};
+use crate::schema::FormatOptions;
+
+use workspace::*;
+
+use std::io;
+
+/// Runs the language server over stdin/stdout until the client sends `exit`.
+pub fn run() -> io::Result<()> {
+    let stdin = io::stdin();
+    let mut reader = stdin.lock();
+    let mut stdout = io::stdout();
+    let mut server = Server::default();
+    while let Some(message) = transport::read_message(&mut reader)? {
+        let Ok(message) = Json::parse(&message) else {
+            continue;
+        };
+        for outgoing in server.handle(&message) {
+            transport::write_message(&mut stdout, &outgoing)?;
+        }
+        if server.exited {
+            break;
+        }
+    }
+    Ok(())
+}
+
+const ERROR_METHOD_NOT_FOUND: i64 = -32601;
+const ERROR_INVALID_PARAMS: i64 = -32602;
+
+#[derive(Default)]
+pub(crate) struct Server {
+    pub(crate) workspace: Workspace,
+    pub(crate) exited: bool,
+}
+
+impl Server {
+    /// Handles one incoming message and returns the messages to send back.
+    pub(crate) fn handle(&mut self, message: &Json) -> Vec<Json> {
+        let method = message.get("method").and_then(Json::as_str).unwrap_or("");
+        let params = message.get("params").cloned().unwrap_or(Json::Null);
+        let Some(id) = message.get("id").cloned() else {
+            return self.handle_notification(method, &params);
+        };
+        let result = match method {
+            "initialize" => Ok(self.initialize(&params)),
+            "shutdown" => Ok(Json::Null),
+            "textDocument/definition" => Ok(self.definition(&params)),
+            "textDocument/references" => Ok(self.references(&params)),
+            "textDocument/hover" => Ok(self.hover(&params)),
+            "textDocument/completion" => Ok(self.completion(&params)),
+            "textDocument/rename" => self.rename(&params),
+            _ => Err((ERROR_METHOD_NOT_FOUND, format!("Unknown method `{method}`"))),
+        };
+        let response = match result {
+            Ok(result) => Json::object([
+                ("jsonrpc", Json::from("2.0")),
+                ("id", id),
+                ("result", result),
+            ]),
+            Err((code, message)) => Json::object([
+                ("jsonrpc", Json::from("2.0")),
+                ("id", id),
+                (
+                    "error",
+                    Json::object([("code", Json::from(code)), ("message", message.into())]),
+                ),
+            ]),
+        };
+        vec![response]
+    }
+
+    fn handle_notification(&mut self, method: &str, params: &Json) -> Vec<Json> {
+        match method {
+            "exit" => {
+                self.exited = true;
+                vec![]
+            }
+            "textDocument/didOpen" => {
+                let document = params.get("textDocument");
+                let uri = document.and_then(|d| d.get("uri")).and_then(Json::as_str);
+                let text = document.and_then(|d| d.get("text")).and_then(Json::as_str);
+                if let (Some(uri), Some(text)) = (uri, text) {
+                    self.workspace
+                        .insert(Document::new(uri.to_string(), text.to_string(), true));
+                }
+                self.publish_diagnostics()
+            }
+            "textDocument/didChange" => {
+                let uri = text_document_uri(params);
+                let text = params
+                    .get("contentChanges")
+                    .and_then(Json::as_array)
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change.get("text"))
+                    .and_then(Json::as_str);
+                if let (Some(uri), Some(text)) = (uri, text) {
+                    self.workspace
+                        .insert(Document::new(uri.to_string(), text.to_string(), true));
+                }
+                self.publish_diagnostics()
+            }
+            "textDocument/didClose" => {
+                let Some(uri) = text_document_uri(params) else {
+                    return vec![];
+                };
+                let path = uri_to_path(uri);
+                if let Some(document) = self.workspace.documents.get_mut(&path) {
+                    document.open = false;
+                }
+                let mut messages = vec![publish(uri, vec![])];
+                messages.extend(self.publish_diagnostics());
+                messages
+            }
+            _ => vec![],
+        }
+    }
+
+    fn initialize(&mut self, params: &Json) -> Json {
+        let root = params
+            .get("rootUri")
+            .and_then(Json::as_str)
+            .map(uri_to_path)
+            .or_else(|| {
+                params
+                    .get("rootPath")
+                    .and_then(Json::as_str)
+                    .map(String::from)
+            });
+        if let Some(root) = root {
+            self.load_workspace(&root);
+        }
+        Json::object([
+            (
+                "capabilities",
+                Json::object([
+                    ("textDocumentSync", Json::from(1_i64)),
+                    ("definitionProvider", Json::from(true)),
+                    ("referencesProvider", Json::from(true)),
+                    ("hoverProvider", Json::from(true)),
+                    (
+                        "completionProvider",
+                        Json::object([(
+                            "triggerCharacters",
+                            Json::Array(vec![":".into(), "<".into()]),
+                        )]),
+                    ),
+                    ("renameProvider", Json::from(true)),
+                ]),
+            ),
+            (
+                "serverInfo",
+                Json::object([
+                    ("name", Json::from("rgc")),
+                    ("version", Json::from(env!("CARGO_PKG_VERSION"))),
+                ]),
+            ),
+        ])
+    }
+
+    /// Loads every `.rgc` file under `root` so that cross-file queries work before the files are
+    /// opened.
+    fn load_workspace(&mut self, root: &str) {
+        let Ok(files) = crate::cli::collect_schema_files(&[root.into()]) else {
+            return;
+        };
+        for file in files {
+            let Ok(text) = std::fs::read_to_string(&file) else {
+                continue;
+            };
+            let uri = path_to_uri(&file.to_string_lossy());
+            self.workspace.insert(Document::new(uri, text, false));
+        }
+    }
+
+    fn publish_diagnostics(&self) -> Vec<Json> {
+        self.workspace
+            .documents
+            .values()
+            .filter(|doc| doc.open)
+            .map(|doc| {
+                let validation =
+                    module::validate(&doc.ast, &self.workspace.external_types(&doc.uri));
+                publish(
+                    &doc.uri,
+                    doc.syntax_errors
+                        .iter()
+                        .chain(&validation)
+                        .map(|d| lsp_diagnostic(doc, d))
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+
+    /// Resolves the document and byte offset a `TextDocumentPositionParams` points at.
+    fn locate(&self, params: &Json) -> Option<(&Document, usize)> {
+        let document = self.workspace.get(text_document_uri(params)?)?;
+        let position = params.get("position")?;
+        let line = position.get("line")?.as_u64()? as usize;
+        let character = position.get("character")?.as_u64()? as usize;
+        Some((
+            document,
+            document.lines.offset(&document.text, line, character),
+        ))
+    }
+
+    fn definition(&self, params: &Json) -> Json {
+        let Some((document, offset)) = self.locate(params) else {
+            return Json::Null;
+        };
+        match symbol_at(&document.ast, offset) {
+            Some((Symbol::Type(name), _)) => match self.workspace.type_def(&name) {
+                Some((doc, type_def)) => location(doc, type_def.name_span),
+                None => Json::Null,
+            },
+            Some((Symbol::TypeParam(_, param), _)) => location(document, param.span),
+            _ => Json::Null,
+        }
+    }
+
+    fn references(&self, params: &Json) -> Json {
+        let Some((document, offset)) = self.locate(params) else {
+            return Json::Null;
+        };
+        let include_declaration = params
+            .get("context")
+            .and_then(|context| context.get("includeDeclaration"))
+            .and_then(Json::as_bool)
+            .unwrap_or(true);
+        match symbol_at(&document.ast, offset) {
+            Some((Symbol::Type(name), _)) => Json::Array(
+                self.workspace
+                    .references(&name, include_declaration)
+                    .into_iter()
+                    .map(|(doc, span)| location(doc, span))
+                    .collect(),
+            ),
+            Some((Symbol::Field(_, field), _)) if include_declaration => {
+                Json::Array(vec![location(document, field.name_span)])
+            }
+            Some((Symbol::TypeParam(type_def, param), _)) => Json::Array(
+                type_param_references(type_def, param, include_declaration)
+                    .into_iter()
+                    .map(|span| location(document, span))
+                    .collect(),
+            ),
+            _ => Json::Array(vec![]),
+        }
+    }
+
+    fn hover(&self, params: &Json) -> Json {
+        let Some((document, offset)) = self.locate(params) else {
+            return Json::Null;
+        };
+        let Some((symbol, span)) = symbol_at(&document.ast, offset) else {
+            return Json::Null;
+        };
+        let value = match symbol {
+            Symbol::Type(name) => {
+                let Some((_, type_def)) = self.workspace.type_def(&name) else {
+                    return Json::Null;
+                };
+                let mut code = String::new();
+                format_type_def(
+                    &mut code,
+                    &ASTTypeDef {
+                        comments: vec![],
+                        ..type_def.clone()
+                    },
+                    &FormatOptions::default(),
+                );
+                hover_markdown(code.trim_end(), &type_def.comments)
+            }
+            Symbol::Field(type_def, field) => {
+                let code = format!(
+                    "{}.{}{}: {}",
+                    type_def.name,
+                    field.name,
+                    if field.optional { "?" } else { "" },
+                    format_field_type(&field.ty)
+                );
+                hover_markdown(&code, &field.comments)
+            }
+            Symbol::TypeParam(type_def, param) => {
+                let params = type_def
+                    .params
+                    .iter()
+                    .map(|param| param.name.as_str())
+                    .collect::<Vec<_>>();
+                let code = format!("type {}<{}>", type_def.name, params.join(", "));
+                let docs = format!("Type parameter `{}` of `{}`.", param.name, type_def.name);
+                hover_markdown(&code, &[docs])
+            }
+        };
+        Json::object([
+            (
+                "contents",
+                Json::object([("kind", Json::from("markdown")), ("value", value.into())]),
+            ),
+            ("range", range(document, span)),
+        ])
+    }
+
+    fn completion(&self, params: &Json) -> Json {
+        const KIND_KEYWORD: i64 = 14;
+        const KIND_STRUCT: i64 = 22;
+        let Some((document, offset)) = self.locate(params) else {
+            return Json::Array(vec![]);
+        };
+        let item = |label: &str, kind: i64| {
+            Json::object([("label", Json::from(label)), ("kind", Json::from(kind))])
+        };
+        let mut items = Vec::new();
+        match completion_context(&document.text, offset) {
+            CompletionContext::Root => items.push(item("type", KIND_KEYWORD)),
+            CompletionContext::Annotation => {
+                for spec in KNOWN_ANNOTATIONS {
+                    let mut annotation = item(spec.name, KIND_KEYWORD);
+                    annotation.insert("detail", Json::from(spec.doc));
+                    items.push(annotation);
+                }
+            }
+            CompletionContext::FieldName => {}
+            CompletionContext::FieldType { keywords } => {
+                if keywords {
+                    items.push(item("stream", KIND_KEYWORD));
+                    items.push(item("sync", KIND_KEYWORD));
+                }
+                for name in ["integer", "float", "string", "boolean"] {
+                    items.push(item(name, KIND_KEYWORD));
+                }
+                for (name, _) in module::GENERIC_BUILTINS {
+                    items.push(item(name, KIND_STRUCT));
+                }
+                for name in self.workspace.type_names() {
+                    items.push(item(name, KIND_STRUCT));
+                }
+            }
+        }
+        Json::Array(items)
+    }
+
+    fn rename(&mut self, params: &Json) -> Result<Json, (i64, String)> {
+        let new_name = params
+            .get("newName")
+            .and_then(Json::as_str)
+            .unwrap_or_default();
+        if !is_identifier(new_name) || is_builtin(new_name) {
+            return Err((
+                ERROR_INVALID_PARAMS,
+                format!("`{new_name}` is not a valid name"),
+            ));
+        }
+        let Some((document, offset)) = self.locate(params) else {
+            return Ok(Json::Null);
+        };
+        let edits: Vec<(&Document, Span)> = match symbol_at(&document.ast, offset) {
+            Some((Symbol::Type(name), _)) => self.workspace.references(&name, true),
+            Some((Symbol::Field(_, field), _)) => vec![(document, field.name_span)],
+            Some((Symbol::TypeParam(type_def, param), _)) => {
+                type_param_references(type_def, param, true)
+                    .into_iter()
+                    .map(|span| (document, span))
+                    .collect()
+            }
+            None => return Ok(Json::Null),
+        };
+        let mut changes = Json::Object(vec![]);
+        for (doc, span) in edits {
+            let edit = Json::object([
+                ("range", range(doc, span)),
+                ("newText", Json::from(new_name)),
+            ]);
+            let mut list = changes
+                .get(&doc.uri)
+                .and_then(Json::as_array)
+                .map(<[Json]>::to_vec)
+                .unwrap_or_default();
+            list.push(edit);
+            changes.insert(doc.uri.clone(), Json::Array(list));
+        }
+        Ok(Json::object([("changes", changes)]))
+    }
+}
+
+enum CompletionContext {
+    Root,
+    /// Right after `@`.
+    Annotation,
+    FieldName,
+    /// Position of a field type; `keywords` is set right after the colon, where `stream`/`sync`
+    /// are allowed.
+    FieldType {
+        keywords: bool,
+    },
+}
+
+fn completion_context(text: &str, offset: usize) -> CompletionContext {
+    let mut depth = 0;
+    let mut angle_depth = 0;
+    let mut previous = None;
+    for token in Lexer::new(text) {
+        // A word touching the cursor is the one being completed, a punctuation is context.
+        if token.span.end > offset
+            || (token.span.end == offset && token.ty != TokenType::Punctuation)
+        {
+            break;
+        }
+        match (&token.ty, token.value) {
+            (TokenType::Comment, _) => continue,
+            (TokenType::Punctuation, "{") => depth += 1,
+            (TokenType::Punctuation, "}") => depth -= 1,
+            (TokenType::Punctuation, "<") => angle_depth += 1,
+            (TokenType::Punctuation, ">") => angle_depth -= 1,
+            _ => {}
+        }
+        previous = Some(token);
+    }
+    let previous = previous.as_ref().map(|token| (&token.ty, token.value));
+    match previous {
+        Some((TokenType::Punctuation, "@")) => CompletionContext::Annotation,
+        _ if angle_depth > 0 => CompletionContext::FieldType { keywords: false },
+        Some((TokenType::Punctuation, ":")) => CompletionContext::FieldType { keywords: true },
+        Some((TokenType::Keyword, "stream" | "sync")) => {
+            CompletionContext::FieldType { keywords: false }
+        }
+        _ if depth > 0 => CompletionContext::FieldName,
+        _ => CompletionContext::Root,
+    }
+}
+
+fn hover_markdown(code: &str, comments: &[String]) -> String {
+    let mut value = format!("```rgc\n{code}\n```");
+    let docs = comments
+        .iter()
+        .map(|comment| comment.trim_start_matches('/').trim())
+        .collect::<Vec<_>>()
+        .join("\n");
+    if !docs.is_empty() {
+        value.push_str("\n\n");
+        value.push_str(&docs);
+    }
+    value
+}
+
+fn text_document_uri(params: &Json) -> Option<&str> {
+    params.get("textDocument")?.get("uri")?.as_str()
+}
+
+fn range(document: &Document, span: Span) -> Json {
+    let position = |offset| {
+        let (line, character) = document.lines.position(&document.text, offset);
+        Json::object([
+            ("line", Json::from(line)),
+            ("character", Json::from(character)),
+        ])
+    };
+    Json::object([("start", position(span.start)), ("end", position(span.end))])
+}
+
+fn location(document: &Document, span: Span) -> Json {
+    Json::object([
+        ("uri", Json::from(document.uri.as_str())),
+        ("range", range(document, span)),
+    ])
+}
+
+fn lsp_diagnostic(document: &Document, diagnostic: &Diagnostic) -> Json {
+    Json::object([
+        ("range", range(document, diagnostic.span)),
+        (
+            "severity",
+            Json::from(match diagnostic.severity {
+                Severity::Error => 1_i64,
+                Severity::Warning => 2,
+            }),
+        ),
+        ("source", Json::from("rgc")),
+        ("message", Json::from(diagnostic.message.as_str())),
+    ])
+}
+
+fn publish(uri: &str, diagnostics: Vec<Json>) -> Json {
+    Json::object([
+        ("jsonrpc", Json::from("2.0")),
+        ("method", Json::from("textDocument/publishDiagnostics")),
+        (
+            "params",
+            Json::object([
+                ("uri", Json::from(uri)),
+                ("diagnostics", Json::Array(diagnostics)),
+            ]),
+        ),
+    ])
+}
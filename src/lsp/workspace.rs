@@ -0,0 +1,260 @@
+use crate::schema::ast::*;
+use crate::schema::diagnostic::{Diagnostic, Span};
+use crate::schema::module;
+use crate::schema::parse_ast;
+
+use std::collections::BTreeMap;
+
+pub(crate) struct Document {
+    pub(crate) uri: String,
+    pub(crate) text: String,
+    pub(crate) lines: LineIndex,
+    /// Best-effort AST, parsed past syntax errors.
+    pub(crate) ast: ASTRoot,
+    pub(crate) syntax_errors: Vec<Diagnostic>,
+    /// Whether the client has the document open (otherwise it was loaded from disk).
+    pub(crate) open: bool,
+}
+
+impl Document {
+    pub(crate) fn new(uri: String, text: String, open: bool) -> Self {
+        let lines = LineIndex::new(&text);
+        let (ast, syntax_errors) = parse_ast(&text);
+        Self {
+            uri,
+            text,
+            lines,
+            ast,
+            syntax_errors,
+            open,
+        }
+    }
+}
+
+/// What the cursor points at.
+pub(crate) enum Symbol<'a> {
+    /// A type definition or a reference to a user-defined type.
+    Type(String),
+    Field(&'a ASTTypeDef, &'a ASTField),
+    /// A type parameter, declared in `<>` or used by the fields of its type.
+    TypeParam(&'a ASTTypeDef, &'a ASTTypeParam),
+}
+
+pub(crate) fn symbol_at(ast: &ASTRoot, offset: usize) -> Option<(Symbol<'_>, Span)> {
+    for type_def in ast.type_defs() {
+        if type_def.name_span.contains(offset) {
+            return Some((Symbol::Type(type_def.name.clone()), type_def.name_span));
+        }
+        for param in &type_def.params {
+            if param.span.contains(offset) {
+                return Some((Symbol::TypeParam(type_def, param), param.span));
+            }
+        }
+        for (param, name) in type_param_uses(type_def) {
+            if name.span.contains(offset) {
+                return Some((Symbol::TypeParam(type_def, param), name.span));
+            }
+        }
+        for field in &type_def.fields {
+            if field.name_span.contains(offset) {
+                return Some((Symbol::Field(type_def, field), field.name_span));
+            }
+        }
+    }
+    ast.type_names()
+        .into_iter()
+        .find(|name| name.span.contains(offset) && !is_builtin(&name.path.join(".")))
+        .map(|name| (Symbol::Type(name.path.join(".")), name.span))
+}
+
+/// The type names in the fields of `type_def` that stand for one of its type parameters.
+pub(crate) fn type_param_uses(
+    type_def: &ASTTypeDef,
+) -> impl Iterator<Item = (&ASTTypeParam, &ASTTypeName)> {
+    let mut names = Vec::new();
+    for field in &type_def.fields {
+        field.ty.name.collect(&mut names);
+    }
+    names.into_iter().filter_map(|name| {
+        let param = match name.path.as_slice() {
+            [single] => type_def.params.iter().find(|param| param.name == *single)?,
+            _ => return None,
+        };
+        Some((param, name))
+    })
+}
+
+/// Spans of the declaration of a type parameter and of its uses, which never leave its type.
+pub(crate) fn type_param_references(
+    type_def: &ASTTypeDef,
+    param: &ASTTypeParam,
+    include_declaration: bool,
+) -> Vec<Span> {
+    let uses = type_param_uses(type_def)
+        .filter(|(other, _)| *other == param)
+        .map(|(_, name)| name.span);
+    include_declaration
+        .then_some(param.span)
+        .into_iter()
+        .chain(uses)
+        .collect()
+}
+
+pub(crate) fn is_builtin(name: &str) -> bool {
+    module::builtin_type(name).is_some() || module::GENERIC_BUILTINS.iter().any(|(n, _)| *n == name)
+}
+
+/// All documents known to the server, keyed by their decoded file path.
+#[derive(Default)]
+pub(crate) struct Workspace {
+    pub(crate) documents: BTreeMap<String, Document>,
+}
+
+impl Workspace {
+    pub(crate) fn get(&self, uri: &str) -> Option<&Document> {
+        self.documents.get(&uri_to_path(uri))
+    }
+
+    pub(crate) fn insert(&mut self, document: Document) {
+        self.documents.insert(uri_to_path(&document.uri), document);
+    }
+
+    pub(crate) fn type_def(&self, name: &str) -> Option<(&Document, &ASTTypeDef)> {
+        self.documents.values().find_map(|doc| {
+            doc.ast
+                .type_defs()
+                .find(|type_def| type_def.name == name)
+                .map(|type_def| (doc, type_def))
+        })
+    }
+
+    /// Locations of every mention of the type `name`, optionally including its definition.
+    pub(crate) fn references(
+        &self,
+        name: &str,
+        include_declaration: bool,
+    ) -> Vec<(&Document, Span)> {
+        let mut locations = Vec::new();
+        for doc in self.documents.values() {
+            let ast = &doc.ast;
+            if include_declaration {
+                for type_def in ast.type_defs().filter(|type_def| type_def.name == name) {
+                    locations.push((doc, type_def.name_span));
+                }
+            }
+            // A type parameter shadows a type of the same name.
+            let params = ast
+                .type_defs()
+                .flat_map(type_param_uses)
+                .map(|(_, type_name)| type_name.span)
+                .collect::<Vec<_>>();
+            for type_name in ast.type_names() {
+                if type_name.path.join(".") == name && !params.contains(&type_name.span) {
+                    locations.push((doc, type_name.span));
+                }
+            }
+        }
+        locations
+    }
+
+    /// Type names defined in every document except `uri`.
+    pub(crate) fn external_types(&self, uri: &str) -> Vec<&str> {
+        let path = uri_to_path(uri);
+        self.documents
+            .iter()
+            .filter(|(key, _)| **key != path)
+            .map(|(_, doc)| &doc.ast)
+            .flat_map(|ast| ast.type_defs().map(|type_def| type_def.name.as_str()))
+            .collect()
+    }
+
+    pub(crate) fn type_names(&self) -> Vec<&str> {
+        let mut names = self
+            .documents
+            .values()
+            .map(|doc| &doc.ast)
+            .flat_map(|ast| ast.type_defs().map(|type_def| type_def.name.as_str()))
+            .collect::<Vec<_>>();
+        names.sort();
+        names.dedup();
+        names
+    }
+}
+
+/// Converts between byte offsets and LSP positions (zero-based line, UTF-16 column).
+pub(crate) struct LineIndex {
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub(crate) fn new(text: &str) -> Self {
+        let mut line_starts = vec![0];
+        line_starts.extend(text.match_indices('\n').map(|(i, _)| i + 1));
+        Self { line_starts }
+    }
+
+    pub(crate) fn position(&self, text: &str, offset: usize) -> (usize, usize) {
+        let offset = offset.min(text.len());
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let character = text[self.line_starts[line]..offset].encode_utf16().count();
+        (line, character)
+    }
+
+    pub(crate) fn offset(&self, text: &str, line: usize, character: usize) -> usize {
+        let Some(&start) = self.line_starts.get(line) else {
+            return text.len();
+        };
+        let end = self
+            .line_starts
+            .get(line + 1)
+            .copied()
+            .unwrap_or(text.len());
+        let mut units = 0;
+        for (i, c) in text[start..end].char_indices() {
+            if units >= character {
+                return start + i;
+            }
+            units += c.len_utf16();
+        }
+        end
+    }
+}
+
+pub(crate) fn uri_to_path(uri: &str) -> String {
+    let path = uri.strip_prefix("file://").unwrap_or(uri);
+    let bytes = path.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or("");
+            if let Ok(byte) = u8::from_str_radix(hex, 16) {
+                out.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+pub(crate) fn path_to_uri(path: &str) -> String {
+    let mut uri = String::from("file://");
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    uri
+}
+
+pub(crate) fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
@@ -1,9 +1,14 @@
 pub mod schema;
 pub mod codegen;
+mod cli;
+mod import;
+mod json;
+mod lsp;
 #[cfg(test)]
 pub mod tests;
 
 
-fn main() {
-    println!("Hello, world!");
+fn main() -> std::process::ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    cli::run(&args)
 }
@@ -0,0 +1,595 @@
+use super::ast::*;
+use super::diagnostic::Diagnostic;
+use super::module::{
+    check_value, literal_value, BuiltinType, Consts, SchemeAnnotation, SchemeAnnotationArg,
+    SchemeFieldType, SchemeFieldTypeKind, SchemeTypeRef, SchemeValue,
+};
+use super::regex::Regex;
+
+use std::cmp::Ordering;
+
+/// Kind of declaration an annotation is written on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AnnotationTarget {
+    Type,
+    Field,
+    Service,
+    Function,
+    /// A parameter of a function.
+    Param,
+    Const,
+}
+
+impl AnnotationTarget {
+    fn plural(self) -> &'static str {
+        match self {
+            AnnotationTarget::Type => "types",
+            AnnotationTarget::Field => "fields",
+            AnnotationTarget::Service => "services",
+            AnnotationTarget::Function => "functions",
+            AnnotationTarget::Param => "parameters",
+            AnnotationTarget::Const => "constants",
+        }
+    }
+}
+
+/// An annotation understood by rgc itself. Other annotations are not checked and are handed to
+/// the code generators as they are.
+pub(crate) struct AnnotationSpec {
+    pub name: &'static str,
+    pub doc: &'static str,
+    pub targets: &'static [AnnotationTarget],
+    /// Parameters in positional order. Every argument may also be given as `name = value`.
+    pub params: &'static [AnnotationParam],
+    /// Minimum number of arguments, for annotations whose parameters are all optional.
+    pub min_args: usize,
+    /// Set for constraints on field values, which generated validators check.
+    pub constrains: Option<ConstraintSubject>,
+}
+
+pub(crate) struct AnnotationParam {
+    pub name: &'static str,
+    pub ty: ParamType,
+    pub required: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ParamType {
+    Builtin(BuiltinType),
+    /// The type of the constrained value, e.g. the bounds of `@range` on a `float` field.
+    Subject,
+}
+
+/// Values a constraint applies to. Constraints on `Optional<T>` fields apply to present values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ConstraintSubject {
+    Number,
+    String,
+    Array,
+}
+
+impl ConstraintSubject {
+    fn accepts(self, ty_ref: &SchemeTypeRef) -> bool {
+        matches!(
+            (self, ty_ref),
+            (
+                ConstraintSubject::Number,
+                SchemeTypeRef::Builtin(BuiltinType::Integer | BuiltinType::Float)
+            ) | (
+                ConstraintSubject::String,
+                SchemeTypeRef::Builtin(BuiltinType::String)
+            ) | (ConstraintSubject::Array, SchemeTypeRef::Array(_))
+        )
+    }
+}
+
+/// Type of the values checked by constraints on a field of type `ty_ref`.
+pub(crate) fn constraint_subject(ty_ref: &SchemeTypeRef) -> &SchemeTypeRef {
+    match ty_ref {
+        SchemeTypeRef::Optional(item) => constraint_subject(item),
+        ty_ref => ty_ref,
+    }
+}
+
+const ALL: &[AnnotationTarget] = &[
+    AnnotationTarget::Type,
+    AnnotationTarget::Field,
+    AnnotationTarget::Service,
+    AnnotationTarget::Function,
+    AnnotationTarget::Const,
+];
+const STRING: ParamType = ParamType::Builtin(BuiltinType::String);
+const INTEGER: ParamType = ParamType::Builtin(BuiltinType::Integer);
+
+pub(crate) const KNOWN_ANNOTATIONS: &[AnnotationSpec] = &[
+    AnnotationSpec {
+        name: "deprecated",
+        doc: "Marks the declaration as deprecated, with an optional reason.",
+        targets: ALL,
+        params: &[AnnotationParam {
+            name: "reason",
+            ty: STRING,
+            required: false,
+        }],
+        min_args: 0,
+        constrains: None,
+    },
+    AnnotationSpec {
+        name: "rename",
+        doc: "Uses another name in generated code, for every language (`name`) or for one of them \
+              (`rust`, `ts`, `py`, `go`, `kotlin`, `swift`, `csharp`, `proto`, `graphql`, \
+              `avro`). The name on the wire is unchanged.",
+        targets: ALL,
+        params: &[
+            AnnotationParam {
+                name: "name",
+                ty: STRING,
+                required: false,
+            },
+            AnnotationParam {
+                name: "rust",
+                ty: STRING,
+                required: false,
+            },
+            AnnotationParam {
+                name: "ts",
+                ty: STRING,
+                required: false,
+            },
+            AnnotationParam {
+                name: "py",
+                ty: STRING,
+                required: false,
+            },
+            AnnotationParam {
+                name: "go",
+                ty: STRING,
+                required: false,
+            },
+            AnnotationParam {
+                name: "kotlin",
+                ty: STRING,
+                required: false,
+            },
+            AnnotationParam {
+                name: "swift",
+                ty: STRING,
+                required: false,
+            },
+            AnnotationParam {
+                name: "csharp",
+                ty: STRING,
+                required: false,
+            },
+            AnnotationParam {
+                name: "proto",
+                ty: STRING,
+                required: false,
+            },
+            AnnotationParam {
+                name: "graphql",
+                ty: STRING,
+                required: false,
+            },
+            AnnotationParam {
+                name: "avro",
+                ty: STRING,
+                required: false,
+            },
+        ],
+        min_args: 1,
+        constrains: None,
+    },
+    AnnotationSpec {
+        name: "id",
+        doc: "Marks the field that identifies a value of the type.",
+        targets: &[AnnotationTarget::Field],
+        params: &[],
+        min_args: 0,
+        constrains: None,
+    },
+    AnnotationSpec {
+        name: "tag",
+        doc:
+            "Number of the field in binary encodings such as protobuf, or of the parameter in the \
+              request message of its function. It must not change once published, nor be given \
+              to another field or parameter.",
+        targets: &[AnnotationTarget::Field, AnnotationTarget::Param],
+        params: &[AnnotationParam {
+            name: "n",
+            ty: INTEGER,
+            required: true,
+        }],
+        min_args: 0,
+        constrains: None,
+    },
+    AnnotationSpec {
+        name: "since",
+        doc: "Version of the schema in which the declaration was added.",
+        targets: ALL,
+        params: &[AnnotationParam {
+            name: "version",
+            ty: INTEGER,
+            required: true,
+        }],
+        min_args: 0,
+        constrains: None,
+    },
+    AnnotationSpec {
+        name: "range",
+        doc: "Inclusive bounds of a number.",
+        targets: &[AnnotationTarget::Field],
+        params: &[
+            AnnotationParam {
+                name: "min",
+                ty: ParamType::Subject,
+                required: false,
+            },
+            AnnotationParam {
+                name: "max",
+                ty: ParamType::Subject,
+                required: false,
+            },
+        ],
+        min_args: 1,
+        constrains: Some(ConstraintSubject::Number),
+    },
+    AnnotationSpec {
+        name: "pattern",
+        doc: "Regular expression that a string must contain a match of.",
+        targets: &[AnnotationTarget::Field],
+        params: &[AnnotationParam {
+            name: "regex",
+            ty: STRING,
+            required: true,
+        }],
+        min_args: 0,
+        constrains: Some(ConstraintSubject::String),
+    },
+    AnnotationSpec {
+        name: "min_len",
+        doc: "Minimum number of characters of a string.",
+        targets: &[AnnotationTarget::Field],
+        params: &[AnnotationParam {
+            name: "len",
+            ty: INTEGER,
+            required: true,
+        }],
+        min_args: 0,
+        constrains: Some(ConstraintSubject::String),
+    },
+    AnnotationSpec {
+        name: "max_len",
+        doc: "Maximum number of characters of a string.",
+        targets: &[AnnotationTarget::Field],
+        params: &[AnnotationParam {
+            name: "len",
+            ty: INTEGER,
+            required: true,
+        }],
+        min_args: 0,
+        constrains: Some(ConstraintSubject::String),
+    },
+    AnnotationSpec {
+        name: "min_items",
+        doc: "Minimum number of items of an array.",
+        targets: &[AnnotationTarget::Field],
+        params: &[AnnotationParam {
+            name: "count",
+            ty: INTEGER,
+            required: true,
+        }],
+        min_args: 0,
+        constrains: Some(ConstraintSubject::Array),
+    },
+    AnnotationSpec {
+        name: "max_items",
+        doc: "Maximum number of items of an array.",
+        targets: &[AnnotationTarget::Field],
+        params: &[AnnotationParam {
+            name: "count",
+            ty: INTEGER,
+            required: true,
+        }],
+        min_args: 0,
+        constrains: Some(ConstraintSubject::Array),
+    },
+];
+
+pub(crate) fn known_annotation(name: &str) -> Option<&'static AnnotationSpec> {
+    KNOWN_ANNOTATIONS.iter().find(|spec| spec.name == name)
+}
+
+/// Checks an annotation against the registry. Positional arguments of known annotations are
+/// given their parameter name. `field` is the type of the annotated field, if it resolves.
+pub(crate) fn resolve(
+    annotation: &ASTAnnotation,
+    target: AnnotationTarget,
+    field: Option<&SchemeFieldType>,
+    consts: &Consts,
+) -> Result<SchemeAnnotation, Vec<Diagnostic>> {
+    let mut diagnostics = Vec::new();
+    let mut args = Vec::new();
+    let Some(spec) = known_annotation(&annotation.name) else {
+        for arg in &annotation.args {
+            match literal_value(&arg.value, consts) {
+                Ok(value) => args.push(SchemeAnnotationArg {
+                    name: arg.name.clone(),
+                    value,
+                }),
+                Err(err) => diagnostics.push(err),
+            }
+        }
+        return finish(annotation, args, diagnostics);
+    };
+
+    let name = &annotation.name;
+    let mut given = Vec::new();
+    if !spec.targets.contains(&target) {
+        diagnostics.push(Diagnostic::error(
+            annotation.name_span,
+            format!("`@{name}` cannot be used on {}", target.plural()),
+        ));
+    }
+    let subject = field.map(|field| constraint_subject(&field.ty_ref));
+    if let (Some(constrains), Some(field), Some(subject)) = (spec.constrains, field, subject) {
+        if field.kind != SchemeFieldTypeKind::Normal {
+            diagnostics.push(Diagnostic::error(
+                annotation.name_span,
+                format!("`@{name}` cannot be used on `stream` or `sync` fields"),
+            ));
+            return finish(annotation, args, diagnostics);
+        }
+        if !constrains.accepts(subject) {
+            diagnostics.push(Diagnostic::error(
+                annotation.name_span,
+                format!(
+                    "`@{name}` cannot be used on a field of type `{}`",
+                    field.ty_ref
+                ),
+            ));
+            return finish(annotation, args, diagnostics);
+        }
+    }
+    for (i, arg) in annotation.args.iter().enumerate() {
+        let param = match &arg.name {
+            Some(arg_name) => spec.params.iter().find(|param| param.name == arg_name),
+            None => spec.params.get(i),
+        };
+        let Some(param) = param else {
+            diagnostics.push(Diagnostic::error(
+                arg.span,
+                match &arg.name {
+                    Some(arg_name) => format!("Unknown argument `{arg_name}` for `@{name}`"),
+                    None if spec.params.is_empty() => format!("`@{name}` takes no arguments"),
+                    None => format!("`@{name}` takes at most {} argument(s)", spec.params.len()),
+                },
+            ));
+            continue;
+        };
+        if given.contains(&param.name) {
+            diagnostics.push(Diagnostic::error(
+                arg.span,
+                format!(
+                    "Argument `{}` of `@{name}` is given more than once",
+                    param.name
+                ),
+            ));
+            continue;
+        }
+        given.push(param.name);
+        let ty = match (param.ty, subject) {
+            (ParamType::Builtin(builtin), _) => &SchemeTypeRef::Builtin(builtin),
+            (ParamType::Subject, Some(subject)) => subject,
+            // Without a resolved field type there is nothing to check the argument against.
+            (ParamType::Subject, None) => continue,
+        };
+        match check_value(&arg.value, ty, consts) {
+            Ok(SchemeValue::String(regex)) if spec.name == "pattern" => match Regex::new(&regex) {
+                Ok(_) => args.push(SchemeAnnotationArg {
+                    name: Some(param.name.to_string()),
+                    value: SchemeValue::String(regex),
+                }),
+                Err(message) => diagnostics.push(Diagnostic::error(
+                    arg.value.span,
+                    format!("Invalid regular expression: {message}"),
+                )),
+            },
+            // Lengths and counts of items.
+            Ok(SchemeValue::Integer(n)) if n < 0 && matches!(param.name, "len" | "count") => {
+                diagnostics.push(Diagnostic::error(
+                    arg.value.span,
+                    format!("Argument `{}` of `@{name}` cannot be negative", param.name),
+                ))
+            }
+            Ok(value) => args.push(SchemeAnnotationArg {
+                name: Some(param.name.to_string()),
+                value,
+            }),
+            Err(err) => diagnostics.push(err),
+        }
+    }
+    for param in spec.params.iter().filter(|param| param.required) {
+        if !given.contains(&param.name) {
+            diagnostics.push(Diagnostic::error(
+                annotation.name_span,
+                format!("`@{name}` requires argument `{}`", param.name),
+            ));
+        }
+    }
+    if let (Some(min), Some(max)) = (arg(&args, "min"), arg(&args, "max")) {
+        if compare(max, min).is_lt() {
+            diagnostics.push(Diagnostic::error(
+                annotation.name_span,
+                format!("`@{name}` has a minimum greater than its maximum"),
+            ));
+        }
+    }
+    if annotation.args.len() < spec.min_args {
+        diagnostics.push(Diagnostic::error(
+            annotation.name_span,
+            format!("`@{name}` expects at least {} argument(s)", spec.min_args),
+        ));
+    }
+    finish(annotation, args, diagnostics)
+}
+
+fn arg<'a>(args: &'a [SchemeAnnotationArg], name: &str) -> Option<&'a SchemeValue> {
+    args.iter()
+        .find(|arg| arg.name.as_deref() == Some(name))
+        .map(|arg| &arg.value)
+}
+
+/// Order of two numbers of the same type.
+fn compare(a: &SchemeValue, b: &SchemeValue) -> Ordering {
+    match (a, b) {
+        (SchemeValue::Integer(a), SchemeValue::Integer(b)) => a.cmp(b),
+        (SchemeValue::Float(a), SchemeValue::Float(b)) => a.total_cmp(b),
+        _ => Ordering::Equal,
+    }
+}
+
+/// Checks a value, e.g. the default of a field, against the constraints among `annotations`.
+/// Constraints do not apply to `null`, the absence of an `Optional` value.
+pub(crate) fn check_constraints(
+    annotations: &[SchemeAnnotation],
+    value: &SchemeValue,
+) -> Result<(), String> {
+    if *value == SchemeValue::Null {
+        return Ok(());
+    }
+    for annotation in annotations {
+        let bound = |name| match arg(&annotation.args, name) {
+            Some(SchemeValue::Integer(n)) => *n as usize,
+            _ => 0,
+        };
+        let broken = match (annotation.name.as_str(), value) {
+            ("range", value) => {
+                if let Some(min) = annotation
+                    .arg("min")
+                    .filter(|min| compare(value, min).is_lt())
+                {
+                    Some(format!("must be at least {}", display(min)))
+                } else {
+                    let max = annotation
+                        .arg("max")
+                        .filter(|max| compare(value, max).is_gt());
+                    max.map(|max| format!("must be at most {}", display(max)))
+                }
+            }
+            ("pattern", SchemeValue::String(text)) => match annotation.arg("regex") {
+                Some(SchemeValue::String(regex)) => {
+                    match Regex::new(regex).map(|compiled| compiled.is_match(text)) {
+                        Ok(Some(false)) => Some(format!("must match the pattern {regex}")),
+                        Ok(None) => {
+                            return Err(format!(
+                                "The default value cannot be matched against the pattern {regex} \
+                                 in a reasonable time"
+                            ))
+                        }
+                        _ => None,
+                    }
+                }
+                _ => None,
+            },
+            ("min_len", SchemeValue::String(text)) if text.chars().count() < bound("len") => {
+                Some(format!("must have at least {} characters", bound("len")))
+            }
+            ("max_len", SchemeValue::String(text)) if text.chars().count() > bound("len") => {
+                Some(format!("must have at most {} characters", bound("len")))
+            }
+            ("min_items", SchemeValue::Array(items)) if items.len() < bound("count") => {
+                Some(format!("must have at least {} items", bound("count")))
+            }
+            ("max_items", SchemeValue::Array(items)) if items.len() > bound("count") => {
+                Some(format!("must have at most {} items", bound("count")))
+            }
+            _ => None,
+        };
+        if let Some(message) = broken {
+            return Err(format!(
+                "The default value breaks `@{}`: it {message}",
+                annotation.name
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn display(value: &SchemeValue) -> String {
+    match value {
+        SchemeValue::Integer(n) => n.to_string(),
+        SchemeValue::Float(x) => x.to_string(),
+        _ => String::new(),
+    }
+}
+
+fn finish(
+    annotation: &ASTAnnotation,
+    args: Vec<SchemeAnnotationArg>,
+    diagnostics: Vec<Diagnostic>,
+) -> Result<SchemeAnnotation, Vec<Diagnostic>> {
+    if !diagnostics.is_empty() {
+        return Err(diagnostics);
+    }
+    Ok(SchemeAnnotation {
+        name: annotation.name.clone(),
+        args,
+    })
+}
+
+/// Checks every annotation of a declaration, including repeated known annotations.
+pub(crate) fn validate(
+    annotations: &[ASTAnnotation],
+    target: AnnotationTarget,
+    field: Option<&SchemeFieldType>,
+    consts: &Consts,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    for (i, annotation) in annotations.iter().enumerate() {
+        if let Err(errors) = resolve(annotation, target, field, consts) {
+            diagnostics.extend(errors);
+        }
+        let repeated = annotations[..i]
+            .iter()
+            .any(|other| other.name == annotation.name);
+        if repeated && known_annotation(&annotation.name).is_some() {
+            diagnostics.push(Diagnostic::error(
+                annotation.name_span,
+                format!("`@{}` is applied more than once", annotation.name),
+            ));
+        }
+    }
+    // Like the bounds of `@range`, a minimum length or count must not exceed the maximum.
+    for (min, max, param) in [
+        ("min_len", "max_len", "len"),
+        ("min_items", "max_items", "count"),
+    ] {
+        let bound = |name| {
+            let annotation = annotations.iter().find(|a| a.name == name)?;
+            let resolved = resolve(annotation, target, field, consts).ok()?;
+            Some((annotation, resolved.arg(param)?.clone()))
+        };
+        if let (Some((_, min_value)), Some((annotation, max_value))) = (bound(min), bound(max)) {
+            if compare(&max_value, &min_value).is_lt() {
+                diagnostics.push(Diagnostic::error(
+                    annotation.name_span,
+                    format!("`@{max}` is less than `@{min}`"),
+                ));
+            }
+        }
+    }
+}
+
+/// Annotations of a declaration that passed [`validate`].
+pub(crate) fn resolve_all(
+    annotations: &[ASTAnnotation],
+    target: AnnotationTarget,
+    field: Option<&SchemeFieldType>,
+    consts: &Consts,
+) -> Vec<SchemeAnnotation> {
+    annotations
+        .iter()
+        .filter_map(|annotation| resolve(annotation, target, field, consts).ok())
+        .collect()
+}
@@ -1,27 +1,62 @@
 use super::module::*;
 
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Assembly {
     pub types: Vec<Rc<RefCell<AssemblyType>>>,
+    pub services: Vec<AssemblyService>,
+    /// Constants need no linking and are kept as they are.
+    pub consts: Vec<SchemeConst>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct AssemblyType {
     pub name: String,
+    /// Type parameters of a generic type, e.g. `T` in `type Page<T>`.
+    pub params: Vec<String>,
+    /// Type this one extends. Its fields are copied in front of the fields of this type.
+    pub base: Option<Rc<RefCell<AssemblyType>>>,
     pub fields: Vec<AssemblyField>,
+    pub annotations: Vec<SchemeAnnotation>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct AssemblyField {
     pub name: String,
     pub ty: AssemblyFieldType,
+    /// Whether the field may be absent from payloads (`name?: T`).
+    pub optional: bool,
+    pub default: Option<SchemeValue>,
+    pub annotations: Vec<SchemeAnnotation>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssemblyService {
+    pub name: String,
+    pub functions: Vec<AssemblyFunction>,
+    pub annotations: Vec<SchemeAnnotation>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssemblyFunction {
+    pub name: String,
+    pub params: Vec<AssemblyParam>,
+    /// `None` for functions that return nothing.
+    pub returns: Option<AssemblyFieldType>,
+    pub annotations: Vec<SchemeAnnotation>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssemblyParam {
+    pub name: String,
+    pub ty: AssemblyTypeRef,
+    pub annotations: Vec<SchemeAnnotation>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct AssemblyFieldType {
     pub kind: AssemblyFieldTypeKind,
     pub ty_ref: AssemblyTypeRef,
@@ -34,57 +69,148 @@ pub enum AssemblyFieldTypeKind {
     Sync,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum AssemblyTypeRef {
     Builtin(BuiltinType),
     Custom(Rc<RefCell<AssemblyType>>),
+    /// A generic type given its type arguments, e.g. `Page<Post>`.
+    Instance(Rc<RefCell<AssemblyType>>, Vec<AssemblyTypeRef>),
+    /// A type parameter of the enclosing type.
+    Param(String),
+    Array(Box<AssemblyTypeRef>),
+    Optional(Box<AssemblyTypeRef>),
+    Map(Box<AssemblyTypeRef>, Box<AssemblyTypeRef>),
 }
 
+/// Links the types of `module` together. Types keep the order in which they were declared.
 pub fn generate(module: &SchemeModule) -> Assembly {
     let mut types: HashMap<String, (Rc<RefCell<AssemblyType>>, &SchemeType)> = HashMap::new();
+    let mut order = Vec::new();
     for type_def in &module.types {
-        types.insert(
-            type_def.name.clone(),
-            (
-                Rc::new(RefCell::new(AssemblyType {
-                    name: type_def.name.clone(),
-                    fields: vec![],
-                })),
-                type_def,
-            ),
-        );
+        let ty = Rc::new(RefCell::new(AssemblyType {
+            name: type_def.name.clone(),
+            params: type_def.params.clone(),
+            base: None,
+            fields: vec![],
+            annotations: type_def.annotations.clone(),
+        }));
+        order.push(ty.clone());
+        types.insert(type_def.name.clone(), (ty, type_def));
     }
 
-    for (_, (ty, type_def)) in types.iter() {
-        for field in &type_def.fields {
-            let ty_ref = match &field.ty.ty_ref {
-                SchemeTypeRef::Builtin(ty_ref) => match &ty_ref {
-                    &BuiltinType::Integer => AssemblyTypeRef::Builtin(BuiltinType::Integer),
-                    &BuiltinType::Float => AssemblyTypeRef::Builtin(BuiltinType::Float),
-                    &BuiltinType::String => AssemblyTypeRef::Builtin(BuiltinType::String),
-                    &BuiltinType::Boolean => AssemblyTypeRef::Builtin(BuiltinType::Boolean),
-                },
-                SchemeTypeRef::Custom(ty_ref) => {
-                    let ty = types.get(ty_ref).expect("Type not found").0.clone();
-                    AssemblyTypeRef::Custom(ty)
-                }
-            };
-            (&mut (&*(ty.to_owned())).borrow_mut())
-                .fields
-                .push(AssemblyField {
-                    name: field.name.clone(),
-                    ty: AssemblyFieldType {
-                        kind: match field.ty.kind {
-                            SchemeFieldTypeKind::Normal => AssemblyFieldTypeKind::Normal,
-                            SchemeFieldTypeKind::Stream => AssemblyFieldTypeKind::Stream,
-                            SchemeFieldTypeKind::Sync => AssemblyFieldTypeKind::Sync,
-                        },
-                        ty_ref,
-                    },
-                });
-        }
+    let mut linked = HashSet::new();
+    for type_def in &module.types {
+        link(type_def, &types, &mut linked);
     }
+    let services = module
+        .services
+        .iter()
+        .map(|service| AssemblyService {
+            name: service.name.clone(),
+            functions: service
+                .functions
+                .iter()
+                .map(|function| AssemblyFunction {
+                    name: function.name.clone(),
+                    params: function
+                        .params
+                        .iter()
+                        .map(|param| AssemblyParam {
+                            name: param.name.clone(),
+                            ty: resolve_type_ref(&param.ty, &types),
+                            annotations: param.annotations.clone(),
+                        })
+                        .collect(),
+                    returns: function
+                        .returns
+                        .as_ref()
+                        .map(|ty| resolve_field_type(ty, &types)),
+                    annotations: function.annotations.clone(),
+                })
+                .collect(),
+            annotations: service.annotations.clone(),
+        })
+        .collect();
     Assembly {
-        types: types.iter().map(|(_, (ty, _))| ty.clone()).collect(),
+        types: order,
+        services,
+        consts: module.consts.clone(),
     }
-}
\ No newline at end of file
+}
+
+/// Fills in the fields of `type_def`, after the fields inherited from its base type, which is
+/// linked first.
+fn link(
+    type_def: &SchemeType,
+    types: &HashMap<String, (Rc<RefCell<AssemblyType>>, &SchemeType)>,
+    linked: &mut HashSet<String>,
+) {
+    if !linked.insert(type_def.name.clone()) {
+        return;
+    }
+    let ty = &types[&type_def.name].0;
+    if let Some(base) = &type_def.base {
+        let (base_ty, base_def) = types.get(base).expect("Type not found");
+        link(base_def, types, linked);
+        let inherited = base_ty.borrow().fields.clone();
+        let mut ty = ty.borrow_mut();
+        ty.base = Some(base_ty.clone());
+        ty.fields = inherited;
+    }
+    for field in &type_def.fields {
+        let field_type = resolve_field_type(&field.ty, types);
+        ty.borrow_mut().fields.push(AssemblyField {
+            name: field.name.clone(),
+            ty: field_type,
+            optional: field.optional,
+            default: field.default.clone(),
+            annotations: field.annotations.clone(),
+        });
+    }
+}
+
+fn resolve_field_type(
+    ty: &SchemeFieldType,
+    types: &HashMap<String, (Rc<RefCell<AssemblyType>>, &SchemeType)>,
+) -> AssemblyFieldType {
+    AssemblyFieldType {
+        kind: match ty.kind {
+            SchemeFieldTypeKind::Normal => AssemblyFieldTypeKind::Normal,
+            SchemeFieldTypeKind::Stream => AssemblyFieldTypeKind::Stream,
+            SchemeFieldTypeKind::Sync => AssemblyFieldTypeKind::Sync,
+        },
+        ty_ref: resolve_type_ref(&ty.ty_ref, types),
+    }
+}
+
+fn resolve_type_ref(
+    ty_ref: &SchemeTypeRef,
+    types: &HashMap<String, (Rc<RefCell<AssemblyType>>, &SchemeType)>,
+) -> AssemblyTypeRef {
+    match ty_ref {
+        SchemeTypeRef::Builtin(ty_ref) => AssemblyTypeRef::Builtin(*ty_ref),
+        SchemeTypeRef::Custom(ty_ref) => {
+            let ty = types.get(ty_ref).expect("Type not found").0.clone();
+            AssemblyTypeRef::Custom(ty)
+        }
+        SchemeTypeRef::Instance(name, args) => {
+            let ty = types.get(name).expect("Type not found").0.clone();
+            let args = args
+                .iter()
+                .map(|arg| resolve_type_ref(arg, types))
+                .collect();
+            AssemblyTypeRef::Instance(ty, args)
+        }
+        SchemeTypeRef::Param(name) => AssemblyTypeRef::Param(name.clone()),
+        SchemeTypeRef::Array(item) => {
+            AssemblyTypeRef::Array(Box::new(resolve_type_ref(item, types)))
+        }
+        SchemeTypeRef::Optional(item) => {
+            AssemblyTypeRef::Optional(Box::new(resolve_type_ref(item, types)))
+        }
+        SchemeTypeRef::Map(key, value) => AssemblyTypeRef::Map(
+            Box::new(resolve_type_ref(key, types)),
+            Box::new(resolve_type_ref(value, types)),
+        ),
+    }
+}
@@ -1,3 +1,4 @@
+use super::diagnostic::{Diagnostic, Span};
 use super::tokenize::*;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -9,18 +10,101 @@ pub(crate) struct ASTRoot {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) enum ASTRootBlock {
     TypeDef(ASTTypeDef),
+    Service(ASTService),
+    Const(ASTConst),
+    /// A comment that is not followed by any declaration (e.g. at the end of the file).
+    Comment(String),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) struct ASTTypeDef {
     pub name: String,
+    pub name_span: Span,
+    /// Type parameters of a generic type, e.g. `T` in `type Page<T>`.
+    pub params: Vec<ASTTypeParam>,
+    /// Base type written after `extends`, whose fields are inherited.
+    pub extends: Option<ASTTypeName>,
     pub fields: Vec<ASTField>,
+    pub annotations: Vec<ASTAnnotation>,
+    /// Comments directly preceding the `type` keyword.
+    pub comments: Vec<String>,
+    /// Comments after the last field, right before the closing brace.
+    pub trailing_comments: Vec<String>,
+    /// Comment on the line of the closing brace, after it.
+    pub closing_comment: Option<String>,
+}
+
+/// `service Name { remote function ...; }`, a group of functions served together.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ASTService {
+    pub name: String,
+    pub name_span: Span,
+    pub functions: Vec<ASTFunction>,
+    pub annotations: Vec<ASTAnnotation>,
+    /// Comments directly preceding the `service` keyword.
+    pub comments: Vec<String>,
+    /// Comments after the last function, right before the closing brace.
+    pub trailing_comments: Vec<String>,
+    /// Comment on the line of the closing brace, after it.
+    pub closing_comment: Option<String>,
+}
+
+/// `remote function name(params) -> returns`. Functions without `->` return nothing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ASTFunction {
+    pub name: String,
+    pub name_span: Span,
+    pub params: Vec<ASTParam>,
+    pub returns: Option<ASTType>,
+    pub annotations: Vec<ASTAnnotation>,
+    /// Comments directly preceding the function.
+    pub comments: Vec<String>,
+    /// Comment on the line of the function, after it or its semicolon.
+    pub trailing_comment: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ASTParam {
+    pub name: String,
+    pub name_span: Span,
+    pub ty: ASTTypeName,
+    /// Written after the type, like the constraints of fields.
+    pub annotations: Vec<ASTAnnotation>,
+}
+
+/// `const NAME: type = value`, a named value that other values may refer to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ASTConst {
+    pub name: String,
+    pub name_span: Span,
+    pub ty: ASTTypeName,
+    pub value: ASTValue,
+    pub annotations: Vec<ASTAnnotation>,
+    /// Comments directly preceding the `const` keyword.
+    pub comments: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ASTTypeParam {
+    pub name: String,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) struct ASTField {
     pub name: String,
+    pub name_span: Span,
     pub ty: ASTType,
+    /// Written `name?: T`: the field may be absent, unlike `Optional<T>` fields which are present
+    /// but may be `null`.
+    pub optional: bool,
+    /// Value written after `=`, used when the field is absent.
+    pub default: Option<ASTValue>,
+    pub annotations: Vec<ASTAnnotation>,
+    /// Comments directly preceding the field.
+    pub comments: Vec<String>,
+    /// Comment after the field, on the same line.
+    pub trailing_comment: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -32,9 +116,51 @@ pub(crate) struct ASTType {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) struct ASTTypeName {
     pub path: Vec<String>,
+    /// Location of the (possibly dotted) path, without generic parameters.
+    pub span: Span,
     pub params: Vec<ASTTypeName>,
 }
 
+/// `@name` or `@name(args)`, written before a declaration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ASTAnnotation {
+    pub name: String,
+    /// Location of `@name`.
+    pub name_span: Span,
+    pub args: Vec<ASTAnnotationArg>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ASTAnnotationArg {
+    /// Set for `name = value` arguments.
+    pub name: Option<String>,
+    pub value: ASTValue,
+    /// Location of the whole argument, including its name.
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ASTValue {
+    pub kind: ASTValueKind,
+    pub span: Span,
+}
+
+/// A literal value. Scalars keep the text they were written with; they are only interpreted once
+/// the expected type is known.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ASTValueKind {
+    /// The raw token, including quotes and escapes.
+    String(String),
+    Integer(String),
+    Float(String),
+    Boolean(bool),
+    Null,
+    Array(Vec<ASTValue>),
+    Map(Vec<(ASTValue, ASTValue)>),
+    /// The name of a constant, standing for its value.
+    Const(String),
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) enum ASTTypeKind {
     Normal,
@@ -42,58 +168,396 @@ pub(crate) enum ASTTypeKind {
     Sync,
 }
 
-fn parse_type_name(tokens: &[Token], mut i: usize) -> (ASTTypeName, usize) {
+fn parse_type_name(tokens: &[Token], mut i: usize) -> Result<(ASTTypeName, usize), Diagnostic> {
     let mut path = Vec::new();
+    let mut span = Span::default();
     let mut params = Vec::new();
+    #[allow(clippy::enum_variant_names)]
     enum State {
         ExpectingIdentifier,
-        ExpectingOpeningAngleBracket,
+        ExpectingDotOrOpeningAngleBracket,
         ExpectingTypeName,
-        ExpectingComma,
+        ExpectingCommaOrClosingAngleBracket,
     }
     let mut state = State::ExpectingIdentifier;
     loop {
+        // A comment after the type name belongs to the field, so only skip the ones inside `<>`.
+        if !matches!(state, State::ExpectingDotOrOpeningAngleBracket) {
+            i = skip_comments(tokens, i);
+        }
+        let Some(token) = tokens.get(i) else {
+            if let State::ExpectingDotOrOpeningAngleBracket = state {
+                return Ok((ASTTypeName { path, span, params }, i));
+            }
+            return Err(unexpected_eof(tokens));
+        };
         match state {
             State::ExpectingIdentifier => {
-                let token = &tokens[i];
                 if token.ty == TokenType::Identifier {
-                    state = State::ExpectingOpeningAngleBracket;
-                    path.push(token.value.clone());
+                    state = State::ExpectingDotOrOpeningAngleBracket;
+                    if path.is_empty() {
+                        span.start = token.span.start;
+                    }
+                    span.end = token.span.end;
+                    path.push(token.value.to_string());
                     i += 1;
                 } else {
-                    panic!("Unexpected token: {:?}", token);
+                    return Err(unexpected_token(token));
                 }
             }
-            State::ExpectingOpeningAngleBracket => {
-                let token = &tokens[i];
-                if token.ty == TokenType::Punctuation && token.value == "<" {
+            State::ExpectingDotOrOpeningAngleBracket => {
+                if token.ty == TokenType::Punctuation && token.value == "." {
+                    state = State::ExpectingIdentifier;
+                    i += 1;
+                } else if token.ty == TokenType::Punctuation && token.value == "<" {
                     state = State::ExpectingTypeName;
                     i += 1;
                 } else {
-                    return (ASTTypeName { path, params }, i);
+                    return Ok((ASTTypeName { path, span, params }, i));
                 }
             }
             State::ExpectingTypeName => {
-                let (typename, n) = parse_type_name(tokens, i);
-                if n != i {
-                    params.push(typename);
-                }
+                let (typename, n) = parse_type_name(tokens, i)?;
+                params.push(typename);
                 i = n;
-                state = State::ExpectingComma;
+                state = State::ExpectingCommaOrClosingAngleBracket;
             }
-            _ => {
-                todo!()
+            State::ExpectingCommaOrClosingAngleBracket => {
+                if token.ty == TokenType::Punctuation && token.value == "," {
+                    state = State::ExpectingTypeName;
+                    i += 1;
+                } else if token.ty == TokenType::Punctuation && token.value == ">" {
+                    return Ok((ASTTypeName { path, span, params }, i + 1));
+                } else {
+                    return Err(unexpected_token(token));
+                }
             }
         }
     }
 }
 
-pub(crate) fn gen_ast(tokens: &[Token]) -> ASTRoot {
+/// Parses `remote function name(params) -> returns`, starting at `remote`.
+fn parse_function(tokens: &[Token], i: usize) -> Result<(ASTFunction, usize), Diagnostic> {
+    let token = |i: usize| tokens.get(i).ok_or_else(|| unexpected_eof(tokens));
+    let function = token(i + 1)?;
+    if function.ty != TokenType::Identifier || function.value != "function" {
+        return Err(unexpected_token(function));
+    }
+    let name = token(i + 2)?;
+    if name.ty != TokenType::Identifier {
+        return Err(unexpected_token(name));
+    }
+    let open = token(i + 3)?;
+    if !is_punctuation(Some(open), "(") {
+        return Err(unexpected_token(open));
+    }
+    let mut params = Vec::new();
+    let mut i = i + 4;
+    loop {
+        i = skip_comments(tokens, i);
+        let param = token(i)?;
+        if is_punctuation(Some(param), ")") {
+            i += 1;
+            break;
+        }
+        if param.ty != TokenType::Identifier {
+            return Err(unexpected_token(param));
+        }
+        let colon = token(i + 1)?;
+        if !is_punctuation(Some(colon), ":") {
+            return Err(unexpected_token(colon));
+        }
+        let (ty, n) = parse_type_name(tokens, i + 2)?;
+        i = skip_comments(tokens, n);
+        let mut annotations = Vec::new();
+        while is_punctuation(tokens.get(i), "@") {
+            let (annotation, n) = parse_annotation(tokens, i)?;
+            annotations.push(annotation);
+            i = skip_comments(tokens, n);
+        }
+        params.push(ASTParam {
+            name: param.value.to_string(),
+            name_span: param.span,
+            ty,
+            annotations,
+        });
+        let separator = token(i)?;
+        if is_punctuation(Some(separator), ",") {
+            i += 1;
+        } else if !is_punctuation(Some(separator), ")") {
+            return Err(unexpected_token(separator));
+        }
+    }
+    let mut returns = None;
+    if is_punctuation(tokens.get(i), "->") {
+        let kind = match token(i + 1)? {
+            token if token.ty == TokenType::Keyword && token.value == "stream" => {
+                ASTTypeKind::Stream
+            }
+            token if token.ty == TokenType::Keyword && token.value == "sync" => ASTTypeKind::Sync,
+            _ => ASTTypeKind::Normal,
+        };
+        let start = if kind == ASTTypeKind::Normal {
+            i + 1
+        } else {
+            i + 2
+        };
+        let (name, n) = parse_type_name(tokens, start)?;
+        returns = Some(ASTType { kind, name });
+        i = n;
+    }
+    Ok((
+        ASTFunction {
+            name: name.value.to_string(),
+            name_span: name.span,
+            params,
+            returns,
+            annotations: Vec::new(),
+            comments: Vec::new(),
+            trailing_comment: None,
+        },
+        i,
+    ))
+}
+
+/// Parses the names of type parameters up to the closing `>`, e.g. `T, U>`.
+fn parse_type_params(
+    tokens: &[Token],
+    mut i: usize,
+) -> Result<(Vec<ASTTypeParam>, usize), Diagnostic> {
+    let mut params = Vec::new();
+    loop {
+        let Some(token) = tokens.get(i) else {
+            return Err(unexpected_eof(tokens));
+        };
+        if token.ty != TokenType::Identifier {
+            return Err(unexpected_token(token));
+        }
+        params.push(ASTTypeParam {
+            name: token.value.to_string(),
+            span: token.span,
+        });
+        match tokens.get(i + 1) {
+            Some(token) if token.ty == TokenType::Punctuation && token.value == "," => i += 2,
+            Some(token) if token.ty == TokenType::Punctuation && token.value == ">" => {
+                return Ok((params, i + 2));
+            }
+            Some(token) => return Err(unexpected_token(token)),
+            None => return Err(unexpected_eof(tokens)),
+        }
+    }
+}
+
+/// Parses a literal: a string, number, boolean, `null`, `[items]`, `{key: value}` or the name of a
+/// constant.
+fn parse_value(tokens: &[Token], i: usize) -> Result<(ASTValue, usize), Diagnostic> {
+    let i = skip_comments(tokens, i);
+    let Some(token) = tokens.get(i) else {
+        return Err(unexpected_eof(tokens));
+    };
+    let literal = |kind| {
+        Ok((
+            ASTValue {
+                kind,
+                span: token.span,
+            },
+            i + 1,
+        ))
+    };
+    match (token.ty, token.value) {
+        (TokenType::String, raw) => literal(ASTValueKind::String(raw.to_string())),
+        (TokenType::Integer, raw) => literal(ASTValueKind::Integer(raw.to_string())),
+        (TokenType::Float, raw) => literal(ASTValueKind::Float(raw.to_string())),
+        (TokenType::Boolean, raw) => literal(ASTValueKind::Boolean(raw == "true")),
+        (TokenType::Identifier, "null") => literal(ASTValueKind::Null),
+        (TokenType::Identifier, name) => literal(ASTValueKind::Const(name.to_string())),
+        (TokenType::Punctuation, "[") => {
+            let mut items = Vec::new();
+            let mut i = i + 1;
+            loop {
+                i = skip_comments(tokens, i);
+                if is_punctuation(tokens.get(i), "]") {
+                    break;
+                }
+                let (item, n) = parse_value(tokens, i)?;
+                items.push(item);
+                i = skip_comments(tokens, n);
+                if is_punctuation(tokens.get(i), ",") {
+                    i += 1;
+                } else if !is_punctuation(tokens.get(i), "]") {
+                    return Err(tokens
+                        .get(i)
+                        .map_or_else(|| unexpected_eof(tokens), unexpected_token));
+                }
+            }
+            let span = Span::new(token.span.start, tokens[i].span.end);
+            Ok((
+                ASTValue {
+                    kind: ASTValueKind::Array(items),
+                    span,
+                },
+                i + 1,
+            ))
+        }
+        (TokenType::Punctuation, "{") => {
+            let mut entries = Vec::new();
+            let mut i = i + 1;
+            loop {
+                i = skip_comments(tokens, i);
+                if is_punctuation(tokens.get(i), "}") {
+                    break;
+                }
+                let (key, n) = parse_value(tokens, i)?;
+                let n = skip_comments(tokens, n);
+                if !is_punctuation(tokens.get(n), ":") {
+                    return Err(tokens
+                        .get(n)
+                        .map_or_else(|| unexpected_eof(tokens), unexpected_token));
+                }
+                let (value, n) = parse_value(tokens, n + 1)?;
+                entries.push((key, value));
+                i = skip_comments(tokens, n);
+                if is_punctuation(tokens.get(i), ",") {
+                    i += 1;
+                } else if !is_punctuation(tokens.get(i), "}") {
+                    return Err(tokens
+                        .get(i)
+                        .map_or_else(|| unexpected_eof(tokens), unexpected_token));
+                }
+            }
+            let span = Span::new(token.span.start, tokens[i].span.end);
+            Ok((
+                ASTValue {
+                    kind: ASTValueKind::Map(entries),
+                    span,
+                },
+                i + 1,
+            ))
+        }
+        _ => Err(unexpected_token(token)),
+    }
+}
+
+/// Parses `const NAME: type = value`, starting at `const`.
+fn parse_const(tokens: &[Token], i: usize) -> Result<(ASTConst, usize), Diagnostic> {
+    let token = |i: usize| tokens.get(i).ok_or_else(|| unexpected_eof(tokens));
+    let name = token(i + 1)?;
+    if name.ty != TokenType::Identifier {
+        return Err(unexpected_token(name));
+    }
+    let colon = token(i + 2)?;
+    if !is_punctuation(Some(colon), ":") {
+        return Err(unexpected_token(colon));
+    }
+    let (ty, n) = parse_type_name(tokens, i + 3)?;
+    let equals = token(n)?;
+    if !is_punctuation(Some(equals), "=") {
+        return Err(unexpected_token(equals));
+    }
+    let (value, n) = parse_value(tokens, n + 1)?;
+    Ok((
+        ASTConst {
+            name: name.value.to_string(),
+            name_span: name.span,
+            ty,
+            value,
+            annotations: Vec::new(),
+            comments: Vec::new(),
+        },
+        n,
+    ))
+}
+
+/// Parses `@name` with optional arguments `(value, key = value)`.
+fn parse_annotation(tokens: &[Token], i: usize) -> Result<(ASTAnnotation, usize), Diagnostic> {
+    let at = &tokens[i];
+    let name = match tokens.get(i + 1) {
+        Some(name) if name.ty == TokenType::Identifier && name.span.start == at.span.end => name,
+        Some(token) => {
+            return Err(Diagnostic::error(
+                token.span,
+                "Expected an annotation name right after `@`",
+            ))
+        }
+        None => return Err(unexpected_eof(tokens)),
+    };
+    let mut annotation = ASTAnnotation {
+        name: name.value.to_string(),
+        name_span: Span::new(at.span.start, name.span.end),
+        args: Vec::new(),
+    };
+    let mut i = i + 2;
+    if !is_punctuation(tokens.get(i), "(") {
+        return Ok((annotation, i));
+    }
+    i += 1;
+    loop {
+        i = skip_comments(tokens, i);
+        if is_punctuation(tokens.get(i), ")") {
+            return Ok((annotation, i + 1));
+        }
+        let start = tokens.get(i).map_or(0, |token| token.span.start);
+        let mut arg_name = None;
+        if let Some(token) = tokens.get(i) {
+            if token.ty == TokenType::Identifier && is_punctuation(tokens.get(i + 1), "=") {
+                arg_name = Some(token.value.to_string());
+                i += 2;
+            }
+        }
+        let (value, n) = parse_value(tokens, i)?;
+        annotation.args.push(ASTAnnotationArg {
+            name: arg_name,
+            span: Span::new(start, value.span.end),
+            value,
+        });
+        i = skip_comments(tokens, n);
+        if is_punctuation(tokens.get(i), ",") {
+            i += 1;
+        } else if !is_punctuation(tokens.get(i), ")") {
+            return Err(tokens
+                .get(i)
+                .map_or_else(|| unexpected_eof(tokens), unexpected_token));
+        }
+    }
+}
+
+/// Returns the index of the first token from `i` on that is not a comment.
+fn skip_comments(tokens: &[Token], mut i: usize) -> usize {
+    while tokens
+        .get(i)
+        .is_some_and(|token| token.ty == TokenType::Comment)
+    {
+        i += 1;
+    }
+    i
+}
+
+fn is_punctuation(token: Option<&Token>, value: &str) -> bool {
+    token.is_some_and(|token| token.ty == TokenType::Punctuation && token.value == value)
+}
+
+pub(crate) fn gen_ast(tokens: &[Token]) -> Result<ASTRoot, Vec<Diagnostic>> {
+    let (ast, diagnostics) = gen_ast_partial(tokens);
+    if diagnostics.is_empty() {
+        Ok(ast)
+    } else {
+        Err(diagnostics)
+    }
+}
+
+/// Parses as much as possible: a malformed field is skipped up to the next `,` or `}`, and a
+/// malformed declaration up to the next `type` keyword. Returns the partial AST together with all
+/// syntax errors found on the way.
+pub(crate) fn gen_ast_partial(tokens: &[Token]) -> (ASTRoot, Vec<Diagnostic>) {
     let mut blocks = Vec::new();
+    let mut diagnostics = Vec::new();
     let mut current_block: Option<ASTRootBlock> = None;
+    let mut comments: Vec<String> = Vec::new();
+    // Annotations waiting for the declaration they apply to.
+    let mut annotations: Vec<ASTAnnotation> = Vec::new();
     let mut i = 0;
 
-    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     enum State {
         Root,
         TypeDefExpectingName,
@@ -103,6 +567,16 @@ pub(crate) fn gen_ast(tokens: &[Token]) -> ASTRoot {
         TypeDefBlockFieldExpectingTypeKind,
         TypeDefBlockFieldExpectingTypeName,
         TypeDefBlockFieldExpectingComma,
+        ServiceExpectingName,
+        ServiceExpectingBlock,
+        ServiceBlock,
+        ServiceBlockExpectingSemicolon,
+        /// Skipping tokens until the next top-level declaration.
+        RecoverRoot,
+        /// Skipping the rest of a malformed field.
+        RecoverField,
+        /// Skipping the rest of a malformed function.
+        RecoverFunction,
     }
 
     let mut state = State::Root;
@@ -113,38 +587,216 @@ pub(crate) fn gen_ast(tokens: &[Token]) -> ASTRoot {
         }
         let token = &tokens[i];
 
+        if token.ty == TokenType::Comment {
+            // A comment on the line of a field or function, after it or its separator, is about
+            // that field or function, and one after a closing brace is about the whole block.
+            let previous = i.checked_sub(1).map(|i| tokens[i].value);
+            let slot = match (state, current_block.as_mut(), blocks.last_mut()) {
+                _ if token.line_start => None,
+                (
+                    State::TypeDefBlockFieldExpectingComma,
+                    Some(ASTRootBlock::TypeDef(typedef)),
+                    _,
+                ) => typedef
+                    .fields
+                    .last_mut()
+                    .map(|field| &mut field.trailing_comment),
+                (State::TypeDefBlock, Some(ASTRootBlock::TypeDef(typedef)), _)
+                    if previous == Some(",") =>
+                {
+                    typedef
+                        .fields
+                        .last_mut()
+                        .map(|field| &mut field.trailing_comment)
+                }
+                (
+                    State::ServiceBlockExpectingSemicolon,
+                    Some(ASTRootBlock::Service(service)),
+                    _,
+                ) => service
+                    .functions
+                    .last_mut()
+                    .map(|function| &mut function.trailing_comment),
+                (State::ServiceBlock, Some(ASTRootBlock::Service(service)), _)
+                    if previous == Some(";") =>
+                {
+                    service
+                        .functions
+                        .last_mut()
+                        .map(|function| &mut function.trailing_comment)
+                }
+                (State::Root, None, Some(ASTRootBlock::TypeDef(typedef)))
+                    if previous == Some("}") =>
+                {
+                    Some(&mut typedef.closing_comment)
+                }
+                (State::Root, None, Some(ASTRootBlock::Service(service)))
+                    if previous == Some("}") =>
+                {
+                    Some(&mut service.closing_comment)
+                }
+                _ => None,
+            };
+            match slot {
+                Some(slot) if slot.is_none() => *slot = Some(token.value.to_string()),
+                _ => comments.push(token.value.to_string()),
+            }
+            i += 1;
+            continue;
+        }
+
+        if token.ty == TokenType::Punctuation
+            && token.value == "@"
+            && matches!(
+                state,
+                State::Root
+                    | State::TypeDefBlock
+                    | State::TypeDefBlockFieldExpectingComma
+                    | State::ServiceBlock
+            )
+        {
+            match parse_annotation(tokens, i) {
+                Ok((annotation, n)) if state == State::TypeDefBlockFieldExpectingComma => {
+                    // Written after the field type, like constraints usually are.
+                    if let Some(ASTRootBlock::TypeDef(typedef)) = current_block.as_mut() {
+                        if let Some(field) = typedef.fields.last_mut() {
+                            field.annotations.push(annotation);
+                        }
+                    }
+                    i = n;
+                }
+                Ok((annotation, n)) => {
+                    annotations.push(annotation);
+                    i = n;
+                }
+                Err(err) => {
+                    diagnostics.push(err);
+                    state = match state {
+                        State::Root => State::RecoverRoot,
+                        State::ServiceBlock => State::RecoverFunction,
+                        _ => State::RecoverField,
+                    };
+                    i += 1;
+                }
+            }
+            continue;
+        }
+
         match state {
             State::Root => {
                 if token.ty == TokenType::Keyword && token.value == "type" {
                     state = State::TypeDefExpectingName;
                     i += 1;
+                } else if token.ty == TokenType::Identifier && token.value == "service" {
+                    state = State::ServiceExpectingName;
+                    i += 1;
+                } else if token.ty == TokenType::Identifier && token.value == "const" {
+                    match parse_const(tokens, i) {
+                        Ok((mut constant, n)) => {
+                            constant.annotations = std::mem::take(&mut annotations);
+                            constant.comments = std::mem::take(&mut comments);
+                            blocks.push(ASTRootBlock::Const(constant));
+                            i = n;
+                        }
+                        Err(err) => {
+                            diagnostics.push(err);
+                            state = State::RecoverRoot;
+                            i += 1;
+                        }
+                    }
+                } else if token.ty == TokenType::Identifier && token.value == "remote" {
+                    diagnostics.push(Diagnostic::error(
+                        token.span,
+                        "Remote functions must be declared in a `service` block",
+                    ));
+                    state = State::RecoverRoot;
                 } else {
-                    panic!("Unexpected token: {:?}", token);
+                    diagnostics.push(unexpected_token(token));
+                    state = State::RecoverRoot;
                 }
             }
             State::TypeDefExpectingName => {
                 if token.ty == TokenType::Identifier {
                     current_block = Some(ASTRootBlock::TypeDef(ASTTypeDef {
-                        name: token.value.clone(),
+                        name: token.value.to_string(),
+                        name_span: token.span,
+                        params: Vec::new(),
+                        extends: None,
                         fields: Vec::new(),
+                        annotations: std::mem::take(&mut annotations),
+                        comments: std::mem::take(&mut comments),
+                        trailing_comments: Vec::new(),
+                        closing_comment: None,
                     }));
                     state = State::TypeDefExpectingBlock;
                     i += 1;
                 } else {
-                    panic!("Unexpected token: {:?}", token);
+                    diagnostics.push(unexpected_token(token));
+                    state = State::RecoverRoot;
                 }
             }
             State::TypeDefExpectingBlock => {
+                let Some(ASTRootBlock::TypeDef(ref mut typedef)) = current_block.as_mut() else {
+                    panic!("!!!BUG!!! Current parser state (TypeDefExpectingBlock) implies that the current block is a TypeDef, but it is not a TypeDef");
+                };
                 if token.ty == TokenType::Punctuation && token.value == "{" {
                     state = State::TypeDefBlock;
                     i += 1;
+                } else if token.ty == TokenType::Punctuation
+                    && token.value == "<"
+                    && typedef.params.is_empty()
+                    && typedef.extends.is_none()
+                {
+                    match parse_type_params(tokens, i + 1) {
+                        Ok((params, n)) => {
+                            typedef.params = params;
+                            i = n;
+                        }
+                        Err(err) => {
+                            diagnostics.push(err);
+                            if let Some(block) = current_block.take() {
+                                blocks.push(finish_block(block, &mut comments));
+                            }
+                            state = State::RecoverRoot;
+                            i += 1;
+                        }
+                    }
+                } else if token.ty == TokenType::Identifier
+                    && token.value == "extends"
+                    && typedef.extends.is_none()
+                {
+                    // `extends` is only special here, fields may still be called so.
+                    match parse_type_name(tokens, i + 1) {
+                        Ok((base, n)) => {
+                            typedef.extends = Some(base);
+                            i = n;
+                        }
+                        Err(err) => {
+                            diagnostics.push(err);
+                            if let Some(block) = current_block.take() {
+                                blocks.push(finish_block(block, &mut comments));
+                            }
+                            state = State::RecoverRoot;
+                            i += 1;
+                        }
+                    }
                 } else {
-                    panic!("Unexpected token: {:?}", token);
+                    // Keep the type without fields so that references to it still resolve.
+                    diagnostics.push(unexpected_token(token));
+                    if let Some(block) = current_block.take() {
+                        blocks.push(finish_block(block, &mut comments));
+                    }
+                    state = State::RecoverRoot;
                 }
             }
             State::TypeDefBlock => {
                 if token.ty == TokenType::Punctuation && token.value == "}" {
+                    dangling_annotations(&mut annotations, &mut diagnostics);
                     state = State::Root;
+                    let Some(block) = current_block.take() else {
+                        panic!("!!!BUG!!! Current parser state (TypeDefBlock) implies that the current block is ASTTypeDef, but it is not TypeDef");
+                    };
+                    blocks.push(finish_block(block, &mut comments));
                     i += 1;
                 } else if token.ty == TokenType::Identifier {
                     #[allow(irrefutable_let_patterns)]
@@ -153,27 +805,52 @@ pub(crate) fn gen_ast(tokens: &[Token]) -> ASTRoot {
                     };
                     state = State::TypeDefBlockFieldExpectingColon;
                     let field = ASTField {
-                        name: token.value.clone(),
+                        name: token.value.to_string(),
+                        name_span: token.span,
                         ty: ASTType {
                             kind: ASTTypeKind::Normal,
-                            name: "".to_string(),
+                            name: ASTTypeName {
+                                path: Vec::new(),
+                                span: Span::default(),
+                                params: Vec::new(),
+                            },
                         },
+                        optional: false,
+                        default: None,
+                        annotations: std::mem::take(&mut annotations),
+                        comments: std::mem::take(&mut comments),
+                        trailing_comment: None,
                     };
                     typedef.fields.push(field);
                     i += 1;
                 } else {
-                    panic!("Unexpected token: {:?}", token);
+                    diagnostics.push(unexpected_token(token));
+                    state = State::RecoverField;
                 }
             }
             State::TypeDefBlockFieldExpectingColon => {
+                let Some(ASTRootBlock::TypeDef(ref mut typedef)) = current_block.as_mut() else {
+                    panic!("!!!BUG!!! Current parser state (TypeDefBlockFieldExpectingColon) implies that the current block is a TypeDef, but it is not a TypeDef");
+                };
+                let Some(field) = typedef.fields.last_mut() else {
+                    panic!("!!!BUG!!! Current parser state (TypeDefBlockFieldExpectingColon) implies that the current block contains at least one field, but it does not");
+                };
                 if token.ty == TokenType::Punctuation && token.value == ":" {
                     state = State::TypeDefBlockFieldExpectingTypeKind;
                     i += 1;
+                } else if token.ty == TokenType::Punctuation
+                    && token.value == "?"
+                    && !field.optional
+                {
+                    field.optional = true;
+                    i += 1;
                 } else {
-                    panic!("Unexpected token: {:?}", token);
+                    diagnostics.push(unexpected_token(token));
+                    drop_current_field(&mut current_block);
+                    state = State::RecoverField;
                 }
             }
-            State::TypeDefBlockFieldExpectingTypeKind => match (&token.ty, token.value.as_str()) {
+            State::TypeDefBlockFieldExpectingTypeKind => match (&token.ty, token.value) {
                 (&TokenType::Keyword, "normal") => {
                     state = State::TypeDefBlockFieldExpectingTypeName;
                     let Some(ASTRootBlock::TypeDef(ref mut typedef)) = current_block.as_mut()
@@ -213,6 +890,15 @@ pub(crate) fn gen_ast(tokens: &[Token]) -> ASTRoot {
                 }
                 (&TokenType::Identifier, _) => {
                     state = State::TypeDefBlockFieldExpectingComma;
+                    let (typename, n) = match parse_type_name(tokens, i) {
+                        Ok(result) => result,
+                        Err(err) => {
+                            diagnostics.push(err);
+                            drop_current_field(&mut current_block);
+                            state = State::RecoverField;
+                            continue;
+                        }
+                    };
                     let Some(ASTRootBlock::TypeDef(ref mut typedef)) = current_block.as_mut()
                     else {
                         panic!("!!!BUG!!! Current parser state (TypeDefBlockFieldExpectingTypeKind) implies that the current block is a TypeDef, but it is not a TypeDef");
@@ -221,21 +907,28 @@ pub(crate) fn gen_ast(tokens: &[Token]) -> ASTRoot {
                         panic!("!!!BUG!!! Current parser state (TypeDefBlockFieldExpectingTypeKind) implies that the current block contains at least one field, but it does not");
                     };
                     field.ty.kind = ASTTypeKind::Normal;
-                    field.ty.name = token.value.clone();
-                    i += 1;
+                    field.ty.name = typename;
+                    i = n;
                 }
 
-                (ty, value) => panic!(
-                    "Unexpected token: {:?}",
-                    Token {
-                        ty: ty.clone(),
-                        value: value.to_string(),
-                    }
-                ),
+                _ => {
+                    diagnostics.push(unexpected_token(token));
+                    drop_current_field(&mut current_block);
+                    state = State::RecoverField;
+                }
             },
-            State::TypeDefBlockFieldExpectingTypeName => match (&token.ty, token.value.as_str()) {
-                (&TokenType::Identifier, typename) => {
+            State::TypeDefBlockFieldExpectingTypeName => match (&token.ty, token.value) {
+                (&TokenType::Identifier, _) => {
                     state = State::TypeDefBlockFieldExpectingComma;
+                    let (typename, n) = match parse_type_name(tokens, i) {
+                        Ok(result) => result,
+                        Err(err) => {
+                            diagnostics.push(err);
+                            drop_current_field(&mut current_block);
+                            state = State::RecoverField;
+                            continue;
+                        }
+                    };
                     let Some(ASTRootBlock::TypeDef(ref mut typedef)) = current_block.as_mut()
                     else {
                         panic!("!!!BUG!!! Current parser state (TypeDefBlockFieldExpectingTypeName) implies that the current block is a TypeDef, but it is not a TypeDef");
@@ -243,43 +936,305 @@ pub(crate) fn gen_ast(tokens: &[Token]) -> ASTRoot {
                     let Some(field) = typedef.fields.last_mut() else {
                         panic!("!!!BUG!!! Current parser state (TypeDefBlockFieldExpectingTypeName) implies that the current block contains at least one field, but it does not");
                     };
-                    field.ty.name = typename.to_string();
-                    i += 1;
+                    field.ty.name = typename;
+                    i = n;
+                }
+                _ => {
+                    diagnostics.push(unexpected_token(token));
+                    drop_current_field(&mut current_block);
+                    state = State::RecoverField;
                 }
-                (ty, value) => panic!(
-                    "Unexpected token: {:?}",
-                    Token {
-                        ty: ty.clone(),
-                        value: value.to_string(),
-                    }
-                ),
             },
-            State::TypeDefBlockFieldExpectingComma => match (&token.ty, token.value.as_str()) {
+            State::TypeDefBlockFieldExpectingComma => match (&token.ty, token.value) {
                 (&TokenType::Punctuation, ",") => {
                     state = State::TypeDefBlock;
                     i += 1;
                 }
                 (&TokenType::Punctuation, "}") => {
                     state = State::Root;
-                    let Some(block) = current_block else {
+                    let Some(block) = current_block.take() else {
                         panic!("!!!BUG!!! Current parser state (TypeDefBlockFieldExpectingComma) implies that the current block is ASTTypeDef, but it is not TypeDef");
                     };
-                    blocks.push(block);
-                    current_block = None;
+                    blocks.push(finish_block(block, &mut comments));
                     i += 1;
                 }
-                (ty, value) => panic!(
-                    "Unexpected token: {:?}",
-                    Token {
-                        ty: ty.clone(),
-                        value: value.to_string(),
+                (&TokenType::Punctuation, "=") => {
+                    let Some(ASTRootBlock::TypeDef(ref mut typedef)) = current_block.as_mut()
+                    else {
+                        panic!("!!!BUG!!! Current parser state (TypeDefBlockFieldExpectingComma) implies that the current block is a TypeDef, but it is not a TypeDef");
+                    };
+                    let Some(field) = typedef.fields.last_mut() else {
+                        panic!("!!!BUG!!! Current parser state (TypeDefBlockFieldExpectingComma) implies that the current block contains at least one field, but it does not");
+                    };
+                    if field.default.is_some() {
+                        diagnostics.push(unexpected_token(token));
+                        state = State::RecoverField;
+                        continue;
                     }
-                ),
+                    match parse_value(tokens, i + 1) {
+                        Ok((value, n)) => {
+                            field.default = Some(value);
+                            i = n;
+                        }
+                        Err(err) => {
+                            diagnostics.push(err);
+                            state = State::RecoverField;
+                            i += 1;
+                        }
+                    }
+                }
+                (&TokenType::Identifier, _) => {
+                    // The next field starts right away, most likely the comma was forgotten.
+                    diagnostics.push(Diagnostic::error(
+                        token.span,
+                        format!("Expected `,` before `{}`", token.value),
+                    ));
+                    state = State::TypeDefBlock;
+                }
+                _ => {
+                    diagnostics.push(unexpected_token(token));
+                    state = State::RecoverField;
+                }
             },
+            State::ServiceExpectingName => {
+                if token.ty == TokenType::Identifier {
+                    current_block = Some(ASTRootBlock::Service(ASTService {
+                        name: token.value.to_string(),
+                        name_span: token.span,
+                        functions: Vec::new(),
+                        annotations: std::mem::take(&mut annotations),
+                        comments: std::mem::take(&mut comments),
+                        trailing_comments: Vec::new(),
+                        closing_comment: None,
+                    }));
+                    state = State::ServiceExpectingBlock;
+                    i += 1;
+                } else {
+                    diagnostics.push(unexpected_token(token));
+                    state = State::RecoverRoot;
+                }
+            }
+            State::ServiceExpectingBlock => {
+                if token.ty == TokenType::Punctuation && token.value == "{" {
+                    state = State::ServiceBlock;
+                    i += 1;
+                } else {
+                    diagnostics.push(unexpected_token(token));
+                    if let Some(block) = current_block.take() {
+                        blocks.push(finish_block(block, &mut comments));
+                    }
+                    state = State::RecoverRoot;
+                }
+            }
+            State::ServiceBlock => {
+                if token.ty == TokenType::Punctuation && token.value == "}" {
+                    dangling_annotations(&mut annotations, &mut diagnostics);
+                    state = State::Root;
+                    if let Some(block) = current_block.take() {
+                        blocks.push(finish_block(block, &mut comments));
+                    }
+                    i += 1;
+                } else if token.ty == TokenType::Identifier && token.value == "remote" {
+                    match parse_function(tokens, i) {
+                        Ok((mut function, n)) => {
+                            function.annotations = std::mem::take(&mut annotations);
+                            function.comments = std::mem::take(&mut comments);
+                            if let Some(ASTRootBlock::Service(service)) = current_block.as_mut() {
+                                service.functions.push(function);
+                            }
+                            state = State::ServiceBlockExpectingSemicolon;
+                            i = n;
+                        }
+                        Err(err) => {
+                            diagnostics.push(err);
+                            state = State::RecoverFunction;
+                            i += 1;
+                        }
+                    }
+                } else {
+                    diagnostics.push(unexpected_token(token));
+                    state = State::RecoverFunction;
+                }
+            }
+            State::ServiceBlockExpectingSemicolon => match (&token.ty, token.value) {
+                (&TokenType::Punctuation, ";") => {
+                    state = State::ServiceBlock;
+                    i += 1;
+                }
+                (&TokenType::Punctuation, "}") => state = State::ServiceBlock,
+                (&TokenType::Identifier, "remote") => {
+                    diagnostics.push(Diagnostic::error(
+                        token.span,
+                        "Expected `;` before `remote`",
+                    ));
+                    state = State::ServiceBlock;
+                }
+                _ => {
+                    diagnostics.push(unexpected_token(token));
+                    state = State::RecoverFunction;
+                }
+            },
+            State::RecoverFunction => {
+                annotations.clear();
+                match (&token.ty, token.value) {
+                    (&TokenType::Punctuation, ";") => {
+                        state = State::ServiceBlock;
+                        i += 1;
+                    }
+                    (&TokenType::Punctuation, "}") => state = State::ServiceBlock,
+                    (&TokenType::Keyword, "type") => {
+                        state = State::Root;
+                        if let Some(block) = current_block.take() {
+                            blocks.push(finish_block(block, &mut comments));
+                        }
+                    }
+                    _ => i += 1,
+                }
+            }
+            State::RecoverRoot => {
+                annotations.clear();
+                if (token.ty == TokenType::Keyword && token.value == "type")
+                    || (token.ty == TokenType::Identifier
+                        && (token.value == "service" || token.value == "const")
+                        && tokens
+                            .get(i + 1)
+                            .is_some_and(|next| next.ty == TokenType::Identifier))
+                {
+                    state = State::Root;
+                } else {
+                    i += 1;
+                }
+            }
+            State::RecoverField => {
+                annotations.clear();
+                match (&token.ty, token.value) {
+                    (&TokenType::Punctuation, ",") => {
+                        state = State::TypeDefBlock;
+                        i += 1;
+                    }
+                    (&TokenType::Punctuation, "}") | (&TokenType::Keyword, "type") => {
+                        state = State::Root;
+                        if let Some(block) = current_block.take() {
+                            blocks.push(finish_block(block, &mut comments));
+                        }
+                        if token.value == "}" {
+                            i += 1;
+                        }
+                    }
+                    _ => {
+                        i += 1;
+                    }
+                }
+            }
+        }
+    }
+    if state != State::Root && state != State::RecoverRoot {
+        diagnostics.push(unexpected_eof(tokens));
+        if let Some(block) = current_block.take() {
+            blocks.push(finish_block(block, &mut comments));
         }
     }
-    if state != State::Root {
-        panic!("Unexpected EOF");
+    dangling_annotations(&mut annotations, &mut diagnostics);
+    blocks.extend(comments.into_iter().map(ASTRootBlock::Comment));
+    (ASTRoot { blocks }, diagnostics)
+}
+
+fn unexpected_token(token: &Token) -> Diagnostic {
+    Diagnostic::error(token.span, format!("Unexpected token `{}`", token.value))
+}
+
+fn unexpected_eof(tokens: &[Token]) -> Diagnostic {
+    let end = tokens.last().map_or(0, |token| token.span.end);
+    Diagnostic::error(Span::new(end, end), "Unexpected end of file")
+}
+
+fn dangling_annotations(annotations: &mut Vec<ASTAnnotation>, diagnostics: &mut Vec<Diagnostic>) {
+    for annotation in annotations.drain(..) {
+        diagnostics.push(Diagnostic::error(
+            annotation.name_span,
+            format!(
+                "Annotation `@{}` is not followed by a declaration",
+                annotation.name
+            ),
+        ));
+    }
+}
+
+/// Removes the field that was being parsed when a syntax error was found.
+fn drop_current_field(current_block: &mut Option<ASTRootBlock>) {
+    if let Some(ASTRootBlock::TypeDef(typedef)) = current_block {
+        typedef.fields.pop();
+    }
+}
+
+/// Attaches comments collected since the last field or function to the closing declaration.
+fn finish_block(mut block: ASTRootBlock, comments: &mut Vec<String>) -> ASTRootBlock {
+    match block {
+        ASTRootBlock::TypeDef(ref mut typedef) => {
+            typedef.trailing_comments = std::mem::take(comments);
+        }
+        ASTRootBlock::Service(ref mut service) => {
+            service.trailing_comments = std::mem::take(comments);
+        }
+        ASTRootBlock::Const(_) | ASTRootBlock::Comment(_) => {}
+    }
+    block
+}
+
+impl ASTRoot {
+    pub fn type_defs(&self) -> impl Iterator<Item = &ASTTypeDef> {
+        self.blocks.iter().filter_map(|block| match block {
+            ASTRootBlock::TypeDef(type_def) => Some(type_def),
+            _ => None,
+        })
+    }
+
+    pub fn services(&self) -> impl Iterator<Item = &ASTService> {
+        self.blocks.iter().filter_map(|block| match block {
+            ASTRootBlock::Service(service) => Some(service),
+            _ => None,
+        })
+    }
+
+    pub fn consts(&self) -> impl Iterator<Item = &ASTConst> {
+        self.blocks.iter().filter_map(|block| match block {
+            ASTRootBlock::Const(constant) => Some(constant),
+            _ => None,
+        })
+    }
+
+    /// Every type name used in the schema, including generic parameters.
+    pub fn type_names(&self) -> Vec<&ASTTypeName> {
+        let mut names = Vec::new();
+        for constant in self.consts() {
+            constant.ty.collect(&mut names);
+        }
+        for function in self.services().flat_map(|service| &service.functions) {
+            for param in &function.params {
+                param.ty.collect(&mut names);
+            }
+            if let Some(returns) = &function.returns {
+                returns.name.collect(&mut names);
+            }
+        }
+        for type_def in self.type_defs() {
+            if let Some(base) = &type_def.extends {
+                base.collect(&mut names);
+            }
+            for field in &type_def.fields {
+                field.ty.name.collect(&mut names);
+            }
+        }
+        names
+    }
+}
+
+impl ASTTypeName {
+    /// Adds this name and its generic parameters, recursively, to `names`.
+    pub(crate) fn collect<'a>(&'a self, names: &mut Vec<&'a ASTTypeName>) {
+        names.push(self);
+        for param in &self.params {
+            param.collect(names);
+        }
     }
-    ASTRoot { blocks }
 }
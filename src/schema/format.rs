@@ -0,0 +1,291 @@
+use super::annotation::known_annotation;
+use super::ast::*;
+
+/// Layout settings for the canonical `.rgc` printer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// Number of spaces per indentation level.
+    pub indent_width: usize,
+    /// Whether the last field of a type is followed by a comma, and the last function of a service
+    /// by a semicolon.
+    pub trailing_comma: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            indent_width: 4,
+            trailing_comma: false,
+        }
+    }
+}
+
+pub(crate) fn format(ast: &ASTRoot, options: &FormatOptions) -> String {
+    let mut out = String::new();
+    let mut previous: Option<&ASTRootBlock> = None;
+    for block in &ast.blocks {
+        // Dangling comments stay grouped, everything else is separated by a blank line.
+        match (previous, block) {
+            (None, _) => {}
+            (Some(ASTRootBlock::Comment(_)), ASTRootBlock::Comment(_)) => {}
+            // Consecutive constants stay grouped as well, unless they are commented or annotated.
+            (Some(ASTRootBlock::Const(_)), ASTRootBlock::Const(constant))
+                if constant.comments.is_empty() && constant.annotations.is_empty() => {}
+            _ => out.push('\n'),
+        }
+        match block {
+            ASTRootBlock::TypeDef(type_def) => format_type_def(&mut out, type_def, options),
+            ASTRootBlock::Service(service) => format_service(&mut out, service, options),
+            ASTRootBlock::Const(constant) => format_const(&mut out, constant),
+            ASTRootBlock::Comment(comment) => {
+                out.push_str(comment);
+                out.push('\n');
+            }
+        }
+        previous = Some(block);
+    }
+    out
+}
+
+fn format_const(out: &mut String, constant: &ASTConst) {
+    for comment in &constant.comments {
+        out.push_str(comment);
+        out.push('\n');
+    }
+    for annotation in &constant.annotations {
+        out.push_str(&format_annotation(annotation));
+        out.push('\n');
+    }
+    out.push_str(&format!(
+        "const {}: {} = {}\n",
+        constant.name,
+        format_type_name(&constant.ty),
+        format_value(&constant.value)
+    ));
+}
+
+pub(crate) fn format_type_def(out: &mut String, type_def: &ASTTypeDef, options: &FormatOptions) {
+    let indent = " ".repeat(options.indent_width);
+    for comment in &type_def.comments {
+        out.push_str(comment);
+        out.push('\n');
+    }
+    for annotation in &type_def.annotations {
+        out.push_str(&format_annotation(annotation));
+        out.push('\n');
+    }
+    out.push_str("type ");
+    out.push_str(&type_def.name);
+    if !type_def.params.is_empty() {
+        let params = type_def
+            .params
+            .iter()
+            .map(|param| param.name.as_str())
+            .collect::<Vec<_>>();
+        out.push('<');
+        out.push_str(&params.join(", "));
+        out.push('>');
+    }
+    if let Some(base) = &type_def.extends {
+        out.push_str(" extends ");
+        out.push_str(&format_type_name(base));
+    }
+    if type_def.fields.is_empty() && type_def.trailing_comments.is_empty() {
+        out.push_str(" {}");
+        push_closing_comment(out, type_def.closing_comment.as_deref());
+        return;
+    }
+    out.push_str(" {\n");
+    for (i, field) in type_def.fields.iter().enumerate() {
+        for comment in &field.comments {
+            out.push_str(&indent);
+            out.push_str(comment);
+            out.push('\n');
+        }
+        // Constraints follow the type they restrict, other annotations precede the field.
+        let (constraints, annotations): (Vec<_>, Vec<_>) =
+            field.annotations.iter().partition(|annotation| {
+                known_annotation(&annotation.name).is_some_and(|spec| spec.constrains.is_some())
+            });
+        for annotation in annotations {
+            out.push_str(&indent);
+            out.push_str(&format_annotation(annotation));
+            out.push('\n');
+        }
+        out.push_str(&indent);
+        out.push_str(&field.name);
+        if field.optional {
+            out.push('?');
+        }
+        out.push_str(": ");
+        out.push_str(&format_field_type(&field.ty));
+        for constraint in constraints {
+            out.push(' ');
+            out.push_str(&format_annotation(constraint));
+        }
+        if let Some(default) = &field.default {
+            out.push_str(" = ");
+            out.push_str(&format_value(default));
+        }
+        if i + 1 < type_def.fields.len() || options.trailing_comma {
+            out.push(',');
+        }
+        if let Some(comment) = &field.trailing_comment {
+            out.push(' ');
+            out.push_str(comment);
+        }
+        out.push('\n');
+    }
+    for comment in &type_def.trailing_comments {
+        out.push_str(&indent);
+        out.push_str(comment);
+        out.push('\n');
+    }
+    out.push('}');
+    push_closing_comment(out, type_def.closing_comment.as_deref());
+}
+
+pub(crate) fn format_service(out: &mut String, service: &ASTService, options: &FormatOptions) {
+    let indent = " ".repeat(options.indent_width);
+    for comment in &service.comments {
+        out.push_str(comment);
+        out.push('\n');
+    }
+    for annotation in &service.annotations {
+        out.push_str(&format_annotation(annotation));
+        out.push('\n');
+    }
+    out.push_str("service ");
+    out.push_str(&service.name);
+    if service.functions.is_empty() && service.trailing_comments.is_empty() {
+        out.push_str(" {}");
+        push_closing_comment(out, service.closing_comment.as_deref());
+        return;
+    }
+    out.push_str(" {\n");
+    for (i, function) in service.functions.iter().enumerate() {
+        for comment in &function.comments {
+            out.push_str(&indent);
+            out.push_str(comment);
+            out.push('\n');
+        }
+        for annotation in &function.annotations {
+            out.push_str(&indent);
+            out.push_str(&format_annotation(annotation));
+            out.push('\n');
+        }
+        out.push_str(&indent);
+        out.push_str(&format_function(function));
+        if i + 1 < service.functions.len() || options.trailing_comma {
+            out.push(';');
+        }
+        if let Some(comment) = &function.trailing_comment {
+            out.push(' ');
+            out.push_str(comment);
+        }
+        out.push('\n');
+    }
+    for comment in &service.trailing_comments {
+        out.push_str(&indent);
+        out.push_str(comment);
+        out.push('\n');
+    }
+    out.push('}');
+    push_closing_comment(out, service.closing_comment.as_deref());
+}
+
+/// Ends a block whose closing brace was just written, keeping the comment that followed it.
+fn push_closing_comment(out: &mut String, comment: Option<&str>) {
+    if let Some(comment) = comment {
+        out.push(' ');
+        out.push_str(comment);
+    }
+    out.push('\n');
+}
+
+/// The signature of a function, without the annotations of the function.
+fn format_function(function: &ASTFunction) -> String {
+    let params = function
+        .params
+        .iter()
+        .map(|param| {
+            let mut out = format!("{}: {}", param.name, format_type_name(&param.ty));
+            for annotation in &param.annotations {
+                out.push(' ');
+                out.push_str(&format_annotation(annotation));
+            }
+            out
+        })
+        .collect::<Vec<_>>();
+    let mut out = format!("remote function {}({})", function.name, params.join(", "));
+    if let Some(returns) = &function.returns {
+        out.push_str(" -> ");
+        out.push_str(&format_field_type(returns));
+    }
+    out
+}
+
+pub(crate) fn format_field_type(ty: &ASTType) -> String {
+    let kind = match ty.kind {
+        ASTTypeKind::Normal => "",
+        ASTTypeKind::Stream => "stream ",
+        ASTTypeKind::Sync => "sync ",
+    };
+    format!("{kind}{}", format_type_name(&ty.name))
+}
+
+pub(crate) fn format_type_name(name: &ASTTypeName) -> String {
+    let mut out = name.path.join(".");
+    if !name.params.is_empty() {
+        out.push('<');
+        out.push_str(
+            &name
+                .params
+                .iter()
+                .map(format_type_name)
+                .collect::<Vec<_>>()
+                .join(", "),
+        );
+        out.push('>');
+    }
+    out
+}
+
+pub(crate) fn format_annotation(annotation: &ASTAnnotation) -> String {
+    let mut out = format!("@{}", annotation.name);
+    if !annotation.args.is_empty() {
+        let args = annotation
+            .args
+            .iter()
+            .map(|arg| match &arg.name {
+                Some(name) => format!("{name} = {}", format_value(&arg.value)),
+                None => format_value(&arg.value),
+            })
+            .collect::<Vec<_>>();
+        out.push_str(&format!("({})", args.join(", ")));
+    }
+    out
+}
+
+pub(crate) fn format_value(value: &ASTValue) -> String {
+    match &value.kind {
+        ASTValueKind::String(raw) | ASTValueKind::Integer(raw) | ASTValueKind::Float(raw) => {
+            raw.clone()
+        }
+        ASTValueKind::Boolean(value) => value.to_string(),
+        ASTValueKind::Null => "null".to_string(),
+        ASTValueKind::Const(name) => name.clone(),
+        ASTValueKind::Array(items) => {
+            let items = items.iter().map(format_value).collect::<Vec<_>>();
+            format!("[{}]", items.join(", "))
+        }
+        ASTValueKind::Map(entries) if entries.is_empty() => "{}".to_string(),
+        ASTValueKind::Map(entries) => {
+            let entries = entries
+                .iter()
+                .map(|(key, value)| format!("{}: {}", format_value(key), format_value(value)))
+                .collect::<Vec<_>>();
+            format!("{{ {} }}", entries.join(", "))
+        }
+    }
+}
@@ -1,17 +1,84 @@
+pub(crate) mod diagnostic;
 pub(crate) mod tokenize;
 pub(crate) mod ast;
+pub(crate) mod annotation;
 pub(crate) mod module;
+pub(crate) mod regex;
 pub(crate) mod assembly;
+pub(crate) mod format;
 pub use assembly::Assembly;
+pub use diagnostic::{Diagnostic, Severity, Span};
+pub use format::FormatOptions;
 
 
-pub fn parse(schema: &str) -> Assembly {
-    let tokens = tokenize::tokenize(schema);
-    let ast = ast::gen_ast(&tokens);
-    let module = module::create_module(&ast);
-    let assembly = assembly::generate(&module);
-    return assembly;
+/// Lexes and parses a schema past any errors, returning the partial AST and all syntax errors.
+pub(crate) fn parse_ast(schema: &str) -> (ast::ASTRoot, Vec<Diagnostic>) {
+    let (tokens, mut diagnostics) = tokenize::tokenize(schema);
+    let (ast, syntax_errors) = ast::gen_ast_partial(&tokens);
+    diagnostics.extend(syntax_errors);
+    (ast, diagnostics)
 }
 
+pub fn parse(schema: &str) -> Result<Assembly, Vec<Diagnostic>> {
+    let (ast, mut diagnostics) = parse_ast(schema);
+    if !diagnostics.is_empty() {
+        diagnostics.extend(module::validate(&ast, &[]));
+        return Err(diagnostics);
+    }
+    let module = module::create_module(&ast)?;
+    Ok(assembly::generate(&module))
+}
+
+/// Returns every problem found in the schema, continuing past syntax errors.
+pub fn check(schema: &str) -> Vec<Diagnostic> {
+    let (ast, mut diagnostics) = parse_ast(schema);
+    diagnostics.extend(module::validate(&ast, &[]));
+    diagnostics
+}
+
+/// Reprints a schema in the canonical layout, keeping its comments.
+pub fn format(schema: &str, options: &FormatOptions) -> Result<String, Vec<Diagnostic>> {
+    let (tokens, mut diagnostics) = tokenize::tokenize(schema);
+    match ast::gen_ast(&tokens) {
+        Ok(ast) if diagnostics.is_empty() => {
+            let formatted = format::format(&ast, options);
+            let lost = lost_comments(&tokens, &formatted);
+            if lost.is_empty() {
+                Ok(formatted)
+            } else {
+                Err(lost)
+            }
+        }
+        Ok(_) => Err(diagnostics),
+        Err(syntax_errors) => {
+            diagnostics.extend(syntax_errors);
+            Err(diagnostics)
+        }
+    }
+}
+
+/// Reports the comments of `tokens` that the formatter had no place for, such as those inside a
+/// multi-line value, rather than silently dropping them.
+fn lost_comments(tokens: &[tokenize::Token], formatted: &str) -> Vec<Diagnostic> {
+    let (kept, _) = tokenize::tokenize(formatted);
+    let mut kept = kept
+        .iter()
+        .filter(|token| token.ty == tokenize::TokenType::Comment)
+        .map(|token| token.value)
+        .collect::<Vec<_>>();
+    let mut lost = Vec::new();
+    for token in tokens.iter().filter(|token| token.ty == tokenize::TokenType::Comment) {
+        match kept.iter().position(|&value| value == token.value) {
+            Some(index) => {
+                kept.remove(index);
+            }
+            None => lost.push(Diagnostic::error(
+                token.span,
+                "The formatter can't keep this comment, move it above the field or function",
+            )),
+        }
+    }
+    lost
+}
 
 
@@ -1,20 +1,71 @@
+use super::annotation::{self, AnnotationTarget};
 use super::ast::*;
+use super::diagnostic::{Diagnostic, Severity};
+use super::tokenize::unescape;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct SchemeModule {
     pub types: Vec<SchemeType>,
+    pub services: Vec<SchemeService>,
+    pub consts: Vec<SchemeConst>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct SchemeType {
     pub name: String,
+    /// Type parameters of a generic type, e.g. `T` in `type Page<T>`.
+    pub params: Vec<String>,
+    /// Type whose fields are inherited (`type Post extends Entity`). `fields` only holds the
+    /// fields declared by this type.
+    pub base: Option<String>,
     pub fields: Vec<SchemeField>,
+    pub annotations: Vec<SchemeAnnotation>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemeConst {
+    pub name: String,
+    pub ty: BuiltinType,
+    pub value: SchemeValue,
+    pub annotations: Vec<SchemeAnnotation>,
+}
+
+/// A group of remote functions, served together.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemeService {
+    pub name: String,
+    pub functions: Vec<SchemeFunction>,
+    pub annotations: Vec<SchemeAnnotation>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemeFunction {
+    pub name: String,
+    pub params: Vec<SchemeParam>,
+    /// `None` for functions that return nothing.
+    pub returns: Option<SchemeFieldType>,
+    pub annotations: Vec<SchemeAnnotation>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemeParam {
+    pub name: String,
+    pub ty: SchemeTypeRef,
+    pub annotations: Vec<SchemeAnnotation>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct SchemeField {
     pub name: String,
     pub ty: SchemeFieldType,
+    /// Whether the field may be absent (`name?: T`).
+    pub optional: bool,
+    /// Value used when the field is absent, already checked against `ty`.
+    pub default: Option<SchemeValue>,
+    pub annotations: Vec<SchemeAnnotation>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -34,6 +85,56 @@ pub enum SchemeFieldTypeKind {
 pub enum SchemeTypeRef {
     Builtin(BuiltinType),
     Custom(String),
+    /// A generic type given its type arguments, e.g. `Page<Post>`.
+    Instance(String, Vec<SchemeTypeRef>),
+    /// A type parameter of the enclosing type.
+    Param(String),
+    Array(Box<SchemeTypeRef>),
+    Optional(Box<SchemeTypeRef>),
+    Map(Box<SchemeTypeRef>, Box<SchemeTypeRef>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemeAnnotation {
+    pub name: String,
+    pub args: Vec<SchemeAnnotationArg>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemeAnnotationArg {
+    /// Always set for known annotations, see [`annotation::KNOWN_ANNOTATIONS`].
+    pub name: Option<String>,
+    pub value: SchemeValue,
+}
+
+impl SchemeAnnotation {
+    pub fn arg(&self, name: &str) -> Option<&SchemeValue> {
+        self.args
+            .iter()
+            .find(|arg| arg.name.as_deref() == Some(name))
+            .map(|arg| &arg.value)
+    }
+}
+
+pub fn find_annotation<'a>(
+    annotations: &'a [SchemeAnnotation],
+    name: &str,
+) -> Option<&'a SchemeAnnotation> {
+    annotations
+        .iter()
+        .find(|annotation| annotation.name == name)
+}
+
+/// A literal value of a known type. Integer literals given for `float` are stored as floats.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SchemeValue {
+    Integer(i64),
+    Float(f64),
+    String(String),
+    Boolean(bool),
+    Null,
+    Array(Vec<SchemeValue>),
+    Map(Vec<(SchemeValue, SchemeValue)>),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Copy)]
@@ -43,37 +144,712 @@ pub enum BuiltinType {
     String,
     Boolean,
 }
-pub(crate) fn create_module(ast: &ASTRoot) -> SchemeModule {
+/// Builtin generic types and the number of type parameters they take.
+pub(crate) const GENERIC_BUILTINS: &[(&str, usize)] = &[("Array", 1), ("Optional", 1), ("Map", 2)];
+
+pub(crate) fn builtin_type(name: &str) -> Option<BuiltinType> {
+    match name {
+        "integer" => Some(BuiltinType::Integer),
+        "float" => Some(BuiltinType::Float),
+        "string" => Some(BuiltinType::String),
+        "boolean" => Some(BuiltinType::Boolean),
+        _ => None,
+    }
+}
+
+/// Checks the names used by the schema. `external_types` are types defined outside of `ast`
+/// (e.g. in other files of the same workspace) that may be referenced.
+pub(crate) fn validate(ast: &ASTRoot, external_types: &[&str]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    // Types that may be referenced, with their number of type parameters when it is known.
+    let mut defined: HashMap<&str, Option<usize>> =
+        external_types.iter().map(|name| (*name, None)).collect();
+    let consts = const_table(ast);
+    validate_consts(ast, &consts, &mut diagnostics);
+    let mut seen: HashSet<&str> = HashSet::new();
+    for block in &ast.blocks {
+        let ASTRootBlock::TypeDef(type_def) = block else {
+            continue;
+        };
+        let name = type_def.name.as_str();
+        if builtin_type(name).is_some() || GENERIC_BUILTINS.iter().any(|(n, _)| *n == name) {
+            diagnostics.push(Diagnostic::error(
+                type_def.name_span,
+                format!("`{name}` is a builtin type and cannot be redefined"),
+            ));
+        } else if !seen.insert(name) {
+            diagnostics.push(Diagnostic::error(
+                type_def.name_span,
+                format!("Type `{name}` is defined more than once"),
+            ));
+        }
+        defined.insert(name, Some(type_def.params.len()));
+        annotation::validate(
+            &type_def.annotations,
+            AnnotationTarget::Type,
+            None,
+            &consts,
+            &mut diagnostics,
+        );
+    }
+
+    let mut type_defs: HashMap<&str, &ASTTypeDef> = HashMap::new();
+    for type_def in ast.type_defs() {
+        type_defs.entry(&type_def.name).or_insert(type_def);
+    }
+    for block in &ast.blocks {
+        let ASTRootBlock::TypeDef(type_def) = block else {
+            continue;
+        };
+        let mut inherited = HashMap::new();
+        if let Some(base) = &type_def.extends {
+            let path = base.path.join(".");
+            if builtin_type(&path).is_some() || GENERIC_BUILTINS.iter().any(|(n, _)| *n == path) {
+                diagnostics.push(Diagnostic::error(
+                    base.span,
+                    format!("`{path}` is a builtin type and cannot be extended"),
+                ));
+            } else if !base.params.is_empty()
+                || matches!(defined.get(path.as_str()), Some(Some(arity)) if *arity > 0)
+            {
+                diagnostics.push(Diagnostic::error(
+                    base.span,
+                    format!("`{path}` is generic and cannot be extended"),
+                ));
+            } else {
+                validate_type_name(base, &defined, &[], &mut diagnostics);
+                match inherited_fields(type_def, &type_defs) {
+                    Ok(fields) => inherited = fields,
+                    Err(()) => diagnostics.push(Diagnostic::error(
+                        base.span,
+                        format!("Type `{}` extends itself", type_def.name),
+                    )),
+                }
+            }
+        }
+        validate_type_params(type_def, &defined, &mut diagnostics);
+        let mut fields: HashSet<&str> = HashSet::new();
+        for field in &type_def.fields {
+            if let Some(owner) = inherited.get(field.name.as_str()) {
+                diagnostics.push(Diagnostic::error(
+                    field.name_span,
+                    format!(
+                        "Field `{}` is already defined in base type `{owner}`",
+                        field.name
+                    ),
+                ));
+            }
+            if !fields.insert(field.name.as_str()) {
+                diagnostics.push(Diagnostic::error(
+                    field.name_span,
+                    format!(
+                        "Field `{}` is defined more than once in type `{}`",
+                        field.name, type_def.name
+                    ),
+                ));
+            }
+            let errors = diagnostics.len();
+            validate_type_name(&field.ty.name, &defined, &type_def.params, &mut diagnostics);
+            // Constraints are checked against the field type once it is known to be valid.
+            let ty = (diagnostics.len() == errors).then(|| field_type(&field.ty, &type_def.params));
+            annotation::validate(
+                &field.annotations,
+                AnnotationTarget::Field,
+                ty.as_ref(),
+                &consts,
+                &mut diagnostics,
+            );
+            if let (Some(ty), Some(value)) = (&ty, &field.default) {
+                validate_default(field, value, ty, &consts, &mut diagnostics);
+            }
+        }
+    }
+    validate_services(ast, &defined, &consts, &mut diagnostics);
+    diagnostics
+}
+
+/// Constants of a schema by name. Values in the schema may refer to them.
+pub(crate) type Consts<'a> = HashMap<&'a str, &'a ASTConst>;
+
+pub(crate) fn const_table(ast: &ASTRoot) -> Consts<'_> {
+    let mut consts = Consts::new();
+    for constant in ast.consts() {
+        consts.entry(constant.name.as_str()).or_insert(constant);
+    }
+    consts
+}
+
+/// Type of `constant`, if it is one of the builtin types constants may have.
+fn const_type(constant: &ASTConst) -> Option<BuiltinType> {
+    if !constant.ty.params.is_empty() {
+        return None;
+    }
+    builtin_type(&constant.ty.path.join("."))
+}
+
+fn validate_consts(ast: &ASTRoot, consts: &Consts, diagnostics: &mut Vec<Diagnostic>) {
+    let mut seen: HashSet<&str> = HashSet::new();
+    for constant in ast.consts() {
+        if !seen.insert(constant.name.as_str()) {
+            diagnostics.push(Diagnostic::error(
+                constant.name_span,
+                format!("Constant `{}` is defined more than once", constant.name),
+            ));
+        }
+        annotation::validate(
+            &constant.annotations,
+            AnnotationTarget::Const,
+            None,
+            consts,
+            diagnostics,
+        );
+        let Some(ty) = const_type(constant) else {
+            diagnostics.push(Diagnostic::error(
+                constant.ty.span,
+                "Constants must be of type `integer`, `float`, `string` or `boolean`",
+            ));
+            continue;
+        };
+        if let ASTValueKind::Const(_) = constant.value.kind {
+            diagnostics.push(Diagnostic::error(
+                constant.value.span,
+                "The value of a constant must be a literal",
+            ));
+        } else if let Err(err) =
+            check_value(&constant.value, &SchemeTypeRef::Builtin(ty), &Consts::new())
+        {
+            diagnostics.push(err);
+        }
+    }
+}
+
+fn validate_services(
+    ast: &ASTRoot,
+    defined: &HashMap<&str, Option<usize>>,
+    consts: &Consts,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let mut seen: HashSet<&str> = HashSet::new();
+    for service in ast.services() {
+        let name = service.name.as_str();
+        // Services and types share the namespace of the generated code.
+        if defined.contains_key(name)
+            || builtin_type(name).is_some()
+            || GENERIC_BUILTINS.iter().any(|(n, _)| *n == name)
+        {
+            diagnostics.push(Diagnostic::error(
+                service.name_span,
+                format!("Service `{name}` has the same name as a type"),
+            ));
+        } else if !seen.insert(name) {
+            diagnostics.push(Diagnostic::error(
+                service.name_span,
+                format!("Service `{name}` is defined more than once"),
+            ));
+        }
+        annotation::validate(
+            &service.annotations,
+            AnnotationTarget::Service,
+            None,
+            consts,
+            diagnostics,
+        );
+        let mut functions: HashSet<&str> = HashSet::new();
+        for function in &service.functions {
+            if !functions.insert(function.name.as_str()) {
+                diagnostics.push(Diagnostic::error(
+                    function.name_span,
+                    format!(
+                        "Function `{}` is defined more than once in service `{name}`",
+                        function.name
+                    ),
+                ));
+            }
+            annotation::validate(
+                &function.annotations,
+                AnnotationTarget::Function,
+                None,
+                consts,
+                diagnostics,
+            );
+            let mut params: HashSet<&str> = HashSet::new();
+            for param in &function.params {
+                if !params.insert(param.name.as_str()) {
+                    diagnostics.push(Diagnostic::error(
+                        param.name_span,
+                        format!(
+                            "Parameter `{}` is declared more than once in function `{}`",
+                            param.name, function.name
+                        ),
+                    ));
+                }
+                validate_type_name(&param.ty, defined, &[], diagnostics);
+                annotation::validate(
+                    &param.annotations,
+                    AnnotationTarget::Param,
+                    None,
+                    consts,
+                    diagnostics,
+                );
+            }
+            if let Some(returns) = &function.returns {
+                validate_type_name(&returns.name, defined, &[], diagnostics);
+            }
+        }
+    }
+}
+
+/// Fields inherited by `type_def` through its chain of base types, mapped to the type declaring
+/// them. Bases defined outside of `type_defs` are skipped. Fails if the chain loops.
+fn inherited_fields<'a>(
+    type_def: &ASTTypeDef,
+    type_defs: &HashMap<&str, &'a ASTTypeDef>,
+) -> Result<HashMap<&'a str, &'a str>, ()> {
+    let mut fields = HashMap::new();
+    let mut visited = HashSet::from([type_def.name.as_str()]);
+    let mut base = type_def.extends.as_ref();
+    while let Some(name) = base {
+        let Some(&base_def) = type_defs.get(name.path.join(".").as_str()) else {
+            break;
+        };
+        if !visited.insert(&base_def.name) {
+            return Err(());
+        }
+        for field in &base_def.fields {
+            fields
+                .entry(field.name.as_str())
+                .or_insert(base_def.name.as_str());
+        }
+        base = base_def.extends.as_ref();
+    }
+    Ok(fields)
+}
+
+/// Type parameters must have distinct names, unused by types, and appear in the fields: generated
+/// code cannot declare unused parameters.
+fn validate_type_params(
+    type_def: &ASTTypeDef,
+    defined: &HashMap<&str, Option<usize>>,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    for (i, param) in type_def.params.iter().enumerate() {
+        let name = param.name.as_str();
+        if type_def.params[..i].iter().any(|other| other.name == name) {
+            diagnostics.push(Diagnostic::error(
+                param.span,
+                format!("Type parameter `{name}` is declared more than once"),
+            ));
+        } else if builtin_type(name).is_some()
+            || GENERIC_BUILTINS.iter().any(|(n, _)| *n == name)
+            || defined.contains_key(name)
+        {
+            diagnostics.push(Diagnostic::error(
+                param.span,
+                format!("Type parameter `{name}` has the same name as a type"),
+            ));
+        } else if !type_def
+            .fields
+            .iter()
+            .any(|field| mentions(&field.ty.name, name))
+        {
+            diagnostics.push(Diagnostic::error(
+                param.span,
+                format!("Type parameter `{name}` is never used"),
+            ));
+        }
+    }
+}
+
+fn mentions(type_name: &ASTTypeName, name: &str) -> bool {
+    type_name.path.join(".") == name || type_name.params.iter().any(|arg| mentions(arg, name))
+}
+
+/// `params` are the type parameters of the enclosing type.
+fn validate_type_name(
+    name: &ASTTypeName,
+    defined: &HashMap<&str, Option<usize>>,
+    params: &[ASTTypeParam],
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let path = name.path.join(".");
+    let arity = if builtin_type(&path).is_some() {
+        Some(0)
+    } else if let Some(arity) = defined.get(path.as_str()) {
+        *arity
+    } else if let Some((_, arity)) = GENERIC_BUILTINS.iter().find(|(n, _)| *n == path) {
+        Some(*arity)
+    } else if params.iter().any(|param| param.name == path) {
+        if !name.params.is_empty() {
+            diagnostics.push(Diagnostic::error(
+                name.span,
+                format!("Type parameter `{path}` cannot take type parameters"),
+            ));
+        }
+        return;
+    } else {
+        diagnostics.push(Diagnostic::error(
+            name.span,
+            format!("Unknown type `{path}`"),
+        ));
+        return;
+    };
+    // The arity of types from other files is not known here.
+    match arity {
+        Some(arity) if name.params.len() != arity => {
+            diagnostics.push(Diagnostic::error(
+                name.span,
+                format!(
+                    "`{path}` expects {arity} type parameter(s), found {}",
+                    name.params.len()
+                ),
+            ));
+        }
+        _ => {}
+    }
+    for param in &name.params {
+        validate_type_name(param, defined, params, diagnostics);
+    }
+}
+
+pub(crate) fn create_module(ast: &ASTRoot) -> Result<SchemeModule, Vec<Diagnostic>> {
+    let mut diagnostics = validate(ast, &[]);
+    if diagnostics.iter().any(|d| d.severity == Severity::Error) {
+        return Err(diagnostics);
+    }
+    let consts = const_table(ast);
     let mut types = Vec::new();
+    let mut services = Vec::new();
+    let mut scheme_consts = Vec::new();
     for block in &ast.blocks {
         match block {
             ASTRootBlock::TypeDef(type_def) => {
                 types.push(SchemeType {
                     name: type_def.name.clone(),
+                    params: type_def
+                        .params
+                        .iter()
+                        .map(|param| param.name.clone())
+                        .collect(),
+                    base: type_def.extends.as_ref().map(|base| base.path.join(".")),
                     fields: type_def
                         .fields
                         .iter()
-                        .map(|x| SchemeField {
-                            name: x.name.clone(),
-                            ty: SchemeFieldType {
-                                kind: match &x.ty.kind {
-                                    ASTTypeKind::Normal => SchemeFieldTypeKind::Normal,
-                                    ASTTypeKind::Stream => SchemeFieldTypeKind::Stream,
-                                    ASTTypeKind::Sync => SchemeFieldTypeKind::Sync,
-                                },
-                                ty_ref: match x.ty.name.as_str() {
-                                    "integer" => SchemeTypeRef::Builtin(BuiltinType::Integer),
-                                    "float" => SchemeTypeRef::Builtin(BuiltinType::Float),
-                                    "string" => SchemeTypeRef::Builtin(BuiltinType::String),
-                                    "boolean" => SchemeTypeRef::Builtin(BuiltinType::Boolean),
-                                    _ => SchemeTypeRef::Custom(x.ty.name.clone()),
-                                },
-                            },
+                        .map(|x| {
+                            let ty = field_type(&x.ty, &type_def.params);
+                            // Default values were checked by `validate`.
+                            let default = x.default.as_ref().and_then(|value| {
+                                check_default(value, x.optional, &ty, &consts).ok()
+                            });
+                            let annotations = annotation::resolve_all(
+                                &x.annotations,
+                                AnnotationTarget::Field,
+                                Some(&ty),
+                                &consts,
+                            );
+                            SchemeField {
+                                name: x.name.clone(),
+                                ty,
+                                optional: x.optional,
+                                default,
+                                annotations,
+                            }
                         })
                         .collect::<_>(),
+                    annotations: annotation::resolve_all(
+                        &type_def.annotations,
+                        AnnotationTarget::Type,
+                        None,
+                        &consts,
+                    ),
                 });
             }
+            ASTRootBlock::Service(service) => services.push(SchemeService {
+                name: service.name.clone(),
+                functions: service
+                    .functions
+                    .iter()
+                    .map(|function| SchemeFunction {
+                        name: function.name.clone(),
+                        params: function
+                            .params
+                            .iter()
+                            .map(|param| SchemeParam {
+                                name: param.name.clone(),
+                                ty: resolve_type_ref(&param.ty, &[]),
+                                annotations: annotation::resolve_all(
+                                    &param.annotations,
+                                    AnnotationTarget::Param,
+                                    None,
+                                    &consts,
+                                ),
+                            })
+                            .collect(),
+                        returns: function.returns.as_ref().map(|ty| field_type(ty, &[])),
+                        annotations: annotation::resolve_all(
+                            &function.annotations,
+                            AnnotationTarget::Function,
+                            None,
+                            &consts,
+                        ),
+                    })
+                    .collect(),
+                annotations: annotation::resolve_all(
+                    &service.annotations,
+                    AnnotationTarget::Service,
+                    None,
+                    &consts,
+                ),
+            }),
+            ASTRootBlock::Const(constant) => {
+                let ty = const_type(constant).expect("Constant types are validated");
+                match check_value(&constant.value, &SchemeTypeRef::Builtin(ty), &consts) {
+                    Ok(value) => scheme_consts.push(SchemeConst {
+                        name: constant.name.clone(),
+                        ty,
+                        value,
+                        annotations: annotation::resolve_all(
+                            &constant.annotations,
+                            AnnotationTarget::Const,
+                            None,
+                            &consts,
+                        ),
+                    }),
+                    Err(err) => diagnostics.push(err),
+                }
+            }
+            ASTRootBlock::Comment(_) => {}
+        }
+    }
+    if diagnostics.iter().any(|d| d.severity == Severity::Error) {
+        return Err(diagnostics);
+    }
+    Ok(SchemeModule {
+        types,
+        services,
+        consts: scheme_consts,
+    })
+}
+
+fn field_type(ty: &ASTType, params: &[ASTTypeParam]) -> SchemeFieldType {
+    SchemeFieldType {
+        kind: match &ty.kind {
+            ASTTypeKind::Normal => SchemeFieldTypeKind::Normal,
+            ASTTypeKind::Stream => SchemeFieldTypeKind::Stream,
+            ASTTypeKind::Sync => SchemeFieldTypeKind::Sync,
+        },
+        ty_ref: resolve_type_ref(&ty.name, params),
+    }
+}
+
+/// Checks the default value of a field against its type and constraints.
+fn validate_default(
+    field: &ASTField,
+    value: &ASTValue,
+    ty: &SchemeFieldType,
+    consts: &Consts,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    match check_default(value, field.optional, ty, consts) {
+        Ok(default) => {
+            let annotations = annotation::resolve_all(
+                &field.annotations,
+                AnnotationTarget::Field,
+                Some(ty),
+                consts,
+            );
+            if let Err(message) = annotation::check_constraints(&annotations, &default) {
+                diagnostics.push(Diagnostic::error(value.span, message));
+            }
         }
+        Err(err) => diagnostics.push(err),
+    }
+}
+
+fn check_default(
+    value: &ASTValue,
+    optional: bool,
+    ty: &SchemeFieldType,
+    consts: &Consts,
+) -> Result<SchemeValue, Diagnostic> {
+    if optional {
+        // A default value would always take the place of an absent field.
+        return Err(Diagnostic::error(
+            value.span,
+            "Fields that may be absent (`?`) cannot have a default value",
+        ));
     }
-    SchemeModule { types }
-}
\ No newline at end of file
+    if ty.kind == SchemeFieldTypeKind::Stream {
+        return Err(Diagnostic::error(
+            value.span,
+            "`stream` fields cannot have a default value",
+        ));
+    }
+    check_value(value, &ty.ty_ref, consts)
+}
+
+/// Converts a literal whose type is not known in advance, e.g. an annotation argument.
+pub(crate) fn literal_value(value: &ASTValue, consts: &Consts) -> Result<SchemeValue, Diagnostic> {
+    Ok(match &value.kind {
+        ASTValueKind::String(raw) => SchemeValue::String(unescape(raw)),
+        ASTValueKind::Integer(_) => {
+            check_value(value, &SchemeTypeRef::Builtin(BuiltinType::Integer), consts)?
+        }
+        ASTValueKind::Float(_) => {
+            check_value(value, &SchemeTypeRef::Builtin(BuiltinType::Float), consts)?
+        }
+        ASTValueKind::Boolean(value) => SchemeValue::Boolean(*value),
+        ASTValueKind::Null => SchemeValue::Null,
+        ASTValueKind::Array(items) => SchemeValue::Array(
+            items
+                .iter()
+                .map(|item| literal_value(item, consts))
+                .collect::<Result<_, _>>()?,
+        ),
+        ASTValueKind::Map(entries) => SchemeValue::Map(
+            entries
+                .iter()
+                .map(|(k, v)| Ok((literal_value(k, consts)?, literal_value(v, consts)?)))
+                .collect::<Result<_, _>>()?,
+        ),
+        ASTValueKind::Const(name) => {
+            let Some(constant) = consts.get(name.as_str()) else {
+                return Err(Diagnostic::error(
+                    value.span,
+                    format!("Unknown constant `{name}`"),
+                ));
+            };
+            let ty = const_type(constant).ok_or_else(|| {
+                Diagnostic::error(
+                    value.span,
+                    format!("Constant `{name}` does not have a valid value"),
+                )
+            })?;
+            check_value(value, &SchemeTypeRef::Builtin(ty), consts)?
+        }
+    })
+}
+
+/// Converts a literal to a value of type `ty_ref`, or reports the first mismatch. Names of
+/// constants stand for their value.
+pub(crate) fn check_value(
+    value: &ASTValue,
+    ty_ref: &SchemeTypeRef,
+    consts: &Consts,
+) -> Result<SchemeValue, Diagnostic> {
+    let mismatch = || Diagnostic::error(value.span, format!("Expected a value of type `{ty_ref}`"));
+    let integer = |raw: &str| {
+        raw.parse::<i64>().map_err(|_| {
+            Diagnostic::error(
+                value.span,
+                format!("Integer literal `{raw}` is out of range"),
+            )
+        })
+    };
+    match (&value.kind, ty_ref) {
+        (ASTValueKind::Null, SchemeTypeRef::Optional(_)) => Ok(SchemeValue::Null),
+        (_, SchemeTypeRef::Optional(inner)) => check_value(value, inner, consts),
+        (ASTValueKind::Const(name), ty_ref) => {
+            let Some(constant) = consts.get(name.as_str()) else {
+                return Err(Diagnostic::error(
+                    value.span,
+                    format!("Unknown constant `{name}`"),
+                ));
+            };
+            // Like integer literals, integer constants may be used as floats.
+            let fits = match (const_type(constant), ty_ref) {
+                (Some(BuiltinType::Integer), SchemeTypeRef::Builtin(BuiltinType::Float)) => true,
+                (Some(ty), ty_ref) => SchemeTypeRef::Builtin(ty) == *ty_ref,
+                (None, _) => false,
+            };
+            if !fits {
+                return Err(Diagnostic::error(
+                    value.span,
+                    format!("Constant `{name}` is not of type `{ty_ref}`"),
+                ));
+            }
+            // Constant values are literals, which are reported where the constant is defined.
+            check_value(&constant.value, ty_ref, &Consts::new()).map_err(|_| {
+                Diagnostic::error(
+                    value.span,
+                    format!("Constant `{name}` does not have a valid value"),
+                )
+            })
+        }
+        (ASTValueKind::Integer(raw), SchemeTypeRef::Builtin(BuiltinType::Integer)) => {
+            integer(raw).map(SchemeValue::Integer)
+        }
+        (
+            ASTValueKind::Integer(raw) | ASTValueKind::Float(raw),
+            SchemeTypeRef::Builtin(BuiltinType::Float),
+        ) => raw
+            .parse::<f64>()
+            .ok()
+            .filter(|value| value.is_finite())
+            .map(SchemeValue::Float)
+            .ok_or_else(|| {
+                Diagnostic::error(value.span, format!("Float literal `{raw}` is out of range"))
+            }),
+        (ASTValueKind::String(raw), SchemeTypeRef::Builtin(BuiltinType::String)) => {
+            Ok(SchemeValue::String(unescape(raw)))
+        }
+        (ASTValueKind::Boolean(value), SchemeTypeRef::Builtin(BuiltinType::Boolean)) => {
+            Ok(SchemeValue::Boolean(*value))
+        }
+        (ASTValueKind::Array(items), SchemeTypeRef::Array(item)) => items
+            .iter()
+            .map(|value| check_value(value, item, consts))
+            .collect::<Result<_, _>>()
+            .map(SchemeValue::Array),
+        (ASTValueKind::Map(entries), SchemeTypeRef::Map(key, item)) => entries
+            .iter()
+            .map(|(k, v)| Ok((check_value(k, key, consts)?, check_value(v, item, consts)?)))
+            .collect::<Result<_, _>>()
+            .map(SchemeValue::Map),
+        _ => Err(mismatch()),
+    }
+}
+
+/// Prints the type the way it is written in a schema.
+impl fmt::Display for SchemeTypeRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemeTypeRef::Builtin(BuiltinType::Integer) => f.write_str("integer"),
+            SchemeTypeRef::Builtin(BuiltinType::Float) => f.write_str("float"),
+            SchemeTypeRef::Builtin(BuiltinType::String) => f.write_str("string"),
+            SchemeTypeRef::Builtin(BuiltinType::Boolean) => f.write_str("boolean"),
+            SchemeTypeRef::Custom(name) | SchemeTypeRef::Param(name) => f.write_str(name),
+            SchemeTypeRef::Instance(name, args) => {
+                let args = args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+                write!(f, "{name}<{}>", args.join(", "))
+            }
+            SchemeTypeRef::Array(item) => write!(f, "Array<{item}>"),
+            SchemeTypeRef::Optional(item) => write!(f, "Optional<{item}>"),
+            SchemeTypeRef::Map(key, value) => write!(f, "Map<{key}, {value}>"),
+        }
+    }
+}
+
+/// `params` are the type parameters of the enclosing type.
+fn resolve_type_ref(name: &ASTTypeName, params: &[ASTTypeParam]) -> SchemeTypeRef {
+    let path = name.path.join(".");
+    let param = |i: usize| Box::new(resolve_type_ref(&name.params[i], params));
+    if let Some(builtin) = builtin_type(&path) {
+        return SchemeTypeRef::Builtin(builtin);
+    }
+    if params.iter().any(|param| param.name == path) {
+        return SchemeTypeRef::Param(path);
+    }
+    match path.as_str() {
+        "Array" => SchemeTypeRef::Array(param(0)),
+        "Optional" => SchemeTypeRef::Optional(param(0)),
+        "Map" => SchemeTypeRef::Map(param(0), param(1)),
+        _ if name.params.is_empty() => SchemeTypeRef::Custom(path),
+        _ => SchemeTypeRef::Instance(
+            path,
+            name.params
+                .iter()
+                .map(|arg| resolve_type_ref(arg, params))
+                .collect(),
+        ),
+    }
+}
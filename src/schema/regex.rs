@@ -0,0 +1,401 @@
+//! The regular expressions of `@pattern`, which every target checks with its own engine: Rust's
+//! `regex`, JavaScript's `RegExp` with the `u` flag, and pydantic's default engine in Python, which
+//! is Rust's `regex` again. Only the syntax these engines share is accepted, so that a pattern
+//! compiles everywhere, and default values are matched here before any code is generated.
+//!
+//! Beyond compiling, a pattern must mean the same to every engine:
+//!
+//! - `\d`, `\w`, `\s` and `\b` are rejected: the first three only cover ASCII in JavaScript but
+//!   all of Unicode in Rust, and the spaces of `\s` differ slightly. Classes such as `[0-9]` or
+//!   `[A-Za-z0-9_]` say which characters are meant.
+//! - `^` and `$` only match at the start and the end of the text, without multi-line mode.
+//!   Python's `re` would also match `$` before a final line break, which is why the generated
+//!   models keep to pydantic's default engine.
+//! - `.` matches any character but `\n`, while JavaScript also leaves out `\r`, `\u2028` and
+//!   `\u2029`. [`javascript`] rewrites it for JavaScript and JSON Schema.
+
+use std::cell::Cell;
+
+/// A parsed regular expression.
+#[derive(Debug)]
+pub(crate) struct Regex {
+    root: Node,
+}
+
+#[derive(Debug)]
+enum Node {
+    Char(char),
+    /// `.`, any character but a line break.
+    Any,
+    Class(Class),
+    /// `^`
+    Start,
+    /// `$`
+    End,
+    /// Alternatives, each a sequence of nodes.
+    Group(Vec<Vec<Node>>),
+    Repeat {
+        node: Box<Node>,
+        min: u32,
+        max: Option<u32>,
+        greedy: bool,
+    },
+}
+
+#[derive(Debug)]
+struct Class {
+    negated: bool,
+    /// Inclusive ranges of characters.
+    ranges: Vec<(char, char)>,
+}
+
+/// Characters that keep their meaning when escaped, in and out of classes.
+const ESCAPABLE: &str = "^$\\.*+?()[]{}|/";
+
+/// Most steps taken to match a default value, as backtracking through a pattern like `(a|a)*b`
+/// takes exponential time.
+const MAX_STEPS: usize = 100_000;
+
+impl Regex {
+    /// Parses `pattern`, or describes the first construct that not every target supports.
+    pub(crate) fn new(pattern: &str) -> Result<Regex, String> {
+        let mut parser = Parser {
+            chars: pattern.chars().collect(),
+            pos: 0,
+        };
+        let root = parser.alternatives()?;
+        match parser.peek() {
+            None => Ok(Regex {
+                root: Node::Group(root),
+            }),
+            Some(')') => Err("Unmatched `)`".to_string()),
+            Some(c) => Err(format!("Unexpected `{c}`")),
+        }
+    }
+
+    /// Whether `text` contains a match of the expression, as `@pattern` requires, or `None` if
+    /// that takes more than [`MAX_STEPS`].
+    pub(crate) fn is_match(&self, text: &str) -> Option<bool> {
+        let matcher = Matcher {
+            text: text.chars().collect(),
+            steps: Cell::new(0),
+        };
+        let found =
+            (0..=matcher.text.len()).any(|start| matcher.matches(&self.root, start, &mut |_| true));
+        (matcher.steps.get() <= MAX_STEPS).then_some(found)
+    }
+}
+
+/// Rewrites a pattern for JavaScript engines, whose `.` does not match every character that the
+/// other targets' `.` does. The pattern must be valid.
+pub(crate) fn javascript(pattern: &str) -> String {
+    let mut out = String::new();
+    let mut chars = pattern.chars();
+    let mut in_class = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                out.push(c);
+                out.extend(chars.next());
+            }
+            '[' => {
+                in_class = true;
+                out.push(c);
+            }
+            ']' => {
+                in_class = false;
+                out.push(c);
+            }
+            '.' if !in_class => out.push_str("[^\\n]"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.pos += 1;
+        c
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let eaten = self.peek() == Some(c);
+        if eaten {
+            self.pos += 1;
+        }
+        eaten
+    }
+
+    fn alternatives(&mut self) -> Result<Vec<Vec<Node>>, String> {
+        let mut alternatives = vec![self.sequence()?];
+        while self.eat('|') {
+            alternatives.push(self.sequence()?);
+        }
+        Ok(alternatives)
+    }
+
+    fn sequence(&mut self) -> Result<Vec<Node>, String> {
+        let mut nodes = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let node = self.atom()?;
+            nodes.push(self.quantified(node)?);
+        }
+        Ok(nodes)
+    }
+
+    fn atom(&mut self) -> Result<Node, String> {
+        Ok(match self.next().unwrap() {
+            '.' => Node::Any,
+            '^' => Node::Start,
+            '$' => Node::End,
+            '(' => {
+                if self.eat('?') && !self.eat(':') {
+                    return Err(
+                        "Only non-capturing groups `(?:...)` are supported after `(?`".to_string(),
+                    );
+                }
+                let alternatives = self.alternatives()?;
+                if !self.eat(')') {
+                    return Err("Unclosed group, expected `)`".to_string());
+                }
+                Node::Group(alternatives)
+            }
+            '[' => Node::Class(self.class()?),
+            '\\' => Node::Char(self.escape(false)?),
+            c @ ('*' | '+' | '?' | '{') => {
+                return Err(format!("`{c}` does not follow anything to repeat"));
+            }
+            ']' | '}' => return Err("Unescaped `]` or `}`".to_string()),
+            c => Node::Char(c),
+        })
+    }
+
+    /// The character of an escape sequence after `\`.
+    fn escape(&mut self, in_class: bool) -> Result<char, String> {
+        let Some(c) = self.next() else {
+            return Err("Trailing `\\`".to_string());
+        };
+        match c {
+            'd' | 'D' => Err(unportable(c, "[0-9]")),
+            'w' | 'W' | 'b' | 'B' => Err(unportable(c, "[A-Za-z0-9_]")),
+            's' | 'S' => Err(unportable(c, "[ \\t\\n\\r\\f\\v]")),
+            'n' => Ok('\n'),
+            't' => Ok('\t'),
+            'r' => Ok('\r'),
+            'f' => Ok('\x0C'),
+            'v' => Ok('\x0B'),
+            'x' => self.hex(2),
+            'u' => self.hex(4),
+            '-' if in_class => Ok('-'),
+            c if ESCAPABLE.contains(c) => Ok(c),
+            c => Err(format!("Unsupported escape sequence `\\{c}`")),
+        }
+    }
+
+    fn hex(&mut self, digits: usize) -> Result<char, String> {
+        let start = self.pos;
+        let end = (start + digits).min(self.chars.len());
+        self.pos = end;
+        let hex = self.chars[start..end].iter().collect::<String>();
+        match (end - start == digits, u32::from_str_radix(&hex, 16).ok()) {
+            (true, Some(code)) => char::from_u32(code)
+                .ok_or_else(|| format!("`{hex}` is not the code of a character")),
+            _ => Err(format!(
+                "Expected {digits} hexadecimal digits in an escape sequence"
+            )),
+        }
+    }
+
+    fn class(&mut self) -> Result<Class, String> {
+        let negated = self.eat('^');
+        let mut ranges = Vec::new();
+        if self.peek() == Some(']') {
+            return Err("Empty character class, a `]` in a class must be escaped".to_string());
+        }
+        loop {
+            let low = match self.next() {
+                None => return Err("Unclosed character class, expected `]`".to_string()),
+                Some(']') => return Ok(Class { negated, ranges }),
+                Some('[') => return Err("A `[` in a character class must be escaped".to_string()),
+                Some('\\') => self.escape(true)?,
+                Some(c) => c,
+            };
+            // `-` between two characters makes a range, and is literal elsewhere.
+            if self.peek() != Some('-') || matches!(self.chars.get(self.pos + 1), Some(']') | None)
+            {
+                ranges.push((low, low));
+                continue;
+            }
+            self.pos += 1;
+            let high = match self.next().unwrap() {
+                '\\' => self.escape(true)?,
+                c => c,
+            };
+            if high < low {
+                return Err(format!("Range `{low}-{high}` is out of order"));
+            }
+            ranges.push((low, high));
+        }
+    }
+
+    fn quantified(&mut self, node: Node) -> Result<Node, String> {
+        let (min, max) = match self.peek() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => {
+                self.pos += 1;
+                let min = self.number()?;
+                let max = match self.eat(',') {
+                    true if self.peek() == Some('}') => None,
+                    true => Some(self.number()?),
+                    false => Some(min),
+                };
+                if self.peek() != Some('}') {
+                    return Err("Unclosed repetition, expected `}`".to_string());
+                }
+                if max.is_some_and(|max| max < min) {
+                    return Err(format!(
+                        "Repetition `{{{min},{}}}` is out of order",
+                        max.unwrap()
+                    ));
+                }
+                (min, max)
+            }
+            _ => return Ok(node),
+        };
+        self.pos += 1;
+        if matches!(node, Node::Start | Node::End) {
+            return Err("Anchors cannot be repeated".to_string());
+        }
+        let greedy = !self.eat('?');
+        if matches!(self.peek(), Some('*' | '+' | '?' | '{')) {
+            return Err("A repetition cannot be repeated, wrap it in a group".to_string());
+        }
+        Ok(Node::Repeat {
+            node: Box::new(node),
+            min,
+            max,
+            greedy,
+        })
+    }
+
+    fn number(&mut self) -> Result<u32, String> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos]
+            .iter()
+            .collect::<String>()
+            .parse()
+            .map_err(|_| "Expected a number of repetitions in `{...}`".to_string())
+    }
+}
+
+/// Why `\{c}` is rejected, and the class to write instead.
+fn unportable(c: char, class: &str) -> String {
+    format!("`\\{c}` means different characters to each target, write a class such as `{class}`")
+}
+
+struct Matcher {
+    text: Vec<char>,
+    /// Number of nodes tried so far, which stops the match once it exceeds [`MAX_STEPS`].
+    steps: Cell<usize>,
+}
+
+impl Matcher {
+    /// Whether `node` matches the text from `pos`, with the rest of the expression matching from
+    /// where it ends, as told by `next`. Alternatives and repetitions backtrack through `next`.
+    fn matches(&self, node: &Node, pos: usize, next: &mut dyn FnMut(usize) -> bool) -> bool {
+        self.steps.set(self.steps.get() + 1);
+        if self.steps.get() > MAX_STEPS {
+            return false;
+        }
+        let char = |test: &dyn Fn(char) -> bool| self.text.get(pos).is_some_and(|c| test(*c));
+        match node {
+            Node::Char(c) => char(&|other| other == *c) && next(pos + 1),
+            Node::Any => char(&|c| c != '\n') && next(pos + 1),
+            Node::Class(class) => {
+                let in_class = |c: char| {
+                    class
+                        .ranges
+                        .iter()
+                        .any(|(low, high)| (*low..=*high).contains(&c))
+                        != class.negated
+                };
+                char(&in_class) && next(pos + 1)
+            }
+            Node::Start => pos == 0 && next(pos),
+            Node::End => pos == self.text.len() && next(pos),
+            Node::Group(alternatives) => alternatives
+                .iter()
+                .any(|sequence| self.matches_sequence(sequence, pos, next)),
+            Node::Repeat {
+                node,
+                min,
+                max,
+                greedy,
+            } => self.repeat(node, *min, *max, *greedy, pos, next),
+        }
+    }
+
+    fn matches_sequence(
+        &self,
+        sequence: &[Node],
+        pos: usize,
+        next: &mut dyn FnMut(usize) -> bool,
+    ) -> bool {
+        match sequence.split_first() {
+            None => next(pos),
+            Some((first, rest)) => self.matches(first, pos, &mut |end| {
+                self.matches_sequence(rest, end, next)
+            }),
+        }
+    }
+
+    fn repeat(
+        &self,
+        node: &Node,
+        min: u32,
+        max: Option<u32>,
+        greedy: bool,
+        pos: usize,
+        next: &mut dyn FnMut(usize) -> bool,
+    ) -> bool {
+        if max == Some(0) {
+            return next(pos);
+        }
+        let again = |end: usize, next: &mut dyn FnMut(usize) -> bool| {
+            // A repetition that matched nothing would match nothing forever.
+            (end > pos || min > 0)
+                && self.repeat(
+                    node,
+                    min.saturating_sub(1),
+                    max.map(|max| max - 1),
+                    greedy,
+                    end,
+                    next,
+                )
+        };
+        match (min > 0, greedy) {
+            (true, _) => self.matches(node, pos, &mut |end| again(end, next)),
+            (false, true) => self.matches(node, pos, &mut |end| again(end, next)) || next(pos),
+            (false, false) => next(pos) || self.matches(node, pos, &mut |end| again(end, next)),
+        }
+    }
+}
@@ -1,85 +1,259 @@
+use super::diagnostic::{Diagnostic, Span};
+
 const KEYWORDS: &[&str] = &["type", "stream", "sync"];
-const PUNCTUATIONS: &[char] = &['{', '}', '.', ',', ':', ';', '<', '>'];
+const PUNCTUATIONS: &[char] = &[
+    '{', '}', '.', ',', ':', ';', '<', '>', '=', '[', ']', '(', ')', '@', '?',
+];
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) enum TokenType {
     Keyword,
     Identifier,
     Punctuation,
-    Defer,
+    Comment,
+    /// A double-quoted string; the token value includes the quotes and raw escapes, see
+    /// [`unescape`].
+    String,
+    Integer,
+    Float,
+    /// `true` or `false`.
+    Boolean,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub(crate) struct Token {
+/// A token borrowing its text from the schema source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Token<'a> {
     pub ty: TokenType,
-    pub value: String,
+    pub value: &'a str,
+    pub span: Span,
+    /// Whether the token is the first of its line, which tells comments after a declaration from
+    /// comments on the lines before the next one.
+    pub line_start: bool,
+}
+
+/// Streaming lexer over a schema source. Characters that cannot start a token are reported in
+/// `diagnostics` and skipped.
+pub(crate) struct Lexer<'a> {
+    source: &'a str,
+    pos: usize,
+    /// Whether no token was read since the last line break.
+    line_start: bool,
+    pub diagnostics: Vec<Diagnostic>,
 }
 
-impl Token {
-    fn new() -> Self {
+impl<'a> Lexer<'a> {
+    pub fn new(source: &'a str) -> Self {
         Self {
-            ty: TokenType::Defer,
-            value: String::new(),
+            source,
+            pos: if source.starts_with('\u{feff}') { 3 } else { 0 },
+            line_start: true,
+            diagnostics: Vec::new(),
         }
     }
-    fn push(&mut self, c: char) {
-        self.value.push(c);
+
+    fn token(&mut self, ty: TokenType, start: usize, end: usize) -> Token<'a> {
+        self.pos = end;
+        Token {
+            ty,
+            value: &self.source[start..end],
+            span: Span::new(start, end),
+            line_start: std::mem::take(&mut self.line_start),
+        }
     }
-    fn is_empty(&self) -> bool {
-        self.value.is_empty()
+
+    /// End of the run of characters starting at `start` that satisfy `pred`.
+    fn scan_while(&self, start: usize, pred: impl Fn(char) -> bool) -> usize {
+        self.source[start..]
+            .char_indices()
+            .find(|&(_, c)| !pred(c))
+            .map_or(self.source.len(), |(i, _)| start + i)
     }
-    fn finish(&mut self) {
-        if self.value.is_empty() {
-            panic!("Token is empty");
+}
+
+impl<'a> Lexer<'a> {
+    fn starts_signed_number(&self, start: usize) -> bool {
+        let mut chars = self.source[start..].chars();
+        matches!(chars.next(), Some('-' | '+')) && chars.next().is_some_and(|c| c.is_ascii_digit())
+    }
+
+    fn string(&mut self, start: usize) -> Token<'a> {
+        let mut chars = self.source[start + 1..].char_indices();
+        while let Some((i, c)) = chars.next() {
+            let pos = start + 1 + i;
+            match c {
+                '"' => return self.token(TokenType::String, start, pos + 1),
+                '\\' => match chars.next() {
+                    Some((_, '"' | '\\' | 'n' | 'r' | 't' | '0')) => {}
+                    Some((_, 'u')) => {
+                        let rest = &self.source[pos + 2..];
+                        let code = rest
+                            .strip_prefix('{')
+                            .and_then(|rest| rest.split_once('}'))
+                            .map(|(hex, _)| hex);
+                        match code.and_then(|hex| u32::from_str_radix(hex, 16).ok()) {
+                            Some(code) if char::from_u32(code).is_some() => {
+                                // Skip `{`, the hex digits and `}`, all of them ASCII.
+                                let len = rest.find('}').map_or(0, |i| i + 1);
+                                for _ in 0..len {
+                                    chars.next();
+                                }
+                            }
+                            _ => self.diagnostics.push(Diagnostic::error(
+                                Span::new(pos, pos + 2),
+                                "Invalid unicode escape, expected `\\u{XXXX}`",
+                            )),
+                        }
+                    }
+                    Some((j, '\n')) | Some((j, '\r')) => {
+                        let end = start + 1 + j;
+                        return self.unterminated_string(start, end);
+                    }
+                    Some((_, escape)) => self.diagnostics.push(Diagnostic::error(
+                        Span::new(pos, pos + 1 + escape.len_utf8()),
+                        format!("Unknown escape sequence `\\{}`", escape.escape_debug()),
+                    )),
+                    None => break,
+                },
+                '\n' | '\r' => return self.unterminated_string(start, pos),
+                _ => {}
+            }
         }
-        if self.ty != TokenType::Defer {
-            panic!("Token has been finished already");
+        self.unterminated_string(start, self.source.len())
+    }
+
+    fn unterminated_string(&mut self, start: usize, end: usize) -> Token<'a> {
+        self.diagnostics.push(Diagnostic::error(
+            Span::new(start, end),
+            "Unterminated string literal",
+        ));
+        self.token(TokenType::String, start, end)
+    }
+
+    /// `[+-]digits[.digits][(e|E)[+-]digits]`. Letters glued to the number make it an invalid
+    /// identifier instead.
+    fn number(&mut self, start: usize) -> Token<'a> {
+        let digits = |lexer: &Self, from: usize| lexer.scan_while(from, |c| c.is_ascii_digit());
+        let sign = usize::from(!self.source[start..].starts_with(|c: char| c.is_ascii_digit()));
+        let mut end = digits(self, start + sign);
+        let mut ty = TokenType::Integer;
+        let rest = &self.source[end..];
+        if rest.starts_with('.') && rest[1..].starts_with(|c: char| c.is_ascii_digit()) {
+            end = digits(self, end + 1);
+            ty = TokenType::Float;
         }
-        if KEYWORDS.contains(&self.value.as_str()) {
-            self.ty = TokenType::Keyword;
-        } else if PUNCTUATIONS.contains(&self.value.chars().next().unwrap()) {
-            if self.value.len() != 1 {
-                panic!("Punctuation token must be a single character");
+        let rest = &self.source[end..];
+        if let Some(exponent) = rest.strip_prefix(['e', 'E']) {
+            let sign = usize::from(exponent.starts_with(['+', '-']));
+            if exponent[sign..].starts_with(|c: char| c.is_ascii_digit()) {
+                end = digits(self, end + 1 + sign);
+                ty = TokenType::Float;
             }
-            self.ty = TokenType::Punctuation;
-        } else {
-            self.ty = TokenType::Identifier;
         }
+        if self.source[end..].starts_with(is_identifier_continue) {
+            end = self.scan_while(end, is_identifier_continue);
+            self.diagnostics.push(Diagnostic::error(
+                Span::new(start, end),
+                "Identifiers cannot start with a digit",
+            ));
+            return self.token(TokenType::Identifier, start, end);
+        }
+        self.token(ty, start, end)
     }
 }
 
-pub(crate) fn tokenize(schema: &str) -> Vec<Token> {
-    let mut tokens = Vec::new();
-    let mut current_token = Token::new();
-    for c in schema.chars() {
-        match c {
-            ' ' | '\t' | '\n' => {
-                if !current_token.is_empty() {
-                    current_token.finish();
-                    tokens.push(current_token);
-                    current_token = Token::new();
-                }
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Token<'a>> {
+        loop {
+            let start = self.pos;
+            let c = self.source[start..].chars().next()?;
+            if c.is_whitespace() {
+                self.pos = self.scan_while(start, char::is_whitespace);
+                self.line_start |= self.source[start..self.pos].contains('\n');
+            } else if self.source[start..].starts_with("//") {
+                let end = self.scan_while(start, |c| c != '\n');
+                let value = self.source[start..end].trim_end();
+                let token = self.token(TokenType::Comment, start, start + value.len());
+                self.pos = end;
+                return Some(token);
+            } else if self.source[start..].starts_with("->") {
+                return Some(self.token(TokenType::Punctuation, start, start + 2));
+            } else if PUNCTUATIONS.contains(&c) {
+                return Some(self.token(TokenType::Punctuation, start, start + 1));
+            } else if c == '"' {
+                return Some(self.string(start));
+            } else if c.is_ascii_digit() || self.starts_signed_number(start) {
+                return Some(self.number(start));
+            } else if is_identifier_start(c) {
+                let end = self.scan_while(start, is_identifier_continue);
+                let ty = match &self.source[start..end] {
+                    "true" | "false" => TokenType::Boolean,
+                    word if KEYWORDS.contains(&word) => TokenType::Keyword,
+                    _ => TokenType::Identifier,
+                };
+                return Some(self.token(ty, start, end));
+            } else {
+                self.pos = start + c.len_utf8();
+                self.diagnostics.push(Diagnostic::error(
+                    Span::new(start, self.pos),
+                    format!("Invalid character `{}`", c.escape_debug()),
+                ));
             }
-            '{' | '}' | '.' | ',' | ':' | ';' | '<' | '>' => {
-                if !current_token.is_empty() {
-                    current_token.finish();
-                    tokens.push(current_token);
-                    current_token = Token::new();
+        }
+    }
+}
+
+pub(crate) fn is_identifier_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
+}
+
+pub(crate) fn is_identifier_continue(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Decodes the escapes of a string literal token (including its quotes). Invalid escapes have
+/// already been reported by the lexer and are kept verbatim.
+pub(crate) fn unescape(raw: &str) -> String {
+    let inner = raw.strip_prefix('"').unwrap_or(raw);
+    let inner = inner.strip_suffix('"').unwrap_or(inner);
+    let mut out = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('t') => out.push('\t'),
+            Some('0') => out.push('\0'),
+            Some('u') => {
+                let rest = chars.as_str();
+                let decoded = rest
+                    .strip_prefix('{')
+                    .and_then(|rest| rest.split_once('}'))
+                    .and_then(|(hex, _)| u32::from_str_radix(hex, 16).ok())
+                    .and_then(char::from_u32);
+                match decoded {
+                    Some(decoded) => {
+                        out.push(decoded);
+                        let len = rest.find('}').map_or(0, |i| i + 1);
+                        chars = rest[len..].chars();
+                    }
+                    None => out.push_str("\\u"),
                 }
-                current_token.push(c);
-                current_token.finish();
-                tokens.push(current_token);
-                current_token = Token::new();
-            }
-            _ => {
-                current_token.push(c);
             }
+            Some(c) => out.push(c),
+            None => out.push('\\'),
         }
     }
-    if !current_token.is_empty() {
-        current_token.finish();
-        tokens.push(current_token);
-    }
-    tokens
+    out
 }
 
+pub(crate) fn tokenize(schema: &str) -> (Vec<Token<'_>>, Vec<Diagnostic>) {
+    let mut lexer = Lexer::new(schema);
+    let tokens = lexer.by_ref().collect();
+    (tokens, lexer.diagnostics)
+}
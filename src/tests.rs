@@ -1,6 +1,7 @@
 use super::*;
 use schema::assembly::*;
 use schema::ast::*;
+use schema::diagnostic::*;
 use schema::module::*;
 use schema::tokenize::*;
 
@@ -63,89 +64,33 @@ const MULTIPLE_TYPES_WITH_GENERICS_SCHEMA: &str = r#"
 
 #[test]
 fn test_simple_tokenize() {
-    use schema::*;
-    let tokens = tokenize(SIMPLE_SCHEMA);
+    let (tokens, diagnostics) = tokenize(SIMPLE_SCHEMA);
+    assert!(diagnostics.is_empty());
     let target_tokens = vec![
-        Token {
-            ty: TokenType::Keyword,
-            value: "type".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "Post".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: "{".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "id".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: ":".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "integer".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: ",".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "title".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: ":".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "string".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: ",".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "body".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: ":".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "string".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: ",".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "author".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: ":".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "string".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: "}".to_string(),
-        },
+        (TokenType::Keyword, "type"),
+        (TokenType::Identifier, "Post"),
+        (TokenType::Punctuation, "{"),
+        (TokenType::Identifier, "id"),
+        (TokenType::Punctuation, ":"),
+        (TokenType::Identifier, "integer"),
+        (TokenType::Punctuation, ","),
+        (TokenType::Identifier, "title"),
+        (TokenType::Punctuation, ":"),
+        (TokenType::Identifier, "string"),
+        (TokenType::Punctuation, ","),
+        (TokenType::Identifier, "body"),
+        (TokenType::Punctuation, ":"),
+        (TokenType::Identifier, "string"),
+        (TokenType::Punctuation, ","),
+        (TokenType::Identifier, "author"),
+        (TokenType::Punctuation, ":"),
+        (TokenType::Identifier, "string"),
+        (TokenType::Punctuation, "}"),
     ];
 
     for i in 0..tokens.len() {
-        if tokens[i] != target_tokens[i] {
+        let token = (tokens[i].ty, tokens[i].value);
+        if token != target_tokens[i] {
             let offset = if (i as i32 - 2) < 0 { 0 } else { i - 2 };
             let size = if i + 2 >= tokens.len() {
                 tokens.len() - i
@@ -157,7 +102,7 @@ fn test_simple_tokenize() {
             println!("Context: {:?}", context);
             panic!(
                 "Tokens are not equal at index {}: {:?} != {:?}",
-                i, tokens[i], target_tokens[i]
+                i, token, target_tokens[i]
             );
         }
     }
@@ -165,93 +110,34 @@ fn test_simple_tokenize() {
 
 #[test]
 fn test_stream_tokenize() {
-    use schema::*;
-    let tokens = tokenize(STREAM_SCHEMA);
+    let (tokens, diagnostics) = tokenize(STREAM_SCHEMA);
+    assert!(diagnostics.is_empty());
     let target_tokens = vec![
-        Token {
-            ty: TokenType::Keyword,
-            value: "type".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "Post".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: "{".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "id".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: ":".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "integer".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: ",".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "title".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: ":".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "string".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: ",".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "body".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: ":".to_string(),
-        },
-        Token {
-            ty: TokenType::Keyword,
-            value: "stream".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "string".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: ",".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "author".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: ":".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "string".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: "}".to_string(),
-        },
+        (TokenType::Keyword, "type"),
+        (TokenType::Identifier, "Post"),
+        (TokenType::Punctuation, "{"),
+        (TokenType::Identifier, "id"),
+        (TokenType::Punctuation, ":"),
+        (TokenType::Identifier, "integer"),
+        (TokenType::Punctuation, ","),
+        (TokenType::Identifier, "title"),
+        (TokenType::Punctuation, ":"),
+        (TokenType::Identifier, "string"),
+        (TokenType::Punctuation, ","),
+        (TokenType::Identifier, "body"),
+        (TokenType::Punctuation, ":"),
+        (TokenType::Keyword, "stream"),
+        (TokenType::Identifier, "string"),
+        (TokenType::Punctuation, ","),
+        (TokenType::Identifier, "author"),
+        (TokenType::Punctuation, ":"),
+        (TokenType::Identifier, "string"),
+        (TokenType::Punctuation, "}"),
     ];
 
     for i in 0..tokens.len() {
-        if tokens[i] != target_tokens[i] {
+        let token = (tokens[i].ty, tokens[i].value);
+        if token != target_tokens[i] {
             let offset = if (i as i32 - 2) < 0 { 0 } else { i - 2 };
             let size = if i + 2 >= tokens.len() {
                 tokens.len() - i
@@ -263,7 +149,7 @@ fn test_stream_tokenize() {
             println!("Context: {:?}", context);
             panic!(
                 "Tokens are not equal at index {}: {:?} != {:?}",
-                i, tokens[i], target_tokens[i]
+                i, token, target_tokens[i]
             );
         }
     }
@@ -271,109 +157,38 @@ fn test_stream_tokenize() {
 
 #[test]
 fn test_sync_tokenize() {
-    use schema::*;
-    let tokens = tokenize(SYNC_SCHEMA);
+    let (tokens, diagnostics) = tokenize(SYNC_SCHEMA);
+    assert!(diagnostics.is_empty());
     let target_tokens = vec![
-        Token {
-            ty: TokenType::Keyword,
-            value: "type".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "Post".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: "{".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "id".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: ":".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "integer".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: ",".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "title".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: ":".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "string".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: ",".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "body".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: ":".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "string".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: ",".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "author".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: ":".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "string".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: ",".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "likes".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: ":".to_string(),
-        },
-        Token {
-            ty: TokenType::Keyword,
-            value: "sync".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "integer".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: "}".to_string(),
-        },
+        (TokenType::Keyword, "type"),
+        (TokenType::Identifier, "Post"),
+        (TokenType::Punctuation, "{"),
+        (TokenType::Identifier, "id"),
+        (TokenType::Punctuation, ":"),
+        (TokenType::Identifier, "integer"),
+        (TokenType::Punctuation, ","),
+        (TokenType::Identifier, "title"),
+        (TokenType::Punctuation, ":"),
+        (TokenType::Identifier, "string"),
+        (TokenType::Punctuation, ","),
+        (TokenType::Identifier, "body"),
+        (TokenType::Punctuation, ":"),
+        (TokenType::Identifier, "string"),
+        (TokenType::Punctuation, ","),
+        (TokenType::Identifier, "author"),
+        (TokenType::Punctuation, ":"),
+        (TokenType::Identifier, "string"),
+        (TokenType::Punctuation, ","),
+        (TokenType::Identifier, "likes"),
+        (TokenType::Punctuation, ":"),
+        (TokenType::Keyword, "sync"),
+        (TokenType::Identifier, "integer"),
+        (TokenType::Punctuation, "}"),
     ];
 
     for i in 0..tokens.len() {
-        if tokens[i] != target_tokens[i] {
+        let token = (tokens[i].ty, tokens[i].value);
+        if token != target_tokens[i] {
             let offset = if (i as i32 - 2) < 0 { 0 } else { i - 2 };
             let size = if i + 2 >= tokens.len() {
                 tokens.len() - i
@@ -385,7 +200,7 @@ fn test_sync_tokenize() {
             println!("Context: {:?}", context);
             panic!(
                 "Tokens are not equal at index {}: {:?} != {:?}",
-                i, tokens[i], target_tokens[i]
+                i, token, target_tokens[i]
             );
         }
     }
@@ -393,173 +208,50 @@ fn test_sync_tokenize() {
 
 #[test]
 fn test_multiple_types_with_generics_tokenize() {
-    use schema::*;
-    let tokens = tokenize(MULTIPLE_TYPES_WITH_GENERICS_SCHEMA);
+    let (tokens, diagnostics) = tokenize(MULTIPLE_TYPES_WITH_GENERICS_SCHEMA);
+    assert!(diagnostics.is_empty());
     let target_tokens = vec![
-        Token {
-            ty: TokenType::Keyword,
-            value: "type".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "Post".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: "{".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "id".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: ":".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "integer".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: ",".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "title".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: ":".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "string".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: ",".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "body".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: ":".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "string".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: ",".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "author".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: ":".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "User".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: "}".to_string(),
-        },
-        Token {
-            ty: TokenType::Keyword,
-            value: "type".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "User".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: "{".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "id".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: ":".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "integer".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: ",".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "name".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: ":".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "string".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: ",".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "email".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: ":".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "string".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: ",".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "posts".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: ":".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "Array".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: "<".to_string(),
-        },
-        Token {
-            ty: TokenType::Identifier,
-            value: "Post".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: ">".to_string(),
-        },
-        Token {
-            ty: TokenType::Punctuation,
-            value: "}".to_string(),
-        },
+        (TokenType::Keyword, "type"),
+        (TokenType::Identifier, "Post"),
+        (TokenType::Punctuation, "{"),
+        (TokenType::Identifier, "id"),
+        (TokenType::Punctuation, ":"),
+        (TokenType::Identifier, "integer"),
+        (TokenType::Punctuation, ","),
+        (TokenType::Identifier, "title"),
+        (TokenType::Punctuation, ":"),
+        (TokenType::Identifier, "string"),
+        (TokenType::Punctuation, ","),
+        (TokenType::Identifier, "body"),
+        (TokenType::Punctuation, ":"),
+        (TokenType::Identifier, "string"),
+        (TokenType::Punctuation, ","),
+        (TokenType::Identifier, "author"),
+        (TokenType::Punctuation, ":"),
+        (TokenType::Identifier, "User"),
+        (TokenType::Punctuation, "}"),
+        (TokenType::Keyword, "type"),
+        (TokenType::Identifier, "User"),
+        (TokenType::Punctuation, "{"),
+        (TokenType::Identifier, "id"),
+        (TokenType::Punctuation, ":"),
+        (TokenType::Identifier, "integer"),
+        (TokenType::Punctuation, ","),
+        (TokenType::Identifier, "name"),
+        (TokenType::Punctuation, ":"),
+        (TokenType::Identifier, "string"),
+        (TokenType::Punctuation, ","),
+        (TokenType::Identifier, "email"),
+        (TokenType::Punctuation, ":"),
+        (TokenType::Identifier, "string"),
+        (TokenType::Punctuation, ","),
+        (TokenType::Identifier, "posts"),
+        (TokenType::Punctuation, ":"),
+        (TokenType::Identifier, "Array"),
+        (TokenType::Punctuation, "<"),
+        (TokenType::Identifier, "Post"),
+        (TokenType::Punctuation, ">"),
+        (TokenType::Punctuation, "}"),
     ];
 
     for i in 0..tokens.len() {
@@ -569,7 +261,8 @@ fn test_multiple_types_with_generics_tokenize() {
                 i, tokens[i], None::<Token>
             );
         }
-        if tokens[i] != target_tokens[i] {
+        let token = (tokens[i].ty, tokens[i].value);
+        if token != target_tokens[i] {
             let offset = if (i as i32 - 2) < 0 { 0 } else { i - 2 };
             let size = if i + 2 >= tokens.len() {
                 tokens.len() - i
@@ -581,7 +274,7 @@ fn test_multiple_types_with_generics_tokenize() {
             println!("Context: {:?}", context);
             panic!(
                 "Tokens are not equal at index {}: {:?} != {:?}",
-                i, tokens[i], target_tokens[i]
+                i, token, target_tokens[i]
             );
         }
     }
@@ -589,44 +282,91 @@ fn test_multiple_types_with_generics_tokenize() {
 
 #[test]
 fn test_simple_ast() {
-    let tokens = tokenize(SIMPLE_SCHEMA);
-    let ast = gen_ast(&tokens);
+    let (tokens, _) = tokenize(SIMPLE_SCHEMA);
+    let ast = gen_ast(&tokens).unwrap();
     println!("{:?}", tokens);
     println!("{:?}", ast);
 
     let target_ast = ASTRoot {
         blocks: vec![ASTRootBlock::TypeDef(ASTTypeDef {
             name: "Post".to_string(),
+            name_span: Span::new(10, 14),
+            params: vec![],
+            extends: None,
             fields: vec![
                 ASTField {
                     name: "id".to_string(),
+                    name_span: Span::new(25, 27),
                     ty: ASTType {
                         kind: ASTTypeKind::Normal,
-                        name: "integer".to_string(),
+                        name: ASTTypeName {
+                            path: vec!["integer".to_string()],
+                            span: Span::new(29, 36),
+                            params: vec![],
+                        },
                     },
+                    optional: false,
+                    default: None,
+                    annotations: vec![],
+                    comments: vec![],
+                    trailing_comment: None,
                 },
                 ASTField {
                     name: "title".to_string(),
+                    name_span: Span::new(46, 51),
                     ty: ASTType {
                         kind: ASTTypeKind::Normal,
-                        name: "string".to_string(),
+                        name: ASTTypeName {
+                            path: vec!["string".to_string()],
+                            span: Span::new(53, 59),
+                            params: vec![],
+                        },
                     },
+                    optional: false,
+                    default: None,
+                    annotations: vec![],
+                    comments: vec![],
+                    trailing_comment: None,
                 },
                 ASTField {
                     name: "body".to_string(),
+                    name_span: Span::new(69, 73),
                     ty: ASTType {
                         kind: ASTTypeKind::Normal,
-                        name: "string".to_string(),
+                        name: ASTTypeName {
+                            path: vec!["string".to_string()],
+                            span: Span::new(75, 81),
+                            params: vec![],
+                        },
                     },
+                    optional: false,
+                    default: None,
+                    annotations: vec![],
+                    comments: vec![],
+                    trailing_comment: None,
                 },
                 ASTField {
                     name: "author".to_string(),
+                    name_span: Span::new(91, 97),
                     ty: ASTType {
                         kind: ASTTypeKind::Normal,
-                        name: "string".to_string(),
+                        name: ASTTypeName {
+                            path: vec!["string".to_string()],
+                            span: Span::new(99, 105),
+                            params: vec![],
+                        },
                     },
+                    optional: false,
+                    default: None,
+                    annotations: vec![],
+                    comments: vec![],
+                    trailing_comment: None,
                 },
             ],
+            annotations: vec![],
+            comments: vec![],
+            trailing_comments: vec![],
+            closing_comment: None,
         })],
     };
 
@@ -635,45 +375,91 @@ fn test_simple_ast() {
 
 #[test]
 fn test_stream_ast() {
-    use schema::*;
-    let tokens = tokenize(STREAM_SCHEMA);
-    let ast = gen_ast(&tokens);
+    let (tokens, _) = tokenize(STREAM_SCHEMA);
+    let ast = gen_ast(&tokens).unwrap();
     println!("{:?}", tokens);
     println!("{:?}", ast);
 
     let target_ast = ASTRoot {
         blocks: vec![ASTRootBlock::TypeDef(ASTTypeDef {
             name: "Post".to_string(),
+            name_span: Span::new(10, 14),
+            params: vec![],
+            extends: None,
             fields: vec![
                 ASTField {
                     name: "id".to_string(),
+                    name_span: Span::new(25, 27),
                     ty: ASTType {
                         kind: ASTTypeKind::Normal,
-                        name: "integer".to_string(),
+                        name: ASTTypeName {
+                            path: vec!["integer".to_string()],
+                            span: Span::new(29, 36),
+                            params: vec![],
+                        },
                     },
+                    optional: false,
+                    default: None,
+                    annotations: vec![],
+                    comments: vec![],
+                    trailing_comment: None,
                 },
                 ASTField {
                     name: "title".to_string(),
+                    name_span: Span::new(46, 51),
                     ty: ASTType {
                         kind: ASTTypeKind::Normal,
-                        name: "string".to_string(),
+                        name: ASTTypeName {
+                            path: vec!["string".to_string()],
+                            span: Span::new(53, 59),
+                            params: vec![],
+                        },
                     },
+                    optional: false,
+                    default: None,
+                    annotations: vec![],
+                    comments: vec![],
+                    trailing_comment: None,
                 },
                 ASTField {
                     name: "body".to_string(),
+                    name_span: Span::new(69, 73),
                     ty: ASTType {
                         kind: ASTTypeKind::Stream,
-                        name: "string".to_string(),
+                        name: ASTTypeName {
+                            path: vec!["string".to_string()],
+                            span: Span::new(82, 88),
+                            params: vec![],
+                        },
                     },
+                    optional: false,
+                    default: None,
+                    annotations: vec![],
+                    comments: vec![],
+                    trailing_comment: None,
                 },
                 ASTField {
                     name: "author".to_string(),
+                    name_span: Span::new(98, 104),
                     ty: ASTType {
                         kind: ASTTypeKind::Normal,
-                        name: "string".to_string(),
+                        name: ASTTypeName {
+                            path: vec!["string".to_string()],
+                            span: Span::new(106, 112),
+                            params: vec![],
+                        },
                     },
+                    optional: false,
+                    default: None,
+                    annotations: vec![],
+                    comments: vec![],
+                    trailing_comment: None,
                 },
             ],
+            annotations: vec![],
+            comments: vec![],
+            trailing_comments: vec![],
+            closing_comment: None,
         })],
     };
 
@@ -682,52 +468,108 @@ fn test_stream_ast() {
 
 #[test]
 fn test_sync_ast() {
-    use schema::*;
-    let tokens = tokenize(SYNC_SCHEMA);
-    let ast = gen_ast(&tokens);
+    let (tokens, _) = tokenize(SYNC_SCHEMA);
+    let ast = gen_ast(&tokens).unwrap();
     println!("{:?}", tokens);
     println!("{:?}", ast);
 
     let target_ast = ASTRoot {
         blocks: vec![ASTRootBlock::TypeDef(ASTTypeDef {
             name: "Post".to_string(),
+            name_span: Span::new(10, 14),
+            params: vec![],
+            extends: None,
             fields: vec![
                 ASTField {
                     name: "id".to_string(),
+                    name_span: Span::new(25, 27),
                     ty: ASTType {
                         kind: ASTTypeKind::Normal,
-                        name: "integer".to_string(),
+                        name: ASTTypeName {
+                            path: vec!["integer".to_string()],
+                            span: Span::new(29, 36),
+                            params: vec![],
+                        },
                     },
+                    optional: false,
+                    default: None,
+                    annotations: vec![],
+                    comments: vec![],
+                    trailing_comment: None,
                 },
                 ASTField {
                     name: "title".to_string(),
+                    name_span: Span::new(46, 51),
                     ty: ASTType {
                         kind: ASTTypeKind::Normal,
-                        name: "string".to_string(),
+                        name: ASTTypeName {
+                            path: vec!["string".to_string()],
+                            span: Span::new(53, 59),
+                            params: vec![],
+                        },
                     },
+                    optional: false,
+                    default: None,
+                    annotations: vec![],
+                    comments: vec![],
+                    trailing_comment: None,
                 },
                 ASTField {
                     name: "body".to_string(),
+                    name_span: Span::new(69, 73),
                     ty: ASTType {
                         kind: ASTTypeKind::Normal,
-                        name: "string".to_string(),
+                        name: ASTTypeName {
+                            path: vec!["string".to_string()],
+                            span: Span::new(75, 81),
+                            params: vec![],
+                        },
                     },
+                    optional: false,
+                    default: None,
+                    annotations: vec![],
+                    comments: vec![],
+                    trailing_comment: None,
                 },
                 ASTField {
                     name: "author".to_string(),
+                    name_span: Span::new(91, 97),
                     ty: ASTType {
                         kind: ASTTypeKind::Normal,
-                        name: "string".to_string(),
+                        name: ASTTypeName {
+                            path: vec!["string".to_string()],
+                            span: Span::new(99, 105),
+                            params: vec![],
+                        },
                     },
+                    optional: false,
+                    default: None,
+                    annotations: vec![],
+                    comments: vec![],
+                    trailing_comment: None,
                 },
                 ASTField {
                     name: "likes".to_string(),
+                    name_span: Span::new(115, 120),
                     ty: ASTType {
                         kind: ASTTypeKind::Sync,
-                        name: "integer".to_string(),
+                        name: ASTTypeName {
+                            path: vec!["integer".to_string()],
+                            span: Span::new(127, 134),
+                            params: vec![],
+                        },
                     },
+                    optional: false,
+                    default: None,
+                    annotations: vec![],
+                    comments: vec![],
+                    trailing_comment: None,
                 },
             ],
+            annotations: vec![],
+            comments: vec![],
+            trailing_comments: vec![],
+            closing_comment: None,
         })],
     };
 
@@ -736,9 +578,8 @@ fn test_sync_ast() {
 
 #[test]
 fn test_multiple_types_ast() {
-    use schema::*;
-    let tokens = tokenize(MULTIPLE_TYPES_SCHEMA);
-    let ast = gen_ast(&tokens);
+    let (tokens, _) = tokenize(MULTIPLE_TYPES_SCHEMA);
+    let ast = gen_ast(&tokens).unwrap();
     println!("{:?}", tokens);
     println!("{:?}", ast);
 
@@ -746,62 +587,146 @@ fn test_multiple_types_ast() {
         blocks: vec![
             ASTRootBlock::TypeDef(ASTTypeDef {
                 name: "Post".to_string(),
+                name_span: Span::new(10, 14),
+                params: vec![],
+                extends: None,
                 fields: vec![
                     ASTField {
                         name: "id".to_string(),
+                        name_span: Span::new(25, 27),
                         ty: ASTType {
                             kind: ASTTypeKind::Normal,
-                            name: "integer".to_string(),
+                            name: ASTTypeName {
+                                path: vec!["integer".to_string()],
+                                span: Span::new(29, 36),
+                                params: vec![],
+                            },
                         },
+                        optional: false,
+                        default: None,
+                        annotations: vec![],
+                        comments: vec![],
+                        trailing_comment: None,
                     },
                     ASTField {
                         name: "title".to_string(),
+                        name_span: Span::new(46, 51),
                         ty: ASTType {
                             kind: ASTTypeKind::Normal,
-                            name: "string".to_string(),
+                            name: ASTTypeName {
+                                path: vec!["string".to_string()],
+                                span: Span::new(53, 59),
+                                params: vec![],
+                            },
                         },
+                        optional: false,
+                        default: None,
+                        annotations: vec![],
+                        comments: vec![],
+                        trailing_comment: None,
                     },
                     ASTField {
                         name: "body".to_string(),
+                        name_span: Span::new(69, 73),
                         ty: ASTType {
                             kind: ASTTypeKind::Normal,
-                            name: "string".to_string(),
+                            name: ASTTypeName {
+                                path: vec!["string".to_string()],
+                                span: Span::new(75, 81),
+                                params: vec![],
+                            },
                         },
+                        optional: false,
+                        default: None,
+                        annotations: vec![],
+                        comments: vec![],
+                        trailing_comment: None,
                     },
                     ASTField {
                         name: "author".to_string(),
+                        name_span: Span::new(91, 97),
                         ty: ASTType {
                             kind: ASTTypeKind::Normal,
-                            name: "User".to_string(),
+                            name: ASTTypeName {
+                                path: vec!["User".to_string()],
+                                span: Span::new(99, 103),
+                                params: vec![],
+                            },
                         },
+                        optional: false,
+                        default: None,
+                        annotations: vec![],
+                        comments: vec![],
+                        trailing_comment: None,
                     },
                 ],
+                annotations: vec![],
+                comments: vec![],
+                trailing_comments: vec![],
+                closing_comment: None,
             }),
             ASTRootBlock::TypeDef(ASTTypeDef {
                 name: "User".to_string(),
+                name_span: Span::new(119, 123),
+                params: vec![],
+                extends: None,
                 fields: vec![
                     ASTField {
                         name: "id".to_string(),
+                        name_span: Span::new(134, 136),
                         ty: ASTType {
                             kind: ASTTypeKind::Normal,
-                            name: "integer".to_string(),
+                            name: ASTTypeName {
+                                path: vec!["integer".to_string()],
+                                span: Span::new(138, 145),
+                                params: vec![],
+                            },
                         },
+                        optional: false,
+                        default: None,
+                        annotations: vec![],
+                        comments: vec![],
+                        trailing_comment: None,
                     },
                     ASTField {
                         name: "name".to_string(),
+                        name_span: Span::new(155, 159),
                         ty: ASTType {
                             kind: ASTTypeKind::Normal,
-                            name: "string".to_string(),
+                            name: ASTTypeName {
+                                path: vec!["string".to_string()],
+                                span: Span::new(161, 167),
+                                params: vec![],
+                            },
                         },
+                        optional: false,
+                        default: None,
+                        annotations: vec![],
+                        comments: vec![],
+                        trailing_comment: None,
                     },
                     ASTField {
                         name: "email".to_string(),
+                        name_span: Span::new(177, 182),
                         ty: ASTType {
                             kind: ASTTypeKind::Normal,
-                            name: "string".to_string(),
+                            name: ASTTypeName {
+                                path: vec!["string".to_string()],
+                                span: Span::new(184, 190),
+                                params: vec![],
+                            },
                         },
+                        optional: false,
+                        default: None,
+                        annotations: vec![],
+                        comments: vec![],
+                        trailing_comment: None,
                     },
                 ],
+                annotations: vec![],
+                comments: vec![],
+                trailing_comments: vec![],
+                closing_comment: None,
             }),
         ],
     };
@@ -811,8 +736,8 @@ fn test_multiple_types_ast() {
 
 #[test]
 fn test_simple_with_generics_ast() {
-    let tokens = tokenize(SIMPLE_SCHEMA);
-    let ast = gen_ast(&tokens);
+    let (tokens, _) = tokenize(MULTIPLE_TYPES_WITH_GENERICS_SCHEMA);
+    let ast = gen_ast(&tokens).unwrap();
     println!("{:?}", tokens);
     println!("{:?}", ast);
 
@@ -820,69 +745,167 @@ fn test_simple_with_generics_ast() {
         blocks: vec![
             ASTRootBlock::TypeDef(ASTTypeDef {
                 name: "Post".to_string(),
+                name_span: Span::new(10, 14),
+                params: vec![],
+                extends: None,
                 fields: vec![
                     ASTField {
                         name: "id".to_string(),
+                        name_span: Span::new(25, 27),
                         ty: ASTType {
                             kind: ASTTypeKind::Normal,
-                            name: "integer".to_string(),
+                            name: ASTTypeName {
+                                path: vec!["integer".to_string()],
+                                span: Span::new(29, 36),
+                                params: vec![],
+                            },
                         },
+                        optional: false,
+                        default: None,
+                        annotations: vec![],
+                        comments: vec![],
+                        trailing_comment: None,
                     },
                     ASTField {
                         name: "title".to_string(),
+                        name_span: Span::new(46, 51),
                         ty: ASTType {
                             kind: ASTTypeKind::Normal,
-                            name: "string".to_string(),
+                            name: ASTTypeName {
+                                path: vec!["string".to_string()],
+                                span: Span::new(53, 59),
+                                params: vec![],
+                            },
                         },
+                        optional: false,
+                        default: None,
+                        annotations: vec![],
+                        comments: vec![],
+                        trailing_comment: None,
                     },
                     ASTField {
                         name: "body".to_string(),
+                        name_span: Span::new(69, 73),
                         ty: ASTType {
                             kind: ASTTypeKind::Normal,
-                            name: "string".to_string(),
+                            name: ASTTypeName {
+                                path: vec!["string".to_string()],
+                                span: Span::new(75, 81),
+                                params: vec![],
+                            },
                         },
+                        optional: false,
+                        default: None,
+                        annotations: vec![],
+                        comments: vec![],
+                        trailing_comment: None,
                     },
                     ASTField {
                         name: "author".to_string(),
+                        name_span: Span::new(91, 97),
                         ty: ASTType {
                             kind: ASTTypeKind::Normal,
-                            name: "User".to_string(),
+                            name: ASTTypeName {
+                                path: vec!["User".to_string()],
+                                span: Span::new(99, 103),
+                                params: vec![],
+                            },
                         },
+                        optional: false,
+                        default: None,
+                        annotations: vec![],
+                        comments: vec![],
+                        trailing_comment: None,
                     },
                 ],
+                annotations: vec![],
+                comments: vec![],
+                trailing_comments: vec![],
+                closing_comment: None,
             }),
             ASTRootBlock::TypeDef(ASTTypeDef {
                 name: "User".to_string(),
+                name_span: Span::new(119, 123),
+                params: vec![],
+                extends: None,
                 fields: vec![
                     ASTField {
                         name: "id".to_string(),
+                        name_span: Span::new(134, 136),
                         ty: ASTType {
                             kind: ASTTypeKind::Normal,
-                            name: "integer".to_string(),
+                            name: ASTTypeName {
+                                path: vec!["integer".to_string()],
+                                span: Span::new(138, 145),
+                                params: vec![],
+                            },
                         },
+                        optional: false,
+                        default: None,
+                        annotations: vec![],
+                        comments: vec![],
+                        trailing_comment: None,
                     },
                     ASTField {
                         name: "name".to_string(),
+                        name_span: Span::new(155, 159),
                         ty: ASTType {
                             kind: ASTTypeKind::Normal,
-                            name: "string".to_string(),
+                            name: ASTTypeName {
+                                path: vec!["string".to_string()],
+                                span: Span::new(161, 167),
+                                params: vec![],
+                            },
                         },
+                        optional: false,
+                        default: None,
+                        annotations: vec![],
+                        comments: vec![],
+                        trailing_comment: None,
                     },
                     ASTField {
                         name: "email".to_string(),
+                        name_span: Span::new(177, 182),
                         ty: ASTType {
                             kind: ASTTypeKind::Normal,
-                            name: "string".to_string(),
+                            name: ASTTypeName {
+                                path: vec!["string".to_string()],
+                                span: Span::new(184, 190),
+                                params: vec![],
+                            },
                         },
+                        optional: false,
+                        default: None,
+                        annotations: vec![],
+                        comments: vec![],
+                        trailing_comment: None,
                     },
                     ASTField {
                         name: "posts".to_string(),
+                        name_span: Span::new(200, 205),
                         ty: ASTType {
                             kind: ASTTypeKind::Normal,
-                            name: "Post".to_string(),
+                            name: ASTTypeName {
+                                path: vec!["Array".to_string()],
+                                span: Span::new(207, 212),
+                                params: vec![ASTTypeName {
+                                    path: vec!["Post".to_string()],
+                                    span: Span::new(213, 217),
+                                    params: vec![],
+                                }],
+                            },
                         },
+                        optional: false,
+                        default: None,
+                        annotations: vec![],
+                        comments: vec![],
+                        trailing_comment: None,
                     },
                 ],
+                annotations: vec![],
+                comments: vec![],
+                trailing_comments: vec![],
+                closing_comment: None,
             }),
         ],
     };
@@ -892,10 +915,9 @@ fn test_simple_with_generics_ast() {
 
 #[test]
 fn test_simple_module() {
-    use schema::*;
-    let tokens = tokenize(SIMPLE_SCHEMA);
-    let ast = gen_ast(&tokens);
-    let module = create_module(&ast);
+    let (tokens, _) = tokenize(SIMPLE_SCHEMA);
+    let ast = gen_ast(&tokens).unwrap();
+    let module = create_module(&ast).unwrap();
     println!("{:?}", module);
 
     assert_eq!(
@@ -903,6 +925,8 @@ fn test_simple_module() {
         SchemeModule {
             types: vec![SchemeType {
                 name: "Post".to_string(),
+                params: vec![],
+                base: None,
                 fields: vec![
                     SchemeField {
                         name: "id".to_string(),
@@ -910,6 +934,9 @@ fn test_simple_module() {
                             kind: SchemeFieldTypeKind::Normal,
                             ty_ref: SchemeTypeRef::Builtin(BuiltinType::Integer),
                         },
+                        optional: false,
+                        default: None,
+                        annotations: vec![],
                     },
                     SchemeField {
                         name: "title".to_string(),
@@ -917,6 +944,9 @@ fn test_simple_module() {
                             kind: SchemeFieldTypeKind::Normal,
                             ty_ref: SchemeTypeRef::Builtin(BuiltinType::String),
                         },
+                        optional: false,
+                        default: None,
+                        annotations: vec![],
                     },
                     SchemeField {
                         name: "body".to_string(),
@@ -924,6 +954,9 @@ fn test_simple_module() {
                             kind: SchemeFieldTypeKind::Normal,
                             ty_ref: SchemeTypeRef::Builtin(BuiltinType::String),
                         },
+                        optional: false,
+                        default: None,
+                        annotations: vec![],
                     },
                     SchemeField {
                         name: "author".to_string(),
@@ -931,19 +964,24 @@ fn test_simple_module() {
                             kind: SchemeFieldTypeKind::Normal,
                             ty_ref: SchemeTypeRef::Builtin(BuiltinType::String),
                         },
+                        optional: false,
+                        default: None,
+                        annotations: vec![],
                     },
                 ],
+                annotations: vec![],
             }],
+            services: vec![],
+            consts: vec![],
         }
     );
 }
 
 #[test]
 fn test_stream_module() {
-    use schema::*;
-    let tokens = tokenize(STREAM_SCHEMA);
-    let ast = gen_ast(&tokens);
-    let module = create_module(&ast);
+    let (tokens, _) = tokenize(STREAM_SCHEMA);
+    let ast = gen_ast(&tokens).unwrap();
+    let module = create_module(&ast).unwrap();
     println!("{:?}", module);
 
     assert_eq!(
@@ -951,6 +989,8 @@ fn test_stream_module() {
         SchemeModule {
             types: vec![SchemeType {
                 name: "Post".to_string(),
+                params: vec![],
+                base: None,
                 fields: vec![
                     SchemeField {
                         name: "id".to_string(),
@@ -958,6 +998,9 @@ fn test_stream_module() {
                             kind: SchemeFieldTypeKind::Normal,
                             ty_ref: SchemeTypeRef::Builtin(BuiltinType::Integer),
                         },
+                        optional: false,
+                        default: None,
+                        annotations: vec![],
                     },
                     SchemeField {
                         name: "title".to_string(),
@@ -965,6 +1008,9 @@ fn test_stream_module() {
                             kind: SchemeFieldTypeKind::Normal,
                             ty_ref: SchemeTypeRef::Builtin(BuiltinType::String),
                         },
+                        optional: false,
+                        default: None,
+                        annotations: vec![],
                     },
                     SchemeField {
                         name: "body".to_string(),
@@ -972,6 +1018,9 @@ fn test_stream_module() {
                             kind: SchemeFieldTypeKind::Stream,
                             ty_ref: SchemeTypeRef::Builtin(BuiltinType::String),
                         },
+                        optional: false,
+                        default: None,
+                        annotations: vec![],
                     },
                     SchemeField {
                         name: "author".to_string(),
@@ -979,19 +1028,24 @@ fn test_stream_module() {
                             kind: SchemeFieldTypeKind::Normal,
                             ty_ref: SchemeTypeRef::Builtin(BuiltinType::String),
                         },
+                        optional: false,
+                        default: None,
+                        annotations: vec![],
                     },
                 ],
+                annotations: vec![],
             }],
+            services: vec![],
+            consts: vec![],
         }
     );
 }
 
 #[test]
 fn test_sync_module() {
-    use schema::*;
-    let tokens = tokenize(SYNC_SCHEMA);
-    let ast = gen_ast(&tokens);
-    let module = create_module(&ast);
+    let (tokens, _) = tokenize(SYNC_SCHEMA);
+    let ast = gen_ast(&tokens).unwrap();
+    let module = create_module(&ast).unwrap();
     println!("{:?}", module);
 
     assert_eq!(
@@ -999,6 +1053,8 @@ fn test_sync_module() {
         SchemeModule {
             types: vec![SchemeType {
                 name: "Post".to_string(),
+                params: vec![],
+                base: None,
                 fields: vec![
                     SchemeField {
                         name: "id".to_string(),
@@ -1006,6 +1062,9 @@ fn test_sync_module() {
                             kind: SchemeFieldTypeKind::Normal,
                             ty_ref: SchemeTypeRef::Builtin(BuiltinType::Integer),
                         },
+                        optional: false,
+                        default: None,
+                        annotations: vec![],
                     },
                     SchemeField {
                         name: "title".to_string(),
@@ -1013,6 +1072,9 @@ fn test_sync_module() {
                             kind: SchemeFieldTypeKind::Normal,
                             ty_ref: SchemeTypeRef::Builtin(BuiltinType::String),
                         },
+                        optional: false,
+                        default: None,
+                        annotations: vec![],
                     },
                     SchemeField {
                         name: "body".to_string(),
@@ -1020,6 +1082,9 @@ fn test_sync_module() {
                             kind: SchemeFieldTypeKind::Normal,
                             ty_ref: SchemeTypeRef::Builtin(BuiltinType::String),
                         },
+                        optional: false,
+                        default: None,
+                        annotations: vec![],
                     },
                     SchemeField {
                         name: "author".to_string(),
@@ -1027,6 +1092,9 @@ fn test_sync_module() {
                             kind: SchemeFieldTypeKind::Normal,
                             ty_ref: SchemeTypeRef::Builtin(BuiltinType::String),
                         },
+                        optional: false,
+                        default: None,
+                        annotations: vec![],
                     },
                     SchemeField {
                         name: "likes".to_string(),
@@ -1034,19 +1102,24 @@ fn test_sync_module() {
                             kind: SchemeFieldTypeKind::Sync,
                             ty_ref: SchemeTypeRef::Builtin(BuiltinType::Integer),
                         },
+                        optional: false,
+                        default: None,
+                        annotations: vec![],
                     },
                 ],
+                annotations: vec![],
             }],
+            services: vec![],
+            consts: vec![],
         }
     );
 }
 
 #[test]
 fn test_multiple_types_module() {
-    use schema::*;
-    let tokens = tokenize(MULTIPLE_TYPES_SCHEMA);
-    let ast = gen_ast(&tokens);
-    let module = create_module(&ast);
+    let (tokens, _) = tokenize(MULTIPLE_TYPES_SCHEMA);
+    let ast = gen_ast(&tokens).unwrap();
+    let module = create_module(&ast).unwrap();
     println!("{:?}", module);
 
     assert_eq!(
@@ -1055,6 +1128,8 @@ fn test_multiple_types_module() {
             types: vec![
                 SchemeType {
                     name: "Post".to_string(),
+                    params: vec![],
+                    base: None,
                     fields: vec![
                         SchemeField {
                             name: "id".to_string(),
@@ -1062,6 +1137,9 @@ fn test_multiple_types_module() {
                                 kind: SchemeFieldTypeKind::Normal,
                                 ty_ref: SchemeTypeRef::Builtin(BuiltinType::Integer),
                             },
+                            optional: false,
+                            default: None,
+                            annotations: vec![],
                         },
                         SchemeField {
                             name: "title".to_string(),
@@ -1069,6 +1147,9 @@ fn test_multiple_types_module() {
                                 kind: SchemeFieldTypeKind::Normal,
                                 ty_ref: SchemeTypeRef::Builtin(BuiltinType::String),
                             },
+                            optional: false,
+                            default: None,
+                            annotations: vec![],
                         },
                         SchemeField {
                             name: "body".to_string(),
@@ -1076,6 +1157,9 @@ fn test_multiple_types_module() {
                                 kind: SchemeFieldTypeKind::Normal,
                                 ty_ref: SchemeTypeRef::Builtin(BuiltinType::String),
                             },
+                            optional: false,
+                            default: None,
+                            annotations: vec![],
                         },
                         SchemeField {
                             name: "author".to_string(),
@@ -1083,11 +1167,17 @@ fn test_multiple_types_module() {
                                 kind: SchemeFieldTypeKind::Normal,
                                 ty_ref: SchemeTypeRef::Custom("User".to_string()),
                             },
+                            optional: false,
+                            default: None,
+                            annotations: vec![],
                         },
                     ],
+                    annotations: vec![],
                 },
                 SchemeType {
                     name: "User".to_string(),
+                    params: vec![],
+                    base: None,
                     fields: vec![
                         SchemeField {
                             name: "id".to_string(),
@@ -1095,6 +1185,9 @@ fn test_multiple_types_module() {
                                 kind: SchemeFieldTypeKind::Normal,
                                 ty_ref: SchemeTypeRef::Builtin(BuiltinType::Integer),
                             },
+                            optional: false,
+                            default: None,
+                            annotations: vec![],
                         },
                         SchemeField {
                             name: "name".to_string(),
@@ -1102,6 +1195,9 @@ fn test_multiple_types_module() {
                                 kind: SchemeFieldTypeKind::Normal,
                                 ty_ref: SchemeTypeRef::Builtin(BuiltinType::String),
                             },
+                            optional: false,
+                            default: None,
+                            annotations: vec![],
                         },
                         SchemeField {
                             name: "email".to_string(),
@@ -1109,21 +1205,3145 @@ fn test_multiple_types_module() {
                                 kind: SchemeFieldTypeKind::Normal,
                                 ty_ref: SchemeTypeRef::Builtin(BuiltinType::String),
                             },
+                            optional: false,
+                            default: None,
+                            annotations: vec![],
                         },
                     ],
+                    annotations: vec![],
                 },
             ],
+            services: vec![],
+            consts: vec![],
         }
     );
 }
 
 #[test]
 fn test_multiple_types_assembly() {
-    use schema::*;
-    let tokens = tokenize(MULTIPLE_TYPES_SCHEMA);
-    let ast = gen_ast(&tokens);
-    let module = create_module(&ast);
+    let (tokens, _) = tokenize(MULTIPLE_TYPES_SCHEMA);
+    let ast = gen_ast(&tokens).unwrap();
+    let module = create_module(&ast).unwrap();
     let assembly = generate(&module);
     println!("{:?}", assembly);
 }
 
+
+const COMMENTED_SCHEMA: &str = r#"
+// A blog post.
+type Post {
+  // Primary key.
+  id:integer,
+  tags: Array< string >,
+  body : stream string,
+  // TODO: likes
+}
+type Empty { }
+// trailing
+"#;
+
+#[test]
+fn test_format() {
+    let formatted = schema::format(COMMENTED_SCHEMA, &schema::FormatOptions::default()).unwrap();
+    assert_eq!(
+        formatted,
+        r#"// A blog post.
+type Post {
+    // Primary key.
+    id: integer,
+    tags: Array<string>,
+    body: stream string
+    // TODO: likes
+}
+
+type Empty {}
+
+// trailing
+"#
+    );
+}
+
+const SAME_LINE_COMMENTS_SCHEMA: &str = r#"
+type User { // not about `id`
+    id: integer, // the id
+    name: string
+    ,email: string // kept after the comma
+} // end of `User`
+
+type Empty {} // no fields yet
+
+service Users {
+    remote function get(id: integer) -> User; // by id
+    remote function count() -> integer // all users
+} // end of `Users`
+// dangling
+"#;
+
+#[test]
+fn test_format_same_line_comments() {
+    let options = schema::FormatOptions::default();
+    let formatted = schema::format(SAME_LINE_COMMENTS_SCHEMA, &options).unwrap();
+    assert_eq!(
+        formatted,
+        "type User {\n    // not about `id`\n    id: integer, // the id\n    name: string,\n    \
+         email: string // kept after the comma\n} // end of `User`\n\n\
+         type Empty {} // no fields yet\n\n\
+         service Users {\n    remote function get(id: integer) -> User; // by id\n    \
+         remote function count() -> integer // all users\n} // end of `Users`\n\n\
+         // dangling\n"
+    );
+    assert_eq!(schema::format(&formatted, &options).unwrap(), formatted);
+}
+
+#[test]
+fn test_format_trailing_comma() {
+    let options = schema::FormatOptions {
+        indent_width: 2,
+        trailing_comma: true,
+    };
+    let formatted = schema::format(SYNC_SCHEMA, &options).unwrap();
+    assert_eq!(
+        formatted,
+        "type Post {\n  id: integer,\n  title: string,\n  body: string,\n  author: string,\n  likes: sync integer,\n}\n"
+    );
+}
+
+#[test]
+fn test_format_is_idempotent() {
+    let options = schema::FormatOptions::default();
+    for source in [
+        COMMENTED_SCHEMA,
+        MULTIPLE_TYPES_WITH_GENERICS_SCHEMA,
+        STREAM_SCHEMA,
+    ] {
+        let formatted = schema::format(source, &options).unwrap();
+        assert_eq!(schema::format(&formatted, &options).unwrap(), formatted);
+    }
+}
+
+#[test]
+fn test_parse_error() {
+    let (tokens, _) = tokenize("type Post {\n    id integer\n}");
+    let err = gen_ast(&tokens).unwrap_err().remove(0);
+    assert_eq!(err.message, "Unexpected token `integer`");
+    assert_eq!(err.span, Span::new(19, 26));
+    assert_eq!(
+        err.render("post.rgc", "type Post {\n    id integer\n}"),
+        "post.rgc:2:8: error: Unexpected token `integer`"
+    );
+}
+
+#[test]
+fn test_module_validation() {
+    let source = r#"
+        type Post {
+            id: integer,
+            id: string,
+            author: Author,
+            tags: Array<string, string>
+        }
+        type Post {}
+    "#;
+    let ast = gen_ast(&tokenize(source).0).unwrap();
+    let messages = create_module(&ast)
+        .unwrap_err()
+        .into_iter()
+        .map(|d| d.message)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        messages,
+        vec![
+            "Type `Post` is defined more than once",
+            "Field `id` is defined more than once in type `Post`",
+            "Unknown type `Author`",
+            "`Array` expects 1 type parameter(s), found 2",
+        ]
+    );
+    assert!(validate(&ast, &["Author"]).len() == 3);
+}
+
+fn lsp_request(server: &mut lsp::Server, method: &str, params: json::Json) -> json::Json {
+    let request = json::Json::object([
+        ("jsonrpc", json::Json::from("2.0")),
+        ("id", json::Json::from(1_i64)),
+        ("method", json::Json::from(method)),
+        ("params", params),
+    ]);
+    let response = server.handle(&request);
+    response[0].get("result").cloned().unwrap()
+}
+
+fn lsp_open(server: &mut lsp::Server, uri: &str, text: &str) -> Vec<json::Json> {
+    let notification = json::Json::parse(&format!(
+        r#"{{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{{"textDocument":{{"uri":"{uri}","languageId":"rgc","version":1,"text":{}}}}}}}"#,
+        json::Json::from(text)
+    ))
+    .unwrap();
+    server.handle(&notification)
+}
+
+fn lsp_position(uri: &str, line: usize, character: usize) -> json::Json {
+    json::Json::parse(&format!(
+        r#"{{"textDocument":{{"uri":"{uri}"}},"position":{{"line":{line},"character":{character}}}}}"#
+    ))
+    .unwrap()
+}
+
+#[test]
+fn test_lsp_diagnostics_and_navigation() {
+    let mut server = lsp::Server::default();
+    let published = lsp_open(
+        &mut server,
+        "file:///post.rgc",
+        "// A post.\ntype Post {\n    author: User,\n    tags: Array<Tag>\n}\n",
+    );
+    assert_eq!(
+        published[0].to_string(),
+        r#"{"jsonrpc":"2.0","method":"textDocument/publishDiagnostics","params":{"uri":"file:///post.rgc","diagnostics":[{"range":{"start":{"line":2,"character":12},"end":{"line":2,"character":16}},"severity":1,"source":"rgc","message":"Unknown type `User`"},{"range":{"start":{"line":3,"character":16},"end":{"line":3,"character":19}},"severity":1,"source":"rgc","message":"Unknown type `Tag`"}]}}"#
+    );
+
+    // Types from other documents resolve, so the diagnostics go away.
+    let published = lsp_open(
+        &mut server,
+        "file:///user.rgc",
+        "type User {\n    posts: Array<Post>\n}\ntype Tag {}\n",
+    );
+    assert!(published
+        .iter()
+        .all(|message| message.to_string().contains(r#""diagnostics":[]"#)));
+
+    let definition = lsp_request(
+        &mut server,
+        "textDocument/definition",
+        lsp_position("file:///post.rgc", 2, 14),
+    );
+    assert_eq!(
+        definition.to_string(),
+        r#"{"uri":"file:///user.rgc","range":{"start":{"line":0,"character":5},"end":{"line":0,"character":9}}}"#
+    );
+
+    let references = lsp_request(
+        &mut server,
+        "textDocument/references",
+        lsp_position("file:///user.rgc", 1, 19),
+    );
+    assert_eq!(references.as_array().unwrap().len(), 2);
+
+    let hover = lsp_request(
+        &mut server,
+        "textDocument/hover",
+        lsp_position("file:///user.rgc", 1, 19),
+    );
+    assert_eq!(
+        hover.get("contents").unwrap().get("value").unwrap().as_str(),
+        Some("```rgc\ntype Post {\n    author: User,\n    tags: Array<Tag>\n}\n```\n\nA post.")
+    );
+
+    let completion = lsp_request(
+        &mut server,
+        "textDocument/completion",
+        lsp_position("file:///post.rgc", 2, 12),
+    );
+    let labels = completion
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item.get("label").unwrap().as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert!(labels.contains(&"stream") && labels.contains(&"Tag") && labels.contains(&"Array"));
+
+    // Default values are checked as well.
+    let published = lsp_open(
+        &mut server,
+        "file:///count.rgc",
+        "type Count {\n    x: integer = \"a\"\n}\n",
+    );
+    let count = published
+        .iter()
+        .find(|message| message.to_string().contains("file:///count.rgc"))
+        .unwrap();
+    assert!(count
+        .to_string()
+        .contains(r#""message":"Expected a value of type `integer`""#));
+}
+
+#[test]
+fn test_lsp_rename() {
+    let mut server = lsp::Server::default();
+    lsp_open(&mut server, "file:///a.rgc", "type User {\n    id: integer\n}\n");
+    lsp_open(&mut server, "file:///b.rgc", "type Post {\n    author: User\n}\n");
+    let mut params = lsp_position("file:///b.rgc", 1, 13);
+    params.insert("newName", json::Json::from("Account"));
+    let edit = lsp_request(&mut server, "textDocument/rename", params);
+    assert_eq!(
+        edit.to_string(),
+        r#"{"changes":{"file:///a.rgc":[{"range":{"start":{"line":0,"character":5},"end":{"line":0,"character":9}},"newText":"Account"}],"file:///b.rgc":[{"range":{"start":{"line":1,"character":12},"end":{"line":1,"character":16}},"newText":"Account"}]}}"#
+    );
+}
+
+#[test]
+fn test_lsp_type_params() {
+    let mut server = lsp::Server::default();
+    lsp_open(
+        &mut server,
+        "file:///page.rgc",
+        "type Page<T> {\n    items: Array<T>,\n    first: Optional<T>\n}\ntype Pair<T> {\n    left: T,\n    right: T\n}\ntype T {}\ntype Uses {\n    t: T\n}\n",
+    );
+    let lines = |locations: &json::Json| {
+        let mut lines = locations
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|location| {
+                let start = location.get("range").unwrap().get("start").unwrap();
+                (
+                    start.get("line").unwrap().as_u64().unwrap(),
+                    start.get("character").unwrap().as_u64().unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+        lines.sort();
+        lines
+    };
+
+    // `T` in `Array<T>` is the parameter of `Page`, not the type `T` nor that of `Pair`.
+    let references = lsp_request(
+        &mut server,
+        "textDocument/references",
+        lsp_position("file:///page.rgc", 1, 17),
+    );
+    assert_eq!(lines(&references), vec![(0, 10), (1, 17), (2, 20)]);
+    let references = lsp_request(
+        &mut server,
+        "textDocument/references",
+        lsp_position("file:///page.rgc", 4, 10),
+    );
+    assert_eq!(lines(&references), vec![(4, 10), (5, 10), (6, 11)]);
+    let references = lsp_request(
+        &mut server,
+        "textDocument/references",
+        lsp_position("file:///page.rgc", 8, 5),
+    );
+    assert_eq!(lines(&references), vec![(8, 5), (10, 7)]);
+
+    let definition = lsp_request(
+        &mut server,
+        "textDocument/definition",
+        lsp_position("file:///page.rgc", 6, 11),
+    );
+    assert_eq!(
+        definition.to_string(),
+        r#"{"uri":"file:///page.rgc","range":{"start":{"line":4,"character":10},"end":{"line":4,"character":11}}}"#
+    );
+
+    let mut params = lsp_position("file:///page.rgc", 0, 10);
+    params.insert("newName", json::Json::from("Item"));
+    let edit = lsp_request(&mut server, "textDocument/rename", params);
+    let changes = edit.get("changes").unwrap().get("file:///page.rgc").unwrap();
+    assert_eq!(lines(changes), vec![(0, 10), (1, 17), (2, 20)]);
+}
+
+#[test]
+fn test_parse_recovery() {
+    let source = r#"
+        type Post {
+            id: integer,
+            title string,
+            body: stream string
+            author: User<,
+            likes: sync integer
+        }
+        type { x: integer }
+        type User {
+            name: string
+    "#;
+    let (ast, diagnostics) = gen_ast_partial(&tokenize(source).0);
+    let messages = diagnostics
+        .iter()
+        .map(|d| d.message.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        messages,
+        vec![
+            "Unexpected token `string`",
+            "Expected `,` before `author`",
+            "Unexpected token `,`",
+            "Unexpected token `{`",
+            "Unexpected end of file",
+        ]
+    );
+    let types = ast
+        .type_defs()
+        .map(|type_def| {
+            let fields = type_def.fields.iter().map(|f| f.name.as_str());
+            format!("{}: {}", type_def.name, fields.collect::<Vec<_>>().join(" "))
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(types, vec!["Post: id body likes", "User: name"]);
+    assert_eq!(schema::check(source).len(), 5);
+}
+
+#[test]
+fn test_lexer_whitespace_and_spans() {
+    let source = "\u{feff}type\u{a0}Post {\r\n\tid: integer,\u{2003}// ünïcode\r\n}\r\n";
+    let (tokens, diagnostics) = tokenize(source);
+    assert!(diagnostics.is_empty());
+    let values = tokens.iter().map(|t| t.value).collect::<Vec<_>>();
+    assert_eq!(
+        values,
+        vec!["type", "Post", "{", "id", ":", "integer", ",", "// ünïcode", "}"]
+    );
+    for token in &tokens {
+        assert_eq!(&source[token.span.start..token.span.end], token.value);
+    }
+}
+
+#[test]
+fn test_lexer_invalid_characters() {
+    let source = "type Pöst {\n    2fa: boolean, $x: string\n}";
+    let (tokens, diagnostics) = tokenize(source);
+    assert_eq!(tokens[1].value, "Pöst");
+    let messages = diagnostics
+        .iter()
+        .map(|d| (d.message.as_str(), &source[d.span.start..d.span.end]))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        messages,
+        vec![
+            ("Identifiers cannot start with a digit", "2fa"),
+            ("Invalid character `$`", "$"),
+        ]
+    );
+}
+
+#[test]
+fn test_lexer_literals() {
+    let source = r#""a\n\u{41}" -3 42 +1.5e-3 2E10 true false"#;
+    let (tokens, diagnostics) = tokenize(source);
+    assert!(diagnostics.is_empty());
+    let tokens = tokens.iter().map(|t| (t.ty, t.value)).collect::<Vec<_>>();
+    assert_eq!(
+        tokens,
+        vec![
+            (TokenType::String, r#""a\n\u{41}""#),
+            (TokenType::Integer, "-3"),
+            (TokenType::Integer, "42"),
+            (TokenType::Float, "+1.5e-3"),
+            (TokenType::Float, "2E10"),
+            (TokenType::Boolean, "true"),
+            (TokenType::Boolean, "false"),
+        ]
+    );
+}
+
+#[test]
+fn test_lexer_string_errors() {
+    let source = "\"bad \\q \\u{zz}\"\n\"open\ntype";
+    let (tokens, diagnostics) = tokenize(source);
+    assert_eq!(tokens.last().unwrap().value, "type");
+    let messages = diagnostics
+        .iter()
+        .map(|d| (d.message.as_str(), &source[d.span.start..d.span.end]))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        messages,
+        vec![
+            ("Unknown escape sequence `\\q`", "\\q"),
+            ("Invalid unicode escape, expected `\\u{XXXX}`", "\\u"),
+            ("Unterminated string literal", "\"open"),
+        ]
+    );
+}
+
+const DEFAULTS_SCHEMA: &str = r#"
+type Config {
+    retries: integer = 3,
+    ratio: float = 1,
+    tags: Array<string> = [],
+    name: Optional<string> = "a\tb",
+    createdAt: string
+}
+"#;
+
+#[test]
+fn test_default_values() {
+    let (tokens, _) = tokenize(DEFAULTS_SCHEMA);
+    let ast = gen_ast(&tokens).unwrap();
+    let module = create_module(&ast).unwrap();
+    let defaults = module.types[0]
+        .fields
+        .iter()
+        .map(|field| field.default.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        defaults,
+        vec![
+            Some(SchemeValue::Integer(3)),
+            Some(SchemeValue::Float(1.0)),
+            Some(SchemeValue::Array(vec![])),
+            Some(SchemeValue::String("a\tb".to_string())),
+            None,
+        ]
+    );
+    let options = schema::FormatOptions::default();
+    let formatted = schema::format(DEFAULTS_SCHEMA, &options).unwrap();
+    assert_eq!(formatted, DEFAULTS_SCHEMA.trim_start());
+}
+
+#[test]
+fn test_default_values_with_comments() {
+    let source = r#"type Post {
+    tags: Array<string> = [
+        "a", // first
+        // second
+        "b"
+    ],
+    scores: Map<
+        string, // key
+        integer
+    > = {
+        "x": 1, // one
+        "y" // key
+        : 2
+    },
+    id: string @min_len(
+        1 // not empty
+    )
+}"#;
+    let (tokens, _) = tokenize(source);
+    let ast = gen_ast(&tokens).unwrap();
+    let module = create_module(&ast).unwrap();
+    let fields = &module.types[0].fields;
+    assert_eq!(
+        fields[0].default,
+        Some(SchemeValue::Array(vec![
+            SchemeValue::String("a".to_string()),
+            SchemeValue::String("b".to_string()),
+        ]))
+    );
+    assert!(matches!(&fields[1].default, Some(SchemeValue::Map(entries)) if entries.len() == 2));
+    assert_eq!(fields[2].annotations.len(), 1);
+    // The formatter prints values on one line, so it refuses rather than drop the comments.
+    let options = schema::FormatOptions::default();
+    let errors = schema::format(source, &options).unwrap_err();
+    assert_eq!(errors.len(), 6);
+}
+
+#[test]
+fn test_default_value_errors() {
+    let source = r#"type A {
+    a: integer = "3",
+    b: Array<integer> = [1, 2.5],
+    c: stream integer = 1,
+    d: integer = 99999999999999999999
+}"#;
+    let messages = schema::check(source)
+        .iter()
+        .map(|d| (d.message.clone(), &source[d.span.start..d.span.end]))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        messages,
+        vec![
+            ("Expected a value of type `integer`".to_string(), "\"3\""),
+            ("Expected a value of type `integer`".to_string(), "2.5"),
+            (
+                "`stream` fields cannot have a default value".to_string(),
+                "1"
+            ),
+            (
+                "Integer literal `99999999999999999999` is out of range".to_string(),
+                "99999999999999999999"
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_constraint_argument_errors() {
+    let source = r#"type A {
+    a: string @pattern("(abc"),
+    b: string @pattern("a**"),
+    c: string @pattern("(?=x)"),
+    d: string @pattern("[z-a]"),
+    e: integer @range(10, 0),
+    f: string @pattern("^[\\w-]+$")
+}"#;
+    let messages = schema::check(source)
+        .iter()
+        .map(|d| (d.message.clone(), &source[d.span.start..d.span.end]))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        messages,
+        vec![
+            (
+                "Invalid regular expression: Unclosed group, expected `)`".to_string(),
+                "\"(abc\""
+            ),
+            (
+                "Invalid regular expression: A repetition cannot be repeated, wrap it in a group"
+                    .to_string(),
+                "\"a**\""
+            ),
+            (
+                "Invalid regular expression: Only non-capturing groups `(?:...)` are supported \
+                 after `(?`"
+                    .to_string(),
+                "\"(?=x)\""
+            ),
+            (
+                "Invalid regular expression: Range `z-a` is out of order".to_string(),
+                "\"[z-a]\""
+            ),
+            (
+                "`@range` has a minimum greater than its maximum".to_string(),
+                "@range"
+            ),
+            (
+                "Invalid regular expression: `\\w` means different characters to each target, \
+                 write a class such as `[A-Za-z0-9_]`"
+                    .to_string(),
+                "\"^[\\\\w-]+$\""
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_default_value_constraints() {
+    let source = r#"type A {
+    a: integer @range(0, 10) = 50,
+    b: float @range(min = 0.5) = 0.25,
+    c: string @max_len(2) = "hello",
+    d: Array<string> @min_items(1) = [],
+    e: string @pattern("^[a-z]+$") = "Hello",
+    f: Optional<string> @min_len(3) = null,
+    g: string @pattern("^(?:[a-z]+-)*[0-9]{2,3}$") = "ab-cd-123",
+    h: Array<integer> @max_items(2) = [1, 2]
+}"#;
+    let messages = schema::check(source)
+        .iter()
+        .map(|d| (d.message.clone(), &source[d.span.start..d.span.end]))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        messages,
+        vec![
+            (
+                "The default value breaks `@range`: it must be at most 10".to_string(),
+                "50"
+            ),
+            (
+                "The default value breaks `@range`: it must be at least 0.5".to_string(),
+                "0.25"
+            ),
+            (
+                "The default value breaks `@max_len`: it must have at most 2 characters"
+                    .to_string(),
+                "\"hello\""
+            ),
+            (
+                "The default value breaks `@min_items`: it must have at least 1 items".to_string(),
+                "[]"
+            ),
+            (
+                "The default value breaks `@pattern`: it must match the pattern ^[a-z]+$"
+                    .to_string(),
+                "\"Hello\""
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_pattern_matching() {
+    use schema::regex::Regex;
+    let cases = [
+        ("^[a-z]+$", "hello", true),
+        ("^[a-z]+$", "Hello", false),
+        ("b+c", "abbbcd", true),
+        ("^a{2,3}$", "aaaa", false),
+        ("^a{2,}?b$", "aaab", true),
+        ("^(?:ab|cd)*$", "abcdab", true),
+        ("^(?:ab|cd)*$", "abca", false),
+        ("^[^0-9 ]+$", "abc", true),
+        ("^[^0-9 ]+$", "a1", false),
+        ("^a.c$", "a\nc", false),
+        ("^abc$", "abc\n", false),
+        ("^\\u00e9.$", "\u{e9}x", true),
+        ("^(a*)*b$", "aaab", true),
+        ("x?", "", true),
+        ("^[a-z.-]+@[a-z-]+\\.[a-z]{2,}$", "first.last@example.org", true),
+    ];
+    for (pattern, text, expected) in cases {
+        let regex = Regex::new(pattern).unwrap();
+        assert_eq!(regex.is_match(text), Some(expected), "{pattern} on {text:?}");
+    }
+    for escape in ["\\d", "\\W", "[\\s]", "\\b"] {
+        assert!(Regex::new(escape).is_err(), "{escape}");
+    }
+    // Backtracking is exponential here, so the match gives up.
+    let regex = Regex::new("^(a|a)*b$").unwrap();
+    assert_eq!(regex.is_match(&"a".repeat(40)), None);
+    assert_eq!(
+        schema::regex::javascript("^a.[.]\\.$"),
+        "^a[^\\n][.]\\.$"
+    );
+}
+
+#[test]
+fn test_codegen_rust_defaults() {
+    let assembly = schema::parse(DEFAULTS_SCHEMA).unwrap();
+    assert_eq!(
+        codegen::rust::generate(&assembly),
+        r#"// Generated by rgc. Do not edit.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Config {
+    #[serde(default = "Config::default_retries")]
+    pub retries: i64,
+    #[serde(default = "Config::default_ratio")]
+    pub ratio: f64,
+    #[serde(default = "Config::default_tags")]
+    pub tags: Vec<String>,
+    #[serde(default = "Config::default_name")]
+    pub name: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+}
+
+impl Config {
+    pub fn default_retries() -> i64 {
+        3
+    }
+
+    pub fn default_ratio() -> f64 {
+        1.0
+    }
+
+    pub fn default_tags() -> Vec<String> {
+        Vec::new()
+    }
+
+    pub fn default_name() -> Option<String> {
+        Some("a\tb".to_string())
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            retries: Self::default_retries(),
+            ratio: Self::default_ratio(),
+            tags: Self::default_tags(),
+            name: Self::default_name(),
+            created_at: Default::default(),
+        }
+    }
+}
+"#
+    );
+}
+
+/// Compiles generated Rust code with rustc. The crates it uses are replaced by stubs: derives and
+/// attributes of serde are left out, and `tokio::sync::watch` holds a single value.
+fn compile_rust(code: &str) -> Result<(), String> {
+    const STUBS: &str = "
+mod tokio {
+    pub mod sync {
+        pub mod watch {
+            pub struct Receiver<T>(T);
+            pub struct Sender<T>(std::marker::PhantomData<T>);
+            pub fn channel<T>(value: T) -> (Sender<T>, Receiver<T>) {
+                (Sender(std::marker::PhantomData), Receiver(value))
+            }
+        }
+    }
+}
+";
+    let code = code
+        .lines()
+        .filter(|line| !line.trim_start().starts_with("#[serde(") && !line.starts_with("use serde"))
+        .map(|line| line.replace(", Serialize, Deserialize", ""))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let dir = std::env::temp_dir().join(format!("rgc-rustc-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("generated.rs");
+    std::fs::write(&file, format!("#![allow(dead_code)]\n{code}\n{STUBS}")).unwrap();
+    let output = std::process::Command::new(std::env::var("RUSTC").unwrap_or("rustc".into()))
+        .args(["--edition", "2021", "--crate-type", "lib", "--emit", "metadata", "--out-dir"])
+        .arg(&dir)
+        .arg(&file)
+        .output()
+        .unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    match output.status.success() {
+        true => Ok(()),
+        false => Err(String::from_utf8_lossy(&output.stderr).into_owned()),
+    }
+}
+
+#[test]
+fn test_codegen_rust_sync_defaults_and_keywords() {
+    let source = r#"type Post {
+    self: string,
+    crate: integer = 1,
+    likes: sync integer = 3,
+    tags: sync Array<string> = ["a"]
+}
+type Plain {
+    Self: string,
+    super: Optional<string>
+}
+"#;
+    let assembly = schema::parse(source).unwrap();
+    let code = codegen::rust::generate(&assembly);
+    assert!(code.contains(
+        "pub struct Post {\n    pub self_: String,\n    pub crate_: i64,\n    \
+         pub likes: SyncValue<i64>,\n"
+    ));
+    assert!(code.contains(
+        "    pub fn default_likes() -> SyncValue<i64> {\n        \
+         tokio::sync::watch::channel(3).1\n    }\n"
+    ));
+    assert!(code.contains("    #[serde(rename = \"Self\")]\n    pub self_: String,\n"));
+    assert!(code.contains("    #[serde(rename = \"super\")]\n    pub super_: Option<String>,\n"));
+    compile_rust(&code).unwrap();
+}
+
+#[test]
+fn test_codegen_sync_defaults() {
+    let assembly = schema::parse("type Post {\n    likes: sync integer = 3\n}\n").unwrap();
+    let typescript = codegen::typescript::generate(&assembly);
+    assert!(typescript.contains("        likes: { value: 3, subscribe: () => () => {} },\n"));
+    assert!(typescript.contains(
+        "likes: json.likes === undefined ? { value: 3, subscribe: () => () => {} } : json.likes,"
+    ));
+    let python = codegen::python::generate(&assembly, codegen::python::Flavor::Dataclasses);
+    assert!(python.contains(
+        "    likes: Observable[int] = dataclasses.field(default_factory=lambda: Observable(3))\n"
+    ));
+    let pydantic = codegen::python::generate(&assembly, codegen::python::Flavor::Pydantic);
+    assert!(pydantic.contains(
+        "    likes: Observable[int] = Field(default_factory=lambda: Observable(3))\n"
+    ));
+}
+
+#[test]
+fn test_codegen_typescript_defaults() {
+    let assembly = schema::parse(DEFAULTS_SCHEMA).unwrap();
+    let code = codegen::typescript::generate(&assembly);
+    assert!(code.contains(
+        "export function createConfig(fields: Omit<Config, \"retries\" | \"ratio\" | \"tags\" | \"name\"> & Partial<Pick<Config, \"retries\" | \"ratio\" | \"tags\" | \"name\">>): Config {\n    return {\n        retries: 3,\n        ratio: 1.0,\n        tags: [],\n        name: \"a\\tb\",\n        ...fields,\n    };\n}\n"
+    ));
+    assert!(code.contains("        retries: json.retries === undefined ? 3 : json.retries,\n"));
+    assert!(code.contains("        createdAt: json.createdAt,\n"));
+}
+
+#[test]
+fn test_codegen_type_order_and_recursion() {
+    let source = "type B { next: Optional<B>, a: A }\ntype A { items: Array<B> }";
+    let assembly = schema::parse(source).unwrap();
+    let code = codegen::rust::generate(&assembly);
+    assert!(code.find("pub struct B").unwrap() < code.find("pub struct A").unwrap());
+    assert!(code.contains("    pub next: Option<Box<B>>,\n    pub a: A,\n"));
+}
+
+#[test]
+fn test_codegen_recursion_through_generic_type() {
+    let source = "type Wrap<T> { v: T }\ntype Node { next: Optional<Wrap<Node>>, name: string }";
+    let assembly = schema::parse(source).unwrap();
+    let rust = codegen::rust::generate(&assembly);
+    assert!(rust.contains("pub struct Wrap<T> {\n    pub v: T,\n}\n"));
+    assert!(rust.contains("    pub next: Option<Box<Wrap<Node>>>,\n"));
+    compile_rust(&rust).unwrap();
+    let swift = codegen::swift::generate(&assembly);
+    assert!(swift.contains("struct Wrap<T> {\n"));
+    assert!(swift.contains("extension Wrap: Codable where T: Codable {}\n"));
+    assert!(swift.contains("final class Node: Codable {\n    var next: Wrap<Node>?\n"));
+}
+
+const ANNOTATED_SCHEMA: &str = r#"// Users.
+@deprecated("use v2")
+type User {
+    @id
+    id: integer,
+    @rename(ts = "userName")
+    name: string,
+    @audit(level = 2, "x")
+    age: integer
+}
+"#;
+
+#[test]
+fn test_annotations() {
+    let (tokens, _) = tokenize(ANNOTATED_SCHEMA);
+    let ast = gen_ast(&tokens).unwrap();
+    let type_def = ast.type_defs().next().unwrap();
+    assert_eq!(type_def.annotations[0].name, "deprecated");
+    assert_eq!(type_def.annotations[0].name_span, Span::new(10, 21));
+    assert_eq!(type_def.fields[1].annotations[0].args[0].name.as_deref(), Some("ts"));
+
+    let module = create_module(&ast).unwrap();
+    assert_eq!(
+        module.types[0].annotations,
+        vec![SchemeAnnotation {
+            name: "deprecated".to_string(),
+            args: vec![SchemeAnnotationArg {
+                name: Some("reason".to_string()),
+                value: SchemeValue::String("use v2".to_string()),
+            }],
+        }]
+    );
+    // Unknown annotations are kept as written.
+    assert_eq!(
+        module.types[0].fields[2].annotations[0].args,
+        vec![
+            SchemeAnnotationArg {
+                name: Some("level".to_string()),
+                value: SchemeValue::Integer(2),
+            },
+            SchemeAnnotationArg {
+                name: None,
+                value: SchemeValue::String("x".to_string()),
+            },
+        ]
+    );
+
+    let options = schema::FormatOptions::default();
+    assert_eq!(schema::format(ANNOTATED_SCHEMA, &options).unwrap(), ANNOTATED_SCHEMA);
+}
+
+#[test]
+fn test_annotation_errors() {
+    let source = r#"@id
+type A {
+    @since
+    @id(1)
+    @deprecated(reason = 1)
+    @deprecated
+    x: integer,
+    @ y: string
+}
+@rename
+"#;
+    let messages = schema::check(source)
+        .iter()
+        .map(|d| d.message.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        messages,
+        vec![
+            "Expected an annotation name right after `@`",
+            "Annotation `@rename` is not followed by a declaration",
+            "`@id` cannot be used on types",
+            "`@since` requires argument `version`",
+            "`@id` takes no arguments",
+            "Expected a value of type `string`",
+            "`@deprecated` is applied more than once",
+        ]
+    );
+}
+
+#[test]
+fn test_codegen_annotations() {
+    let assembly = schema::parse(ANNOTATED_SCHEMA).unwrap();
+    let rust = codegen::rust::generate(&assembly);
+    assert!(rust.contains("#[deprecated(note = \"use v2\")]\n#[derive("));
+    assert!(rust.contains("    pub name: String,\n"));
+    let typescript = codegen::typescript::generate(&assembly);
+    assert!(typescript.contains("/** @deprecated use v2 */\nexport interface User {\n"));
+    assert!(typescript.contains("    userName: string;\n"));
+    assert!(typescript.contains("        userName: json.name,\n"));
+}
+
+#[test]
+fn test_lsp_annotation_completion() {
+    let mut server = lsp::Server::default();
+    lsp_open(&mut server, "file:///a.rgc", "type A {\n    @\n}");
+    let completion = lsp_request(
+        &mut server,
+        "textDocument/completion",
+        lsp_position("file:///a.rgc", 1, 5),
+    );
+    let labels = completion
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|item| item.get("label").and_then(json::Json::as_str))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        labels,
+        vec![
+            "deprecated",
+            "rename",
+            "id",
+            "tag",
+            "since",
+            "range",
+            "pattern",
+            "min_len",
+            "max_len",
+            "min_items",
+            "max_items",
+        ]
+    );
+}
+
+const CONSTRAINED_SCHEMA: &str = r#"type Person {
+    age: integer @range(0, 150),
+    email: Optional<string> @pattern("^[^@]+@[^@]+$") @max_len(254),
+    tags: Array<string> @min_items(1),
+    score: float @range(max = 1),
+    friends: Array<Person>
+}
+"#;
+
+#[test]
+fn test_constraints() {
+    let assembly = schema::parse(CONSTRAINED_SCHEMA).unwrap();
+    let person = assembly.types[0].borrow();
+    let email = &person.fields[1];
+    assert_eq!(email.annotations.len(), 2);
+    assert_eq!(
+        email.annotations[0].arg("regex"),
+        Some(&SchemeValue::String("^[^@]+@[^@]+$".to_string()))
+    );
+    // Integer bounds of float fields are floats.
+    assert_eq!(
+        person.fields[3].annotations[0].arg("max"),
+        Some(&SchemeValue::Float(1.0))
+    );
+    let options = schema::FormatOptions::default();
+    let formatted = schema::format(CONSTRAINED_SCHEMA, &options).unwrap();
+    assert_eq!(formatted, CONSTRAINED_SCHEMA);
+}
+
+#[test]
+fn test_constraint_errors() {
+    let source = r#"@min_len(1)
+type A {
+    a: string @range(0, 10),
+    b: integer @range(0, "10"),
+    c: integer @range,
+    d: stream Array<string> @min_items(1),
+    e: Array<string> @pattern("x"),
+    f: Optional<float> @range(min = 0, min = 1),
+    g: string @min_len(-1),
+    h: string @max_len(len = -2),
+    i: Array<string> @min_items(-1) @max_items(-3),
+    j: string @min_len(5) @max_len(2),
+    k: Array<integer> @min_items(4) @max_items(3),
+    l: integer @range(-5, -1) @since(-1)
+}
+"#;
+    let messages = schema::check(source)
+        .iter()
+        .map(|d| d.message.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        messages,
+        vec![
+            "`@min_len` cannot be used on types",
+            "`@range` cannot be used on a field of type `string`",
+            "Expected a value of type `integer`",
+            "`@range` expects at least 1 argument(s)",
+            "`@min_items` cannot be used on `stream` or `sync` fields",
+            "`@pattern` cannot be used on a field of type `Array<string>`",
+            "Argument `min` of `@range` is given more than once",
+            "Argument `len` of `@min_len` cannot be negative",
+            "Argument `len` of `@max_len` cannot be negative",
+            "Argument `count` of `@min_items` cannot be negative",
+            "Argument `count` of `@max_items` cannot be negative",
+            "`@max_len` is less than `@min_len`",
+            "`@max_items` is less than `@min_items`",
+        ]
+    );
+}
+
+#[test]
+fn test_codegen_rust_validators() {
+    let assembly = schema::parse(CONSTRAINED_SCHEMA).unwrap();
+    let rust = codegen::rust::generate(&assembly);
+    assert!(rust.contains("pub trait Validate {\n"));
+    assert!(rust.contains(
+        r#"impl Validate for Person {
+    fn validate_at(&self, path: &str, errors: &mut Vec<ValidationError>) {
+        if self.age < 0 {
+            errors.push(ValidationError {
+                path: format!("{path}age"),
+                constraint: "range",
+                message: "must be at least 0".to_string(),
+            });
+        }
+"#
+    ));
+    assert!(rust.contains(
+        r#"        if let Some(value0) = &self.email {
+            static EMAIL_PATTERN: std::sync::OnceLock<regex::Regex> = std::sync::OnceLock::new();
+            let email_pattern = EMAIL_PATTERN.get_or_init(|| regex::Regex::new("^[^@]+@[^@]+$").unwrap());
+            if !email_pattern.is_match(&(*value0)) {
+"#
+    ));
+    assert!(rust.contains("        if self.score > 1.0 {\n"));
+    assert!(rust.contains(
+        r#"        for (i0, item0) in self.friends.iter().enumerate() {
+            item0.validate_at(&format!("{path}friends[{i0}]."), errors);
+        }
+"#
+    ));
+}
+
+#[test]
+fn test_codegen_typescript_validators() {
+    let assembly = schema::parse(CONSTRAINED_SCHEMA).unwrap();
+    let typescript = codegen::typescript::generate(&assembly);
+    assert!(typescript.contains("export interface ValidationError {\n"));
+    assert!(typescript.contains(
+        r#"export function validatePerson(value: Person, path: string = ""): ValidationError[] {
+    const errors: ValidationError[] = [];
+    if (value.age < 0) {
+        errors.push({ path: `${path}age`, constraint: "range", message: "must be at least 0" });
+    }
+"#
+    ));
+    assert!(typescript.contains(
+        r#"    if (value.email != null) {
+        if (!new RegExp("^[^@]+@[^@]+$", "u").test(value.email)) {
+"#
+    ));
+    assert!(typescript.contains(
+        r#"    value.friends.forEach((item0, i0) => {
+        errors.push(...validatePerson(item0, `${path}friends[${i0}].`));
+    });
+    return errors;
+}
+"#
+    ));
+}
+
+const GENERIC_CONSTRAINED_SCHEMA: &str = r#"type Post {
+    title: string @max_len(10)
+}
+type Page<T> {
+    items: Array<T>,
+    next: Optional<Page<T>>
+}
+type Holder<T> {
+    value: T
+}
+type Feed {
+    posts: Page<Post>,
+    nested: Holder<Array<Post>>,
+    counts: Holder<integer>
+}
+"#;
+
+#[test]
+fn test_codegen_rust_generic_validators() {
+    let assembly = schema::parse(GENERIC_CONSTRAINED_SCHEMA).unwrap();
+    let rust = codegen::rust::generate(&assembly);
+    assert!(rust.contains(
+        r#"impl<T> Page<T> {
+    /// Validates `self` like [`Validate::validate_at`], checking the values of type parameters
+    /// with `validate_t` for `T`.
+    pub fn validate_with(
+        &self,
+        path: &str,
+        errors: &mut Vec<ValidationError>,
+        validate_t: &dyn Fn(&T, &str, &mut Vec<ValidationError>),
+    ) {
+        for (i0, item0) in self.items.iter().enumerate() {
+            validate_t(item0, &format!("{path}items[{i0}]"), errors);
+        }
+        if let Some(value0) = &self.next {
+            value0.validate_with(&format!("{path}next."), errors, validate_t);
+        }
+    }
+}
+
+impl<T> Validate for Page<T> {
+    fn validate_at(&self, path: &str, errors: &mut Vec<ValidationError>) {
+        self.validate_with(path, errors, &|_, _, _| {});
+    }
+}
+"#
+    ));
+    assert!(rust.contains(
+        r#"impl Validate for Feed {
+    fn validate_at(&self, path: &str, errors: &mut Vec<ValidationError>) {
+        self.posts.validate_with(
+            &format!("{path}posts."),
+            errors,
+            &|value, path, errors| {
+                value.validate_at(&format!("{path}."), errors);
+            },
+        );
+        self.nested.validate_with(
+            &format!("{path}nested."),
+            errors,
+            &|value, path, errors| {
+                for (i0, item0) in value.iter().enumerate() {
+                    item0.validate_at(&format!("{path}[{i0}]."), errors);
+                }
+            },
+        );
+        self.counts.validate_with(&format!("{path}counts."), errors, &|_, _, _| {});
+    }
+}
+"#
+    ));
+    compile_rust(&rust).unwrap();
+}
+
+#[test]
+fn test_codegen_typescript_generic_validators() {
+    let assembly = schema::parse(GENERIC_CONSTRAINED_SCHEMA).unwrap();
+    let typescript = codegen::typescript::generate(&assembly);
+    assert!(typescript.contains(
+        r#"export function validatePage<T>(value: Page<T>, path: string = "", validateT: (value: T, path: string) => ValidationError[] = () => []): ValidationError[] {
+    const errors: ValidationError[] = [];
+    value.items.forEach((item0, i0) => {
+        errors.push(...validateT(item0, `${path}items[${i0}]`));
+    });
+    if (value.next != null) {
+        errors.push(...validatePage(value.next, `${path}next.`, validateT));
+    }
+    return errors;
+}
+"#
+    ));
+    assert!(typescript.contains(
+        r#"    errors.push(...validatePage(
+        value.posts,
+        `${path}posts.`,
+        (value, path) => {
+            const errors: ValidationError[] = [];
+            errors.push(...validatePost(value, `${path}.`));
+            return errors;
+        },
+    ));
+"#
+    ));
+    assert!(typescript
+        .contains("    errors.push(...validateHolder(value.counts, `${path}counts.`, () => []));\n"));
+}
+
+const ABSENT_FIELDS_SCHEMA: &str = r#"type User {
+    nickname?: string @min_len(1),
+    bio?: Optional<string>,
+    manager?: User,
+    middle: Optional<string>
+}
+"#;
+
+#[test]
+fn test_fields_that_may_be_absent() {
+    let assembly = schema::parse(ABSENT_FIELDS_SCHEMA).unwrap();
+    let user = assembly.types[0].borrow();
+    let optional = user
+        .fields
+        .iter()
+        .map(|field| field.optional)
+        .collect::<Vec<_>>();
+    assert_eq!(optional, vec![true, true, true, false]);
+    let options = schema::FormatOptions::default();
+    let formatted = schema::format(ABSENT_FIELDS_SCHEMA, &options).unwrap();
+    assert_eq!(formatted, ABSENT_FIELDS_SCHEMA);
+
+    for (source, message) in [
+        ("type A {\n    a??: string\n}\n", "Unexpected token `?`"),
+        (
+            "type A {\n    b?: integer = 1\n}\n",
+            "Fields that may be absent (`?`) cannot have a default value",
+        ),
+    ] {
+        let messages = schema::check(source)
+            .iter()
+            .map(|d| d.message.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(messages, vec![message]);
+    }
+}
+
+#[test]
+fn test_codegen_fields_that_may_be_absent() {
+    let assembly = schema::parse(ABSENT_FIELDS_SCHEMA).unwrap();
+    let rust = codegen::rust::generate(&assembly);
+    assert!(rust.contains(
+        r#"pub struct User {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nickname: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "deserialize_present")]
+    pub bio: Option<Option<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manager: Option<Box<User>>,
+    pub middle: Option<String>,
+}
+"#
+    ));
+    assert!(rust.contains("fn deserialize_present<'de, T, D>"));
+    assert!(rust.contains("        if let Some(value0) = &self.nickname {\n"));
+
+    let typescript = codegen::typescript::generate(&assembly);
+    assert!(typescript.contains(
+        "    nickname?: string;\n    bio?: string | null;\n    manager?: User;\n    middle: string | null;\n"
+    ));
+    assert!(typescript.contains(
+        "        manager: json.manager === undefined ? undefined : decodeUser(json.manager),\n"
+    ));
+    assert!(typescript.contains("    if (value.nickname != null) {\n"));
+}
+
+const EXTENDED_SCHEMA: &str = r#"type Entity {
+    id: string,
+    createdAt: integer
+}
+
+type Post extends Entity {
+    title: string
+}
+
+type Pinned extends Post {}
+"#;
+
+#[test]
+fn test_extends() {
+    let assembly = schema::parse(EXTENDED_SCHEMA).unwrap();
+    let pinned = assembly.types[2].borrow();
+    let fields = pinned
+        .fields
+        .iter()
+        .map(|field| field.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(fields, vec!["id", "createdAt", "title"]);
+    assert_eq!(pinned.base.as_ref().unwrap().borrow().name, "Post");
+    let options = schema::FormatOptions::default();
+    let formatted = schema::format(EXTENDED_SCHEMA, &options).unwrap();
+    assert_eq!(formatted, EXTENDED_SCHEMA);
+
+    let source = r#"type A extends B {
+    x: string
+}
+type B extends A {}
+type C extends string {}
+type D extends Entity {
+    id: integer
+}
+type Entity {
+    id: string
+}
+"#;
+    let messages = schema::check(source)
+        .iter()
+        .map(|d| d.message.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        messages,
+        vec![
+            "Type `A` extends itself",
+            "Type `B` extends itself",
+            "`string` is a builtin type and cannot be extended",
+            "Field `id` is already defined in base type `Entity`",
+        ]
+    );
+}
+
+#[test]
+fn test_codegen_extends() {
+    let assembly = schema::parse(EXTENDED_SCHEMA).unwrap();
+    let typescript = codegen::typescript::generate(&assembly);
+    assert!(typescript.contains("export interface Post extends Entity {\n    title: string;\n}\n"));
+    assert!(typescript.contains(
+        "export function decodePost(json: any): Post {\n    return {\n        id: json.id,\n"
+    ));
+    let rust = codegen::rust::generate(&assembly);
+    assert!(rust.contains(
+        "pub struct Post {\n    pub id: String,\n    #[serde(rename = \"createdAt\")]\n    pub created_at: i64,\n    pub title: String,\n}\n"
+    ));
+}
+
+const GENERIC_SCHEMA: &str = r#"type Page<T> {
+    items: Array<T>,
+    next: Optional<string>
+}
+
+type Pair<A, B> {
+    first: A,
+    second: B
+}
+
+type Feed {
+    posts: Page<Post>,
+    pairs: Page<Pair<string, Post>>
+}
+
+type Post {
+    title: string
+}
+"#;
+
+#[test]
+fn test_generics() {
+    let (ast, diagnostics) = schema::parse_ast(GENERIC_SCHEMA);
+    assert!(diagnostics.is_empty());
+    let module = create_module(&ast).unwrap();
+    assert_eq!(module.types[0].params, vec!["T".to_string()]);
+    assert_eq!(
+        module.types[0].fields[0].ty.ty_ref,
+        SchemeTypeRef::Array(Box::new(SchemeTypeRef::Param("T".to_string())))
+    );
+    assert_eq!(
+        module.types[2].fields[1].ty.ty_ref,
+        SchemeTypeRef::Instance(
+            "Page".to_string(),
+            vec![SchemeTypeRef::Instance(
+                "Pair".to_string(),
+                vec![
+                    SchemeTypeRef::Builtin(BuiltinType::String),
+                    SchemeTypeRef::Custom("Post".to_string()),
+                ]
+            )]
+        )
+    );
+    let options = schema::FormatOptions::default();
+    let formatted = schema::format(GENERIC_SCHEMA, &options).unwrap();
+    assert_eq!(formatted, GENERIC_SCHEMA);
+
+    let source = r#"type Page<T, T> {
+    items: Array<T>
+}
+type Box<string, U> {
+    value: string
+}
+type Holder {
+    a: Page,
+    b: Page<string>,
+    c: T
+}
+type Wrapper<T> {
+    value: T<string>
+}
+type Child extends Wrapper {}
+"#;
+    let messages = schema::check(source)
+        .iter()
+        .map(|d| d.message.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        messages,
+        vec![
+            "Type parameter `T` is declared more than once",
+            "Type parameter `string` has the same name as a type",
+            "Type parameter `U` is never used",
+            "`Page` expects 2 type parameter(s), found 0",
+            "`Page` expects 2 type parameter(s), found 1",
+            "Unknown type `T`",
+            "Type parameter `T` cannot take type parameters",
+            "`Wrapper` is generic and cannot be extended",
+        ]
+    );
+}
+
+#[test]
+fn test_codegen_generics() {
+    let assembly = schema::parse(GENERIC_SCHEMA).unwrap();
+    let rust = codegen::rust::generate(&assembly);
+    assert!(rust.contains("pub struct Page<T> {\n    pub items: Vec<T>,\n"));
+    assert!(rust.contains("pub struct Pair<A, B> {\n    pub first: A,\n    pub second: B,\n}\n"));
+    assert!(rust.contains("    pub pairs: Page<Pair<String, Post>>,\n"));
+    let typescript = codegen::typescript::generate(&assembly);
+    assert!(typescript.contains("export interface Page<T> {\n    items: Array<T>;\n"));
+    assert!(typescript.contains(
+        "export function decodePage<T>(json: any, decodeT: (json: any) => T): Page<T> {\n    return {\n        items: json.items.map((item0: any) => decodeT(item0)),\n"
+    ));
+    assert!(typescript.contains(
+        "        pairs: decodePage(json.pairs, (json0: any) => decodePair(json0, (json1: any) => json1, decodePost)),\n"
+    ));
+}
+
+const SERVICE_SCHEMA: &str = r#"type User {
+    id: integer,
+    name: string
+}
+
+service Users {
+    remote function get(id: integer) -> User;
+    remote function search(query: string, limit: integer) -> stream User;
+    @rename("current")
+    remote function me() -> sync User;
+    remote function names() -> stream string;
+    remote function ping()
+}
+"#;
+
+#[test]
+fn test_services() {
+    let (ast, diagnostics) = schema::parse_ast(SERVICE_SCHEMA);
+    assert!(diagnostics.is_empty());
+    let module = create_module(&ast).unwrap();
+    let service = &module.services[0];
+    assert_eq!(service.name, "Users");
+    assert_eq!(service.functions.len(), 5);
+    assert_eq!(
+        service.functions[1].params,
+        vec![
+            SchemeParam {
+                name: "query".to_string(),
+                ty: SchemeTypeRef::Builtin(BuiltinType::String),
+                annotations: vec![],
+            },
+            SchemeParam {
+                name: "limit".to_string(),
+                ty: SchemeTypeRef::Builtin(BuiltinType::Integer),
+                annotations: vec![],
+            },
+        ]
+    );
+    assert_eq!(
+        service.functions[1].returns,
+        Some(SchemeFieldType {
+            kind: SchemeFieldTypeKind::Stream,
+            ty_ref: SchemeTypeRef::Custom("User".to_string()),
+        })
+    );
+    assert_eq!(service.functions[4].returns, None);
+    let options = schema::FormatOptions::default();
+    let formatted = schema::format(SERVICE_SCHEMA, &options).unwrap();
+    assert_eq!(formatted, SERVICE_SCHEMA);
+
+    // Comments may sit between the parameters of a function spread over several lines.
+    let source = r#"service Search {
+    remote function find(
+        // What to look for.
+        query: string, // free text
+        limit: integer @tag(3) // at most
+    ) -> stream string
+}
+"#;
+    let (ast, diagnostics) = schema::parse_ast(source);
+    assert!(diagnostics.is_empty());
+    let module = create_module(&ast).unwrap();
+    let function = &module.services[0].functions[0];
+    let names = function.params.iter().map(|param| param.name.as_str());
+    assert_eq!(names.collect::<Vec<_>>(), vec!["query", "limit"]);
+    assert_eq!(function.params[1].annotations.len(), 1);
+
+    let source = r#"type User {
+    id: integer
+}
+service User {}
+service S {
+    remote function a(x: integer, x: Nope) -> Page<User>;
+    remote function a();
+    @max_len(1)
+    remote function b()
+}
+service S {}
+"#;
+    let messages = schema::check(source)
+        .iter()
+        .map(|d| d.message.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        messages,
+        vec![
+            "Service `User` has the same name as a type",
+            "Parameter `x` is declared more than once in function `a`",
+            "Unknown type `Nope`",
+            "Unknown type `Page`",
+            "Function `a` is defined more than once in service `S`",
+            "`@max_len` cannot be used on functions",
+            "Service `S` is defined more than once",
+        ]
+    );
+    let messages = schema::check("remote function a()\ntype A {}\n")
+        .iter()
+        .map(|d| d.message.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        messages,
+        vec!["Remote functions must be declared in a `service` block"]
+    );
+}
+
+#[test]
+fn test_codegen_services() {
+    let assembly = schema::parse(SERVICE_SCHEMA).unwrap();
+    let rust = codegen::rust::generate(&assembly);
+    assert!(rust.contains("pub type Stream<T> ="));
+    assert!(rust.contains("pub type SyncValue<T> ="));
+    assert!(rust.contains(
+        "pub trait Users: Send + Sync {\n    fn get(&self, id: i64) -> impl std::future::Future<Output = User> + Send;\n"
+    ));
+    assert!(rust.contains("    fn search(&self, query: String, limit: i64) -> Stream<User>;\n"));
+    assert!(rust.contains("    fn current(&self) -> SyncValue<User>;\n"));
+    assert!(rust.contains("    fn ping(&self) -> impl std::future::Future<Output = ()> + Send;\n}\n"));
+    let typescript = codegen::typescript::generate(&assembly);
+    assert!(typescript.contains("export interface Transport {\n"));
+    assert!(typescript.contains(
+        "export class UsersClient {\n    constructor(private readonly transport: Transport) {}\n"
+    ));
+    assert!(typescript.contains(
+        "    async get(id: number): Promise<User> {\n        const json = await this.transport.call(\"Users\", \"get\", { id });\n        return decodeUser(json);\n    }\n"
+    ));
+    assert!(typescript.contains(
+        "    async *search(query: string, limit: number): AsyncIterable<User> {\n        for await (const item of this.transport.stream(\"Users\", \"search\", { query, limit })) {\n            yield decodeUser(item);\n        }\n    }\n"
+    ));
+    assert!(typescript.contains(
+        "    current(): Sync<User> {\n        return this.transport.sync(\"Users\", \"me\", {});\n    }\n"
+    ));
+    assert!(typescript.contains(
+        "    names(): AsyncIterable<string> {\n        return this.transport.stream(\"Users\", \"names\", {});\n    }\n"
+    ));
+    assert!(typescript.contains(
+        "    async ping(): Promise<void> {\n        await this.transport.call(\"Users\", \"ping\", {});\n    }\n"
+    ));
+}
+
+const CONST_SCHEMA: &str = r#"// Paging limits.
+const MAX_PAGE_SIZE: integer = 100
+const DEFAULT_PAGE_SIZE: integer = 20
+
+@deprecated
+const API_PREFIX: string = "/api/v1"
+
+type Query {
+    limit: integer @range(min = 1, max = MAX_PAGE_SIZE) = DEFAULT_PAGE_SIZE,
+    scale: float = MAX_PAGE_SIZE,
+    prefix: Optional<string> = API_PREFIX
+}
+"#;
+
+#[test]
+fn test_consts() {
+    let (ast, diagnostics) = schema::parse_ast(CONST_SCHEMA);
+    assert!(diagnostics.is_empty());
+    let module = create_module(&ast).unwrap();
+    assert_eq!(
+        module.consts[0],
+        SchemeConst {
+            name: "MAX_PAGE_SIZE".to_string(),
+            ty: BuiltinType::Integer,
+            value: SchemeValue::Integer(100),
+            annotations: vec![],
+        }
+    );
+    assert_eq!(
+        module.consts[2].value,
+        SchemeValue::String("/api/v1".to_string())
+    );
+    let fields = &module.types[0].fields;
+    assert_eq!(fields[0].default, Some(SchemeValue::Integer(20)));
+    assert_eq!(
+        fields[0].annotations[0].arg("max"),
+        Some(&SchemeValue::Integer(100))
+    );
+    assert_eq!(fields[1].default, Some(SchemeValue::Float(100.0)));
+    assert_eq!(
+        fields[2].default,
+        Some(SchemeValue::String("/api/v1".to_string()))
+    );
+    let options = schema::FormatOptions::default();
+    let formatted = schema::format(CONST_SCHEMA, &options).unwrap();
+    assert_eq!(formatted, CONST_SCHEMA);
+
+    let source = r#"const A: integer = 1
+const A: integer = 2
+const B: Array<integer> = [1]
+const C: string = 5
+const D: integer = A
+@min_len(1)
+const E: string = "x"
+type T {
+    d: string @max_len(E)
+}
+"#;
+    let messages = schema::check(source)
+        .iter()
+        .map(|d| d.message.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        messages,
+        vec![
+            "Constant `A` is defined more than once",
+            "Constants must be of type `integer`, `float`, `string` or `boolean`",
+            "Expected a value of type `string`",
+            "The value of a constant must be a literal",
+            "`@min_len` cannot be used on constants",
+            "Constant `E` is not of type `integer`",
+        ]
+    );
+    let source = r#"const A: integer = 1
+type T {
+    a: string = A,
+    b: integer = NOPE,
+    c: float = A
+}
+"#;
+    let messages = schema::check(source)
+        .iter()
+        .map(|d| d.message.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        messages,
+        vec![
+            "Constant `A` is not of type `string`",
+            "Unknown constant `NOPE`",
+        ]
+    );
+}
+
+#[test]
+fn test_codegen_consts() {
+    let assembly = schema::parse(CONST_SCHEMA).unwrap();
+    let rust = codegen::rust::generate(&assembly);
+    assert!(rust.contains(
+        "pub const MAX_PAGE_SIZE: i64 = 100;\npub const DEFAULT_PAGE_SIZE: i64 = 20;\n#[deprecated]\npub const API_PREFIX: &str = \"/api/v1\";\n"
+    ));
+    assert!(rust.contains("    pub fn default_limit() -> i64 {\n        20\n    }\n"));
+    let typescript = codegen::typescript::generate(&assembly);
+    assert!(typescript.contains(
+        "export const MAX_PAGE_SIZE: number = 100;\nexport const DEFAULT_PAGE_SIZE: number = 20;\n/** @deprecated */\nexport const API_PREFIX: string = \"/api/v1\";\n"
+    ));
+}
+
+const PYTHON_SCHEMA: &str = r#"
+const MAX_AGE: integer = 150
+
+type User {
+    displayName: string @max_len(50),
+    age?: integer @range(min = 0, max = 150),
+    posts: Array<Post>,
+    tags: Array<string> = ["new"],
+    flags: Map<boolean, string>,
+    class: string @rename(py = "kind")
+}
+
+type Post extends Entity {
+    author: User
+}
+
+type Live {
+    updates: stream Post,
+    likes: sync integer
+}
+
+type Entity {
+    id: integer
+}
+
+type Page<T> {
+    items: Array<T>
+}
+"#;
+
+#[test]
+fn test_codegen_python() {
+    let assembly = schema::parse(PYTHON_SCHEMA).unwrap();
+    let python = codegen::python::generate(&assembly, codegen::python::Flavor::Dataclasses);
+    assert!(python.contains("from __future__ import annotations\n\nimport dataclasses\n"));
+    assert!(python.contains("MAX_AGE: Final[int] = 150\n"));
+    assert!(python.contains("class Observable(Generic[_T]):\n"));
+    assert!(python.contains(
+        "@dataclasses.dataclass(kw_only=True)\nclass User:\n    display_name: str\n    age: Optional[int] = None\n    posts: list[Post]\n    tags: list[str] = dataclasses.field(default_factory=lambda: [\"new\"])\n    flags: dict[bool, str]\n    kind: str\n"
+    ));
+    assert!(python.contains("\"posts\": [item0.to_json() for item0 in self.posts],"));
+    assert!(python.contains("flags={key0: value0 for key0, value0 in data[\"flags\"]},"));
+    assert!(python.contains(
+        "class Live:\n    updates: AsyncIterator[Post]\n    likes: Observable[int]\n"
+    ));
+    // Base classes are declared before the classes extending them.
+    assert!(python.find("class Entity:").unwrap() < python.find("class Post(Entity):").unwrap());
+    assert!(python.contains("class Page(Generic[T]):\n    items: list[T]\n"));
+    assert!(python.contains("def from_json(cls, data: Any, decode_t: Callable[[Any], T]) -> Page[T]:"));
+
+    let pydantic = codegen::python::generate(&assembly, codegen::python::Flavor::Pydantic);
+    assert!(!pydantic.contains("import dataclasses"));
+    assert!(pydantic.contains(
+        "class User(BaseModel):\n    model_config = ConfigDict(populate_by_name=True)\n    display_name: str = Field(alias=\"displayName\", max_length=50)\n    age: Optional[int] = Field(None, ge=0, le=150)\n"
+    ));
+    assert!(pydantic.contains(
+        "    flags: Annotated[dict[bool, str], BeforeValidator(_from_pairs), PlainSerializer(_to_pairs)]\n    kind: str = Field(alias=\"class\")\n"
+    ));
+    assert!(pydantic.contains(
+        "class Live(BaseModel):\n    model_config = ConfigDict(populate_by_name=True, arbitrary_types_allowed=True)\n"
+    ));
+    assert!(pydantic.contains("class Page(BaseModel, Generic[T]):\n"));
+    assert!(pydantic.ends_with("\n\n\nUser.model_rebuild()\n"));
+}
+
+#[test]
+fn test_codegen_python_round_trip() {
+    let assembly = schema::parse(PYTHON_SCHEMA).unwrap();
+    let python = codegen::python::generate(&assembly, codegen::python::Flavor::Dataclasses);
+    // Subclasses convert their inherited fields too, and live types the rest of their fields.
+    assert!(python.contains(
+        "    def from_json(cls, data: Any) -> Post:\n        return cls(\n            id=data[\"id\"],\n            author=User.from_json(data[\"author\"]),\n"
+    ));
+    assert!(python.contains(
+        "    def from_json(cls, data: Any, *, updates: AsyncIterator[Post], likes: Observable[int]) -> Live:\n"
+    ));
+    let script = format!(
+        r#"{python}
+
+user = {{"displayName": "Ann", "posts": [], "tags": ["a"], "flags": [[True, "x"]], "class": "admin"}}
+post = {{"id": 7, "author": dict(user, posts=[{{"id": 8, "author": user}}])}}
+decoded = Post.from_json(post)
+assert isinstance(decoded, Entity) and decoded.id == 7, decoded
+assert decoded.to_json() == post, decoded.to_json()
+live = Live.from_json({{}}, updates=None, likes=Observable(1))
+assert live.likes.value == 1 and live.to_json() == {{}}
+"#
+    );
+    let output = match std::process::Command::new("python3")
+        .args(["-c", &script])
+        .output()
+    {
+        Ok(output) => output,
+        // Without Python, the generated code is only checked by the assertions above.
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return,
+        Err(err) => panic!("{err}"),
+    };
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+const GO_SCHEMA: &str = r#"
+const MAX_PAGE_SIZE: integer = 100
+
+type User {
+    userId: integer,
+    displayName: string,
+    bestFriend: Optional<User>,
+    nickname?: string,
+    tags: Array<string> = ["new"],
+    flags: Map<boolean, string>,
+    @deprecated("use tags")
+    labels?: Array<string>
+}
+
+type Node {
+    value: integer,
+    child: Leaf
+}
+
+type Leaf {
+    root: Node
+}
+
+type Child extends Entity {
+    name: string
+}
+
+type Entity {
+    id: integer
+}
+
+type Page<T> {
+    items: Array<T>
+}
+
+type Live {
+    title: string,
+    updates: stream User,
+    count: sync integer
+}
+
+service Users {
+    remote function get(id: integer) -> User;
+    remote function search(query: string) -> stream User;
+    remote function ping()
+}
+"#;
+
+#[test]
+fn test_codegen_go() {
+    let assembly = schema::parse(GO_SCHEMA).unwrap();
+    let go = codegen::go::generate(&assembly, "users");
+    assert!(go.starts_with(
+        "// Code generated by rgc. DO NOT EDIT.\n\npackage users\n\nimport (\n\t\"context\"\n\t\"encoding/json\"\n\t\"sync\"\n)\n"
+    ));
+    assert!(go.contains("\nconst MaxPageSize int64 = 100\n"));
+    assert!(go.contains(
+        "type User struct {\n\
+         \tUserID      int64               `json:\"userId\"`\n\
+         \tDisplayName string              `json:\"displayName\"`\n\
+         \tBestFriend  *User               `json:\"bestFriend\"`\n\
+         \tNickname    *string             `json:\"nickname,omitempty\"`\n\
+         \tTags        []string            `json:\"tags\"`\n\
+         \tFlags       Pairs[bool, string] `json:\"flags\"`\n\
+         \n\
+         \t// Deprecated: use tags\n\
+         \tLabels *[]string `json:\"labels,omitempty\"`\n\
+         }\n"
+    ));
+    assert!(go.contains(
+        "func NewUser() User {\n\tvar value User\n\tvalue.Tags = []string{\"new\"}\n\treturn value\n}\n"
+    ));
+    // Cyclic values are stored behind pointers.
+    assert!(go.contains("\tChild *Leaf `json:\"child\"`\n"));
+    assert!(go.contains("type Child struct {\n\tEntity\n\n\tName string `json:\"name\"`\n}\n"));
+    assert!(go.contains("type Page[T any] struct {\n\tItems []T `json:\"items\"`\n}\n"));
+    assert!(go.contains(
+        "type Live struct {\n\tTitle   string            `json:\"title\"`\n\tUpdates <-chan User       `json:\"-\"`\n\tCount   *SyncValue[int64] `json:\"-\"`\n}\n"
+    ));
+    assert!(go.contains("type SyncValue[T any] struct {\n\tmu        sync.Mutex\n"));
+    assert!(go.contains(
+        "type Users interface {\n\
+         \tGet(ctx context.Context, id int64) (User, error)\n\
+         \tSearch(ctx context.Context, query string) (<-chan User, error)\n\
+         \tPing(ctx context.Context) error\n\
+         }\n"
+    ));
+
+    assert_eq!(codegen::go::package_name("user-api"), "userapi");
+    assert_eq!(codegen::go::package_name("2024"), "schema");
+}
+
+#[test]
+fn test_codegen_go_map_keyed_by_param() {
+    // The argument of `K` may not be a string or a number, so the map is sent as pairs.
+    let source = "type Index<K, V> {\n    entries: Map<K, V>,\n    byName: Map<string, V>\n}\n";
+    let assembly = schema::parse(source).unwrap();
+    let go = codegen::go::generate(&assembly, "index");
+    assert!(go.contains("type Pairs[K comparable, V any] map[K]V\n"));
+    assert!(go.contains(
+        "type Index[K comparable, V any] struct {\n\
+         \tEntries Pairs[K, V]  `json:\"entries\"`\n\
+         \tByName  map[string]V `json:\"byName\"`\n\
+         }\n"
+    ));
+}
+
+const MOBILE_SCHEMA: &str = r#"
+const MAX_PAGE_SIZE: integer = 100
+
+type User {
+    id: integer,
+    display_name: string,
+    bestFriend: Optional<User>,
+    nickname?: string,
+    tags: Array<string> = ["new"],
+    flags: Map<boolean, string>,
+    @deprecated("use tags")
+    labels?: Array<string>
+}
+
+type Post {
+    title: string = "untitled",
+    author: User
+}
+
+type Child extends Post {
+    extra: integer
+}
+
+type Index<K, V> {
+    entries: Map<K, V>
+}
+
+type Live {
+    updates: stream Post,
+    count: sync integer = 0
+}
+
+service Posts {
+    remote function get(id: integer) -> Post;
+    remote function feed() -> stream Post;
+    remote function count() -> sync integer;
+    remote function clear()
+}
+"#;
+
+#[test]
+fn test_codegen_kotlin() {
+    let assembly = schema::parse(MOBILE_SCHEMA).unwrap();
+    let kotlin = codegen::kotlin::generate(&assembly);
+    assert!(kotlin.contains("const val MAX_PAGE_SIZE: Long = 100L\n"));
+    assert!(kotlin.contains(
+        "@Serializable\n\
+         data class User(\n    \
+             val id: Long,\n    \
+             @SerialName(\"display_name\")\n    \
+             val displayName: String,\n    \
+             val bestFriend: User?,\n    \
+             val nickname: String? = null,\n    \
+             val tags: List<String> = listOf(\"new\"),\n    \
+             val flags: @Serializable(with = PairsSerializer::class) Map<Boolean, String>,\n    \
+             @Deprecated(\"use tags\")\n    \
+             val labels: List<String>? = null,\n\
+         )\n"
+    ));
+    assert!(kotlin.contains("class PairsSerializer<K, V>("));
+    // Inherited fields are copied.
+    assert!(kotlin.contains(
+        "data class Child(\n    val title: String = \"untitled\",\n    val author: User,\n    val extra: Long,\n)\n"
+    ));
+    assert!(kotlin.contains(
+        "\ndata class Live(\n    val updates: Flow<Post>,\n    val count: StateFlow<Long> = MutableStateFlow(0L),\n)\n"
+    ));
+    assert!(kotlin.contains(
+        "interface Posts {\n    \
+             suspend fun get(id: Long): Post\n    \
+             fun feed(): Flow<Post>\n    \
+             fun count(): StateFlow<Long>\n    \
+             suspend fun clear()\n\
+         }\n"
+    ));
+}
+
+#[test]
+fn test_codegen_swift() {
+    let assembly = schema::parse(MOBILE_SCHEMA).unwrap();
+    let swift = codegen::swift::generate(&assembly);
+    assert!(swift.contains("let MAX_PAGE_SIZE: Int = 100\n"));
+    // `User` contains itself, it is a class.
+    assert!(swift.contains(
+        "final class User: Codable {\n    \
+             var id: Int\n    \
+             var displayName: String\n    \
+             var bestFriend: User?\n    \
+             var nickname: String?\n    \
+             var tags: [String]\n    \
+             var flags: Pairs<Bool, String>\n    \
+             @available(*, deprecated, message: \"use tags\")\n    \
+             var labels: [String]?\n\
+         \n    \
+             enum CodingKeys: String, CodingKey {\n        \
+                 case id\n        \
+                 case displayName = \"display_name\"\n"
+    ));
+    assert!(swift.contains("        self.tags = try container.decodeIfPresent([String].self, forKey: .tags) ?? [\"new\"]\n"));
+    assert!(swift.contains("struct Post: Codable {\n    var title: String = \"untitled\"\n    var author: User\n"));
+    // Structs keep their memberwise initializer when the decoder is declared in an extension.
+    assert!(swift.contains("extension Post {\n    init(from decoder: Decoder) throws {\n"));
+    // Generic structs are Codable when the values of their parameters are.
+    assert!(swift.contains("struct Index<K: Hashable, V> {\n    var entries: Pairs<K, V>\n}\n\nextension Index: Codable where K: Codable, V: Codable {}\n"));
+    assert!(swift.contains("extension Pairs: Codable where Key: Codable, Value: Codable {\n"));
+    assert!(swift.contains(
+        "struct Live {\n    var updates: AsyncStream<Post>\n    @Synced var count: Int = 0\n}\n"
+    ));
+    assert!(swift.contains("@propertyWrapper\nfinal class Synced<Value> {\n"));
+    assert!(swift.contains(
+        "protocol Posts {\n    \
+             func get(id: Int) async throws -> Post\n    \
+             func feed() -> AsyncStream<Post>\n    \
+             func count() -> Synced<Int>\n    \
+             func clear() async throws\n\
+         }\n"
+    ));
+}
+
+#[test]
+fn test_codegen_csharp() {
+    let assembly = schema::parse(MOBILE_SCHEMA).unwrap();
+    let csharp = codegen::csharp::generate(&assembly, "Billing.Invoices");
+    assert!(csharp.contains("#nullable enable\n\nusing System;\nusing System.Collections.Generic;\n"));
+    assert!(csharp.contains("\nnamespace Billing.Invoices;\n"));
+    assert!(csharp.contains(
+        "public static class Constants\n{\n    public const long MaxPageSize = 100;\n}\n"
+    ));
+    assert!(csharp.contains(
+        "public record User\n\
+         {\n    \
+             [JsonPropertyName(\"id\")]\n    \
+             public required long Id { get; init; }\n\
+         \n    \
+             [JsonPropertyName(\"display_name\")]\n    \
+             public required string DisplayName { get; init; }\n\
+         \n    \
+             [JsonPropertyName(\"bestFriend\")]\n    \
+             public required User? BestFriend { get; init; }\n\
+         \n    \
+             [JsonPropertyName(\"nickname\")]\n    \
+             [JsonIgnore(Condition = JsonIgnoreCondition.WhenWritingNull)]\n    \
+             public string? Nickname { get; init; }\n\
+         \n    \
+             [JsonPropertyName(\"tags\")]\n    \
+             public List<string> Tags { get; init; } = new List<string> { \"new\" };\n\
+         \n    \
+             [JsonPropertyName(\"flags\")]\n    \
+             public required Pairs<bool, string> Flags { get; init; }\n\
+         \n    \
+             [Obsolete(\"use tags\")]\n"
+    ));
+    assert!(csharp.contains("[JsonConverter(typeof(PairsConverter))]\npublic class Pairs<TKey, TValue>"));
+    // Only the fields of the type itself are declared.
+    assert!(csharp.contains(
+        "public record Child : Post\n{\n    [JsonPropertyName(\"extra\")]\n    public required long Extra { get; init; }\n}\n"
+    ));
+    assert!(csharp.contains("public record Index<K, V>\n    where K : notnull\n{\n"));
+    assert!(csharp.contains(
+        "public record Live\n\
+         {\n    \
+             public required IAsyncEnumerable<Post> Updates { get; init; }\n\
+         \n    \
+             public required IObservable<long> Count { get; init; }\n\
+         }\n"
+    ));
+    assert!(csharp.contains(
+        "public interface IPosts\n\
+         {\n    \
+             Task<Post> GetAsync(long id, CancellationToken cancellationToken = default);\n    \
+             IAsyncEnumerable<Post> Feed(CancellationToken cancellationToken = default);\n    \
+             IObservable<long> Count();\n    \
+             Task ClearAsync(CancellationToken cancellationToken = default);\n\
+         }\n"
+    ));
+
+    assert_eq!(
+        codegen::csharp::namespace(std::path::Path::new("billing/invoice-items.rgc")),
+        "Billing.InvoiceItems"
+    );
+    assert_eq!(
+        codegen::csharp::namespace(std::path::Path::new("/tmp/users.rgc")),
+        "Users"
+    );
+}
+
+#[test]
+fn test_codegen_csharp_member_names() {
+    let source = "@deprecated\ntype Name {\n    name: string,\n    first: string\n}\n";
+    let assembly = schema::parse(source).unwrap();
+    let csharp = codegen::csharp::generate(&assembly, "People");
+    assert!(csharp.contains("using System;\nusing System.Text.Json.Serialization;\n"));
+    assert!(csharp.contains(
+        "[Obsolete]\npublic record Name\n{\n    \
+         [JsonPropertyName(\"name\")]\n    \
+         public required string NameValue { get; init; }\n"
+    ));
+    assert!(csharp.contains("    public required string First { get; init; }\n"));
+}
+
+const JSON_SCHEMA_SCHEMA: &str = r#"
+type User {
+    @range(min = 0)
+    id: integer,
+    @pattern("^[a-z]+$")
+    name: string,
+    friend: Optional<User>,
+    score: float = 1.5,
+    flags: Map<boolean, string>,
+    @deprecated("use name")
+    old?: string
+}
+
+type Page<T> {
+    items: Array<T>,
+    next: Optional<Page<T>>
+}
+
+type Feed {
+    users: Page<User>
+}
+
+type Live {
+    title: string,
+    updates: stream User
+}
+"#;
+
+#[test]
+fn test_codegen_jsonschema() {
+    let assembly = schema::parse(JSON_SCHEMA_SCHEMA).unwrap();
+    let document = json::Json::parse(&codegen::jsonschema::generate(&assembly)).unwrap();
+    assert_eq!(
+        document.get("$schema").and_then(json::Json::as_str),
+        Some("https://json-schema.org/draft/2020-12/schema")
+    );
+    let defs = document.get("$defs").unwrap();
+    let user = defs.get("User").unwrap();
+    assert_eq!(
+        user.get("required"),
+        Some(&json::Json::parse(r#"["id", "name", "friend", "flags"]"#).unwrap())
+    );
+    let properties = user.get("properties").unwrap();
+    let expected = [
+        ("id", r#"{"type": "integer", "minimum": 0}"#),
+        ("name", r#"{"type": "string", "pattern": "^[a-z]+$"}"#),
+        (
+            "friend",
+            r##"{"anyOf": [{"$ref": "#/$defs/User"}, {"type": "null"}]}"##,
+        ),
+        ("score", r#"{"type": "number", "default": 1.5}"#),
+        (
+            "flags",
+            r#"{"type": "array", "items": {
+                "type": "array",
+                "prefixItems": [{"type": "boolean"}, {"type": "string"}],
+                "minItems": 2,
+                "maxItems": 2
+            }}"#,
+        ),
+        (
+            "old",
+            r#"{"type": "string", "description": "use name", "deprecated": true}"#,
+        ),
+    ];
+    for (name, schema) in expected {
+        assert_eq!(
+            properties.get(name),
+            Some(&json::Json::parse(schema).unwrap()),
+            "{name}"
+        );
+    }
+    // Generic types accept anything for their parameters, instances get a definition of their own.
+    assert_eq!(
+        defs.get("Page").unwrap().get("properties").unwrap().get("items"),
+        Some(&json::Json::parse(r#"{"type": "array", "items": {}}"#).unwrap())
+    );
+    assert_eq!(
+        defs.get("Feed").unwrap().get("properties").unwrap().get("users"),
+        Some(&json::Json::parse(r##"{"$ref": "#/$defs/Page%3CUser%3E"}"##).unwrap())
+    );
+    let page = defs.get("Page<User>").unwrap().get("properties").unwrap();
+    assert_eq!(
+        page.get("next"),
+        Some(
+            &json::Json::parse(
+                r##"{"anyOf": [{"$ref": "#/$defs/Page%3CUser%3E"}, {"type": "null"}]}"##
+            )
+            .unwrap()
+        )
+    );
+    // Only the fields put together by the transport are left out.
+    assert_eq!(
+        defs.get("Live"),
+        Some(
+            &json::Json::parse(
+                r#"{"type": "object", "properties": {"title": {"type": "string"}}, "required": ["title"]}"#
+            )
+            .unwrap()
+        )
+    );
+}
+
+#[test]
+fn test_json_pretty() {
+    let value = json::Json::parse(r#"{"a": [1, {"b": null}], "c": {}, "d": []}"#).unwrap();
+    assert_eq!(
+        value.pretty(),
+        "{\n  \"a\": [\n    1,\n    {\n      \"b\": null\n    }\n  ],\n  \"c\": {},\n  \"d\": []\n}\n"
+    );
+    assert_eq!(json::Json::parse(&value.pretty()), Ok(value));
+}
+
+const LEGACY_JSON_SCHEMA: &str = r##"{
+  "$schema": "http://json-schema.org/draft-07/schema#",
+  "title": "order",
+  "description": "An order placed by a customer.",
+  "type": "object",
+  "required": ["id", "lines"],
+  "properties": {
+    "id": {"type": "string", "format": "uuid"},
+    "status": {"enum": ["open", "paid"], "default": "open"},
+    "lines": {"type": "array", "minItems": 1, "items": {"$ref": "#/definitions/line"}},
+    "customer": {"oneOf": [{"$ref": "#/definitions/person"}, {"$ref": "#/definitions/company"}]},
+    "note": {"type": ["string", "null"], "maxLength": 200},
+    "shipping-address": {
+      "type": "object",
+      "properties": {"street": {"type": "string"}},
+      "required": ["street"]
+    },
+    "metadata": {"type": "object", "additionalProperties": {"type": "string"}},
+    "total": {"$ref": "#/definitions/money"}
+  },
+  "definitions": {
+    "money": {"type": "number", "minimum": 0},
+    "currency": {"enum": ["EUR", "USD"]},
+    "line": {
+      "type": "object",
+      "properties": {"sku": {"type": "string"}, "qty": {"type": "integer", "minimum": 1}},
+      "required": ["sku", "qty"]
+    },
+    "person": {"type": "object", "properties": {"name": {"type": "string"}}},
+    "company": {
+      "allOf": [
+        {"$ref": "#/definitions/person"},
+        {"properties": {"vat": {"type": "string"}}, "required": ["vat"]}
+      ],
+      "deprecated": true
+    }
+  }
+}"##;
+
+#[test]
+fn test_import_jsonschema() {
+    let imported = import::jsonschema::import(LEGACY_JSON_SCHEMA, "fallback").unwrap();
+    assert_eq!(
+        imported.source,
+        r#"// An order placed by a customer.
+type Order {
+    id: string,
+    // One of: "open", "paid"
+    status: string = "open",
+    lines: Array<Line> @min_items(1),
+    customer?: OrderCustomer,
+    note?: Optional<string> @max_len(200),
+    shipping_address?: OrderShippingAddress,
+    metadata?: Map<string, string>,
+    total?: float @range(min = 0)
+}
+
+type OrderCustomer {
+    // Exactly one of `person`, `company` is set
+    person?: Person,
+    company?: Company
+}
+
+type OrderShippingAddress {
+    street: string
+}
+
+type Line {
+    sku: string,
+    qty: integer @range(min = 1)
+}
+
+type Person {
+    name?: string
+}
+
+@deprecated
+type Company extends Person {
+    vat: string
+}
+"#
+    );
+    let warnings = imported
+        .warnings
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        warnings,
+        [
+            "#/properties/id: warning: `format` is not supported and is ignored",
+            "#/properties/status: warning: rgc has no enums, the values of `enum` are not checked",
+            "#/properties/customer: warning: rgc has no unions, `oneOf` is imported as \
+             `OrderCustomer`, a type with a field per alternative, which may be absent",
+            "#/properties/shipping-address: warning: `shipping-address` is not a valid field name, \
+             imported as `shipping_address` which changes its name in payloads",
+            "#/definitions/money: warning: `money` is not an object, it is inlined where it is \
+             referenced",
+            "#/definitions/currency: warning: `currency` is neither an object nor referenced, it \
+             is not imported",
+        ]
+    );
+    assert!(schema::parse(&imported.source).is_ok());
+}
+
+#[test]
+fn test_import_jsonschema_round_trip() {
+    let assembly = schema::parse(JSON_SCHEMA_SCHEMA).unwrap();
+    let exported = codegen::jsonschema::generate(&assembly);
+    let imported = import::jsonschema::import(&exported, "schema").unwrap();
+    assert!(imported.source.starts_with(
+        "type User {\n    \
+             id: integer @range(min = 0),\n    \
+             name: string @pattern(\"^[a-z]+$\"),\n    \
+             friend: Optional<User>,\n    \
+             score: float = 1.5,\n    \
+             flags: Map<boolean, string>,\n    \
+             @deprecated(\"use name\")\n    \
+             old?: string\n\
+         }\n"
+    ));
+    // Instances of generic types are types of their own.
+    assert!(imported.source.contains("type Feed {\n    users: PageUser\n}\n"));
+    assert!(imported.source.contains(
+        "type PageUser {\n    items: Array<User>,\n    next: Optional<PageUser>\n}\n"
+    ));
+    assert!(schema::parse(&imported.source).is_ok());
+}
+
+const OPENAPI_SCHEMA: &str = r#"
+type User {
+    id: integer
+}
+
+type Page<T> {
+    items: Array<T>
+}
+
+@since(3)
+service Users {
+    remote function get(id: integer) -> User;
+    remote function list(cursor: Optional<string>) -> Page<User>;
+    remote function watch() -> stream User;
+    @deprecated("use watch")
+    remote function count() -> sync integer;
+    remote function clear()
+}
+"#;
+
+#[test]
+fn test_codegen_openapi() {
+    let assembly = schema::parse(OPENAPI_SCHEMA).unwrap();
+    let document = json::Json::parse(&codegen::openapi::generate(&assembly, "users")).unwrap();
+    let parse = |text: &str| json::Json::parse(text).unwrap();
+    assert_eq!(document.get("openapi"), Some(&parse(r#""3.1.0""#)));
+    assert_eq!(
+        document.get("info"),
+        Some(&parse(r#"{"title": "users", "version": "3"}"#))
+    );
+    let paths = document.get("paths").unwrap();
+    assert_eq!(
+        paths.get("/Users/get"),
+        Some(&parse(
+            r##"{"post": {
+                "operationId": "Users.get",
+                "tags": ["Users"],
+                "requestBody": {
+                    "required": true,
+                    "content": {"application/json": {"schema": {
+                        "type": "object",
+                        "properties": {"id": {"type": "integer"}},
+                        "required": ["id"]
+                    }}}
+                },
+                "responses": {"200": {
+                    "description": "The returned value",
+                    "content": {"application/json": {
+                        "schema": {"$ref": "#/components/schemas/User"}
+                    }}
+                }}
+            }}"##
+        ))
+    );
+    let list = paths.get("/Users/list").unwrap().get("post").unwrap();
+    let response = list.get("responses").unwrap().get("200").unwrap();
+    assert_eq!(
+        response.get("content"),
+        Some(&parse(
+            r##"{"application/json": {"schema": {"$ref": "#/components/schemas/Page_User"}}}"##
+        ))
+    );
+    let watch = paths.get("/Users/watch").unwrap().get("post").unwrap();
+    assert_eq!(watch.get("requestBody"), None);
+    let response = watch.get("responses").unwrap().get("200").unwrap();
+    assert_eq!(
+        response.get("content"),
+        Some(&parse(
+            r##"{
+                "text/event-stream": {"schema": {"$ref": "#/components/schemas/User"}},
+                "application/x-ndjson": {"schema": {"$ref": "#/components/schemas/User"}}
+            }"##
+        ))
+    );
+    let count = paths.get("/Users/count").unwrap().get("post").unwrap();
+    assert_eq!(count.get("deprecated"), Some(&json::Json::Bool(true)));
+    assert_eq!(count.get("description"), Some(&parse(r#""use watch""#)));
+    let clear = paths.get("/Users/clear").unwrap().get("post").unwrap();
+    assert_eq!(
+        clear.get("responses"),
+        Some(&parse(r#"{"204": {"description": "No content"}}"#))
+    );
+    let schemas = document.get("components").unwrap().get("schemas").unwrap();
+    assert_eq!(
+        schemas.get("Page_User"),
+        Some(&parse(
+            r##"{
+                "type": "object",
+                "properties": {"items": {
+                    "type": "array",
+                    "items": {"$ref": "#/components/schemas/User"}
+                }},
+                "required": ["items"]
+            }"##
+        ))
+    );
+}
+
+const PROTOBUF_SCHEMA: &str = r#"
+type User {
+    id: integer @tag(1),
+    @deprecated("use tags")
+    group: string @tag(3),
+    tags: Array<string> @tag(2),
+    email?: string @tag(4),
+    grid: Array<Array<integer>> @tag(5)
+}
+
+type Page<T> {
+    items: Array<T> @tag(1)
+}
+
+type Admin extends User {
+    level: integer @tag(6)
+}
+
+service Users {
+    remote function get(id: integer @tag(2)) -> User;
+    remote function list() -> Page<User>;
+    remote function count() -> integer;
+    remote function watch(id: integer @tag(1)) -> stream User;
+    remote function clear()
+}
+"#;
+
+#[test]
+fn test_codegen_protobuf() {
+    let assembly = schema::parse(PROTOBUF_SCHEMA).unwrap();
+    let proto = codegen::protobuf::generate(&assembly, "users.v1").unwrap();
+    assert!(proto.starts_with(
+        "// Generated by rgc. Do not edit.\n\nsyntax = \"proto3\";\n\npackage users.v1;\n\n\
+         import \"google/protobuf/empty.proto\";\n"
+    ));
+    assert!(proto.contains(
+        r#"message User {
+  int64 id = 1;
+  // Deprecated: use tags
+  string group = 3 [deprecated = true];
+  repeated string tags = 2;
+  optional string email = 4;
+  repeated IntegerList grid = 5;
+}
+"#
+    ));
+    assert!(proto.contains("message IntegerList {\n  repeated int64 items = 1;\n}\n"));
+    assert!(proto.contains("message Admin {\n  int64 id = 1;\n"));
+    assert!(proto.contains("  int64 level = 6;\n}\n"));
+    assert!(proto.contains("message PageUser {\n  repeated User items = 1;\n}\n"));
+    assert!(!proto.contains("message Page {"));
+    assert!(proto.contains(
+        r#"service Users {
+  rpc Get(UsersGetRequest) returns (User);
+  rpc List(google.protobuf.Empty) returns (PageUser);
+  rpc Count(google.protobuf.Empty) returns (UsersCountResponse);
+  rpc Watch(UsersWatchRequest) returns (stream User);
+  rpc Clear(google.protobuf.Empty) returns (google.protobuf.Empty);
+}
+"#
+    ));
+    assert!(proto.contains("message UsersCountResponse {\n  int64 value = 1;\n}\n"));
+    assert!(proto.contains("message UsersGetRequest {\n  int64 id = 2;\n}\n"));
+
+    let source = "type A {\n    a: integer,\n    b: string @tag(4)\n}\n\n\
+                  service S {\n    remote function f(x: integer, y: A @tag(1))\n}\n";
+    let assembly = schema::parse(source).unwrap();
+    assert_eq!(
+        codegen::protobuf::generate(&assembly, "a").unwrap_err(),
+        "fields and parameters need a number, given with `@tag(n)`: A.a, S.f(x)"
+    );
+
+    let package = |path: &str| codegen::protobuf::package(std::path::Path::new(path));
+    assert_eq!(package("billing/invoice-items.rgc"), "billing.invoice_items");
+    assert_eq!(package("/tmp/Users.rgc"), "users");
+    assert!(codegen::protobuf::is_package("billing.v1"));
+    assert!(!codegen::protobuf::is_package("billing..v1"));
+}
+
+#[test]
+fn test_tag_validation() {
+    // Field numbers are checked by the protobuf backend, other targets ignore them.
+    for (source, message) in [
+        (
+            "type A {\n    a: integer @tag(1),\n    b: integer @tag(1)\n}\n",
+            "field number 1 of A.b is already given to A.a",
+        ),
+        (
+            "type A {\n    a: integer @tag(1)\n}\n\ntype B extends A {\n    b: integer @tag(1)\n}\n",
+            "field number 1 of B.b is already given to B.a",
+        ),
+        (
+            "type A {\n    a: integer @tag(0)\n}\n",
+            "field number 0 of A.a is out of range, it must be between 1 and 536870911 and not \
+             between 19000 and 19999",
+        ),
+        (
+            "type A {\n    a: integer @tag(19500)\n}\n",
+            "field number 19500 of A.a is out of range, it must be between 1 and 536870911 and \
+             not between 19000 and 19999",
+        ),
+        (
+            "service S {\n    remote function f(a: integer @tag(1), b: string @tag(1))\n}\n",
+            "field number 1 of S.f(b) is already given to S.f(a)",
+        ),
+        (
+            "service S {\n    remote function f(a: integer @tag(0))\n}\n",
+            "field number 0 of S.f(a) is out of range, it must be between 1 and 536870911 and \
+             not between 19000 and 19999",
+        ),
+    ] {
+        assert!(schema::check(source).is_empty());
+        let assembly = schema::parse(source).unwrap();
+        assert_eq!(
+            codegen::protobuf::generate(&assembly, "a").unwrap_err(),
+            message
+        );
+    }
+    let source = "service S {\n    remote function f(a: integer @id)\n}\n";
+    let messages = schema::check(source)
+        .iter()
+        .map(|d| d.message.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(messages, ["`@id` cannot be used on parameters"]);
+}
+
+const LEGACY_PROTO: &str = r#"syntax = "proto3";
+
+package shop.v1;
+
+import "google/protobuf/empty.proto";
+
+// A customer order.
+message Order {
+  int64 id = 1;
+  string customer_name = 2; // as printed
+  repeated Item items = 3;
+  map<string, int32> quantities = 4;
+  Status status = 5;
+  optional string note = 7 [deprecated = true];
+  oneof payment {
+    Card card = 8;
+    string voucher = 9;
+  }
+  bytes signature = 10 [json_name = "sig"];
+
+  message Item {
+    string sku = 1;
+  }
+
+  enum Status {
+    STATUS_UNSPECIFIED = 0;
+    STATUS_OPEN = 1;
+  }
+}
+
+message Card {
+  string number = 1;
+}
+
+message GetOrderRequest {
+  int64 id = 1;
+}
+
+message CountResponse {
+  int64 value = 1;
+}
+
+service Orders {
+  rpc GetOrder(GetOrderRequest) returns (Order);
+  rpc Count(google.protobuf.Empty) returns (CountResponse);
+  rpc Watch(GetOrderRequest) returns (stream Order) {
+    option deprecated = true;
+  }
+  rpc Upload(stream Order) returns (google.protobuf.Empty);
+}
+"#;
+
+#[test]
+fn test_import_protobuf() {
+    let imported = import::protobuf::import(LEGACY_PROTO).unwrap();
+    assert_eq!(
+        imported.source,
+        r#"// A customer order.
+type Order {
+    @tag(1)
+    id: integer,
+    // as printed
+    @tag(2)
+    customerName: string,
+    @tag(3)
+    items: Array<OrderItem>,
+    @tag(4)
+    quantities: Map<string, integer>,
+    // One of: "STATUS_UNSPECIFIED", "STATUS_OPEN"
+    @tag(5)
+    status: string,
+    @deprecated
+    @tag(7)
+    note?: string,
+    // At most one of `card`, `voucher` is set (`payment`)
+    @tag(8)
+    card?: Card,
+    @tag(9)
+    voucher?: string,
+    @rename(proto = "signature")
+    @tag(10)
+    sig: string
+}
+
+type OrderItem {
+    @tag(1)
+    sku: string
+}
+
+type Card {
+    @tag(1)
+    number: string
+}
+
+service Orders {
+    remote function getOrder(id: integer @tag(1)) -> Order;
+    remote function count() -> integer;
+    @deprecated
+    remote function watch(id: integer @tag(1)) -> stream Order;
+    remote function upload(request: Order @tag(1))
+}
+"#
+    );
+    let warnings = imported
+        .warnings
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        warnings,
+        [
+            "15:3: warning: rgc has no unions, the fields of `oneof payment` are imported as \
+             fields that may be absent",
+            "19:3: warning: `bytes` are imported as `string`, holding base64 as in JSON",
+            "25:3: warning: rgc has no enums, `Order.Status` is imported as `string`",
+            "49:3: warning: client streaming is not supported, `Upload` is imported as taking a \
+             single request",
+        ]
+    );
+    assert!(schema::parse(&imported.source).is_ok());
+
+    let error = import::protobuf::import("message A {\n  int32 a = ;\n}\n").unwrap_err();
+    assert_eq!(error, "2:13: expected an integer, found `;`");
+}
+
+#[test]
+fn test_import_protobuf_round_trip() {
+    let assembly = schema::parse(PROTOBUF_SCHEMA).unwrap();
+    let exported = codegen::protobuf::generate(&assembly, "users.v1").unwrap();
+    let imported = import::protobuf::import(&exported).unwrap();
+    assert!(imported.warnings.is_empty());
+    assert!(imported.source.contains(
+        "    @deprecated(\"use tags\")\n    @tag(3)\n    group: string,\n    \
+         @tag(2)\n    tags: Array<string>,\n"
+    ));
+    assert!(imported.source.contains(
+        "service Users {\n    \
+             remote function get(id: integer @tag(2)) -> User;\n    \
+             remote function list() -> PageUser;\n    \
+             remote function count() -> integer;\n    \
+             remote function watch(id: integer @tag(1)) -> stream User;\n    \
+             remote function clear()\n\
+         }\n"
+    ));
+    let assembly = schema::parse(&imported.source).unwrap();
+    assert_eq!(
+        codegen::protobuf::generate(&assembly, "users.v1").unwrap(),
+        exported
+    );
+}
+
+const GRAPHQL_SCHEMA: &str = r#"
+type User {
+    id: integer,
+    email?: string,
+    tags: Array<Optional<string>>,
+    scores: Map<string, float> = {"a": 1.5},
+    @deprecated("use tags")
+    label: string
+}
+
+type Page<T> {
+    items: Array<T>,
+    next: Optional<string>
+}
+
+type Feed {
+    event: stream User,
+    latest: sync Optional<User>
+}
+
+service Users {
+    remote function get(id: integer) -> User;
+    remote function list(filter: Page<User>) -> Page<User>;
+    remote function watch(id: integer) -> stream User;
+    remote function current() -> sync Optional<User>;
+    @deprecated("use list")
+    remote function clear();
+    remote function feed() -> Feed
+}
+"#;
+
+#[test]
+fn test_codegen_graphql() {
+    let assembly = schema::parse(GRAPHQL_SCHEMA).unwrap();
+    let sdl = codegen::graphql::generate(&assembly).unwrap();
+    assert!(sdl.starts_with(
+        "# Generated by rgc. Do not edit.\n\n\
+         \"A 64-bit integer, as `Int` only holds 32 bits.\"\nscalar Long\n"
+    ));
+    assert!(sdl.contains(
+        r#"type User {
+  id: Long!
+  email: String
+  tags: [String]!
+  scores: [StringFloatEntry!]!
+  label: String! @deprecated(reason: "use tags")
+}
+"#
+    ));
+    assert!(sdl.contains("type StringFloatEntry {\n  key: String!\n  value: Float!\n}\n"));
+    assert!(sdl.contains("type PageUser {\n  items: [User!]!\n  next: String\n}\n"));
+    assert!(sdl.contains("input PageUserInput {\n  items: [UserInput!]!\n  next: String\n}\n"));
+    assert!(sdl.contains("  scores: [StringFloatEntryInput!]! = [{key: \"a\", value: 1.5}]\n"));
+    assert!(sdl.contains("  label: String!\n}\n"));
+    // `stream` and `sync` fields have their plain type, and a description of their kind.
+    assert!(sdl.contains(
+        "type Feed {\n  \"Each new value, in turn.\"\n  event: User!\n  \
+         \"The current value, then each new value.\"\n  latest: User\n}\n"
+    ));
+    assert!(sdl.ends_with(
+        r#"
+type Query {
+  usersGet(id: Long!): User!
+  usersList(filter: PageUserInput!): PageUser!
+  "Returns nothing, always null."
+  usersClear: Boolean @deprecated(reason: "use list")
+  usersFeed: Feed!
+}
+
+type Subscription {
+  usersWatch(id: Long!): User!
+  "The current value, then each new value."
+  usersCurrent: User
+}
+"#
+    ));
+}
+
+#[test]
+fn test_codegen_graphql_without_queries() {
+    let source = "type Post {\n    title: string\n}\n\n\
+                  service Posts {\n    remote function watch() -> stream Post\n}\n";
+    let assembly = schema::parse(source).unwrap();
+    let sdl = codegen::graphql::generate(&assembly).unwrap();
+    // Without integers, `Long` is not declared.
+    assert!(!sdl.contains("scalar"));
+    assert!(sdl.ends_with(
+        r#"
+type Query {
+  "Always null, as the schema has no queries."
+  _: Boolean
+}
+
+type Subscription {
+  postsWatch: Post!
+}
+"#
+    ));
+    let imported = import::graphql::import(&sdl).unwrap();
+    assert!(imported.warnings.is_empty());
+    assert!(!imported.source.contains("service Query"));
+    assert!(imported.source.contains("remote function postsWatch() -> stream Post"));
+}
+
+const LIBRARY_GRAPHQL: &str = r#"# Library schema
+
+directive @auth(role: String) on FIELD_DEFINITION
+
+scalar DateTime
+
+"""
+Something with a name.
+"""
+interface Node {
+  id: ID!
+}
+
+enum Genre { FICTION, POETRY }
+
+type Book implements Node {
+  id: ID!
+  title: String! # the display title
+  genre: Genre
+  tags: [String!]!
+  published: DateTime
+  author(first: Int): Author @deprecated(reason: "use authors")
+}
+
+type Author implements Node & Named {
+  id: ID!
+  name: String!
+}
+
+union SearchResult = Book | Author
+
+input BookFilter {
+  genre: Genre = FICTION
+  limit: Int = 10
+}
+
+type Query {
+  "Finds books."
+  books(filter: BookFilter): [Book!]! @auth(role: "reader")
+  search(text: String!): [SearchResult!]!
+}
+
+type Mutation {
+  deleteBook(id: ID!): Boolean
+}
+
+type Subscription {
+  bookAdded: Book!
+}
+
+extend type Author {
+  born: Int
+}
+"#;
+
+#[test]
+fn test_import_graphql() {
+    let imported = import::graphql::import(LIBRARY_GRAPHQL).unwrap();
+    assert_eq!(
+        imported.source,
+        r#"// Something with a name.
+// Implemented by `Book`, `Author`
+type Node {
+    id: string
+}
+
+type Book extends Node {
+    // the display title
+    title: string,
+    // One of: "FICTION", "POETRY"
+    genre: Optional<string>,
+    tags: Array<string>,
+    published: Optional<string>,
+    @deprecated("use authors")
+    author: Optional<Author>
+}
+
+type Author extends Node {
+    name: string,
+    born: Optional<integer>
+}
+
+type SearchResult {
+    // Exactly one of `book`, `author` is set
+    book?: Book,
+    author?: Author
+}
+
+type BookFilter {
+    // One of: "FICTION", "POETRY"
+    genre: Optional<string> = "FICTION",
+    limit: Optional<integer> = 10
+}
+
+service Query {
+    // Finds books.
+    remote function books(filter: Optional<BookFilter>) -> Array<Book>;
+    remote function search(text: string) -> Array<SearchResult>
+}
+
+service Mutation {
+    remote function deleteBook(id: string) -> Optional<boolean>
+}
+
+service Subscription {
+    remote function bookAdded() -> stream Book
+}
+"#
+    );
+    let warnings = imported
+        .warnings
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        warnings,
+        [
+            "5:1: warning: custom scalar `DateTime` is imported as `string`",
+            "10:1: warning: rgc has no interfaces, `Node` is imported as a type, which the types \
+             implementing it extend",
+            "14:1: warning: rgc has no enums, `Genre` is imported as `string`",
+            "22:3: warning: arguments of `author` are not supported and are ignored",
+            "25:1: warning: `Named` is not an interface defined in this schema and is ignored",
+            "30:1: warning: rgc has no unions, `SearchResult` is imported as a type with a field \
+             per member, which may be absent",
+            "39:39: warning: directive `@auth` is not supported and is ignored",
+        ]
+    );
+    assert!(schema::parse(&imported.source).is_ok());
+
+    let error = import::graphql::import("type A {\n  a: Int = \n}\n").unwrap_err();
+    assert_eq!(error, "3:1: expected a value, found `}`");
+}
+
+#[test]
+fn test_import_graphql_long_and_empty_query() {
+    let sdl = r#"scalar Long
+
+type Post {
+  views: Long!
+}
+
+type Query {
+  "Always null, as the schema has no queries."
+  _: Boolean
+}
+"#;
+    let imported = import::graphql::import(sdl).unwrap();
+    assert!(imported.warnings.is_empty());
+    assert_eq!(imported.source, "type Post {\n    views: integer\n}\n");
+}
+
+#[test]
+fn test_import_graphql_round_trip() {
+    let assembly = schema::parse(GRAPHQL_SCHEMA).unwrap();
+    let exported = codegen::graphql::generate(&assembly).unwrap();
+    let imported = import::graphql::import(&exported).unwrap();
+    assert!(imported.warnings.is_empty());
+    // Root fields keep their names, functions of several services sharing a root type.
+    assert!(imported.source.contains(
+        "service Subscription {\n    \
+             remote function usersWatch(id: integer) -> stream User;\n    \
+             remote function usersCurrent() -> sync Optional<User>\n\
+         }\n"
+    ));
+    assert!(imported.source.contains(
+        "type Feed {\n    event: stream User,\n    latest: sync Optional<User>\n}\n"
+    ));
+    assert!(imported.source.contains("remote function usersFeed() -> Feed\n"));
+    let assembly = schema::parse(&imported.source).unwrap();
+    // Types are generated as before.
+    let types = |sdl: &str| sdl[..sdl.find("\ntype Query").unwrap()].to_string();
+    let reexported = codegen::graphql::generate(&assembly).unwrap();
+    assert_eq!(types(&reexported), types(&exported));
+}
+
+const AVRO_SCHEMA: &str = r#"
+type Tree {
+    value: integer,
+    children: Array<Tree>,
+    parent?: Tree
+}
+
+type Event {
+    @deprecated("use labels")
+    label: Optional<string> = "none",
+    labels: Map<string, float> = {"a": 1.5},
+    counts: Map<integer, boolean>,
+    page: Page<Tree>,
+    maybe: Optional<Optional<integer>>,
+    owner: Owner
+}
+
+type Page<T> {
+    items: Array<T>
+}
+
+type Owner {
+    events: Array<Event>
+}
+"#;
+
+#[test]
+fn test_codegen_avro() {
+    let assembly = schema::parse(AVRO_SCHEMA).unwrap();
+    let document = json::Json::parse(&codegen::avro::generate(&assembly).unwrap()).unwrap();
+    let records = document.as_array().unwrap();
+    let names = records
+        .iter()
+        .map(|record| record.get("name").and_then(json::Json::as_str).unwrap())
+        .collect::<Vec<_>>();
+    // Used records come first, `Event` and `Owner` being defined within each other.
+    assert_eq!(names, ["Tree", "PageTree", "Owner"]);
+    assert_eq!(
+        records[0].to_string(),
+        r#"{"type":"record","name":"Tree","fields":[{"name":"value","type":"long"},{"name":"children","type":{"type":"array","items":"Tree"}},{"name":"parent","type":["null","Tree"],"default":null}]}"#
+    );
+    let event = records[2].get("fields").unwrap().as_array().unwrap()[0]
+        .get("type")
+        .and_then(|ty| ty.get("items"))
+        .unwrap();
+    let fields = event.get("fields").unwrap().as_array().unwrap();
+    let fields = fields.iter().map(ToString::to_string).collect::<Vec<_>>();
+    assert_eq!(
+        fields,
+        [
+            r#"{"name":"label","type":["string","null"],"default":"none","doc":"Deprecated: use labels"}"#,
+            r#"{"name":"labels","type":{"type":"map","values":"double"},"default":{"a":1.5}}"#,
+            r#"{"name":"counts","type":{"type":"array","items":{"type":"record","name":"IntegerBooleanEntry","fields":[{"name":"key","type":"long"},{"name":"value","type":"boolean"}]}}}"#,
+            r#"{"name":"page","type":"PageTree"}"#,
+            r#"{"name":"maybe","type":["null",{"type":"record","name":"OptionalInteger","fields":[{"name":"value","type":["null","long"]}]}]}"#,
+            r#"{"name":"owner","type":"Owner"}"#,
+        ]
+    );
+
+    let source =
+        "type Event {\n    id: integer\n}\n\ntype Feed {\n    event: stream Event\n}\n";
+    let assembly = schema::parse(source).unwrap();
+    assert_eq!(
+        codegen::avro::generate(&assembly).unwrap_err(),
+        "`stream` and `sync` fields cannot be represented in Avro records: Feed.event"
+    );
+}
+
+#[test]
+fn test_codegen_flat_name_clashes() {
+    let source = r#"
+type User {
+    id: integer @tag(1)
+}
+
+type Page<T> {
+    items: Array<T> @tag(1)
+}
+
+type PageUser {
+    page: Page<User> @tag(1)
+}
+
+type IntegerList {
+    grid: Array<Array<integer>> @tag(1)
+}
+
+type UsersGetRequest {
+    id: integer @tag(1)
+}
+
+service Users {
+    remote function get(id: integer @tag(1)) -> User
+}
+"#;
+    let assembly = schema::parse(source).unwrap();
+    let error = codegen::protobuf::generate(&assembly, "users").unwrap_err();
+    assert!(error.starts_with("generated names clash, give types other names with `@rename`: "));
+    assert!(error.contains("`PageUser` would name both `PageUser` and `Page<User>`"));
+    assert!(error.contains("`IntegerList` would name both `IntegerList` and `Array<integer>`"));
+    assert!(error.contains(
+        "`UsersGetRequest` would name both `UsersGetRequest` and the request of `Users.get`"
+    ));
+    let error = codegen::graphql::generate(&assembly).unwrap_err();
+    assert!(error.contains("`PageUser` would name both `PageUser` and `Page<User>`"));
+    let error = codegen::avro::generate(&assembly).unwrap_err();
+    assert!(error.contains("`PageUser` would name both `PageUser` and `Page<User>`"));
+
+    // Renamed for the target, the types no longer clash.
+    let mut renamed = source.to_string();
+    for (name, proto) in [
+        ("PageUser", "UserPage"),
+        ("IntegerList", "Grid"),
+        ("UsersGetRequest", "Lookup"),
+    ] {
+        let rename = format!("@rename(proto = \"{proto}\")\ntype {name} {{");
+        renamed = renamed.replace(&format!("type {name} {{"), &rename);
+    }
+    let assembly = schema::parse(&renamed).unwrap();
+    assert!(codegen::protobuf::generate(&assembly, "users").is_ok());
+
+    let source =
+        "type Query {\n    id: integer\n}\n\nservice S {\n    remote function f() -> Query\n}\n";
+    let assembly = schema::parse(source).unwrap();
+    assert_eq!(
+        codegen::graphql::generate(&assembly).unwrap_err(),
+        "generated names clash, give types other names with `@rename`: `Query` would name both \
+         `Query` and the query root type"
+    );
+}